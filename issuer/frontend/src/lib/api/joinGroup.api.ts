@@ -19,6 +19,7 @@ export const joinGroup = async ({
     owner,
     group_name: issuerName,
     vc_arguments: vcArguments ? [vcArguments] : [],
+    message: [],
   });
   if ('Err' in response) {
     throw response.Err;
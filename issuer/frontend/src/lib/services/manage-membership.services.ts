@@ -26,7 +26,7 @@ const updaterFactory =
       await updateMembership({
         identity,
         groupName: issuerName,
-        updates: [{ member, new_status: newStatus }],
+        updates: [{ member, new_status: newStatus, note: [] }],
       });
       await loadIssuerDetail({ identity, issuerName, toastStore });
     } catch (e) {
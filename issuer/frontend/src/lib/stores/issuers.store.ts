@@ -29,13 +29,13 @@ const sortCredentialsPerTimestampDescending = (a: PublicGroupData, b: PublicGrou
 const sortCredentialsPerType = (a: PublicGroupData, b: PublicGroupData): number => {
   const statusA = a.membership_status[0];
   const statusB = b.membership_status[0];
-  if (statusA === undefined || 'Rejected' in statusA) {
-    if (statusB === undefined || 'Rejected' in statusB) {
+  if (statusA === undefined || 'Rejected' in statusA || 'Expired' in statusA) {
+    if (statusB === undefined || 'Rejected' in statusB || 'Expired' in statusB) {
       return sortCredentialsPerTimestampDescending(a, b);
     }
     return 1;
   }
-  if (statusB === undefined || 'Rejected' in statusB) {
+  if (statusB === undefined || 'Rejected' in statusB || 'Expired' in statusB) {
     return -1;
   }
 
@@ -0,0 +1,132 @@
+//! Benchmarks of the group management, run with `canbench` in `issuer/`.
+//!
+//! The `legacy_layout` benchmarks measure the costs of the layout in which the members were
+//! stored within the group record, for comparison with the separate storage of the members.
+use super::*;
+use canbench_rs::{bench, bench_fn, BenchResult};
+
+const BENCH_GROUP_NAME: &str = "Bench group";
+
+fn member_principal(i: u32) -> Principal {
+    Principal::from_slice(&i.to_be_bytes())
+}
+
+fn member_record() -> MemberRecord {
+    MemberRecord {
+        joined_timestamp_ns: time(),
+        membership_status: MembershipStatus::PendingReview,
+        vc_arguments: None,
+        status_list_index: None,
+        applicant_message: Some("Please let me in.".to_string()),
+        reviewer_note: None,
+        evidence_ids: None,
+        accepted_timestamp_ns: None,
+    }
+}
+
+/// Adds a group with the given number of members, stored as in the current layout,
+/// or within the group record if `legacy_layout` is set.
+fn add_bench_group(key: &GroupKey, member_count: u32, legacy_layout: bool) {
+    let members: BTreeMap<Principal, MemberRecord> = (0..member_count)
+        .map(|i| (member_principal(i), member_record()))
+        .collect();
+    let group_record = GroupRecord {
+        created_timestamp_ns: time(),
+        membership_validity: None,
+        member_count,
+        legacy_members: legacy_layout.then(|| members.clone()),
+    };
+    GROUPS.with_borrow_mut(|groups| groups.insert(key.clone(), group_record));
+    if !legacy_layout {
+        MEMBERS.with_borrow_mut(|stored_members| {
+            for (member, member_record) in members {
+                stored_members.insert(MemberKey::new(key.clone(), member), member_record);
+            }
+        });
+    }
+}
+
+fn add_bench_groups(group_count: u32, member_count: u32, legacy_layout: bool) {
+    for i in 0..group_count {
+        let key = GroupKey::from((format!("{} {}", BENCH_GROUP_NAME, i), member_principal(i)));
+        add_bench_group(&key, member_count, legacy_layout);
+    }
+}
+
+#[bench(raw)]
+fn join_group_1k_members() -> BenchResult {
+    let owner = member_principal(u32::MAX);
+    add_bench_group(&(BENCH_GROUP_NAME.to_string(), owner).into(), 1_000, false);
+    // The benchmarks are run by the anonymous principal, which may not call `join_group`.
+    bench_fn(|| {
+        request_membership(
+            member_principal(u32::MAX - 1),
+            JoinGroupRequest {
+                group_name: BENCH_GROUP_NAME.to_string(),
+                owner,
+                vc_arguments: None,
+                message: None,
+            },
+            &RateLimits::default(),
+        )
+        .expect("failed to join group");
+    })
+}
+
+// Adding a member to a group record with in-value members, as `join_group` did.
+#[bench(raw)]
+fn join_group_1k_members_legacy_layout() -> BenchResult {
+    let key: GroupKey = (BENCH_GROUP_NAME.to_string(), member_principal(u32::MAX)).into();
+    add_bench_group(&key, 1_000, true);
+    bench_fn(|| {
+        GROUPS.with_borrow_mut(|groups| {
+            let mut group_record = groups.get(&key).expect("group not found");
+            if let Some(members) = group_record.legacy_members.as_mut() {
+                members.insert(caller(), member_record());
+            }
+            groups.insert(key.clone(), group_record);
+        });
+    })
+}
+
+#[bench(raw)]
+fn update_membership_1k_members() -> BenchResult {
+    let key: GroupKey = (BENCH_GROUP_NAME.to_string(), member_principal(u32::MAX)).into();
+    add_bench_group(&key, 1_000, false);
+    let group_record = load_group(&key).expect("group not found");
+    // The benchmarks are run by the anonymous principal, which may not call `update_membership`.
+    bench_fn(|| {
+        update_members(
+            &key,
+            &group_record,
+            vec![MembershipUpdate {
+                member: member_principal(500),
+                new_status: MembershipStatus::Accepted,
+                note: None,
+            }],
+        )
+        .expect("failed to update membership");
+    })
+}
+
+#[bench(raw)]
+fn list_groups_100_groups_100_members() -> BenchResult {
+    add_bench_groups(100, 100, false);
+    bench_fn(|| {
+        list_groups(ListGroupsRequest {
+            group_name_substring: None,
+        })
+        .expect("failed to list groups");
+    })
+}
+
+#[bench(raw)]
+fn list_groups_100_groups_100_members_legacy_layout() -> BenchResult {
+    add_bench_groups(100, 100, true);
+    bench_fn(|| {
+        list_groups(ListGroupsRequest {
+            group_name_substring: None,
+        })
+        .expect("failed to list groups");
+    })
+}
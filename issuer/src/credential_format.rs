@@ -0,0 +1,337 @@
+//! Encoders for the supported credential formats.
+//!
+//! The credential format is negotiated via the optional `credentialFormat`-argument of the
+//! credential spec, which is one of `jwt` (default), `sd-jwt` or `json-ld`. All formats are
+//! derived from the JWT claims built by `build_credential_jwt`, and signed with the canister
+//! signature of the issuer. The issued credential is returned in the `vc_jws`-field of
+//! `IssuedCredentialData`, regardless of the format.
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use candid::{CandidType, Deserialize};
+use ic_canister_sig_creation::CanisterSigPublicKey;
+use ic_verifiable_credentials::{vc_jwt_to_jws, vc_signing_input};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+/// Name of the credential spec argument selecting the credential format.
+pub const CREDENTIAL_FORMAT_ARG: &str = "credentialFormat";
+
+const SD_JWT_SEPARATOR: char = '~';
+const JSON_LD_CRYPTOSUITE: &str = "iccs-jcs-2024";
+
+#[derive(CandidType, Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+pub enum CredentialFormat {
+    #[default]
+    Jwt,
+    SdJwt,
+    JsonLd,
+}
+
+impl FromStr for CredentialFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "jwt" => Ok(CredentialFormat::Jwt),
+            "sd-jwt" => Ok(CredentialFormat::SdJwt),
+            "json-ld" => Ok(CredentialFormat::JsonLd),
+            _ => Err(format!("Credential format {} is not supported", format)),
+        }
+    }
+}
+
+impl CredentialFormat {
+    pub fn encoder(&self) -> &'static dyn CredentialEncoder {
+        match self {
+            CredentialFormat::Jwt => &JwtEncoder,
+            CredentialFormat::SdJwt => &SdJwtEncoder,
+            CredentialFormat::JsonLd => &JsonLdEncoder,
+        }
+    }
+}
+
+/// A credential ready to be signed, passed as `prepared_context` from
+/// `prepare_credential` to `get_credential`.
+#[derive(CandidType, Clone, Deserialize)]
+pub struct PreparedCredential {
+    pub format: CredentialFormat,
+    /// The format-specific payload covered by the signature.
+    pub payload: String,
+    /// SD-JWT disclosures, empty for the other formats.
+    pub disclosures: Vec<String>,
+}
+
+impl PreparedCredential {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        candid::encode_one(self).expect("failed to encode PreparedCredential")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        candid::decode_one(bytes).map_err(|e| format!("invalid prepared_context: {}", e))
+    }
+}
+
+/// Format-specific steps of issuing a credential.
+pub trait CredentialEncoder {
+    /// Builds the credential to be signed from the JWT claims of a credential.
+    /// `salt` is fresh randomness, used by formats that need it.
+    fn prepare(&self, credential_jwt: &str, salt: &[u8]) -> Result<PreparedCredential, String>;
+
+    /// Returns the message to be signed with the canister signature.
+    fn signing_input(
+        &self,
+        prepared: &PreparedCredential,
+        canister_sig_pk: &CanisterSigPublicKey,
+    ) -> Result<Vec<u8>, String>;
+
+    /// Assembles the issued credential from the prepared credential and its signature.
+    fn encode(
+        &self,
+        prepared: &PreparedCredential,
+        canister_sig_pk: &CanisterSigPublicKey,
+        signature: &[u8],
+    ) -> Result<String, String>;
+}
+
+/// Plain JWT, as specified by the VC Data Model 1.1 (JWT encoding).
+pub struct JwtEncoder;
+
+impl CredentialEncoder for JwtEncoder {
+    fn prepare(&self, credential_jwt: &str, _salt: &[u8]) -> Result<PreparedCredential, String> {
+        Ok(PreparedCredential {
+            format: CredentialFormat::Jwt,
+            payload: credential_jwt.to_string(),
+            disclosures: vec![],
+        })
+    }
+
+    fn signing_input(
+        &self,
+        prepared: &PreparedCredential,
+        canister_sig_pk: &CanisterSigPublicKey,
+    ) -> Result<Vec<u8>, String> {
+        jwt_signing_input(&prepared.payload, canister_sig_pk)
+    }
+
+    fn encode(
+        &self,
+        prepared: &PreparedCredential,
+        canister_sig_pk: &CanisterSigPublicKey,
+        signature: &[u8],
+    ) -> Result<String, String> {
+        jwt_to_jws(&prepared.payload, canister_sig_pk, signature)
+    }
+}
+
+/// SD-JWT, where every claim of the credential subject (except its id) is selectively
+/// disclosable. The issued credential has the form `<JWS>~<disclosure>~...~<disclosure>~`.
+pub struct SdJwtEncoder;
+
+impl CredentialEncoder for SdJwtEncoder {
+    fn prepare(&self, credential_jwt: &str, salt: &[u8]) -> Result<PreparedCredential, String> {
+        let mut claims = parse_claims(credential_jwt)?;
+        let subject = claims
+            .get_mut("vc")
+            .and_then(|vc| vc.get_mut("credentialSubject"))
+            .and_then(Value::as_object_mut)
+            .ok_or("missing credentialSubject in credential JWT")?;
+        let mut disclosures = vec![];
+        let mut digests = vec![];
+        for (name, value) in std::mem::take(subject) {
+            if name == "id" {
+                subject.insert(name, value);
+                continue;
+            }
+            let disclosure_salt = URL_SAFE_NO_PAD.encode(
+                &Sha256::new()
+                    .chain_update(salt)
+                    .chain_update(&name)
+                    .finalize()[..16],
+            );
+            let disclosure =
+                URL_SAFE_NO_PAD.encode(json!([disclosure_salt, name, value]).to_string());
+            digests.push(URL_SAFE_NO_PAD.encode(Sha256::digest(disclosure.as_bytes())));
+            disclosures.push(disclosure);
+        }
+        digests.sort();
+        subject.insert("_sd".to_string(), json!(digests));
+        claims.insert("_sd_alg".to_string(), json!("sha-256"));
+        Ok(PreparedCredential {
+            format: CredentialFormat::SdJwt,
+            payload: Value::Object(claims).to_string(),
+            disclosures,
+        })
+    }
+
+    fn signing_input(
+        &self,
+        prepared: &PreparedCredential,
+        canister_sig_pk: &CanisterSigPublicKey,
+    ) -> Result<Vec<u8>, String> {
+        jwt_signing_input(&prepared.payload, canister_sig_pk)
+    }
+
+    fn encode(
+        &self,
+        prepared: &PreparedCredential,
+        canister_sig_pk: &CanisterSigPublicKey,
+        signature: &[u8],
+    ) -> Result<String, String> {
+        let mut sd_jwt = jwt_to_jws(&prepared.payload, canister_sig_pk, signature)?;
+        for disclosure in &prepared.disclosures {
+            sd_jwt.push(SD_JWT_SEPARATOR);
+            sd_jwt.push_str(disclosure);
+        }
+        sd_jwt.push(SD_JWT_SEPARATOR);
+        Ok(sd_jwt)
+    }
+}
+
+/// JSON-LD VC secured with a Data Integrity proof. The signing input follows the JCS-based
+/// cryptosuites: the hash of the canonical proof configuration, followed by the hash
+/// of the canonical document (object keys are sorted, as in JCS).
+pub struct JsonLdEncoder;
+
+impl CredentialEncoder for JsonLdEncoder {
+    fn prepare(&self, credential_jwt: &str, _salt: &[u8]) -> Result<PreparedCredential, String> {
+        let claims = parse_claims(credential_jwt)?;
+        let mut document = claims
+            .get("vc")
+            .and_then(Value::as_object)
+            .cloned()
+            .ok_or("missing vc in credential JWT")?;
+        let issuer = claim_str(&claims, "iss")?;
+        let issuance_date = rfc3339(claim_u64(&claims, "nbf")?);
+        document.insert("id".to_string(), json!(claim_str(&claims, "jti")?));
+        document.insert("issuer".to_string(), json!(issuer));
+        document.insert("issuanceDate".to_string(), json!(issuance_date));
+        document.insert(
+            "expirationDate".to_string(),
+            json!(rfc3339(claim_u64(&claims, "exp")?)),
+        );
+        document
+            .get_mut("credentialSubject")
+            .and_then(Value::as_object_mut)
+            .ok_or("missing credentialSubject in credential JWT")?
+            .insert("id".to_string(), json!(claim_str(&claims, "sub")?));
+        document.insert(
+            "proof".to_string(),
+            json!({
+                "type": "DataIntegrityProof",
+                "cryptosuite": JSON_LD_CRYPTOSUITE,
+                "created": issuance_date,
+                "verificationMethod": format!("{}#canister-sig", issuer),
+                "proofPurpose": "assertionMethod",
+            }),
+        );
+        Ok(PreparedCredential {
+            format: CredentialFormat::JsonLd,
+            payload: Value::Object(document).to_string(),
+            disclosures: vec![],
+        })
+    }
+
+    fn signing_input(
+        &self,
+        prepared: &PreparedCredential,
+        canister_sig_pk: &CanisterSigPublicKey,
+    ) -> Result<Vec<u8>, String> {
+        let mut document = parse_claims(&prepared.payload)?;
+        let mut proof_config = document
+            .remove("proof")
+            .and_then(|proof| proof.as_object().cloned())
+            .ok_or("missing proof in JSON-LD credential")?;
+        // Bind the signature to the signing key.
+        proof_config.insert(
+            "publicKey".to_string(),
+            json!(URL_SAFE_NO_PAD.encode(canister_sig_pk.to_der())),
+        );
+        let mut signing_input = Sha256::digest(Value::Object(proof_config).to_string()).to_vec();
+        signing_input.extend_from_slice(&Sha256::digest(Value::Object(document).to_string()));
+        Ok(signing_input)
+    }
+
+    fn encode(
+        &self,
+        prepared: &PreparedCredential,
+        canister_sig_pk: &CanisterSigPublicKey,
+        signature: &[u8],
+    ) -> Result<String, String> {
+        let mut document = parse_claims(&prepared.payload)?;
+        let proof = document
+            .get_mut("proof")
+            .and_then(Value::as_object_mut)
+            .ok_or("missing proof in JSON-LD credential")?;
+        proof.insert(
+            "publicKey".to_string(),
+            json!(URL_SAFE_NO_PAD.encode(canister_sig_pk.to_der())),
+        );
+        // Multibase-encoded (base64url, no padding).
+        proof.insert(
+            "proofValue".to_string(),
+            json!(format!("u{}", URL_SAFE_NO_PAD.encode(signature))),
+        );
+        Ok(Value::Object(document).to_string())
+    }
+}
+
+fn jwt_signing_input(
+    credential_jwt: &str,
+    canister_sig_pk: &CanisterSigPublicKey,
+) -> Result<Vec<u8>, String> {
+    vc_signing_input(credential_jwt, canister_sig_pk)
+        .map_err(|e| format!("failed getting signing_input: {:?}", e))
+}
+
+fn jwt_to_jws(
+    credential_jwt: &str,
+    canister_sig_pk: &CanisterSigPublicKey,
+    signature: &[u8],
+) -> Result<String, String> {
+    vc_jwt_to_jws(credential_jwt, canister_sig_pk, signature)
+        .map_err(|e| format!("failed constructing JWS: {:?}", e))
+}
+
+fn parse_claims(json: &str) -> Result<Map<String, Value>, String> {
+    serde_json::from_str(json).map_err(|e| format!("malformed credential: {}", e))
+}
+
+fn claim_str<'a>(claims: &'a Map<String, Value>, name: &str) -> Result<&'a str, String> {
+    claims
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or(format!("missing {} in credential JWT", name))
+}
+
+fn claim_u64(claims: &Map<String, Value>, name: &str) -> Result<u64, String> {
+    claims
+        .get(name)
+        .and_then(Value::as_u64)
+        .ok_or(format!("missing {} in credential JWT", name))
+}
+
+/// Formats the given UNIX timestamp (in seconds) as RFC 3339 date-time in UTC.
+fn rfc3339(timestamp_s: u64) -> String {
+    // Civil date from days since epoch, cf. http://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp_s / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let seconds_of_day = timestamp_s % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3_600,
+        seconds_of_day % 3_600 / 60,
+        seconds_of_day % 60
+    )
+}
@@ -0,0 +1,22 @@
+//! Binding of a relying party's nonce into issued credentials.
+//!
+//! A relying party can pass the nonce of its challenge in the optional `nonce`-argument of the
+//! credential spec. The nonce is not part of the credential subject, but is stated in the
+//! `vc.nonce`-claim of the issued credential, so that the relying party can check that the
+//! presented credential was requested for its challenge.
+use serde_json::{Map, Value};
+
+/// Name of the credential spec argument carrying the nonce of the relying party.
+pub const CREDENTIAL_NONCE_ARG: &str = "nonce";
+
+/// Adds the `nonce`-entry to the `vc`-claim of the given JWT claims.
+pub fn add_credential_nonce(credential_jwt: &str, nonce: &str) -> Result<String, String> {
+    let mut claims: Map<String, Value> = serde_json::from_str(credential_jwt)
+        .map_err(|e| format!("malformed credential JWT: {}", e))?;
+    claims
+        .get_mut("vc")
+        .and_then(Value::as_object_mut)
+        .ok_or("missing vc in credential JWT")?
+        .insert("nonce".to_string(), Value::String(nonce.to_string()));
+    serde_json::to_string(&claims).map_err(|e| format!("failed to encode credential JWT: {}", e))
+}
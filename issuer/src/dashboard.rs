@@ -0,0 +1,209 @@
+//! Statistics of the groups shown to their owners, cf. `get_owner_dashboard`.
+//!
+//! The statistics are aggregates which are updated incrementally whenever a member record
+//! changes or a credential is issued, so that the dashboard does not need to visit the members.
+//! Days are counted since the Unix epoch.
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::storable::{Bound, Storable};
+use meta_issuer::groups_api::{
+    ArgumentValue, ArgumentValueCount, DailyCount, GroupDashboardData, MembershipCounts,
+    MembershipStatus, PendingAgeDistribution, VcArguments,
+};
+use stable_schema::VersionedRecord;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+const NS_PER_DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+/// Number of days for which the credentials issued per day are kept.
+pub const DASHBOARD_HISTORY_DAYS: u64 = 30;
+
+/// Key of the statistics of a group. The groups of an owner form a contiguous range.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DashboardKey {
+    pub owner: Principal,
+    pub group_name: String,
+}
+
+impl DashboardKey {
+    /// Returns the smallest key of the given owner.
+    pub fn first_of_owner(owner: Principal) -> Self {
+        Self {
+            owner,
+            group_name: String::new(),
+        }
+    }
+}
+
+impl Storable for DashboardKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode DashboardKey"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode DashboardKey")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Clone, Debug, Default, Deserialize)]
+pub struct GroupDashboardRecord {
+    pub member_counts: MembershipCounts,
+    /// Number of pending membership requests by the day they were made.
+    pub pending_by_join_day: BTreeMap<u64, u32>,
+    pub credentials_issued: u64,
+    /// Number of issued credentials by day, for the last `DASHBOARD_HISTORY_DAYS` days.
+    pub credentials_issued_by_day: BTreeMap<u64, u32>,
+    /// Number of accepted members by VC argument and value.
+    pub argument_values: BTreeMap<String, BTreeMap<String, u32>>,
+    pub last_activity_timestamp_ns: Option<u64>,
+}
+
+impl GroupDashboardRecord {
+    pub fn add_member(
+        &mut self,
+        status: &MembershipStatus,
+        joined_timestamp_ns: u64,
+        vc_arguments: &Option<VcArguments>,
+    ) {
+        self.member_counts.add(status);
+        match status {
+            MembershipStatus::PendingReview => {
+                *self
+                    .pending_by_join_day
+                    .entry(day(joined_timestamp_ns))
+                    .or_default() += 1;
+            }
+            MembershipStatus::Accepted => {
+                for (argument, value) in vc_arguments.iter().flatten() {
+                    *self
+                        .argument_values
+                        .entry(argument.clone())
+                        .or_default()
+                        .entry(value_text(value))
+                        .or_default() += 1;
+                }
+            }
+            MembershipStatus::Rejected | MembershipStatus::Expired => {}
+        }
+    }
+
+    /// Reverts `add_member` with the same arguments.
+    pub fn remove_member(
+        &mut self,
+        status: &MembershipStatus,
+        joined_timestamp_ns: u64,
+        vc_arguments: &Option<VcArguments>,
+    ) {
+        self.member_counts.remove(status);
+        match status {
+            MembershipStatus::PendingReview => {
+                decrement(&mut self.pending_by_join_day, &day(joined_timestamp_ns));
+            }
+            MembershipStatus::Accepted => {
+                for (argument, value) in vc_arguments.iter().flatten() {
+                    if let Some(values) = self.argument_values.get_mut(argument) {
+                        decrement(values, &value_text(value));
+                        if values.is_empty() {
+                            self.argument_values.remove(argument);
+                        }
+                    }
+                }
+            }
+            MembershipStatus::Rejected | MembershipStatus::Expired => {}
+        }
+    }
+
+    pub fn add_issued_credential(&mut self, now_ns: u64) {
+        self.credentials_issued += 1;
+        *self
+            .credentials_issued_by_day
+            .entry(day(now_ns))
+            .or_default() += 1;
+        let first_day = first_history_day(now_ns);
+        self.credentials_issued_by_day
+            .retain(|day, _| *day >= first_day);
+    }
+
+    pub fn record_activity(&mut self, now_ns: u64) {
+        self.last_activity_timestamp_ns = Some(now_ns);
+    }
+
+    pub fn to_data(&self, group_name: String, now_ns: u64) -> GroupDashboardData {
+        let today = day(now_ns);
+        let mut pending_age = PendingAgeDistribution::default();
+        for (join_day, count) in &self.pending_by_join_day {
+            let bucket = match today.saturating_sub(*join_day) {
+                0 => &mut pending_age.under_1_day,
+                1..=6 => &mut pending_age.under_7_days,
+                7..=29 => &mut pending_age.under_30_days,
+                _ => &mut pending_age.over_30_days,
+            };
+            *bucket += count;
+        }
+        let first_day = first_history_day(now_ns);
+        GroupDashboardData {
+            group_name,
+            member_counts: self.member_counts.clone(),
+            pending_age,
+            credentials_issued: self.credentials_issued,
+            credentials_issued_by_day: self
+                .credentials_issued_by_day
+                .range(first_day..)
+                .map(|(day, count)| DailyCount {
+                    day_start_timestamp_ns: day * NS_PER_DAY,
+                    count: *count,
+                })
+                .collect(),
+            argument_values: self
+                .argument_values
+                .iter()
+                .flat_map(|(argument, values)| {
+                    values.iter().map(|(value, count)| ArgumentValueCount {
+                        argument: argument.clone(),
+                        value: value.clone(),
+                        count: *count,
+                    })
+                })
+                .collect(),
+            last_activity_timestamp_ns: self.last_activity_timestamp_ns,
+        }
+    }
+}
+
+impl VersionedRecord for GroupDashboardRecord {
+    const NAME: &'static str = "GroupDashboardRecord";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for GroupDashboardRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(stable_schema::encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        stable_schema::decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+fn day(timestamp_ns: u64) -> u64 {
+    timestamp_ns / NS_PER_DAY
+}
+
+fn first_history_day(now_ns: u64) -> u64 {
+    (day(now_ns) + 1).saturating_sub(DASHBOARD_HISTORY_DAYS)
+}
+
+fn value_text(value: &ArgumentValue) -> String {
+    match value {
+        ArgumentValue::String(text) => text.clone(),
+        ArgumentValue::Int(int) => int.to_string(),
+    }
+}
+
+fn decrement<K: Ord>(counts: &mut BTreeMap<K, u32>, key: &K) {
+    if let Some(count) = counts.get_mut(key) {
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            counts.remove(key);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Evidence documents attached by members to their pending membership requests.
+//!
+//! Documents are uploaded in chunks of `EVIDENCE_CHUNK_SIZE` bytes (the last chunk may be
+//! shorter), and stored encrypted with AES-256-GCM under a key specific to the group owner.
+//! The owner keys are derived vetKD-style: the derivation path is `OWNER_KEY_DOMAIN`, and
+//! the derivation id the owner's principal, so every owner gets an independent key that is
+//! never stored. As vetKD is not yet available to the canister, the master key is simulated
+//! locally by a seed obtained from `raw_rand`, which never leaves the canister. This protects
+//! the documents against leaks of the stored chunks (e.g. via a stable memory dump), but not
+//! against the nodes running the canister, which a real vetKD master key would also cover.
+//! Evidence is readable only by the owner of the group, and deleted once the owner
+//! decides on the membership request.
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::storable::{Bound, Storable};
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+
+pub const EVIDENCE_CHUNK_SIZE: u32 = 256 * 1024;
+pub const MAX_EVIDENCE_SIZE: u32 = 2 * 1024 * 1024;
+pub const MAX_EVIDENCE_PER_MEMBER: usize = 3;
+pub const MAX_EVIDENCE_FILE_NAME_LENGTH: usize = 256;
+
+const OWNER_KEY_DOMAIN: &[u8] = b"meta-issuer-evidence-owner-key";
+
+/// Metadata of an evidence document.
+#[derive(CandidType, Clone, Deserialize)]
+pub struct EvidenceRecord {
+    pub group_name: String,
+    pub owner: Principal,
+    pub member: Principal,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u32,
+    pub uploaded_chunk_count: u32,
+    pub created_timestamp_ns: u64,
+}
+
+impl EvidenceRecord {
+    pub fn chunk_count(&self) -> u32 {
+        self.size.div_ceil(EVIDENCE_CHUNK_SIZE)
+    }
+
+    pub fn expected_chunk_size(&self, chunk_index: u32) -> u32 {
+        if chunk_index + 1 < self.chunk_count() {
+            EVIDENCE_CHUNK_SIZE
+        } else {
+            self.size - chunk_index * EVIDENCE_CHUNK_SIZE
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.uploaded_chunk_count == self.chunk_count()
+    }
+}
+
+impl Storable for EvidenceRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode EvidenceRecord"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode EvidenceRecord")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Canister-wide state for evidence: the seed of the owner keys (empty until the first
+/// upload), and the next evidence id. Ids are never reused, as they are part of the nonces.
+#[derive(CandidType, Clone, Default, Deserialize)]
+pub struct EvidenceState {
+    pub key_seed: ByteBuf,
+    pub next_evidence_id: u64,
+}
+
+impl Storable for EvidenceState {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode EvidenceState"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode EvidenceState")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Derives the evidence key of the given owner from the simulated vetKD master key.
+pub fn owner_key(key_seed: &[u8], owner: Principal) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([OWNER_KEY_DOMAIN.len() as u8])
+        .chain_update(OWNER_KEY_DOMAIN)
+        .chain_update(key_seed)
+        .chain_update(owner.as_slice())
+        .finalize()
+        .into()
+}
+
+pub fn encrypt_chunk(
+    key: &[u8; 32],
+    evidence_id: u64,
+    chunk_index: u32,
+    plaintext: &[u8],
+) -> Vec<u8> {
+    let nonce = chunk_nonce(evidence_id, chunk_index);
+    cipher(key)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &nonce,
+            },
+        )
+        .expect("failed to encrypt evidence chunk")
+}
+
+pub fn decrypt_chunk(
+    key: &[u8; 32],
+    evidence_id: u64,
+    chunk_index: u32,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, String> {
+    let nonce = chunk_nonce(evidence_id, chunk_index);
+    cipher(key)
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &nonce,
+            },
+        )
+        .map_err(|_| "failed to decrypt evidence chunk".to_string())
+}
+
+fn cipher(key: &[u8; 32]) -> Aes256Gcm {
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))
+}
+
+// Evidence ids are unique, so every chunk encrypted under a key gets a distinct nonce.
+fn chunk_nonce(evidence_id: u64, chunk_index: u32) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..8].copy_from_slice(&evidence_id.to_be_bytes());
+    nonce[8..].copy_from_slice(&chunk_index.to_be_bytes());
+    nonce
+}
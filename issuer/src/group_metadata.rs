@@ -0,0 +1,172 @@
+//! Descriptive metadata of groups, set by their owners via `update_group`.
+//!
+//! The metadata is stored next to the group records, so that loading a group for membership
+//! management does not load its description or logo. Logos are stored separately, addressed
+//! by the SHA-256 hash of their content, and served by `http_request` at `logo_path(hash)`.
+//! A logo used by several groups is stored once, and deleted once no group refers to it.
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::storable::{Bound, Storable};
+use meta_issuer::groups_api::{GroupLogo, GroupMetadata, GroupVisibility, UpdateGroupRequest};
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+use stable_schema::VersionedRecord;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+pub const MAX_DESCRIPTION_LENGTH: usize = 2_000;
+pub const MAX_CONTACT_URL_LENGTH: usize = 512;
+pub const MAX_TAGS: usize = 10;
+pub const MAX_TAG_LENGTH: usize = 32;
+pub const MAX_LOGO_SIZE: usize = 64 * 1024;
+/// Raster image formats only: SVG logos could run scripts in the origin of the issuer.
+pub const LOGO_CONTENT_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+pub const LOGO_PATH_PREFIX: &str = "/logos/";
+
+/// Hex-encoded SHA-256 hash of the content of a logo.
+pub type LogoHash = String;
+
+#[derive(CandidType, Clone, Debug, Default, Deserialize)]
+pub struct GroupMetadataRecord {
+    pub description: Option<String>,
+    pub contact_url: Option<String>,
+    pub tags: Vec<String>,
+    pub visibility: GroupVisibility,
+    pub logo_hash: Option<LogoHash>,
+}
+
+impl GroupMetadataRecord {
+    pub fn to_data(&self) -> GroupMetadata {
+        GroupMetadata {
+            description: self.description.clone(),
+            contact_url: self.contact_url.clone(),
+            logo_url: self.logo_hash.as_deref().map(logo_path),
+            tags: self.tags.clone(),
+            visibility: self.visibility.clone(),
+        }
+    }
+}
+
+impl VersionedRecord for GroupMetadataRecord {
+    const NAME: &'static str = "GroupMetadataRecord";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for GroupMetadataRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(stable_schema::encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        stable_schema::decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// A logo, with the number of groups which use it.
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct LogoRecord {
+    pub content_type: String,
+    pub content: ByteBuf,
+    pub ref_count: u32,
+}
+
+impl VersionedRecord for LogoRecord {
+    const NAME: &'static str = "LogoRecord";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for LogoRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(stable_schema::encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        stable_schema::decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Checks the sizes and formats of the metadata in the request.
+pub fn validate_update(req: &UpdateGroupRequest) -> Result<(), String> {
+    check_length(
+        "description",
+        req.description.as_deref(),
+        MAX_DESCRIPTION_LENGTH,
+    )?;
+    if let Some(contact_url) = &req.contact_url {
+        check_length("contact URL", Some(contact_url), MAX_CONTACT_URL_LENGTH)?;
+        if !(contact_url.starts_with("https://") || contact_url.starts_with("mailto:")) {
+            return Err(format!(
+                "contact URL must start with https:// or mailto:, got {}",
+                contact_url
+            ));
+        }
+    }
+    if req.tags.len() > MAX_TAGS {
+        return Err(format!(
+            "too many tags: {}, max. {}",
+            req.tags.len(),
+            MAX_TAGS
+        ));
+    }
+    let mut tags = BTreeSet::new();
+    for tag in &req.tags {
+        check_length("tag", Some(tag), MAX_TAG_LENGTH)?;
+        if tag.trim().is_empty() {
+            return Err("tags must not be empty".to_string());
+        }
+        if !tags.insert(tag) {
+            return Err(format!("duplicate tag: {}", tag));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_logo(logo: &GroupLogo) -> Result<(), String> {
+    if !LOGO_CONTENT_TYPES.contains(&logo.content_type.as_str()) {
+        return Err(format!(
+            "unsupported logo content type: {}, expected one of {}",
+            logo.content_type,
+            LOGO_CONTENT_TYPES.join(", ")
+        ));
+    }
+    if logo.content.is_empty() {
+        return Err("logo must not be empty".to_string());
+    }
+    if logo.content.len() > MAX_LOGO_SIZE {
+        return Err(format!(
+            "logo too large: {} bytes, max. {}",
+            logo.content.len(),
+            MAX_LOGO_SIZE
+        ));
+    }
+    Ok(())
+}
+
+pub fn logo_hash(content: &[u8]) -> LogoHash {
+    hex::encode(Sha256::digest(content))
+}
+
+pub fn logo_path(hash: &str) -> String {
+    format!("{}{}", LOGO_PATH_PREFIX, hash)
+}
+
+/// Returns the hash of the logo addressed by `path`, if it is a logo path.
+pub fn parse_logo_path(path: &str) -> Option<LogoHash> {
+    let hash = path.strip_prefix(LOGO_PATH_PREFIX)?;
+    let is_hash = hash.len() == 64
+        && hash
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    is_hash.then(|| hash.to_string())
+}
+
+fn check_length(field: &str, text: Option<&str>, max_length: usize) -> Result<(), String> {
+    match text {
+        Some(text) if text.len() > max_length => Err(format!(
+            "{} too long: {} bytes, max. {}",
+            field,
+            text.len(),
+            max_length
+        )),
+        _ => Ok(()),
+    }
+}
@@ -1,4 +1,10 @@
 use candid::{CandidType, Deserialize, Principal};
+use ic_verifiable_credentials::issuer_api::{
+    CredentialSpec as IssuerCredentialSpec, IssueCredentialError, IssuedCredentialData,
+    PreparedCredentialData, SignedIdAlias,
+};
+use rate_limit::TokenBucketConfig;
+use serde_bytes::ByteBuf;
 use std::collections::BTreeMap;
 
 // "Copies" of ArgumentValue and CredentialSpec which are defined in `ic_verifiable_credentials`-crate,
@@ -89,17 +95,110 @@ pub struct AddGroupRequest {
     pub group_name: String,
 }
 
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+pub enum GroupVisibility {
+    /// Listed by `list_groups`, anyone can ask to join.
+    #[default]
+    Public,
+    /// Not listed by `list_groups`, anyone who knows the group can ask to join.
+    Unlisted,
+    /// Listed by `list_groups`, but members are added by the owner only, via `import_members`.
+    InviteOnly,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct GroupLogo {
+    pub content_type: String,
+    pub content: ByteBuf,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub enum GroupLogoUpdate {
+    Set(GroupLogo),
+    Remove,
+}
+
+/// Replaces the metadata of the caller's group.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct UpdateGroupRequest {
+    pub group_name: String,
+    pub description: Option<String>,
+    pub contact_url: Option<String>,
+    pub tags: Vec<String>,
+    pub visibility: GroupVisibility,
+    /// `None` keeps the current logo.
+    pub logo: Option<GroupLogoUpdate>,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct GroupMetadata {
+    pub description: Option<String>,
+    pub contact_url: Option<String>,
+    /// Path of the logo, served by `http_request`.
+    pub logo_url: Option<String>,
+    pub tags: Vec<String>,
+    pub visibility: GroupVisibility,
+}
+
+/// Attestation that an issuer, i.e. the owner of groups, is who their nickname claims,
+/// for credentials of the given type, cf. `set_issuer_verification`.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct IssuerVerificationData {
+    pub issuer: Principal,
+    pub credential_type: String,
+    pub verified_by: Principal,
+    pub verified_timestamp_ns: u64,
+    /// Whether the issuer may verify other issuers of the credential type.
+    pub trusted: bool,
+}
+
+/// Verifies an issuer for a credential type, or revokes the verification.
+/// Admins can verify any issuer, and designate trusted issuers, which can verify
+/// other issuers of the same credential type.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct SetIssuerVerificationRequest {
+    pub issuer: Principal,
+    pub credential_type: String,
+    pub verified: bool,
+    /// Can be set by admins only.
+    pub trusted: bool,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct GetIssuerVerificationRequest {
+    pub issuer: Principal,
+    pub credential_type: String,
+}
+
+/// Validity of accepted memberships, counted from the time of acceptance.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct MembershipValidity {
+    pub validity_period_ns: u64,
+    /// If set, expired members go back to `PendingReview` for re-verification,
+    /// otherwise they become `Expired`.
+    pub reverify_on_expiry: bool,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct SetMembershipValidityRequest {
+    pub group_name: String,
+    /// `None` means that memberships never expire.
+    pub validity: Option<MembershipValidity>,
+}
+
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
 pub struct JoinGroupRequest {
     pub group_name: String,
     pub owner: Principal,
     pub vc_arguments: Option<VcArguments>,
+    pub message: Option<String>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
 pub struct MembershipUpdate {
     pub member: Principal,
     pub new_status: MembershipStatus,
+    pub note: Option<String>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
@@ -119,6 +218,7 @@ pub enum MembershipStatus {
     PendingReview,
     Accepted,
     Rejected,
+    Expired,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
@@ -132,8 +232,12 @@ pub struct PublicGroupData {
     pub owner: Principal,
     pub issuer_nickname: String,
     pub stats: GroupStats,
+    pub metadata: GroupMetadata,
+    /// Verification of the owner for the credential type of the group, if any.
+    pub issuer_verification: Option<IssuerVerificationData>,
     pub membership_status: Option<MembershipStatus>,
     pub vc_arguments: Option<VcArguments>,
+    pub reviewer_note: Option<String>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
@@ -143,6 +247,11 @@ pub struct MemberData {
     pub joined_timestamp_ns: u64,
     pub membership_status: MembershipStatus,
     pub vc_arguments: Option<VcArguments>,
+    pub applicant_message: Option<String>,
+    pub reviewer_note: Option<String>,
+    pub evidence: Vec<EvidenceData>,
+    // Set only for accepted members of groups with a membership validity.
+    pub expires_timestamp_ns: Option<u64>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
@@ -151,6 +260,9 @@ pub struct FullGroupData {
     pub owner: Principal,
     pub issuer_nickname: String,
     pub stats: GroupStats,
+    pub metadata: GroupMetadata,
+    pub issuer_verification: Option<IssuerVerificationData>,
+    pub membership_validity: Option<MembershipValidity>,
     pub members: Vec<MemberData>,
 }
 
@@ -161,8 +273,11 @@ impl From<FullGroupData> for PublicGroupData {
             owner: full_data.owner,
             issuer_nickname: full_data.issuer_nickname,
             stats: full_data.stats,
+            metadata: full_data.metadata,
+            issuer_verification: full_data.issuer_verification,
             membership_status: None,
             vc_arguments: None,
+            reviewer_note: None,
         }
     }
 }
@@ -172,11 +287,297 @@ pub struct PublicGroupsData {
     pub groups: Vec<PublicGroupData>,
 }
 
+/// A membership of the caller, cf. `list_my_memberships`.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct MembershipData {
+    pub group_name: String,
+    pub owner: Principal,
+    pub issuer_nickname: String,
+    pub joined_timestamp_ns: u64,
+    pub membership_status: MembershipStatus,
+    pub vc_arguments: Option<VcArguments>,
+    pub reviewer_note: Option<String>,
+    // Set only for accepted members of groups with a membership validity.
+    pub expires_timestamp_ns: Option<u64>,
+    // The spec (including the `owner` argument) to request the credential of the group
+    // via `prepare_credential`, once the membership is accepted.
+    // Not set for groups without a credential type.
+    pub credential_spec: Option<CredentialSpec>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct MembershipsData {
+    pub memberships: Vec<MembershipData>,
+}
+
+/// Number of members of a group, by membership status.
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Eq, PartialEq)]
+pub struct MembershipCounts {
+    pub pending_review: u32,
+    pub accepted: u32,
+    pub rejected: u32,
+    pub expired: u32,
+}
+
+impl MembershipCounts {
+    fn count_mut(&mut self, status: &MembershipStatus) -> &mut u32 {
+        match status {
+            MembershipStatus::PendingReview => &mut self.pending_review,
+            MembershipStatus::Accepted => &mut self.accepted,
+            MembershipStatus::Rejected => &mut self.rejected,
+            MembershipStatus::Expired => &mut self.expired,
+        }
+    }
+
+    pub fn add(&mut self, status: &MembershipStatus) {
+        *self.count_mut(status) += 1;
+    }
+
+    pub fn remove(&mut self, status: &MembershipStatus) {
+        let count = self.count_mut(status);
+        *count = count.saturating_sub(1);
+    }
+
+    pub fn add_counts(&mut self, other: &MembershipCounts) {
+        self.pending_review += other.pending_review;
+        self.accepted += other.accepted;
+        self.rejected += other.rejected;
+        self.expired += other.expired;
+    }
+
+    pub fn by_status(&self) -> [(&'static str, u32); 4] {
+        [
+            ("PendingReview", self.pending_review),
+            ("Accepted", self.accepted),
+            ("Rejected", self.rejected),
+            ("Expired", self.expired),
+        ]
+    }
+}
+
+/// Number of pending membership requests of a group, by age in whole days.
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Eq, PartialEq)]
+pub struct PendingAgeDistribution {
+    pub under_1_day: u32,
+    pub under_7_days: u32,
+    pub under_30_days: u32,
+    pub over_30_days: u32,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct DailyCount {
+    pub day_start_timestamp_ns: u64,
+    pub count: u32,
+}
+
+/// Number of accepted members of a group with the given value of a VC argument.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ArgumentValueCount {
+    pub argument: String,
+    pub value: String,
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct GroupDashboardData {
+    pub group_name: String,
+    pub member_counts: MembershipCounts,
+    pub pending_age: PendingAgeDistribution,
+    pub credentials_issued: u64,
+    // Credentials issued per day during the last 30 days, for the days with
+    // issued credentials, oldest first.
+    pub credentials_issued_by_day: Vec<DailyCount>,
+    pub argument_values: Vec<ArgumentValueCount>,
+    pub last_activity_timestamp_ns: Option<u64>,
+}
+
+/// Statistics of the caller's groups, cf. `get_owner_dashboard`.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct OwnerDashboardData {
+    pub groups: Vec<GroupDashboardData>,
+    // Totals over all groups.
+    pub member_counts: MembershipCounts,
+    pub credentials_issued: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub enum NotificationKind {
+    /// A principal asked to join a group of the recipient.
+    MembershipRequested,
+    /// The owner of a group updated the membership of the recipient.
+    MembershipUpdated,
+}
+
+/// A notification in the outbox of the caller, cf. `get_notifications`.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct NotificationData {
+    /// Ids increase with every notification.
+    pub id: u64,
+    pub kind: NotificationKind,
+    pub timestamp_ns: u64,
+    pub group_name: String,
+    pub owner: Principal,
+    pub member: Principal,
+    pub membership_status: MembershipStatus,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct GetNotificationsRequest {
+    /// If set, only the notifications with a greater id are returned.
+    pub since: Option<u64>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct NotificationsData {
+    /// Oldest first.
+    pub notifications: Vec<NotificationData>,
+    pub webhook_url: Option<String>,
+}
+
+/// Removes the notifications of the caller up to and including the given id.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct AckNotificationsRequest {
+    pub up_to: u64,
+}
+
+/// Sets the HTTPS URL to which new notifications of the caller are posted,
+/// or removes it if not set.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct SetNotificationWebhookRequest {
+    pub url: Option<String>,
+}
+
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
 pub enum GroupsError {
     NotAuthorized(String),
     NotAuthenticated(String),
     AlreadyExists(String),
     NotFound(String),
+    InvalidArgument(String),
     Internal(String),
+    RateLimited(String),
+}
+
+/// Limits of the update calls open to anyone, which can be tuned by the controllers
+/// via `set_rate_limits`. Limits which are not set do not apply.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct RateLimits {
+    // Calls of `add_group`, `join_group`, `set_user` and `set_notification_webhook` per caller.
+    pub per_caller: Option<TokenBucketConfig>,
+    // Calls of `add_group`, `join_group`, `set_user` and `set_notification_webhook`
+    // by all callers together.
+    pub global: Option<TokenBucketConfig>,
+    pub max_groups_per_owner: Option<u32>,
+    pub max_pending_requests_per_group: Option<u32>,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            per_caller: Some(TokenBucketConfig {
+                capacity: 20,
+                refill_period_ns: 3_000_000_000,
+            }),
+            global: Some(TokenBucketConfig {
+                capacity: 1_000,
+                refill_period_ns: 10_000_000,
+            }),
+            max_groups_per_owner: Some(50),
+            max_pending_requests_per_group: Some(1_000),
+        }
+    }
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct EvidenceData {
+    pub evidence_id: u64,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u32,
+    /// Whether all chunks of the document have been uploaded.
+    pub complete: bool,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct AddEvidenceRequest {
+    pub group_name: String,
+    pub owner: Principal,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: u32,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct UploadEvidenceChunkRequest {
+    pub evidence_id: u64,
+    pub chunk_index: u32,
+    pub content: ByteBuf,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct GetEvidenceChunkRequest {
+    pub evidence_id: u64,
+    pub chunk_index: u32,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub enum MembersFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ExportMembersRequest {
+    pub group_name: String,
+    pub format: MembersFormat,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ImportMembersRequest {
+    pub group_name: String,
+    pub format: MembersFormat,
+    pub content: String,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ImportRowError {
+    /// 1-based number of the row, not counting the CSV header.
+    pub row: u32,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ImportMembersResult {
+    pub imported_count: u32,
+    pub errors: Vec<ImportRowError>,
+}
+
+// Types for batch issuance of credentials, which verifies the id alias only once
+// and returns a result per requested credential.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PrepareCredentialsRequest {
+    pub signed_id_alias: SignedIdAlias,
+    pub credential_specs: Vec<IssuerCredentialSpec>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PreparedCredentialsData {
+    pub results: Vec<Result<PreparedCredentialData, IssueCredentialError>>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct CredentialToGet {
+    pub credential_spec: IssuerCredentialSpec,
+    pub prepared_context: Option<ByteBuf>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct GetCredentialsRequest {
+    pub signed_id_alias: SignedIdAlias,
+    pub credentials: Vec<CredentialToGet>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct IssuedCredentialsData {
+    pub results: Vec<Result<IssuedCredentialData, IssueCredentialError>>,
 }
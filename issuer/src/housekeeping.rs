@@ -0,0 +1,87 @@
+//! Periodic housekeeping of the issuer, run by a canister timer.
+//!
+//! Every run executes all jobs in `Job::ALL`. A job goes through its items in batches of at
+//! most `HOUSEKEEPING_BATCH_SIZE` items per timer call (like `stable_schema::Migrator`), and
+//! continues after its cursor in a timer scheduled right away, until it has completed its pass.
+//! For each completed pass, the time and the number of items processed by the job are
+//! recorded, and reported in the metrics.
+use candid::{CandidType, Deserialize};
+
+pub const DEFAULT_HOUSEKEEPING_INTERVAL_S: u64 = 10 * 60;
+/// Max. number of items (signatures, members, groups) scanned by a job per timer call,
+/// to bound the instructions used by a call.
+pub const HOUSEKEEPING_BATCH_SIZE: usize = 500;
+pub const AUTO_REJECT_NOTE: &str = "Automatically rejected: the request was not reviewed in time.";
+
+/// Configuration of the housekeeping, part of the issuer config.
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct HousekeepingConfig {
+    /// Interval between housekeeping runs, in seconds.
+    pub interval_s: u64,
+    /// Age after which pending membership requests are rejected automatically.
+    /// If not set, pending requests are kept until the owner decides on them.
+    pub pending_review_max_age_ns: Option<u64>,
+}
+
+impl Default for HousekeepingConfig {
+    fn default() -> Self {
+        Self {
+            interval_s: DEFAULT_HOUSEKEEPING_INTERVAL_S,
+            pending_review_max_age_ns: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Job {
+    /// Removes expired canister signatures, and updates the certified data accordingly.
+    PruneSignatures,
+    /// Moves members whose membership has expired out of `Accepted`.
+    ExpireMemberships,
+    /// Rejects pending membership requests older than the configured max. age.
+    RejectStalePending,
+    /// Recomputes the per-group membership counts reported in the metrics.
+    CompactGroupStats,
+}
+
+impl Job {
+    pub const ALL: [Job; 4] = [
+        Job::PruneSignatures,
+        Job::ExpireMemberships,
+        Job::RejectStalePending,
+        Job::CompactGroupStats,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Job::PruneSignatures => "prune_signatures",
+            Job::ExpireMemberships => "expire_memberships",
+            Job::RejectStalePending => "reject_stale_pending",
+            Job::CompactGroupStats => "compact_group_stats",
+        }
+    }
+}
+
+/// Result of a batch of a job.
+pub struct JobBatch {
+    /// Cursor of the next batch, or `None` if the job has completed its pass.
+    pub cursor: Option<Vec<u8>>,
+    /// Number of items processed by the batch.
+    pub processed: u64,
+}
+
+/// Progress of a pass of a job which is not completed yet.
+#[derive(Clone, Debug, Default)]
+pub struct JobProgress {
+    pub cursor: Option<Vec<u8>>,
+    /// Number of items processed by the previous batches of the pass.
+    pub processed: u64,
+}
+
+/// Last completed pass of a job.
+#[derive(Clone, Debug)]
+pub struct JobRun {
+    pub timestamp_ns: u64,
+    /// Number of items (signatures, members, groups) processed by the pass.
+    pub processed: u64,
+}
@@ -0,0 +1,92 @@
+//! Verifications of issuers, cf. `set_issuer_verification`.
+//!
+//! Anyone can become an issuer by adding a group, so the nickname of an issuer does not prove
+//! anything. A verification attests that an issuer is who they claim to be, for credentials of
+//! one type. Admins verify issuers, and can make them trusted, so that they can verify other
+//! issuers of the same credential type. A verification made by a trusted issuer lapses once
+//! the verifier is no longer trusted, without having to revoke it.
+//!
+//! Credentials name their issuer principal (the group owner) in the `vc.issuerPrincipal`-claim,
+//! so that relying parties can check the verification of the issuer of a presented credential.
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::storable::{Bound, Storable};
+use meta_issuer::groups_api::IssuerVerificationData;
+use serde_json::{Map, Value};
+use stable_schema::VersionedRecord;
+use std::borrow::Cow;
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct IssuerVerificationKey {
+    pub issuer: Principal,
+    pub credential_type: String,
+}
+
+impl IssuerVerificationKey {
+    pub fn new(issuer: Principal, credential_type: &str) -> Self {
+        Self {
+            issuer,
+            credential_type: credential_type.to_string(),
+        }
+    }
+}
+
+impl Storable for IssuerVerificationKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode IssuerVerificationKey"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode IssuerVerificationKey")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct IssuerVerificationRecord {
+    pub verified_by: Principal,
+    /// Whether the verifier was an admin, rather than a trusted issuer.
+    pub verified_by_admin: bool,
+    pub verified_timestamp_ns: u64,
+    pub trusted: bool,
+}
+
+impl IssuerVerificationRecord {
+    pub fn to_data(&self, key: IssuerVerificationKey) -> IssuerVerificationData {
+        IssuerVerificationData {
+            issuer: key.issuer,
+            credential_type: key.credential_type,
+            verified_by: self.verified_by,
+            verified_timestamp_ns: self.verified_timestamp_ns,
+            trusted: self.trusted,
+        }
+    }
+}
+
+impl VersionedRecord for IssuerVerificationRecord {
+    const NAME: &'static str = "IssuerVerificationRecord";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for IssuerVerificationRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(stable_schema::encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        stable_schema::decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Adds the `issuerPrincipal`-entry to the `vc`-claim of the given JWT claims.
+pub fn add_issuer_principal(credential_jwt: &str, issuer: Principal) -> Result<String, String> {
+    let mut claims: Map<String, Value> = serde_json::from_str(credential_jwt)
+        .map_err(|e| format!("malformed credential JWT: {}", e))?;
+    claims
+        .get_mut("vc")
+        .and_then(Value::as_object_mut)
+        .ok_or("missing vc in credential JWT")?
+        .insert(
+            "issuerPrincipal".to_string(),
+            Value::String(issuer.to_text()),
+        );
+    serde_json::to_string(&claims).map_err(|e| format!("failed to encode credential JWT: {}", e))
+}
@@ -5,8 +5,10 @@ use ic_canister_sig_creation::signature_map::{CanisterSigInputs, SignatureMap, L
 use ic_canister_sig_creation::{
     extract_raw_root_pk_from_der, CanisterSigPublicKey, IC_ROOT_PK_DER,
 };
-use ic_cdk::api::{caller, set_certified_data, time};
-use ic_cdk_macros::{init, query, update};
+use ic_cdk::api::call::{accept_message, arg_data_raw_size, method_name};
+use ic_cdk::api::management_canister::main::raw_rand;
+use ic_cdk::api::{caller, is_controller, set_certified_data, time, trap};
+use ic_cdk_macros::{init, inspect_message, query, update};
 use ic_certification::{fork_hash, labeled_hash, pruned, Hash};
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
 use ic_stable_structures::storable::{Bound, Storable};
@@ -18,25 +20,82 @@ use ic_verifiable_credentials::issuer_api::{
     PrepareCredentialRequest, PreparedCredentialData, SignedIdAlias,
 };
 use ic_verifiable_credentials::{
-    build_credential_jwt, did_for_principal, get_verified_id_alias_from_jws, vc_jwt_to_jws,
-    vc_signing_input, AliasTuple, CredentialParams, VC_SIGNING_INPUT_DOMAIN,
+    build_credential_jwt, did_for_principal, get_verified_id_alias_from_jws, AliasTuple,
+    CredentialParams, VC_SIGNING_INPUT_DOMAIN,
 };
 use include_dir::{include_dir, Dir};
 use lazy_static::lazy_static;
 use meta_issuer::groups_api::{
-    AddGroupRequest, ArgumentValue as OrdArgumentValue, CredentialSpec as OrdCredentialSpec,
-    FullGroupData, GetGroupRequest, GroupStats, GroupType, GroupTypes, GroupsError,
-    JoinGroupRequest, ListGroupsRequest, MemberData, MembershipStatus, PublicGroupData,
-    PublicGroupsData, SetUserRequest, UpdateMembershipRequest, UserData, VcArguments,
+    AckNotificationsRequest, AddEvidenceRequest, AddGroupRequest,
+    ArgumentValue as OrdArgumentValue, CredentialSpec as OrdCredentialSpec, EvidenceData,
+    ExportMembersRequest, FullGroupData, GetCredentialsRequest, GetEvidenceChunkRequest,
+    GetGroupRequest, GetIssuerVerificationRequest, GetNotificationsRequest, GroupDashboardData,
+    GroupLogoUpdate, GroupMetadata, GroupStats, GroupType, GroupTypes, GroupVisibility,
+    GroupsError, ImportMembersRequest, ImportMembersResult, ImportRowError, IssuedCredentialsData,
+    IssuerVerificationData, JoinGroupRequest, ListGroupsRequest, MemberData, MembershipCounts,
+    MembershipData, MembershipStatus, MembershipUpdate, MembershipValidity, MembershipsData,
+    NotificationKind, NotificationsData, OwnerDashboardData, PrepareCredentialsRequest,
+    PreparedCredentialsData, PublicGroupData, PublicGroupsData, RateLimits,
+    SetIssuerVerificationRequest, SetMembershipValidityRequest, SetNotificationWebhookRequest,
+    SetUserRequest, UpdateGroupRequest, UpdateMembershipRequest, UploadEvidenceChunkRequest,
+    UserData, VcArguments,
 };
+use method_policy::{
+    check_call, check_resource_roles, MethodPolicy, PolicyViolation, ResourceRoles,
+};
+use rate_limit::RateLimiter;
 use serde_bytes::ByteBuf;
 use sha2::{Digest, Sha256};
+use stable_schema::{
+    rewrite_batch, Migration, MigrationBatch, MigrationState, Migrator, VersionedRecord,
+};
 use std::borrow::Cow;
 use std::cell::RefCell;
 
-use asset_util::{collect_assets, CertifiedAssets};
+use asset_util::{collect_assets, Asset, CertifiedAssets, ContentEncoding, ContentType};
+use credential_format::{CredentialFormat, PreparedCredential, CREDENTIAL_FORMAT_ARG};
+use credential_nonce::{add_credential_nonce, CREDENTIAL_NONCE_ARG};
+use dashboard::{DashboardKey, GroupDashboardRecord};
+use evidence::{
+    decrypt_chunk, encrypt_chunk, owner_key, EvidenceRecord, EvidenceState, EVIDENCE_CHUNK_SIZE,
+    MAX_EVIDENCE_FILE_NAME_LENGTH, MAX_EVIDENCE_PER_MEMBER, MAX_EVIDENCE_SIZE,
+};
+use group_metadata::{
+    logo_hash, parse_logo_path, validate_logo, validate_update, GroupMetadataRecord, LogoHash,
+    LogoRecord, MAX_LOGO_SIZE,
+};
+use housekeeping::{
+    HousekeepingConfig, Job, JobBatch, JobProgress, JobRun, AUTO_REJECT_NOTE,
+    HOUSEKEEPING_BATCH_SIZE,
+};
 use ic_cdk_macros::post_upgrade;
+use ic_cdk_timers::TimerId;
+use ic_metrics_encoder::MetricsEncoder;
+use issuer_verification::{add_issuer_principal, IssuerVerificationKey, IssuerVerificationRecord};
+use notifications::{
+    post_to_webhook, push_notification, recipient_notifications, validate_webhook_url,
+    HttpsOutcallSender, NotificationKey, NotificationRecord, NotificationState, WebhookRecord,
+    WebhookResponse, WebhookTransformArgs,
+};
+use status_list::{
+    add_credential_status, credential_status, status_list_credential, status_list_path, StatusList,
+};
 use std::collections::BTreeMap;
+use std::ops::Bound as RangeBound;
+use std::time::Duration;
+
+#[cfg(feature = "canbench-rs")]
+mod benches;
+mod credential_format;
+mod credential_nonce;
+mod dashboard;
+mod evidence;
+mod group_metadata;
+mod housekeeping;
+mod issuer_verification;
+mod members_io;
+mod notifications;
+mod status_list;
 
 #[cfg(target_arch = "wasm32")]
 use ic_cdk::println;
@@ -46,10 +105,83 @@ use ic_cdk::println;
 type Memory = RestrictedMemory<DefaultMemoryImpl>;
 type ConfigCell = StableCell<IssuerConfig, Memory>;
 type GroupsMap = StableBTreeMap<GroupKey, GroupRecord, VirtualMemory<Memory>>;
+type MembersMap = StableBTreeMap<MemberKey, MemberRecord, VirtualMemory<Memory>>;
+type MembershipsIndex = StableBTreeMap<MembershipKey, (), VirtualMemory<Memory>>;
+type DashboardsMap = StableBTreeMap<DashboardKey, GroupDashboardRecord, VirtualMemory<Memory>>;
+type GroupMetadataMap = StableBTreeMap<GroupKey, GroupMetadataRecord, VirtualMemory<Memory>>;
+type LogosMap = StableBTreeMap<LogoHash, LogoRecord, VirtualMemory<Memory>>;
+type IssuerVerificationsMap =
+    StableBTreeMap<IssuerVerificationKey, IssuerVerificationRecord, VirtualMemory<Memory>>;
+type NotificationsMap = StableBTreeMap<NotificationKey, NotificationRecord, VirtualMemory<Memory>>;
+type WebhooksMap = StableBTreeMap<Principal, WebhookRecord, VirtualMemory<Memory>>;
+type NotificationStateCell = StableCell<NotificationState, VirtualMemory<Memory>>;
 type UsersMap = StableBTreeMap<Principal, UserRecord, VirtualMemory<Memory>>;
+type StatusListsMap = StableBTreeMap<String, StatusList, VirtualMemory<Memory>>;
+type EvidenceMap = StableBTreeMap<u64, EvidenceRecord, VirtualMemory<Memory>>;
+type EvidenceChunksMap = StableBTreeMap<(u64, u32), Vec<u8>, VirtualMemory<Memory>>;
+type EvidenceStateCell = StableCell<EvidenceState, VirtualMemory<Memory>>;
+type MigrationStateCell = StableCell<MigrationState, VirtualMemory<Memory>>;
 
 const GROUPS_MEMORY_ID: MemoryId = MemoryId::new(0u8);
 const USERS_MEMORY_ID: MemoryId = MemoryId::new(1u8);
+const STATUS_LISTS_MEMORY_ID: MemoryId = MemoryId::new(2u8);
+const EVIDENCE_MEMORY_ID: MemoryId = MemoryId::new(3u8);
+const EVIDENCE_CHUNKS_MEMORY_ID: MemoryId = MemoryId::new(4u8);
+const EVIDENCE_STATE_MEMORY_ID: MemoryId = MemoryId::new(5u8);
+const MIGRATION_STATE_MEMORY_ID: MemoryId = MemoryId::new(6u8);
+const MEMBERS_MEMORY_ID: MemoryId = MemoryId::new(7u8);
+const MEMBERSHIPS_MEMORY_ID: MemoryId = MemoryId::new(8u8);
+const DASHBOARDS_MEMORY_ID: MemoryId = MemoryId::new(9u8);
+const GROUP_METADATA_MEMORY_ID: MemoryId = MemoryId::new(10u8);
+const LOGOS_MEMORY_ID: MemoryId = MemoryId::new(11u8);
+const ISSUER_VERIFICATIONS_MEMORY_ID: MemoryId = MemoryId::new(12u8);
+const NOTIFICATIONS_MEMORY_ID: MemoryId = MemoryId::new(13u8);
+const WEBHOOKS_MEMORY_ID: MemoryId = MemoryId::new(14u8);
+const NOTIFICATION_STATE_MEMORY_ID: MemoryId = MemoryId::new(15u8);
+
+// Migrations of the stable data, run after upgrades (cf. `stable_schema`).
+// New migrations must be appended.
+static MIGRATIONS: [Migration; 7] = [
+    Migration {
+        name: "versioned_config",
+        run_batch: migrate_config,
+    },
+    Migration {
+        name: "versioned_groups",
+        run_batch: migrate_groups,
+    },
+    Migration {
+        name: "versioned_users",
+        run_batch: migrate_users,
+    },
+    Migration {
+        name: "split_group_members",
+        run_batch: split_group_members,
+    },
+    Migration {
+        name: "index_memberships",
+        run_batch: index_memberships,
+    },
+    Migration {
+        name: "reset_group_dashboards",
+        run_batch: reset_group_dashboards,
+    },
+    Migration {
+        name: "count_dashboard_members",
+        run_batch: count_dashboard_members,
+    },
+    Migration {
+        name: "assign_status_list_indices",
+        run_batch: assign_status_list_indices,
+    },
+];
+static MIGRATOR: Migrator = Migrator {
+    migrations: &MIGRATIONS,
+    load_state: load_migration_state,
+    store_state: store_migration_state,
+    batch_size: 100,
+    batches_per_call: 10,
+};
 
 const ISSUER_URL: &str = "https://metaissuer.vc";
 const CREDENTIAL_URL_PREFIX: &str = "data:text/plain;charset=UTF-8,";
@@ -58,15 +190,76 @@ const MINUTE_NS: u64 = 60 * 1_000_000_000;
 const PROD_II_CANISTER_ID: &str = "rdmx6-jaaaa-aaaaa-aaadq-cai";
 // The expiration of issued verifiable credentials.
 const VC_EXPIRATION_PERIOD_NS: u64 = 15 * MINUTE_NS;
+// Max. length (in bytes) of applicants' messages and reviewers' notes.
+const MAX_APPLICANT_MESSAGE_LENGTH: usize = 500;
+const MAX_REVIEWER_NOTE_LENGTH: usize = 500;
+// Max. number of members in an `import_members`-request.
+const MAX_IMPORTED_MEMBERS: usize = 1_000;
+// Max. number of credentials in a `prepare_credentials`/`get_credentials`-request.
+const MAX_CREDENTIALS_PER_BATCH: usize = 10;
+
+/// Policies of all methods of the issuer, checked by `inspect_message` for ingress messages
+/// and by the endpoints via `authorize`/`authorize_for_group`. The owner- and member-roles
+/// refer to the group addressed by a call, and controllers act as admins. The endpoints
+/// of the VC-spec are public, as their callers are authenticated via the id alias.
+const METHOD_POLICIES: &[MethodPolicy] = &[
+    MethodPolicy::authenticated("get_user"),
+    MethodPolicy::authenticated("set_user"),
+    MethodPolicy::public("group_types"),
+    MethodPolicy::public("list_groups"),
+    MethodPolicy::owner("get_group"),
+    MethodPolicy::authenticated("add_group"),
+    MethodPolicy::authenticated("join_group"),
+    MethodPolicy::authenticated("list_my_memberships"),
+    MethodPolicy::authenticated("get_owner_dashboard"),
+    MethodPolicy::owner("update_membership").with_max_arg_bytes(256 * 1024),
+    MethodPolicy::owner("set_membership_validity"),
+    MethodPolicy::owner("update_group").with_max_arg_bytes(MAX_LOGO_SIZE + 8 * 1024),
+    MethodPolicy::owner("export_members"),
+    MethodPolicy::owner("import_members").with_max_arg_bytes(512 * 1024),
+    MethodPolicy::member("add_evidence"),
+    MethodPolicy::authenticated("upload_evidence_chunk")
+        .with_max_arg_bytes(EVIDENCE_CHUNK_SIZE as usize + 4 * 1024),
+    MethodPolicy::owner("get_evidence_chunk"),
+    MethodPolicy::controller("configure").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::controller("set_rate_limits"),
+    MethodPolicy::public("get_rate_limits"),
+    MethodPolicy::authenticated("set_issuer_verification"),
+    MethodPolicy::public("get_issuer_verification"),
+    MethodPolicy::authenticated("get_notifications"),
+    MethodPolicy::authenticated("ack_notifications"),
+    MethodPolicy::authenticated("set_notification_webhook"),
+    MethodPolicy::public("transform_webhook_response").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::public("prepare_credential").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::public("prepare_credentials").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::public("get_credential").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::public("get_credentials").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::public("vc_consent_message"),
+    MethodPolicy::public("derivation_origin"),
+    MethodPolicy::public("http_request").with_max_arg_bytes(64 * 1024),
+];
 
 // VerifiedAge-credentials need special handling.
 const VERIFIED_AGE_CREDENTIAL_TYPE: &str = "VerifiedAge";
 
-// Internal container of per-group data.
+// Internal container of per-group data. The members of the group are stored in MEMBERS.
 #[derive(CandidType, Clone, Deserialize)]
 struct GroupRecord {
     pub created_timestamp_ns: u64,
-    pub members: BTreeMap<Principal, MemberRecord>,
+    pub membership_validity: Option<MembershipValidity>,
+    // Number of members of the group, i.e. of its entries in MEMBERS.
+    pub member_count: u32,
+    // Members stored within the group by version 1, until they are moved to MEMBERS
+    // (cf. `load_group`).
+    pub legacy_members: Option<BTreeMap<Principal, MemberRecord>>,
+}
+
+// Version 1 of GroupRecord, which contained the members of the group.
+#[derive(CandidType, Deserialize)]
+struct GroupRecordV1 {
+    created_timestamp_ns: u64,
+    members: BTreeMap<Principal, MemberRecord>,
+    membership_validity: Option<MembershipValidity>,
 }
 
 // Tuple that identifies any group.  Note that using owner's principal in a real-world
@@ -77,11 +270,81 @@ struct GroupKey {
     owner: Principal,
 }
 
+// Key of the members of all groups. The members of a group form a contiguous range,
+// ordered by their principals.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+struct MemberKey {
+    group: GroupKey,
+    member: Principal,
+}
+
+impl MemberKey {
+    fn new(group: GroupKey, member: Principal) -> Self {
+        Self { group, member }
+    }
+}
+
+// Key of the index of the groups by member, i.e. the reverse of MemberKey.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+struct MembershipKey {
+    member: Principal,
+    group: GroupKey,
+}
+
+impl From<&GroupKey> for DashboardKey {
+    fn from(key: &GroupKey) -> Self {
+        Self {
+            owner: key.owner,
+            group_name: key.group_name.clone(),
+        }
+    }
+}
+
+impl From<&MemberKey> for MembershipKey {
+    fn from(key: &MemberKey) -> Self {
+        Self {
+            member: key.member,
+            group: key.group.clone(),
+        }
+    }
+}
+
 #[derive(CandidType, Clone, Deserialize)]
 struct MemberRecord {
     joined_timestamp_ns: u64,
     membership_status: MembershipStatus,
     vc_arguments: Option<VcArguments>,
+    // Index in the status list of the group type, assigned while the member is accepted.
+    status_list_index: Option<u32>,
+    applicant_message: Option<String>,
+    reviewer_note: Option<String>,
+    // Evidence attached to the pending membership request.
+    evidence_ids: Option<Vec<u64>>,
+    // Time of the (last) acceptance, from which the membership validity is counted.
+    // Not set for members accepted before memberships could expire, for which
+    // the join time is used instead.
+    accepted_timestamp_ns: Option<u64>,
+}
+
+impl MemberRecord {
+    /// Returns the expiry time of the membership, if the member is accepted
+    /// and memberships have the given validity.
+    fn expires_timestamp_ns(&self, validity: Option<&MembershipValidity>) -> Option<u64> {
+        let validity = validity?;
+        if self.membership_status != MembershipStatus::Accepted {
+            return None;
+        }
+        Some(
+            self.accepted_timestamp_ns
+                .unwrap_or(self.joined_timestamp_ns)
+                .saturating_add(validity.validity_period_ns),
+        )
+    }
+
+    fn is_expired(&self, validity: Option<&MembershipValidity>, now: u64) -> bool {
+        self.expires_timestamp_ns(validity)
+            .is_some_and(|expires_timestamp_ns| expires_timestamp_ns <= now)
+    }
 }
 
 #[derive(CandidType, Clone, Deserialize)]
@@ -109,22 +372,78 @@ impl Storable for GroupKey {
     const BOUND: Bound = Bound::Unbounded;
 }
 
+impl VersionedRecord for UserRecord {
+    const NAME: &'static str = "UserRecord";
+    const VERSION: u16 = 1;
+}
+
 impl Storable for UserRecord {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).expect("failed to encode GroupRecord"))
+        Cow::Owned(stable_schema::encode(self))
     }
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).expect("failed to decode GroupRecord")
+        stable_schema::decode(&bytes)
     }
     const BOUND: Bound = Bound::Unbounded;
 }
 
+impl VersionedRecord for GroupRecord {
+    const NAME: &'static str = "GroupRecord";
+    const VERSION: u16 = 2;
+
+    fn decode_older(version: u16, payload: &[u8]) -> Result<Self, String> {
+        let record: GroupRecordV1 =
+            candid::decode_one(payload).map_err(|e| format!("version {}: {}", version, e))?;
+        Ok(GroupRecord {
+            created_timestamp_ns: record.created_timestamp_ns,
+            membership_validity: record.membership_validity,
+            member_count: record.members.len() as u32,
+            legacy_members: Some(record.members),
+        })
+    }
+}
+
 impl Storable for GroupRecord {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).expect("failed to encode GroupRecord"))
+        Cow::Owned(stable_schema::encode(self))
     }
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).expect("failed to decode GroupRecord")
+        stable_schema::decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for MemberKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode MemberKey"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode MemberKey")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for MembershipKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode MembershipKey"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode MembershipKey")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for MemberRecord {
+    const NAME: &'static str = "MemberRecord";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for MemberRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(stable_schema::encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        stable_schema::decode(&bytes)
     }
     const BOUND: Bound = Bound::Unbounded;
 }
@@ -140,16 +459,94 @@ thread_local! {
       StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(GROUPS_MEMORY_ID)),
     ));
+    // Members of all groups, cf. MemberKey.
+    static MEMBERS : RefCell<MembersMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MEMBERS_MEMORY_ID)),
+    ));
+    // Index of the groups by member, cf. MembershipKey.
+    static MEMBERSHIPS : RefCell<MembershipsIndex> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MEMBERSHIPS_MEMORY_ID)),
+    ));
+    // Statistics of the groups for the owner dashboard, cf. `dashboard`.
+    static DASHBOARDS : RefCell<DashboardsMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(DASHBOARDS_MEMORY_ID)),
+    ));
+    // Metadata of the groups set by their owners, and the logos, cf. `group_metadata`.
+    static GROUP_METADATA : RefCell<GroupMetadataMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(GROUP_METADATA_MEMORY_ID)),
+    ));
+    static LOGOS : RefCell<LogosMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(LOGOS_MEMORY_ID)),
+    ));
+    // Verifications of issuers by credential type, cf. `issuer_verification`.
+    static ISSUER_VERIFICATIONS : RefCell<IssuerVerificationsMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(ISSUER_VERIFICATIONS_MEMORY_ID)),
+    ));
+    // Outboxes of notifications by recipient, and their webhooks, cf. `notifications`.
+    static NOTIFICATIONS : RefCell<NotificationsMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(NOTIFICATIONS_MEMORY_ID)),
+    ));
+    static WEBHOOKS : RefCell<WebhooksMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(WEBHOOKS_MEMORY_ID)),
+    ));
+    static NOTIFICATION_STATE : RefCell<NotificationStateCell> = RefCell::new(
+      StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(NOTIFICATION_STATE_MEMORY_ID)),
+            NotificationState::default(),
+    ).expect("failed to initialize stable cell"));
     static USERS : RefCell<UsersMap> = RefCell::new(
       StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(USERS_MEMORY_ID)),
     ));
+    // Revocation status lists, indexed by credential type.
+    static STATUS_LISTS : RefCell<StatusListsMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(STATUS_LISTS_MEMORY_ID)),
+    ));
+    // Evidence attached to membership requests: metadata, and encrypted chunks.
+    static EVIDENCE : RefCell<EvidenceMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(EVIDENCE_MEMORY_ID)),
+    ));
+    static EVIDENCE_CHUNKS : RefCell<EvidenceChunksMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(EVIDENCE_CHUNKS_MEMORY_ID)),
+    ));
+    static EVIDENCE_STATE : RefCell<EvidenceStateCell> = RefCell::new(
+      StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(EVIDENCE_STATE_MEMORY_ID)),
+            EvidenceState::default(),
+    ).expect("failed to initialize stable cell"));
+    // Progress of the migrations of the stable data.
+    static MIGRATION_STATE : RefCell<MigrationStateCell> = RefCell::new(
+      StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MIGRATION_STATE_MEMORY_ID)),
+            MigrationState::default(),
+    ).expect("failed to initialize stable cell"));
 
     /// Non-stable structures
     // Canister signatures
     static SIGNATURES : RefCell<SignatureMap> = RefCell::new(SignatureMap::default());
     // Assets for the management app
     static ASSETS: RefCell<CertifiedAssets> = RefCell::new(CertifiedAssets::default());
+    // Housekeeping: the running timer, the progress of the jobs which have not completed their
+    // pass, the last completed pass of each job, and the membership counts per group (as of
+    // the last pass of `Job::CompactGroupStats`, and of the pass in progress).
+    static HOUSEKEEPING_TIMER: RefCell<Option<TimerId>> = RefCell::new(None);
+    static HOUSEKEEPING_PROGRESS: RefCell<BTreeMap<Job, JobProgress>> = RefCell::new(BTreeMap::new());
+    static HOUSEKEEPING_RUNS: RefCell<BTreeMap<Job, JobRun>> = RefCell::new(BTreeMap::new());
+    static GROUP_STATS: RefCell<BTreeMap<GroupKey, MembershipCounts>> = RefCell::new(BTreeMap::new());
+    static NEXT_GROUP_STATS: RefCell<BTreeMap<GroupKey, MembershipCounts>> = RefCell::new(BTreeMap::new());
+    // Token buckets of the rate-limited update calls, cf. `check_rate_limit`.
+    static RATE_LIMITER: RefCell<RateLimiter> = RefCell::new(RateLimiter::default());
 }
 
 lazy_static! {
@@ -219,7 +616,7 @@ lazy_static! {
     static ref CREDENTIAL_SPEC_FOR_GROUP_NAME: BTreeMap<String, OrdCredentialSpec> = {
         let mut map = BTreeMap::new();
         for group_type in GROUP_TYPES.iter() {
-            map.insert(group_type.credential_spec.credential_type.clone(), group_type.credential_spec.clone());
+            map.insert(group_type.group_name.clone(), group_type.credential_spec.clone());
         }
         map
     };
@@ -238,7 +635,7 @@ fn managed_memory() -> Memory {
     )
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(CandidType, Clone, Deserialize)]
 struct IssuerConfig {
     /// Root of trust for checking canister signatures.
     ic_root_key_raw: Vec<u8>,
@@ -246,14 +643,23 @@ struct IssuerConfig {
     idp_canister_ids: Vec<Principal>,
     /// The derivation origin to be used by the issuer.
     derivation_origin: String,
+    /// Configuration of the periodic housekeeping, defaults apply if not set.
+    housekeeping: Option<HousekeepingConfig>,
+    /// Limits of the update calls open to anyone, defaults apply if not set.
+    rate_limits: Option<RateLimits>,
+}
+
+impl VersionedRecord for IssuerConfig {
+    const NAME: &'static str = "IssuerConfig";
+    const VERSION: u16 = 1;
 }
 
 impl Storable for IssuerConfig {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).expect("failed to encode IssuerConfig"))
+        Cow::Owned(stable_schema::encode(self))
     }
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).expect("failed to decode IssuerConfig")
+        stable_schema::decode(&bytes)
     }
     const BOUND: Bound = Bound::Unbounded;
 }
@@ -266,6 +672,8 @@ impl Default for IssuerConfig {
                 .expect("failed to extract raw root pk from der"),
             idp_canister_ids: vec![Principal::from_text(PROD_II_CANISTER_ID).unwrap()],
             derivation_origin: derivation_origin.clone(),
+            housekeeping: None,
+            rate_limits: None,
         }
     }
 }
@@ -277,6 +685,8 @@ impl From<IssuerInit> for IssuerConfig {
                 .expect("failed to extract raw root pk from der"),
             idp_canister_ids: init.idp_canister_ids,
             derivation_origin: init.derivation_origin,
+            housekeeping: init.housekeeping,
+            rate_limits: init.rate_limits,
         }
     }
 }
@@ -289,38 +699,432 @@ struct IssuerInit {
     idp_canister_ids: Vec<Principal>,
     /// The derivation origin to be used by the issuer.
     derivation_origin: String,
+    /// Configuration of the periodic housekeeping, defaults apply if not set.
+    housekeeping: Option<HousekeepingConfig>,
+    /// Limits of the update calls open to anyone, defaults apply if not set.
+    rate_limits: Option<RateLimits>,
+}
+
+/// Rejects ingress messages which violate `METHOD_POLICIES` before they are executed.
+#[inspect_message]
+fn inspect_message() {
+    let method = method_name();
+    match check_method_policy(&method) {
+        Ok(()) => accept_message(),
+        Err(violation) => trap(&format!("{}: {}", method, violation)),
+    }
 }
 
-fn check_authenticated() -> Result<(), GroupsError> {
-    if caller() == Principal::anonymous() {
-        Err(GroupsError::NotAuthenticated(
-            "anonymous caller not permitted".to_string(),
-        ))
-    } else {
-        Ok(())
+/// Checks the current call of `method` against its policy in `METHOD_POLICIES`,
+/// except for the roles in the addressed group.
+fn check_method_policy(method: &str) -> Result<(), PolicyViolation> {
+    check_call(
+        METHOD_POLICIES,
+        method,
+        caller(),
+        is_controller(&caller()),
+        arg_data_raw_size(),
+    )
+}
+
+/// Authorizes the current call of `method`, which does not address a group.
+fn authorize(method: &str) -> Result<(), GroupsError> {
+    check_method_policy(method).map_err(|violation| match violation {
+        PolicyViolation::AnonymousCaller => GroupsError::NotAuthenticated(violation.to_string()),
+        PolicyViolation::NotController(_)
+        | PolicyViolation::NotOwner(_)
+        | PolicyViolation::NotMember(_) => GroupsError::NotAuthorized(violation.to_string()),
+        PolicyViolation::ArgumentTooLarge { .. } => {
+            GroupsError::InvalidArgument(violation.to_string())
+        }
+        PolicyViolation::UnknownMethod(_) => GroupsError::Internal(violation.to_string()),
+    })
+}
+
+/// Authorizes the current call of `method`, which addresses the group `key`, and returns
+/// the group. Groups in which the caller does not have the required role are reported as
+/// not found, so that the call does not reveal anything about them.
+fn authorize_for_group(method: &str, key: &GroupKey) -> Result<GroupRecord, GroupsError> {
+    authorize(method)?;
+    let not_found =
+        || GroupsError::NotFound(format!("group: {}, owner: {}", key.group_name, key.owner));
+    let group_record = load_group(key).ok_or_else(not_found)?;
+    let roles = ResourceRoles {
+        is_owner: key.owner == caller(),
+        is_member: find_member(key, &group_record, caller()).is_some(),
+    };
+    check_resource_roles(METHOD_POLICIES, method, caller(), roles).map_err(|_| not_found())?;
+    Ok(group_record)
+}
+
+/// Authorizes the current call of `method`, which cannot return an error.
+fn authorize_or_trap(method: &str) {
+    if let Err(violation) = check_method_policy(method) {
+        trap(&format!("{}: {}", method, violation));
     }
 }
 
+fn rate_limits() -> RateLimits {
+    CONFIG.with_borrow(|config| config.get().rate_limits.clone().unwrap_or_default())
+}
+
+/// Takes a token for an update call of the caller, cf. `RateLimits`.
+fn check_rate_limit(limits: &RateLimits) -> Result<(), GroupsError> {
+    RATE_LIMITER
+        .with_borrow_mut(|limiter| {
+            limiter.check(
+                caller(),
+                limits.per_caller.as_ref(),
+                limits.global.as_ref(),
+                time(),
+            )
+        })
+        .map_err(GroupsError::RateLimited)
+}
+
 #[init]
 #[candid_method(init)]
 fn init(init_arg: Option<IssuerInit>) {
+    // A fresh install stores all records with their current versions.
+    store_migration_state(MigrationState::completed(&MIGRATIONS));
+    initialize(init_arg);
+}
+
+#[post_upgrade]
+fn post_upgrade(init_arg: Option<IssuerInit>) {
+    initialize(init_arg);
+    MIGRATOR.run();
+}
+
+fn initialize(init_arg: Option<IssuerInit>) {
     if let Some(init) = init_arg {
         apply_config(init);
     };
 
     init_assets();
+    start_housekeeping();
 }
 
-#[post_upgrade]
-fn post_upgrade(init_arg: Option<IssuerInit>) {
-    init(init_arg);
+fn load_migration_state() -> MigrationState {
+    MIGRATION_STATE.with_borrow(|state| state.get().clone())
+}
+
+fn store_migration_state(state: MigrationState) {
+    MIGRATION_STATE
+        .with_borrow_mut(|cell| cell.set(state))
+        .expect("failed to store migration state");
+}
+
+fn migrate_config(_cursor: Option<Vec<u8>>, _batch_size: usize) -> MigrationBatch {
+    CONFIG.with_borrow_mut(|config_cell| {
+        let config = config_cell.get().clone();
+        config_cell
+            .set(config)
+            .expect("failed to rewrite issuer config");
+    });
+    MigrationBatch {
+        cursor: None,
+        migrated_count: 1,
+    }
+}
+
+fn migrate_groups(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    GROUPS.with_borrow_mut(|groups| rewrite_batch(groups, cursor, batch_size))
+}
+
+fn migrate_users(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    USERS.with_borrow_mut(|users| rewrite_batch(users, cursor, batch_size))
+}
+
+fn split_group_members(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    let start = match cursor {
+        Some(cursor) => RangeBound::Excluded(GroupKey::from_bytes(Cow::Owned(cursor))),
+        None => RangeBound::Unbounded,
+    };
+    let keys: Vec<GroupKey> = GROUPS.with_borrow(|groups| {
+        groups
+            .range((start, RangeBound::Unbounded))
+            .take(batch_size)
+            .map(|(key, _)| key)
+            .collect()
+    });
+    for key in &keys {
+        load_group(key);
+    }
+    MigrationBatch {
+        cursor: if keys.len() < batch_size {
+            None
+        } else {
+            keys.last().map(|key| key.to_bytes().into_owned())
+        },
+        migrated_count: keys.len() as u64,
+    }
+}
+
+fn index_memberships(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    let start = match cursor {
+        Some(cursor) => RangeBound::Excluded(MemberKey::from_bytes(Cow::Owned(cursor))),
+        None => RangeBound::Unbounded,
+    };
+    let keys: Vec<MemberKey> = MEMBERS.with_borrow(|members| {
+        members
+            .range((start, RangeBound::Unbounded))
+            .take(batch_size)
+            .map(|(key, _)| key)
+            .collect()
+    });
+    MEMBERSHIPS.with_borrow_mut(|memberships| {
+        for key in &keys {
+            memberships.insert(MembershipKey::from(key), ());
+        }
+    });
+    MigrationBatch {
+        cursor: if keys.len() < batch_size {
+            None
+        } else {
+            keys.last().map(|key| key.to_bytes().into_owned())
+        },
+        migrated_count: keys.len() as u64,
+    }
+}
+
+// The dashboards are built from scratch in two steps: `reset_group_dashboards` creates an
+// empty dashboard for every group, and `count_dashboard_members` adds the existing members.
+// Until a member has been counted, changes of the member are not applied to the dashboard
+// (cf. `is_dashboard_member_counted`), as they would otherwise be counted twice.
+fn reset_group_dashboards(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    let start = match cursor {
+        Some(cursor) => RangeBound::Excluded(GroupKey::from_bytes(Cow::Owned(cursor))),
+        None => RangeBound::Unbounded,
+    };
+    let groups: Vec<(GroupKey, u64)> = GROUPS.with_borrow(|groups| {
+        groups
+            .range((start, RangeBound::Unbounded))
+            .take(batch_size)
+            .map(|(key, group_record)| (key, group_record.created_timestamp_ns))
+            .collect()
+    });
+    DASHBOARDS.with_borrow_mut(|dashboards| {
+        for (key, created_timestamp_ns) in &groups {
+            let mut dashboard = GroupDashboardRecord::default();
+            dashboard.record_activity(*created_timestamp_ns);
+            dashboards.insert(DashboardKey::from(key), dashboard);
+        }
+    });
+    MigrationBatch {
+        cursor: if groups.len() < batch_size {
+            None
+        } else {
+            groups.last().map(|(key, _)| key.to_bytes().into_owned())
+        },
+        migrated_count: groups.len() as u64,
+    }
+}
+
+fn count_dashboard_members(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    let start = match cursor {
+        Some(cursor) => RangeBound::Excluded(MemberKey::from_bytes(Cow::Owned(cursor))),
+        None => RangeBound::Unbounded,
+    };
+    let members: Vec<(MemberKey, MemberRecord)> = MEMBERS.with_borrow(|members| {
+        members
+            .range((start, RangeBound::Unbounded))
+            .take(batch_size)
+            .collect()
+    });
+    for (key, member_record) in &members {
+        update_dashboard(&key.group, |dashboard| {
+            dashboard.add_member(
+                &member_record.membership_status,
+                member_record.joined_timestamp_ns,
+                &member_record.vc_arguments,
+            )
+        });
+    }
+    MigrationBatch {
+        cursor: if members.len() < batch_size {
+            None
+        } else {
+            members.last().map(|(key, _)| key.to_bytes().into_owned())
+        },
+        migrated_count: members.len() as u64,
+    }
+}
+
+// Members accepted before status lists were introduced get their index in the status list
+// of the group type here, as indices are otherwise assigned only when members are accepted.
+fn assign_status_list_indices(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    let members = members_batch(cursor, batch_size);
+    for (key, member_record) in &members {
+        if member_record.membership_status != MembershipStatus::Accepted
+            || member_record.status_list_index.is_some()
+        {
+            continue;
+        }
+        let Some(credential_type) = credential_type_for_group(&key.group.group_name) else {
+            continue;
+        };
+        match allocate_status_list_index(&credential_type) {
+            Ok(index) => {
+                let mut member_record = member_record.clone();
+                member_record.status_list_index = Some(index);
+                MEMBERS.with_borrow_mut(|members| members.insert(key.clone(), member_record));
+            }
+            Err(err) => println!("Failed to assign a status list index to {:?}: {}", key, err),
+        }
+    }
+    MigrationBatch {
+        cursor: members_cursor(&members, batch_size),
+        migrated_count: members.len() as u64,
+    }
+}
+
+/// Returns the group with the given key. Members stored within the group by a previous
+/// version are moved to MEMBERS first, so that the members of the returned group
+/// can be accessed via `find_member`, `group_members` and `insert_member`.
+fn load_group(key: &GroupKey) -> Option<GroupRecord> {
+    let mut group_record = GROUPS.with_borrow(|groups| groups.get(key))?;
+    if let Some(legacy_members) = group_record.legacy_members.take() {
+        for (member, member_record) in legacy_members {
+            let member_key = MemberKey::new(key.clone(), member);
+            MEMBERSHIPS.with_borrow_mut(|memberships| {
+                memberships.insert(MembershipKey::from(&member_key), ())
+            });
+            MEMBERS.with_borrow_mut(|members| members.insert(member_key, member_record));
+        }
+        GROUPS.with_borrow_mut(|groups| groups.insert(key.clone(), group_record.clone()));
+    }
+    Some(group_record)
+}
+
+/// Returns the member of the group, if any.
+fn find_member(
+    key: &GroupKey,
+    group_record: &GroupRecord,
+    member: Principal,
+) -> Option<MemberRecord> {
+    match &group_record.legacy_members {
+        Some(legacy_members) => legacy_members.get(&member).cloned(),
+        None => MEMBERS.with_borrow(|members| members.get(&MemberKey::new(key.clone(), member))),
+    }
+}
+
+/// Returns the members of the group, ordered by their principals.
+fn group_members<'a>(
+    members: &'a MembersMap,
+    key: &GroupKey,
+) -> impl Iterator<Item = (Principal, MemberRecord)> + 'a {
+    let group = key.clone();
+    members
+        .range(MemberKey::new(key.clone(), Principal::management_canister())..)
+        .take_while(move |(member_key, _)| member_key.group == group)
+        .map(|(member_key, member_record)| (member_key.member, member_record))
+}
+
+/// Returns the groups the given principal is a member of.
+fn member_groups(memberships: &MembershipsIndex, member: Principal) -> Vec<GroupKey> {
+    let first_group = GroupKey {
+        group_name: String::new(),
+        owner: Principal::management_canister(),
+    };
+    memberships
+        .range(
+            MembershipKey {
+                member,
+                group: first_group,
+            }..,
+        )
+        .take_while(|(key, _)| key.member == member)
+        .map(|(key, _)| key.group)
+        .collect()
+}
+
+/// Returns the dashboards of the groups of the given owner, one per group.
+fn owner_dashboards(
+    dashboards: &DashboardsMap,
+    owner: Principal,
+) -> impl Iterator<Item = (DashboardKey, GroupDashboardRecord)> + '_ {
+    dashboards
+        .range(DashboardKey::first_of_owner(owner)..)
+        .take_while(move |(key, _)| key.owner == owner)
+}
+
+/// Applies the given change to the dashboard of the group.
+fn update_dashboard(key: &GroupKey, change: impl FnOnce(&mut GroupDashboardRecord)) {
+    DASHBOARDS.with_borrow_mut(|dashboards| {
+        let dashboard_key = DashboardKey::from(key);
+        let mut dashboard = dashboards.get(&dashboard_key).unwrap_or_default();
+        change(&mut dashboard);
+        dashboards.insert(dashboard_key, dashboard);
+    });
+}
+
+/// Returns whether the member is contained in the dashboard of its group, i.e. whether
+/// `count_dashboard_members` has already visited it, or has completed.
+fn is_dashboard_member_counted(key: &MemberKey) -> bool {
+    let counting = MIGRATIONS
+        .iter()
+        .position(|migration| migration.name == "count_dashboard_members")
+        .expect("missing dashboard migration") as u32;
+    let state = load_migration_state();
+    if state.completed != counting {
+        return state.completed > counting;
+    }
+    state
+        .cursor
+        .is_some_and(|cursor| *key <= MemberKey::from_bytes(Cow::Owned(cursor.into_vec())))
+}
+
+/// Updates the dashboard of the group for a member record replacing `previous`.
+fn update_dashboard_members(
+    key: &MemberKey,
+    previous: Option<&MemberRecord>,
+    member_record: &MemberRecord,
+) {
+    if !is_dashboard_member_counted(key) {
+        return;
+    }
+    update_dashboard(&key.group, |dashboard| {
+        if let Some(previous) = previous {
+            dashboard.remove_member(
+                &previous.membership_status,
+                previous.joined_timestamp_ns,
+                &previous.vc_arguments,
+            );
+        }
+        dashboard.add_member(
+            &member_record.membership_status,
+            member_record.joined_timestamp_ns,
+            &member_record.vc_arguments,
+        );
+    });
+}
+
+/// Inserts or replaces a member of a group loaded via `load_group`, and updates the
+/// member count and the dashboard of the group, and the index of the groups by member.
+fn insert_member(key: &GroupKey, member: Principal, member_record: MemberRecord) {
+    let member_key = MemberKey::new(key.clone(), member);
+    let previous = MEMBERS
+        .with_borrow_mut(|members| members.insert(member_key.clone(), member_record.clone()));
+    update_dashboard_members(&member_key, previous.as_ref(), &member_record);
+    update_dashboard(key, |dashboard| dashboard.record_activity(time()));
+    if previous.is_none() {
+        MEMBERSHIPS.with_borrow_mut(|memberships| {
+            memberships.insert(MembershipKey::from(&member_key), ())
+        });
+        GROUPS.with_borrow_mut(|groups| {
+            let mut group_record = groups.get(key).expect("group not found");
+            group_record.member_count += 1;
+            groups.insert(key.clone(), group_record);
+        });
+    }
 }
 
 /// API for setting/getting user data.
 #[query]
 #[candid_method(query)]
 fn get_user() -> Result<UserData, GroupsError> {
-    check_authenticated()?;
+    authorize("get_user")?;
     USERS.with_borrow(|users| {
         if let Some(user_record) = users.get(&caller()) {
             Ok(UserData {
@@ -369,7 +1173,8 @@ fn ensure_unique_nicknames(
 #[update]
 #[candid_method]
 fn set_user(req: SetUserRequest) -> Result<(), GroupsError> {
-    check_authenticated()?;
+    authorize("set_user")?;
+    check_rate_limit(&rate_limits())?;
     USERS.with_borrow_mut(|users| {
         ensure_unique_nicknames(&req.user_data, caller(), users)?;
         users.insert(
@@ -388,6 +1193,7 @@ fn set_user(req: SetUserRequest) -> Result<(), GroupsError> {
 #[query]
 #[candid_method(query)]
 fn group_types() -> Result<GroupTypes, GroupsError> {
+    authorize("group_types")?;
     Ok(GroupTypes {
         types: GROUP_TYPES.clone(),
     })
@@ -396,6 +1202,7 @@ fn group_types() -> Result<GroupTypes, GroupsError> {
 #[query]
 #[candid_method(query)]
 fn list_groups(req: ListGroupsRequest) -> Result<PublicGroupsData, GroupsError> {
+    authorize("list_groups")?;
     let anonymous = caller() == Principal::anonymous();
     GROUPS.with_borrow(|groups| {
         let mut list = vec![];
@@ -405,29 +1212,34 @@ fn list_groups(req: ListGroupsRequest) -> Result<PublicGroupsData, GroupsError>
                     break;
                 }
             }
-            let (membership_status, vc_arguments) = if anonymous {
-                (None, None)
+            let metadata = load_group_metadata(&key);
+            if metadata.visibility == GroupVisibility::Unlisted {
+                continue;
+            }
+            let (membership_status, vc_arguments, reviewer_note) = if anonymous {
+                (None, None, None)
             } else {
-                group_rec
-                    .members
-                    .get(&caller())
-                    .map_or((None, None), |member_rec| {
-                        (
-                            Some(member_rec.membership_status.clone()),
-                            member_rec.vc_arguments.clone(),
-                        )
-                    })
+                find_member(&key, &group_rec, caller()).map_or((None, None, None), |member_rec| {
+                    (
+                        Some(member_rec.membership_status),
+                        member_rec.vc_arguments,
+                        member_rec.reviewer_note,
+                    )
+                })
             };
             list.push(PublicGroupData {
                 group_name: key.group_name,
                 owner: key.owner,
                 issuer_nickname: maybe_issuer_nickname(&key.owner).unwrap_or("".to_string()),
                 stats: GroupStats {
-                    member_count: group_rec.members.len() as u32,
+                    member_count: group_rec.member_count,
                     created_timestamp_ns: group_rec.created_timestamp_ns,
                 },
+                metadata: metadata.to_data(),
+                issuer_verification: group_issuer_verification(&key),
                 membership_status,
                 vc_arguments,
+                reviewer_note,
             })
         }
         Ok(PublicGroupsData { groups: list })
@@ -456,169 +1268,1401 @@ fn maybe_issuer_nickname(user: &Principal) -> Option<String> {
 
 #[query]
 #[candid_method(query)]
-fn get_group(req: GetGroupRequest) -> Result<FullGroupData, GroupsError> {
-    GROUPS.with_borrow(|groups| {
-        if let Some(group_record) = groups.get(&(req.group_name.clone(), caller()).into()) {
-            let members: Vec<MemberData> = group_record
-                .members
-                .iter()
-                .map(|(member, member_rec)| MemberData {
-                    member: *member,
-                    nickname: maybe_user_nickname(member).unwrap_or("".to_string()),
-                    joined_timestamp_ns: member_rec.joined_timestamp_ns,
-                    membership_status: member_rec.membership_status.clone(),
-                    vc_arguments: member_rec.vc_arguments.clone(),
-                })
-                .collect();
-            Ok(FullGroupData {
-                group_name: req.group_name,
-                owner: caller(),
-                issuer_nickname: maybe_issuer_nickname(&caller()).unwrap_or("".to_string()),
-                stats: GroupStats {
-                    member_count: group_record.members.len() as u32,
-                    created_timestamp_ns: group_record.created_timestamp_ns,
-                },
-                members,
-            })
-        } else {
-            Err(GroupsError::NotFound(format!(
-                "group: {}, owner: {}",
-                req.group_name,
-                caller()
-            )))
+fn get_group(req: GetGroupRequest) -> Result<FullGroupData, GroupsError> {
+    let key: GroupKey = (req.group_name, caller()).into();
+    let group_record = authorize_for_group("get_group", &key)?;
+    Ok(full_group_data(key, group_record))
+}
+
+fn full_group_data(key: GroupKey, group_record: GroupRecord) -> FullGroupData {
+    let members: Vec<MemberData> = MEMBERS.with_borrow(|members| {
+        group_members(members, &key)
+            .map(|(member, member_rec)| MemberData {
+                member,
+                nickname: maybe_user_nickname(&member).unwrap_or("".to_string()),
+                joined_timestamp_ns: member_rec.joined_timestamp_ns,
+                membership_status: member_rec.membership_status.clone(),
+                vc_arguments: member_rec.vc_arguments.clone(),
+                applicant_message: member_rec.applicant_message.clone(),
+                reviewer_note: member_rec.reviewer_note.clone(),
+                evidence: evidence_data(&member_rec.evidence_ids),
+                expires_timestamp_ns: member_rec
+                    .expires_timestamp_ns(group_record.membership_validity.as_ref()),
+            })
+            .collect()
+    });
+    FullGroupData {
+        issuer_nickname: maybe_issuer_nickname(&key.owner).unwrap_or("".to_string()),
+        metadata: load_group_metadata(&key).to_data(),
+        issuer_verification: group_issuer_verification(&key),
+        group_name: key.group_name,
+        owner: key.owner,
+        stats: GroupStats {
+            member_count: group_record.member_count,
+            created_timestamp_ns: group_record.created_timestamp_ns,
+        },
+        membership_validity: group_record.membership_validity,
+        members,
+    }
+}
+
+#[update]
+#[candid_method]
+fn add_group(req: AddGroupRequest) -> Result<FullGroupData, GroupsError> {
+    authorize("add_group")?;
+    let limits = rate_limits();
+    check_rate_limit(&limits)?;
+    if let Some(max_groups) = limits.max_groups_per_owner {
+        let group_count =
+            DASHBOARDS.with_borrow(|dashboards| owner_dashboards(dashboards, caller()).count());
+        if group_count >= max_groups as usize {
+            return Err(GroupsError::RateLimited(format!(
+                "too many groups: {}, max. {}",
+                group_count, max_groups
+            )));
+        }
+    }
+    GROUPS.with_borrow_mut(|groups| {
+        if groups
+            .get(&(req.group_name.clone(), caller()).into())
+            .is_some()
+        {
+            Err(GroupsError::AlreadyExists(format!(
+                "group: {}, owner: {}",
+                req.group_name,
+                caller()
+            )))
+        } else {
+            let created_timestamp_ns = time();
+            let previous = groups.insert(
+                (req.group_name.clone(), caller()).into(),
+                GroupRecord {
+                    created_timestamp_ns,
+                    membership_validity: None,
+                    member_count: 0,
+                    legacy_members: None,
+                },
+            );
+            assert!(previous.is_none());
+            update_dashboard(&(req.group_name.clone(), caller()).into(), |dashboard| {
+                dashboard.record_activity(created_timestamp_ns)
+            });
+            Ok(FullGroupData {
+                group_name: req.group_name,
+                owner: caller(),
+                issuer_nickname: maybe_issuer_nickname(&caller()).unwrap_or("".to_string()),
+                stats: GroupStats {
+                    member_count: 0,
+                    created_timestamp_ns,
+                },
+                metadata: GroupMetadata::default(),
+                issuer_verification: group_issuer_verification(
+                    &(req.group_name.clone(), caller()).into(),
+                ),
+                membership_validity: None,
+                members: vec![],
+            })
+        }
+    })
+}
+
+fn verify_vc_arguments_match_spec(
+    group_name: &str,
+    maybe_args: &Option<VcArguments>,
+) -> Result<(), String> {
+    let Some(group_spec) = CREDENTIAL_SPEC_FOR_GROUP_NAME.get(group_name) else {
+        return Ok(()); // not a supported VC-group, nothing to verify
+    };
+    let derived_spec: CredentialSpec = OrdCredentialSpec {
+        credential_type: group_spec.credential_type.clone(),
+        arguments: maybe_args.clone(),
+    }
+    .into();
+    verify_vc_spec(&derived_spec)
+}
+
+#[update]
+#[candid_method]
+fn join_group(req: JoinGroupRequest) -> Result<(), GroupsError> {
+    authorize("join_group")?;
+    let limits = rate_limits();
+    check_rate_limit(&limits)?;
+    request_membership(caller(), req, &limits)
+}
+
+/// Adds a pending membership request of `member`, unless it is pending or accepted already.
+fn request_membership(
+    member: Principal,
+    req: JoinGroupRequest,
+    limits: &RateLimits,
+) -> Result<(), GroupsError> {
+    check_text_length("message", &req.message, MAX_APPLICANT_MESSAGE_LENGTH)?;
+    let key: GroupKey = (req.group_name.clone(), req.owner).into();
+    let Some(group_record) = load_group(&key) else {
+        return Err(GroupsError::NotFound(format!(
+            "group: {}, owner: {}",
+            req.group_name, req.owner
+        )));
+    };
+    if load_group_metadata(&key).visibility == GroupVisibility::InviteOnly {
+        return Err(GroupsError::NotAuthorized(format!(
+            "group {} accepts members by invitation only",
+            req.group_name
+        )));
+    }
+    verify_vc_arguments_match_spec(&req.group_name, &req.vc_arguments)
+        .map_err(GroupsError::Internal)?;
+    // If a record exists and has `Rejected`- or `Expired`-status,
+    // switch to `PendingReview` and update vc_arguments, message and timestamp,
+    // otherwise do nothing.
+    let may_join = find_member(&key, &group_record, member).map_or(true, |member_record| {
+        member_record.membership_status == MembershipStatus::Rejected
+            || member_record.membership_status == MembershipStatus::Expired
+    });
+    if may_join {
+        if let Some(max_pending) = limits.max_pending_requests_per_group {
+            let pending = DASHBOARDS.with_borrow(|dashboards| {
+                dashboards
+                    .get(&DashboardKey::from(&key))
+                    .map_or(0, |dashboard| dashboard.member_counts.pending_review)
+            });
+            if pending >= max_pending {
+                return Err(GroupsError::RateLimited(format!(
+                    "too many pending membership requests in group {}: {}, max. {}",
+                    req.group_name, pending, max_pending
+                )));
+            }
+        }
+        insert_member(
+            &key,
+            member,
+            MemberRecord {
+                joined_timestamp_ns: time(),
+                membership_status: MembershipStatus::PendingReview,
+                vc_arguments: req.vc_arguments,
+                status_list_index: None,
+                applicant_message: req.message,
+                reviewer_note: None,
+                evidence_ids: None,
+                accepted_timestamp_ns: None,
+            },
+        );
+        notify(
+            req.owner,
+            NotificationKind::MembershipRequested,
+            &key,
+            member,
+            MembershipStatus::PendingReview,
+        );
+    }
+    Ok(())
+}
+
+/// Lists the memberships of the caller in all groups, including pending and rejected ones.
+#[query]
+#[candid_method(query)]
+fn list_my_memberships() -> Result<MembershipsData, GroupsError> {
+    authorize("list_my_memberships")?;
+    let member = caller();
+    let group_keys = MEMBERSHIPS.with_borrow(|memberships| member_groups(memberships, member));
+    let memberships = group_keys
+        .into_iter()
+        .filter_map(|key| {
+            let group_record = GROUPS.with_borrow(|groups| groups.get(&key))?;
+            let member_record = find_member(&key, &group_record, member)?;
+            let credential_spec =
+                credential_type_for_group(&key.group_name).map(|credential_type| {
+                    let mut arguments = member_record.vc_arguments.clone().unwrap_or_default();
+                    arguments.insert(
+                        "owner".to_string(),
+                        OrdArgumentValue::String(key.owner.to_text()),
+                    );
+                    OrdCredentialSpec {
+                        credential_type,
+                        arguments: Some(arguments),
+                    }
+                });
+            Some(MembershipData {
+                issuer_nickname: maybe_issuer_nickname(&key.owner).unwrap_or("".to_string()),
+                joined_timestamp_ns: member_record.joined_timestamp_ns,
+                expires_timestamp_ns: member_record
+                    .expires_timestamp_ns(group_record.membership_validity.as_ref()),
+                membership_status: member_record.membership_status,
+                vc_arguments: member_record.vc_arguments,
+                reviewer_note: member_record.reviewer_note,
+                credential_spec,
+                group_name: key.group_name,
+                owner: key.owner,
+            })
+        })
+        .collect();
+    Ok(MembershipsData { memberships })
+}
+
+/// Returns the statistics of the caller's groups.
+#[query]
+#[candid_method(query)]
+fn get_owner_dashboard() -> Result<OwnerDashboardData, GroupsError> {
+    authorize("get_owner_dashboard")?;
+    let owner = caller();
+    let now = time();
+    let groups: Vec<GroupDashboardData> = DASHBOARDS.with_borrow(|dashboards| {
+        owner_dashboards(dashboards, owner)
+            .map(|(key, dashboard)| dashboard.to_data(key.group_name, now))
+            .collect()
+    });
+    let mut member_counts = MembershipCounts::default();
+    for group in &groups {
+        member_counts.add_counts(&group.member_counts);
+    }
+    Ok(OwnerDashboardData {
+        credentials_issued: groups.iter().map(|group| group.credentials_issued).sum(),
+        member_counts,
+        groups,
+    })
+}
+
+/// Updates the status of the given members. Members who are not accepted anymore
+/// get their credentials revoked in the status list of the group type.
+#[update]
+#[candid_method]
+fn update_membership(req: UpdateMembershipRequest) -> Result<(), GroupsError> {
+    let key: GroupKey = (req.group_name, caller()).into();
+    let group_record = authorize_for_group("update_membership", &key)?;
+    let updated: Vec<(Principal, MembershipStatus)> = req
+        .updates
+        .iter()
+        .map(|update| (update.member, update.new_status.clone()))
+        .collect();
+    update_members(&key, &group_record, req.updates)?;
+    for (member, new_status) in updated {
+        notify(
+            member,
+            NotificationKind::MembershipUpdated,
+            &key,
+            member,
+            new_status,
+        );
+    }
+    Ok(())
+}
+
+fn update_members(
+    key: &GroupKey,
+    group_record: &GroupRecord,
+    updates: Vec<MembershipUpdate>,
+) -> Result<(), GroupsError> {
+    let credential_type = credential_type_for_group(&key.group_name);
+    let mut revoked = false;
+    if let Some(update) = updates
+        .iter()
+        .find(|update| find_member(key, group_record, update.member).is_none())
+    {
+        return Err(GroupsError::NotFound(format!("member: {}", update.member)));
+    }
+    for update in &updates {
+        check_text_length("note", &update.note, MAX_REVIEWER_NOTE_LENGTH)?;
+    }
+    // Checked before any member is updated, so that the updates are not aborted midway.
+    // A member may be updated several times, and holds a status list index after an update
+    // iff the update accepts the member.
+    let mut holds_index = BTreeMap::new();
+    let mut new_status_list_indices = 0;
+    for update in &updates {
+        let has_index = holds_index.get(&update.member).copied().unwrap_or_else(|| {
+            find_member(key, group_record, update.member)
+                .and_then(|record| record.status_list_index)
+                .is_some()
+        });
+        let accepted = update.new_status == MembershipStatus::Accepted;
+        if accepted && !has_index {
+            new_status_list_indices += 1;
+        }
+        holds_index.insert(update.member, accepted);
+    }
+    check_status_list_capacity(credential_type.as_deref(), new_status_list_indices)
+        .map_err(GroupsError::Internal)?;
+    for update in updates {
+        let member_record =
+            find_member(key, group_record, update.member).expect("member not found");
+        let status_list_index = update_status_list_index(
+            credential_type.as_deref(),
+            member_record.status_list_index,
+            &update.new_status,
+            &mut revoked,
+        )
+        .expect("status list capacity was checked");
+        let evidence_ids = if update.new_status == MembershipStatus::PendingReview {
+            member_record.evidence_ids
+        } else {
+            delete_evidence(member_record.evidence_ids);
+            None
+        };
+        insert_member(
+            key,
+            update.member,
+            MemberRecord {
+                joined_timestamp_ns: member_record.joined_timestamp_ns,
+                membership_status: update.new_status,
+                vc_arguments: member_record.vc_arguments,
+                status_list_index,
+                applicant_message: member_record.applicant_message,
+                accepted_timestamp_ns: accepted_timestamp_ns(&update.new_status),
+                reviewer_note: update.note,
+                evidence_ids,
+            },
+        );
+    }
+    if revoked {
+        // Publish the updated status lists.
+        init_assets();
+    }
+    Ok(())
+}
+
+/// Returns the status list index of a member moving to `new_status`: accepted members get
+/// an index assigned, while the index of members who are not accepted anymore gets revoked
+/// (in which case `revoked` is set).
+fn update_status_list_index(
+    credential_type: Option<&str>,
+    status_list_index: Option<u32>,
+    new_status: &MembershipStatus,
+    revoked: &mut bool,
+) -> Result<Option<u32>, String> {
+    let Some(credential_type) = credential_type else {
+        return Ok(status_list_index);
+    };
+    match (new_status, status_list_index) {
+        (MembershipStatus::Accepted, None) => allocate_status_list_index(credential_type).map(Some),
+        (MembershipStatus::Accepted, Some(index)) => Ok(Some(index)),
+        (_, Some(index)) => {
+            revoke_status_list_index(credential_type, index);
+            *revoked = true;
+            Ok(None)
+        }
+        (_, None) => Ok(None),
+    }
+}
+
+/// Returns the acceptance time of a member moving to `new_status`. (Re-)accepting a member
+/// renews the membership.
+fn accepted_timestamp_ns(new_status: &MembershipStatus) -> Option<u64> {
+    (*new_status == MembershipStatus::Accepted).then(time)
+}
+
+/// Adds a notification about the membership of `member` in the group to the outbox
+/// of the recipient, and posts it to the recipient's webhook, if set.
+fn notify(
+    recipient: Principal,
+    kind: NotificationKind,
+    key: &GroupKey,
+    member: Principal,
+    membership_status: MembershipStatus,
+) {
+    let id = NOTIFICATION_STATE.with_borrow_mut(|cell| {
+        let mut state = cell.get().clone();
+        let id = state.next_notification_id;
+        state.next_notification_id += 1;
+        cell.set(state)
+            .expect("failed to update notification state");
+        id
+    });
+    let record = NotificationRecord {
+        kind,
+        timestamp_ns: time(),
+        group_name: key.group_name.clone(),
+        owner: key.owner,
+        member,
+        membership_status,
+    };
+    let notification = record.to_data(id);
+    NOTIFICATIONS.with_borrow_mut(|outbox| {
+        push_notification(outbox, NotificationKey { recipient, id }, record)
+    });
+    let webhook = WEBHOOKS.with_borrow(|webhooks| webhooks.get(&recipient));
+    post_to_webhook(webhook.as_ref(), &notification, &HttpsOutcallSender);
+}
+
+/// Returns the notifications in the caller's outbox, oldest first.
+#[query]
+#[candid_method(query)]
+fn get_notifications(req: GetNotificationsRequest) -> Result<NotificationsData, GroupsError> {
+    authorize("get_notifications")?;
+    let recipient = caller();
+    let notifications = NOTIFICATIONS.with_borrow(|outbox| {
+        recipient_notifications(outbox, recipient, req.since)
+            .map(|(key, record)| record.to_data(key.id))
+            .collect()
+    });
+    Ok(NotificationsData {
+        notifications,
+        webhook_url: WEBHOOKS
+            .with_borrow(|webhooks| webhooks.get(&recipient).map(|webhook| webhook.url)),
+    })
+}
+
+/// Removes the notifications which the caller has processed from the caller's outbox.
+#[update]
+#[candid_method]
+fn ack_notifications(req: AckNotificationsRequest) -> Result<(), GroupsError> {
+    authorize("ack_notifications")?;
+    NOTIFICATIONS
+        .with_borrow_mut(|outbox| notifications::ack_notifications(outbox, caller(), req.up_to));
+    Ok(())
+}
+
+#[update]
+#[candid_method]
+fn set_notification_webhook(req: SetNotificationWebhookRequest) -> Result<(), GroupsError> {
+    authorize("set_notification_webhook")?;
+    check_rate_limit(&rate_limits())?;
+    match req.url {
+        Some(url) => {
+            validate_webhook_url(&url).map_err(GroupsError::InvalidArgument)?;
+            WEBHOOKS.with_borrow_mut(|webhooks| webhooks.insert(caller(), WebhookRecord { url }));
+        }
+        None => {
+            WEBHOOKS.with_borrow_mut(|webhooks| webhooks.remove(&caller()));
+        }
+    }
+    Ok(())
+}
+
+/// Strips the responses of webhooks to their status, cf. `notifications`.
+#[query]
+#[candid_method(query)]
+fn transform_webhook_response(args: WebhookTransformArgs) -> WebhookResponse {
+    authorize_or_trap("transform_webhook_response");
+    WebhookResponse {
+        status: args.response.status,
+        headers: vec![],
+        body: ByteBuf::new(),
+    }
+}
+
+/// Sets the validity of accepted memberships in the caller's group.
+/// Members whose membership is already past the new validity expire with the next sweep.
+#[update]
+#[candid_method]
+fn set_membership_validity(req: SetMembershipValidityRequest) -> Result<(), GroupsError> {
+    let key: GroupKey = (req.group_name.clone(), caller()).into();
+    let mut group_record = authorize_for_group("set_membership_validity", &key)?;
+    if let Some(validity) = &req.validity {
+        if validity.validity_period_ns == 0 {
+            return Err(GroupsError::InvalidArgument(
+                "validity period must be positive".to_string(),
+            ));
+        }
+    }
+    group_record.membership_validity = req.validity;
+    update_dashboard(&key, |dashboard| dashboard.record_activity(time()));
+    GROUPS.with_borrow_mut(|groups| groups.insert(key, group_record));
+    Ok(())
+}
+
+/// Replaces the metadata of the caller's group, and returns it.
+#[update]
+#[candid_method]
+fn update_group(req: UpdateGroupRequest) -> Result<GroupMetadata, GroupsError> {
+    let key: GroupKey = (req.group_name.clone(), caller()).into();
+    authorize_for_group("update_group", &key)?;
+    validate_update(&req).map_err(GroupsError::InvalidArgument)?;
+    let previous = load_group_metadata(&key);
+    let new_logo_hash = match req.logo {
+        None => previous.logo_hash.clone(),
+        Some(GroupLogoUpdate::Remove) => None,
+        Some(GroupLogoUpdate::Set(logo)) => {
+            validate_logo(&logo).map_err(GroupsError::InvalidArgument)?;
+            let hash = logo_hash(&logo.content);
+            if previous.logo_hash.as_ref() != Some(&hash) {
+                LOGOS.with_borrow_mut(|logos| {
+                    let record = match logos.get(&hash) {
+                        Some(record) => LogoRecord {
+                            ref_count: record.ref_count + 1,
+                            ..record
+                        },
+                        None => LogoRecord {
+                            content_type: logo.content_type,
+                            content: logo.content,
+                            ref_count: 1,
+                        },
+                    };
+                    logos.insert(hash.clone(), record)
+                });
+            }
+            Some(hash)
+        }
+    };
+    if let Some(previous_hash) = previous
+        .logo_hash
+        .filter(|hash| new_logo_hash.as_ref() != Some(hash))
+    {
+        release_logo(previous_hash);
+    }
+    let metadata = GroupMetadataRecord {
+        description: req.description,
+        contact_url: req.contact_url,
+        tags: req.tags,
+        visibility: req.visibility,
+        logo_hash: new_logo_hash,
+    };
+    let data = metadata.to_data();
+    update_dashboard(&key, |dashboard| dashboard.record_activity(time()));
+    GROUP_METADATA.with_borrow_mut(|group_metadata| group_metadata.insert(key, metadata));
+    Ok(data)
+}
+
+fn load_group_metadata(key: &GroupKey) -> GroupMetadataRecord {
+    GROUP_METADATA
+        .with_borrow(|group_metadata| group_metadata.get(key))
+        .unwrap_or_default()
+}
+
+/// Drops a reference to the logo, and deletes the logo if it is no longer used.
+fn release_logo(hash: LogoHash) {
+    LOGOS.with_borrow_mut(|logos| {
+        if let Some(record) = logos.get(&hash) {
+            if record.ref_count > 1 {
+                logos.insert(
+                    hash,
+                    LogoRecord {
+                        ref_count: record.ref_count - 1,
+                        ..record
+                    },
+                );
+            } else {
+                logos.remove(&hash);
+            }
+        }
+    });
+}
+
+/// (Re-)starts the housekeeping timer, with the interval from the config.
+fn start_housekeeping() {
+    let interval = Duration::from_secs(housekeeping_config().interval_s.max(1));
+    let timer_id = ic_cdk_timers::set_timer_interval(interval, run_housekeeping);
+    if let Some(previous_timer_id) = HOUSEKEEPING_TIMER.replace(Some(timer_id)) {
+        ic_cdk_timers::clear_timer(previous_timer_id);
+    }
+}
+
+fn housekeeping_config() -> HousekeepingConfig {
+    CONFIG.with_borrow(|config| config.get().housekeeping.clone().unwrap_or_default())
+}
+
+// Starts a pass of the jobs, except for those whose previous pass is still in progress.
+fn run_housekeeping() {
+    let jobs: Vec<Job> = HOUSEKEEPING_PROGRESS.with_borrow(|progress| {
+        Job::ALL
+            .into_iter()
+            .filter(|job| !progress.contains_key(job))
+            .collect()
+    });
+    run_housekeeping_batches(jobs);
+}
+
+// Runs the next batch of the given jobs, and schedules a timer for the jobs which have not
+// completed their pass.
+fn run_housekeeping_batches(jobs: Vec<Job>) {
+    let mut pending_jobs = vec![];
+    for job in jobs {
+        let mut progress = HOUSEKEEPING_PROGRESS
+            .with_borrow_mut(|progress| progress.remove(&job))
+            .unwrap_or_default();
+        let cursor = progress.cursor.take();
+        let batch = match job {
+            Job::PruneSignatures => prune_signatures(cursor, HOUSEKEEPING_BATCH_SIZE),
+            Job::ExpireMemberships => sweep_expired_memberships(cursor, HOUSEKEEPING_BATCH_SIZE),
+            Job::RejectStalePending => reject_stale_pending(cursor, HOUSEKEEPING_BATCH_SIZE),
+            Job::CompactGroupStats => compact_group_stats(cursor, HOUSEKEEPING_BATCH_SIZE),
+        };
+        progress.processed += batch.processed;
+        match batch.cursor {
+            Some(cursor) => {
+                progress.cursor = Some(cursor);
+                HOUSEKEEPING_PROGRESS.with_borrow_mut(|jobs| jobs.insert(job, progress));
+                pending_jobs.push(job);
+            }
+            None => {
+                let run = JobRun {
+                    timestamp_ns: time(),
+                    processed: progress.processed,
+                };
+                HOUSEKEEPING_RUNS.with_borrow_mut(|runs| runs.insert(job, run));
+            }
+        }
+    }
+    if !pending_jobs.is_empty() {
+        ic_cdk_timers::set_timer(Duration::ZERO, move || {
+            run_housekeeping_batches(pending_jobs)
+        });
+    }
+}
+
+/// Removes expired signatures, which otherwise are pruned only when new ones are added.
+fn prune_signatures(_cursor: Option<Vec<u8>>, batch_size: usize) -> JobBatch {
+    let pruned = SIGNATURES.with_borrow_mut(|sigs| sigs.prune_expired(time(), batch_size));
+    if pruned > 0 {
+        update_root_hash();
+    }
+    JobBatch {
+        // More signatures may have expired if the batch is full.
+        cursor: (pruned == batch_size).then(Vec::new),
+        processed: pruned as u64,
+    }
+}
+
+// Returns the batch of members after the cursor.
+fn members_batch(cursor: Option<Vec<u8>>, batch_size: usize) -> Vec<(MemberKey, MemberRecord)> {
+    let start = match cursor {
+        Some(cursor) => RangeBound::Excluded(MemberKey::from_bytes(Cow::Owned(cursor))),
+        None => RangeBound::Unbounded,
+    };
+    MEMBERS.with_borrow(|members| {
+        members
+            .range((start, RangeBound::Unbounded))
+            .take(batch_size)
+            .collect()
+    })
+}
+
+// Returns the cursor after the given batch of members, if the batch is full.
+fn members_cursor(members: &[(MemberKey, MemberRecord)], batch_size: usize) -> Option<Vec<u8>> {
+    if members.len() < batch_size {
+        None
+    } else {
+        members.last().map(|(key, _)| key.to_bytes().into_owned())
+    }
+}
+
+/// Rejects the pending membership requests older than the configured max. age, and notifies
+/// the members like `update_membership`. Returns the number of rejected requests.
+fn reject_stale_pending(cursor: Option<Vec<u8>>, batch_size: usize) -> JobBatch {
+    let Some(max_age_ns) = housekeeping_config().pending_review_max_age_ns else {
+        return JobBatch {
+            cursor: None,
+            processed: 0,
+        };
+    };
+    let now = time();
+    let is_stale = |member_record: &MemberRecord| {
+        member_record.membership_status == MembershipStatus::PendingReview
+            && member_record.joined_timestamp_ns.saturating_add(max_age_ns) <= now
+    };
+    // Members not moved to MEMBERS yet (cf. `load_group`) are considered in a later pass.
+    let members = members_batch(cursor, batch_size);
+    let cursor = members_cursor(&members, batch_size);
+    let mut rejected = 0;
+    for (key, mut member_record) in members {
+        if !is_stale(&member_record) {
+            continue;
+        }
+        let previous = member_record.clone();
+        member_record.membership_status = MembershipStatus::Rejected;
+        member_record.reviewer_note = Some(AUTO_REJECT_NOTE.to_string());
+        delete_evidence(member_record.evidence_ids.take());
+        update_dashboard_members(&key, Some(&previous), &member_record);
+        MEMBERS.with_borrow_mut(|members| members.insert(key.clone(), member_record));
+        notify(
+            key.member,
+            NotificationKind::MembershipUpdated,
+            &key.group,
+            key.member,
+            MembershipStatus::Rejected,
+        );
+        rejected += 1;
+    }
+    JobBatch {
+        cursor,
+        processed: rejected,
+    }
+}
+
+// Cursor of `compact_group_stats`, which first goes through the groups (for groups without
+// members, and members not moved to MEMBERS yet), and then through the members.
+#[derive(CandidType, Deserialize)]
+enum GroupStatsCursor {
+    Groups(GroupKey),
+    Members(Option<MemberKey>),
+}
+
+/// Recomputes the membership counts of all groups, and returns the number of groups.
+/// The counts are published once the pass is completed.
+fn compact_group_stats(cursor: Option<Vec<u8>>, batch_size: usize) -> JobBatch {
+    let cursor =
+        cursor.map(|bytes| candid::decode_one(&bytes).expect("failed to decode GroupStatsCursor"));
+    let encode = |cursor: GroupStatsCursor| {
+        Some(candid::encode_one(cursor).expect("failed to encode GroupStatsCursor"))
+    };
+    match cursor {
+        None | Some(GroupStatsCursor::Groups(_)) => {
+            let start = match cursor {
+                Some(GroupStatsCursor::Groups(key)) => RangeBound::Excluded(key),
+                _ => {
+                    NEXT_GROUP_STATS.with_borrow_mut(|stats| stats.clear());
+                    RangeBound::Unbounded
+                }
+            };
+            let groups: Vec<(GroupKey, MembershipCounts)> = GROUPS.with_borrow(|groups| {
+                groups
+                    .range((start, RangeBound::Unbounded))
+                    .take(batch_size)
+                    .map(|(key, group_record)| {
+                        let mut counts = MembershipCounts::default();
+                        for member_record in
+                            group_record.legacy_members.iter().flat_map(|m| m.values())
+                        {
+                            counts.add(&member_record.membership_status);
+                        }
+                        (key, counts)
+                    })
+                    .collect()
+            });
+            let group_count = groups.len();
+            let last_key = groups.last().map(|(key, _)| key.clone());
+            NEXT_GROUP_STATS.with_borrow_mut(|stats| stats.extend(groups));
+            JobBatch {
+                cursor: match last_key {
+                    Some(key) if group_count == batch_size => encode(GroupStatsCursor::Groups(key)),
+                    _ => encode(GroupStatsCursor::Members(None)),
+                },
+                processed: group_count as u64,
+            }
+        }
+        Some(GroupStatsCursor::Members(member_key)) => {
+            let members = members_batch(
+                member_key.map(|key| key.to_bytes().into_owned()),
+                batch_size,
+            );
+            let last_key = members.last().map(|(key, _)| key.clone());
+            let member_count = members.len();
+            NEXT_GROUP_STATS.with_borrow_mut(|stats| {
+                for (key, member_record) in members {
+                    if let Some(counts) = stats.get_mut(&key.group) {
+                        counts.add(&member_record.membership_status);
+                    }
+                }
+            });
+            if member_count == batch_size {
+                return JobBatch {
+                    cursor: encode(GroupStatsCursor::Members(last_key)),
+                    processed: 0,
+                };
+            }
+            GROUP_STATS.set(NEXT_GROUP_STATS.take());
+            JobBatch {
+                cursor: None,
+                processed: 0,
+            }
+        }
+    }
+}
+
+/// Moves the members whose membership has expired to `Expired`, or back to `PendingReview`
+/// if the group requires re-verification, and revokes their credentials.
+/// Returns the number of expired memberships.
+fn sweep_expired_memberships(cursor: Option<Vec<u8>>, batch_size: usize) -> JobBatch {
+    let now = time();
+    let mut revoked = false;
+    let mut expired = 0;
+    // Members not moved to MEMBERS yet (cf. `load_group`) are considered in a later pass.
+    let members = members_batch(cursor, batch_size);
+    let cursor = members_cursor(&members, batch_size);
+    // Only the members of groups with a membership validity can expire.
+    let mut validities: BTreeMap<GroupKey, Option<MembershipValidity>> = BTreeMap::new();
+    for (key, mut member_record) in members {
+        let validity = validities
+            .entry(key.group.clone())
+            .or_insert_with(|| {
+                GROUPS.with_borrow(|groups| groups.get(&key.group)?.membership_validity)
+            })
+            .clone();
+        let Some(validity) = validity else {
+            continue;
+        };
+        if !member_record.is_expired(Some(&validity), now) {
+            continue;
+        }
+        let previous = member_record.clone();
+        member_record.membership_status = if validity.reverify_on_expiry {
+            MembershipStatus::PendingReview
+        } else {
+            MembershipStatus::Expired
+        };
+        member_record.accepted_timestamp_ns = None;
+        expired += 1;
+        if let (Some(credential_type), Some(index)) = (
+            credential_type_for_group(&key.group.group_name),
+            member_record.status_list_index.take(),
+        ) {
+            revoke_status_list_index(&credential_type, index);
+            revoked = true;
+        }
+        update_dashboard_members(&key, Some(&previous), &member_record);
+        MEMBERS.with_borrow_mut(|members| members.insert(key, member_record));
+    }
+    if revoked {
+        init_assets();
+    }
+    JobBatch {
+        cursor,
+        processed: expired,
+    }
+}
+
+/// Exports the members of the caller's group, as CSV or JSON.
+#[query]
+#[candid_method(query)]
+fn export_members(req: ExportMembersRequest) -> Result<String, GroupsError> {
+    let key: GroupKey = (req.group_name, caller()).into();
+    let group_record = authorize_for_group("export_members", &key)?;
+    let group_data = full_group_data(key, group_record);
+    members_io::export_members(&group_data.members, &req.format).map_err(GroupsError::Internal)
+}
+
+/// Imports members into the caller's group, with preset statuses and VC arguments.
+/// Existing members are updated. Rows with errors are skipped and reported in the result,
+/// all other rows are imported.
+#[update]
+#[candid_method]
+fn import_members(req: ImportMembersRequest) -> Result<ImportMembersResult, GroupsError> {
+    let key: GroupKey = (req.group_name.clone(), caller()).into();
+    let group_record = authorize_for_group("import_members", &key)?;
+    let rows = members_io::parse_members(&req.content, &req.format)
+        .map_err(GroupsError::InvalidArgument)?;
+    if rows.len() > MAX_IMPORTED_MEMBERS {
+        return Err(GroupsError::InvalidArgument(format!(
+            "too many members: {}, max. {}",
+            rows.len(),
+            MAX_IMPORTED_MEMBERS
+        )));
+    }
+    let credential_type = credential_type_for_group(&req.group_name);
+    let mut revoked = false;
+    let mut imported = BTreeMap::new();
+    let mut errors = vec![];
+    for (row, parsed) in rows {
+        let imported_member = parsed.and_then(|member| {
+            if imported.contains_key(&member.member) {
+                return Err(format!("duplicate member: {}", member.member));
+            }
+            verify_vc_arguments_match_spec(&req.group_name, &member.vc_arguments)?;
+            Ok(member)
+        });
+        match imported_member {
+            Ok(member) => {
+                imported.insert(member.member, member);
+            }
+            Err(error) => errors.push(ImportRowError { row, error }),
+        }
+    }
+    let imported_count = imported.len() as u32;
+    let imported: Vec<_> = imported
+        .into_iter()
+        .map(|(principal, member)| {
+            let existing = find_member(&key, &group_record, principal);
+            (principal, member, existing)
+        })
+        .collect();
+    // Checked before any member is inserted, so that the import is not aborted midway.
+    let new_status_list_indices = imported
+        .iter()
+        .filter(|(_, member, existing)| {
+            member.membership_status == MembershipStatus::Accepted
+                && existing
+                    .as_ref()
+                    .and_then(|record| record.status_list_index)
+                    .is_none()
+        })
+        .count();
+    check_status_list_capacity(credential_type.as_deref(), new_status_list_indices)
+        .map_err(GroupsError::Internal)?;
+    for (principal, member, existing) in imported {
+        let status_list_index = update_status_list_index(
+            credential_type.as_deref(),
+            existing
+                .as_ref()
+                .and_then(|record| record.status_list_index),
+            &member.membership_status,
+            &mut revoked,
+        )
+        .expect("status list capacity was checked");
+        let joined_timestamp_ns = member
+            .joined_timestamp_ns
+            .or(existing.as_ref().map(|record| record.joined_timestamp_ns))
+            .unwrap_or_else(time);
+        let mut evidence_ids = existing
+            .as_ref()
+            .and_then(|record| record.evidence_ids.clone());
+        if member.membership_status != MembershipStatus::PendingReview {
+            delete_evidence(evidence_ids.take());
+        }
+        insert_member(
+            &key,
+            principal,
+            MemberRecord {
+                joined_timestamp_ns,
+                accepted_timestamp_ns: accepted_timestamp_ns(&member.membership_status),
+                membership_status: member.membership_status,
+                vc_arguments: member.vc_arguments,
+                status_list_index,
+                applicant_message: existing
+                    .as_ref()
+                    .and_then(|record| record.applicant_message.clone()),
+                reviewer_note: existing
+                    .as_ref()
+                    .and_then(|record| record.reviewer_note.clone()),
+                evidence_ids,
+            },
+        );
+    }
+    if revoked {
+        init_assets();
+    }
+    Ok(ImportMembersResult {
+        imported_count,
+        errors,
+    })
+}
+
+/// Registers an evidence document for the caller's pending membership request,
+/// to be uploaded in chunks via `upload_evidence_chunk`.
+#[update]
+#[candid_method]
+fn add_evidence(req: AddEvidenceRequest) -> Result<EvidenceData, GroupsError> {
+    let key: GroupKey = (req.group_name.clone(), req.owner).into();
+    let group_record = authorize_for_group("add_evidence", &key)?;
+    if req.size == 0 || req.size > MAX_EVIDENCE_SIZE {
+        return Err(GroupsError::InvalidArgument(format!(
+            "evidence size must be between 1 and {} bytes",
+            MAX_EVIDENCE_SIZE
+        )));
+    }
+    check_text_length(
+        "file name",
+        &Some(req.file_name.clone()),
+        MAX_EVIDENCE_FILE_NAME_LENGTH,
+    )?;
+    check_text_length(
+        "content type",
+        &Some(req.content_type.clone()),
+        MAX_EVIDENCE_FILE_NAME_LENGTH,
+    )?;
+    let Some(mut member_record) = find_member(&key, &group_record, caller())
+        .filter(|record| record.membership_status == MembershipStatus::PendingReview)
+    else {
+        return Err(GroupsError::NotFound(format!(
+            "pending membership request of {}",
+            caller()
+        )));
+    };
+    let evidence_ids = member_record.evidence_ids.get_or_insert_with(Vec::new);
+    if evidence_ids.len() >= MAX_EVIDENCE_PER_MEMBER {
+        return Err(GroupsError::InvalidArgument(format!(
+            "at most {} evidence documents per membership request",
+            MAX_EVIDENCE_PER_MEMBER
+        )));
+    }
+    let evidence_id = EVIDENCE_STATE.with_borrow_mut(|cell| {
+        let mut state = cell.get().clone();
+        let evidence_id = state.next_evidence_id;
+        state.next_evidence_id += 1;
+        cell.set(state).expect("failed to update evidence state");
+        evidence_id
+    });
+    evidence_ids.push(evidence_id);
+    let record = EvidenceRecord {
+        group_name: req.group_name,
+        owner: req.owner,
+        member: caller(),
+        file_name: req.file_name,
+        content_type: req.content_type,
+        size: req.size,
+        uploaded_chunk_count: 0,
+        created_timestamp_ns: time(),
+    };
+    let data = to_evidence_data(evidence_id, &record);
+    EVIDENCE.with_borrow_mut(|evidence| evidence.insert(evidence_id, record));
+    insert_member(&key, caller(), member_record);
+    Ok(data)
+}
+
+/// Uploads a chunk of an evidence document of the caller. The chunk is encrypted
+/// with the key of the group owner before being stored.
+#[update]
+#[candid_method]
+async fn upload_evidence_chunk(
+    req: UploadEvidenceChunkRequest,
+) -> Result<EvidenceData, GroupsError> {
+    authorize("upload_evidence_chunk")?;
+    let key_seed = evidence_key_seed().await?;
+    EVIDENCE.with_borrow_mut(|evidence| {
+        let Some(mut record) = evidence
+            .get(&req.evidence_id)
+            .filter(|record| record.member == caller())
+        else {
+            return Err(GroupsError::NotFound(format!(
+                "evidence: {}",
+                req.evidence_id
+            )));
+        };
+        if req.chunk_index >= record.chunk_count() {
+            return Err(GroupsError::InvalidArgument(format!(
+                "chunk index {} out of range, evidence has {} chunks",
+                req.chunk_index,
+                record.chunk_count()
+            )));
+        }
+        let expected_size = record.expected_chunk_size(req.chunk_index);
+        if req.content.len() != expected_size as usize {
+            return Err(GroupsError::InvalidArgument(format!(
+                "chunk {} must have {} bytes, got {}",
+                req.chunk_index,
+                expected_size,
+                req.content.len()
+            )));
+        }
+        let ciphertext = encrypt_chunk(
+            &owner_key(&key_seed, record.owner),
+            req.evidence_id,
+            req.chunk_index,
+            &req.content,
+        );
+        let previous = EVIDENCE_CHUNKS.with_borrow_mut(|chunks| {
+            chunks.insert((req.evidence_id, req.chunk_index), ciphertext)
+        });
+        if previous.is_none() {
+            record.uploaded_chunk_count += 1;
+            evidence.insert(req.evidence_id, record.clone());
+        }
+        Ok(to_evidence_data(req.evidence_id, &record))
+    })
+}
+
+/// Returns a decrypted chunk of an evidence document, only to the owner of the group.
+#[query]
+#[candid_method(query)]
+fn get_evidence_chunk(req: GetEvidenceChunkRequest) -> Result<ByteBuf, GroupsError> {
+    authorize("get_evidence_chunk")?;
+    let not_found = || GroupsError::NotFound(format!("evidence chunk: {}", req.chunk_index));
+    let record = EVIDENCE
+        .with_borrow(|evidence| evidence.get(&req.evidence_id))
+        .ok_or_else(not_found)?;
+    let key: GroupKey = (record.group_name.clone(), record.owner).into();
+    authorize_for_group("get_evidence_chunk", &key).map_err(|_| not_found())?;
+    let ciphertext = EVIDENCE_CHUNKS
+        .with_borrow(|chunks| chunks.get(&(req.evidence_id, req.chunk_index)))
+        .ok_or_else(not_found)?;
+    let key_seed = EVIDENCE_STATE.with_borrow(|cell| cell.get().key_seed.clone());
+    decrypt_chunk(
+        &owner_key(&key_seed, record.owner),
+        req.evidence_id,
+        req.chunk_index,
+        &ciphertext,
+    )
+    .map(ByteBuf::from)
+    .map_err(GroupsError::Internal)
+}
+
+// Returns the seed of the owners' evidence keys, initializing it on first use.
+async fn evidence_key_seed() -> Result<Vec<u8>, GroupsError> {
+    let key_seed = EVIDENCE_STATE.with_borrow(|cell| cell.get().key_seed.clone());
+    if !key_seed.is_empty() {
+        return Ok(key_seed.into_vec());
+    }
+    let (random_bytes,) = raw_rand()
+        .await
+        .map_err(|(_, e)| GroupsError::Internal(format!("failed to get randomness: {}", e)))?;
+    // Another call might have initialized the seed in the meantime.
+    EVIDENCE_STATE.with_borrow_mut(|cell| {
+        let mut state = cell.get().clone();
+        if state.key_seed.is_empty() {
+            state.key_seed = ByteBuf::from(random_bytes);
+            cell.set(state.clone())
+                .expect("failed to update evidence state");
         }
+        Ok(state.key_seed.into_vec())
     })
 }
 
-#[update]
-#[candid_method]
-fn add_group(req: AddGroupRequest) -> Result<FullGroupData, GroupsError> {
-    GROUPS.with_borrow_mut(|groups| {
-        if groups
-            .get(&(req.group_name.clone(), caller()).into())
-            .is_some()
-        {
-            Err(GroupsError::AlreadyExists(format!(
-                "group: {}, owner: {}",
-                req.group_name,
-                caller()
-            )))
-        } else {
-            let created_timestamp_ns = time();
-            let previous = groups.insert(
-                (req.group_name.clone(), caller()).into(),
-                GroupRecord {
-                    created_timestamp_ns,
-                    members: BTreeMap::new(),
-                },
-            );
-            assert!(previous.is_none());
-            Ok(FullGroupData {
-                group_name: req.group_name,
-                owner: caller(),
-                issuer_nickname: maybe_issuer_nickname(&caller()).unwrap_or("".to_string()),
-                stats: GroupStats {
-                    member_count: 0,
-                    created_timestamp_ns,
-                },
-                members: vec![],
+fn delete_evidence(evidence_ids: Option<Vec<u64>>) {
+    for evidence_id in evidence_ids.unwrap_or_default() {
+        let Some(record) = EVIDENCE.with_borrow_mut(|evidence| evidence.remove(&evidence_id))
+        else {
+            continue;
+        };
+        EVIDENCE_CHUNKS.with_borrow_mut(|chunks| {
+            for chunk_index in 0..record.chunk_count() {
+                chunks.remove(&(evidence_id, chunk_index));
+            }
+        });
+    }
+}
+
+fn evidence_data(evidence_ids: &Option<Vec<u64>>) -> Vec<EvidenceData> {
+    EVIDENCE.with_borrow(|evidence| {
+        evidence_ids
+            .iter()
+            .flatten()
+            .filter_map(|evidence_id| {
+                evidence
+                    .get(evidence_id)
+                    .map(|record| to_evidence_data(*evidence_id, &record))
             })
-        }
+            .collect()
     })
 }
 
-fn verify_vc_arguments_match_spec(
-    group_name: &str,
-    maybe_args: &Option<VcArguments>,
-) -> Result<(), String> {
-    let Some(group_spec) = CREDENTIAL_SPEC_FOR_GROUP_NAME.get(group_name) else {
-        return Ok(()); // not a supported VC-group, nothing to verify
+fn to_evidence_data(evidence_id: u64, record: &EvidenceRecord) -> EvidenceData {
+    EvidenceData {
+        evidence_id,
+        file_name: record.file_name.clone(),
+        content_type: record.content_type.clone(),
+        size: record.size,
+        complete: record.is_complete(),
+    }
+}
+
+fn check_text_length(
+    field: &str,
+    text: &Option<String>,
+    max_length: usize,
+) -> Result<(), GroupsError> {
+    match text {
+        Some(text) if text.len() > max_length => Err(GroupsError::InvalidArgument(format!(
+            "{} too long: {} bytes, max. {}",
+            field,
+            text.len(),
+            max_length
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn credential_type_for_group(group_name: &str) -> Option<String> {
+    GROUP_TYPES
+        .iter()
+        .find(|group_type| group_type.group_name == group_name)
+        .map(|group_type| group_type.credential_spec.credential_type.clone())
+}
+
+fn allocate_status_list_index(credential_type: &str) -> Result<u32, String> {
+    STATUS_LISTS.with_borrow_mut(|lists| {
+        let mut list = lists.get(&credential_type.to_string()).unwrap_or_default();
+        let index = list.allocate_index()?;
+        lists.insert(credential_type.to_string(), list);
+        Ok(index)
+    })
+}
+
+/// Checks that `count` indices can be assigned in the status list of the credential type
+/// (if any), so that changes of several members can be checked before applying any of them.
+fn check_status_list_capacity(credential_type: Option<&str>, count: usize) -> Result<(), String> {
+    let Some(credential_type) = credential_type else {
+        return Ok(());
     };
-    let derived_spec: CredentialSpec = OrdCredentialSpec {
-        credential_type: group_spec.credential_type.clone(),
-        arguments: maybe_args.clone(),
+    let available = STATUS_LISTS.with_borrow(|lists| {
+        lists
+            .get(&credential_type.to_string())
+            .unwrap_or_default()
+            .available_indices()
+    });
+    if count > available as usize {
+        return Err(format!(
+            "status list is full: {} entries needed, {} available",
+            count, available
+        ));
     }
-    .into();
-    verify_vc_spec(&derived_spec)
+    Ok(())
+}
+
+fn revoke_status_list_index(credential_type: &str, index: u32) {
+    STATUS_LISTS.with_borrow_mut(|lists| {
+        let mut list = lists.get(&credential_type.to_string()).unwrap_or_default();
+        list.revoke(index);
+        lists.insert(credential_type.to_string(), list);
+    })
+}
+
+/// Returns the index of the member in the status list of the group type, which is assigned
+/// when the member is accepted (or by the `assign_status_list_indices`-migration for members
+/// accepted before status lists were introduced).
+fn member_status_list_index(
+    credential_type: &str,
+    owner: Principal,
+    member: Principal,
+) -> Result<u32, IssueCredentialError> {
+    let group_name = group_name(credential_type)?;
+    let key: GroupKey = (group_name, owner).into();
+    let group_record = GROUPS
+        .with_borrow(|groups| groups.get(&key))
+        .ok_or(internal_error("group not found"))?;
+    find_member(&key, &group_record, member)
+        .ok_or(internal_error("member not found"))?
+        .status_list_index
+        .ok_or(internal_error(
+            "no status list index assigned to the member yet",
+        ))
+}
+
+fn status_list_url(credential_type: &str) -> String {
+    let origin = CONFIG.with_borrow(|config| config.get().derivation_origin.clone());
+    format!("{}{}", origin, status_list_path(credential_type))
+}
+
+/// Returns the status lists of all group types, as assets to be served via `http_request`.
+fn status_list_assets() -> Vec<Asset> {
+    GROUP_TYPES
+        .iter()
+        .map(|group_type| {
+            let credential_type = &group_type.credential_spec.credential_type;
+            let list = STATUS_LISTS
+                .with_borrow(|lists| lists.get(credential_type))
+                .unwrap_or_default();
+            let credential =
+                status_list_credential(&status_list_url(credential_type), ISSUER_URL, &list);
+            Asset {
+                url_path: status_list_path(credential_type),
+                content: credential.to_string().into_bytes(),
+                encoding: ContentEncoding::Identity,
+                content_type: ContentType::JSON,
+            }
+        })
+        .collect()
 }
 
 #[update]
 #[candid_method]
-fn join_group(req: JoinGroupRequest) -> Result<(), GroupsError> {
-    GROUPS.with_borrow_mut(|groups| {
-        if let Some(mut group_record) = groups.get(&(req.group_name.clone(), req.owner).into()) {
-            verify_vc_arguments_match_spec(&req.group_name, &req.vc_arguments)
-                .map_err(GroupsError::Internal)?;
-            if let Some(member_record) = group_record.members.get(&caller()) {
-                // If a record exists and has `Rejected`-status,
-                // switch to `PendingReview` and update vc_arguments and timestamp, otherwise do nothing.
-                if member_record.membership_status == MembershipStatus::Rejected {
-                    group_record.members.insert(
-                        caller(),
-                        MemberRecord {
-                            joined_timestamp_ns: time(),
-                            membership_status: MembershipStatus::PendingReview,
-                            vc_arguments: req.vc_arguments.clone(),
-                        },
-                    );
-                }
-            } else {
-                group_record.members.insert(
-                    caller(),
-                    MemberRecord {
-                        joined_timestamp_ns: time(),
-                        membership_status: MembershipStatus::PendingReview,
-                        vc_arguments: req.vc_arguments.clone(),
-                    },
-                );
-            }
-            groups.insert((req.group_name, req.owner).into(), group_record);
-            Ok(())
-        } else {
-            Err(GroupsError::NotFound(format!(
-                "group: {}, owner: {}",
-                req.group_name, req.owner
-            )))
-        }
-    })
+fn configure(config: IssuerInit) {
+    authorize_or_trap("configure");
+    apply_config(config);
+    // The status lists are published under the derivation origin.
+    init_assets();
+    // The housekeeping interval may have changed.
+    start_housekeeping();
 }
 
+/// Sets the limits of the update calls open to anyone, only for controllers.
 #[update]
 #[candid_method]
-fn update_membership(req: UpdateMembershipRequest) -> Result<(), GroupsError> {
-    GROUPS.with_borrow_mut(|groups| {
-        if let Some(mut group_record) = groups.get(&(req.group_name.clone(), caller()).into()) {
-            for update in req.updates {
-                if let Some(member_record) = group_record.members.get(&update.member) {
-                    group_record.members.insert(
-                        update.member,
-                        MemberRecord {
-                            joined_timestamp_ns: member_record.joined_timestamp_ns,
-                            membership_status: update.new_status,
-                            vc_arguments: member_record.vc_arguments.clone(),
-                        },
-                    );
-                } else {
-                    return Err(GroupsError::NotFound(format!("member: {}", update.member)));
-                }
+fn set_rate_limits(limits: RateLimits) -> Result<(), GroupsError> {
+    authorize("set_rate_limits")?;
+    CONFIG
+        .with_borrow_mut(|config_cell| {
+            let mut config = config_cell.get().clone();
+            config.rate_limits = Some(limits);
+            config_cell.set(config)
+        })
+        .expect("failed to set rate limits");
+    Ok(())
+}
+
+#[query]
+#[candid_method(query)]
+fn get_rate_limits() -> RateLimits {
+    authorize_or_trap("get_rate_limits");
+    rate_limits()
+}
+
+/// Verifies an issuer for a credential type, or revokes the verification, for admins
+/// and for the issuers trusted for the credential type. Only admins can designate trusted
+/// issuers, and trusted issuers can change only the verifications they made themselves.
+#[update]
+#[candid_method]
+fn set_issuer_verification(req: SetIssuerVerificationRequest) -> Result<(), GroupsError> {
+    authorize("set_issuer_verification")?;
+    if !GROUP_TYPES
+        .iter()
+        .any(|group_type| group_type.credential_spec.credential_type == req.credential_type)
+    {
+        return Err(GroupsError::InvalidArgument(format!(
+            "unsupported credential type: {}",
+            req.credential_type
+        )));
+    }
+    if req.issuer == Principal::anonymous() {
+        return Err(GroupsError::InvalidArgument(
+            "the anonymous principal cannot be verified".to_string(),
+        ));
+    }
+    let verifier = caller();
+    let is_admin = is_controller(&verifier);
+    let key = IssuerVerificationKey::new(req.issuer, &req.credential_type);
+    if !is_admin {
+        let is_trusted = issuer_verification(verifier, &req.credential_type)
+            .is_some_and(|verification| verification.trusted);
+        if !is_trusted {
+            return Err(GroupsError::NotAuthorized(format!(
+                "{} may not verify issuers of {}",
+                verifier, req.credential_type
+            )));
+        }
+        if req.trusted {
+            return Err(GroupsError::NotAuthorized(
+                "only admins can designate trusted issuers".to_string(),
+            ));
+        }
+        if let Some(existing) =
+            ISSUER_VERIFICATIONS.with_borrow(|verifications| verifications.get(&key))
+        {
+            if existing.verified_by != verifier {
+                return Err(GroupsError::NotAuthorized(format!(
+                    "the verification of {} for {} was made by {}",
+                    req.issuer, req.credential_type, existing.verified_by
+                )));
             }
-            groups.insert((req.group_name, caller()).into(), group_record);
-            Ok(())
+        }
+    }
+    ISSUER_VERIFICATIONS.with_borrow_mut(|verifications| {
+        if req.verified {
+            verifications.insert(
+                key,
+                IssuerVerificationRecord {
+                    verified_by: verifier,
+                    verified_by_admin: is_admin,
+                    verified_timestamp_ns: time(),
+                    trusted: req.trusted,
+                },
+            );
         } else {
-            Err(GroupsError::NotFound(format!(
-                "group: {}, owner: {}",
-                req.group_name,
-                caller()
-            )))
+            verifications.remove(&key);
+        }
+    });
+    Ok(())
+}
+
+/// Returns the verification of the issuer for the credential type, if it is verified.
+/// Relying parties can call this to check the issuer of a credential.
+#[query]
+#[candid_method(query)]
+fn get_issuer_verification(req: GetIssuerVerificationRequest) -> Option<IssuerVerificationData> {
+    authorize_or_trap("get_issuer_verification");
+    issuer_verification(req.issuer, &req.credential_type)
+}
+
+/// Returns the verification of the issuer for the credential type, unless it is missing
+/// or has lapsed because the trusted issuer who made it is no longer trusted.
+fn issuer_verification(issuer: Principal, credential_type: &str) -> Option<IssuerVerificationData> {
+    ISSUER_VERIFICATIONS.with_borrow(|verifications| {
+        let key = IssuerVerificationKey::new(issuer, credential_type);
+        let record = verifications.get(&key)?;
+        if !record.verified_by_admin {
+            let verifier_key = IssuerVerificationKey::new(record.verified_by, credential_type);
+            let verifier_trusted = verifications
+                .get(&verifier_key)
+                .is_some_and(|verifier| verifier.verified_by_admin && verifier.trusted);
+            if !verifier_trusted {
+                return None;
+            }
         }
+        Some(record.to_data(key))
     })
 }
 
-// TODO: restrict or remove `configure()`.
-#[update]
-#[candid_method]
-fn configure(config: IssuerInit) {
-    apply_config(config);
+/// Returns the verification of the owner of the group for the credential type of the group.
+fn group_issuer_verification(key: &GroupKey) -> Option<IssuerVerificationData> {
+    let credential_type = credential_type_for_group(&key.group_name)?;
+    issuer_verification(key.owner, &credential_type)
 }
 
 fn apply_config(init: IssuerInit) {
@@ -665,17 +2709,83 @@ fn authorize_vc_request(
 async fn prepare_credential(
     req: PrepareCredentialRequest,
 ) -> Result<PreparedCredentialData, IssueCredentialError> {
+    authorize_vc_call("prepare_credential")?;
     let alias_tuple = match authorize_vc_request(&req.signed_id_alias, &caller(), time().into()) {
         Ok(alias_tuple) => alias_tuple,
         Err(err) => return Err(err),
     };
+    let salt = salt_for_specs(std::slice::from_ref(&req.credential_spec)).await?;
+    let result = prepare_and_sign_credential(&req.credential_spec, &alias_tuple, &salt);
+    update_root_hash();
+    result
+}
 
-    let credential_jwt = match prepare_credential_jwt(&req.credential_spec, &alias_tuple) {
-        Ok(credential) => credential,
-        Err(err) => return Result::<PreparedCredentialData, IssueCredentialError>::Err(err),
-    };
-    let signing_input =
-        vc_signing_input(&credential_jwt, &CANISTER_SIG_PK).expect("failed getting signing_input");
+/// Batch version of `prepare_credential`: the id alias is verified once, and the signatures
+/// of all prepared credentials are added with a single update of the certified data.
+#[update]
+#[candid_method]
+async fn prepare_credentials(
+    req: PrepareCredentialsRequest,
+) -> Result<PreparedCredentialsData, IssueCredentialError> {
+    authorize_vc_call("prepare_credentials")?;
+    check_batch_size(req.credential_specs.len())?;
+    let alias_tuple = authorize_vc_request(&req.signed_id_alias, &caller(), time().into())?;
+    let salt = salt_for_specs(&req.credential_specs).await?;
+    let results = req
+        .credential_specs
+        .iter()
+        .enumerate()
+        .map(|(i, spec)| {
+            // Derive a distinct salt per credential.
+            let salt = [salt.as_slice(), &(i as u32).to_be_bytes()[..]].concat();
+            prepare_and_sign_credential(spec, &alias_tuple, &salt)
+        })
+        .collect();
+    update_root_hash();
+    Ok(PreparedCredentialsData { results })
+}
+
+fn check_batch_size(size: usize) -> Result<(), IssueCredentialError> {
+    if size > MAX_CREDENTIALS_PER_BATCH {
+        return Err(IssueCredentialError::UnsupportedCredentialSpec(format!(
+            "too many credentials requested: {}, max. {}",
+            size, MAX_CREDENTIALS_PER_BATCH
+        )));
+    }
+    Ok(())
+}
+
+// Returns fresh randomness if any of the specs requests a format which needs salt.
+async fn salt_for_specs(specs: &[CredentialSpec]) -> Result<Vec<u8>, IssueCredentialError> {
+    let needs_salt = specs
+        .iter()
+        .any(|spec| matches!(get_format_from_spec(spec), Ok((_, CredentialFormat::SdJwt))));
+    if !needs_salt {
+        return Ok(vec![]);
+    }
+    let (salt,) = raw_rand()
+        .await
+        .map_err(|(_, e)| internal_error(&format!("failed to get randomness: {}", e)))?;
+    Ok(salt)
+}
+
+/// Prepares the credential for the given spec and adds its signature to `SIGNATURES`.
+/// The caller is responsible for updating the root hash afterwards.
+fn prepare_and_sign_credential(
+    credential_spec: &CredentialSpec,
+    alias_tuple: &AliasTuple,
+    salt: &[u8],
+) -> Result<PreparedCredentialData, IssueCredentialError> {
+    let (_, format) = get_format_from_spec(credential_spec)
+        .map_err(IssueCredentialError::UnsupportedCredentialSpec)?;
+    let (credential_jwt, group_key) = prepare_credential_jwt(credential_spec, alias_tuple)?;
+    let encoder = format.encoder();
+    let prepared_credential = encoder
+        .prepare(&credential_jwt, salt)
+        .map_err(|e| internal_error(&e))?;
+    let signing_input = encoder
+        .signing_input(&prepared_credential, &CANISTER_SIG_PK)
+        .expect("failed getting signing_input");
 
     SIGNATURES.with(|sigs| {
         let mut sigs = sigs.borrow_mut();
@@ -686,9 +2796,12 @@ async fn prepare_credential(
         };
         sigs.add_signature(&sig_inputs);
     });
-    update_root_hash();
+    update_dashboard(&group_key, |dashboard| {
+        dashboard.add_issued_credential(time());
+        dashboard.record_activity(time());
+    });
     Ok(PreparedCredentialData {
-        prepared_context: Some(ByteBuf::from(credential_jwt.as_bytes())),
+        prepared_context: Some(ByteBuf::from(prepared_credential.to_bytes())),
     })
 }
 
@@ -709,10 +2822,34 @@ fn update_root_hash() {
 #[query]
 #[candid_method(query)]
 fn get_credential(req: GetCredentialRequest) -> Result<IssuedCredentialData, IssueCredentialError> {
+    authorize_vc_call("get_credential")?;
     if let Err(err) = authorize_vc_request(&req.signed_id_alias, &caller(), time().into()) {
         return Result::<IssuedCredentialData, IssueCredentialError>::Err(err);
     };
-    let prepared_context = match req.prepared_context {
+    get_signed_credential(req.prepared_context)
+}
+
+/// Batch version of `get_credential`, the id alias is verified once.
+#[query]
+#[candid_method(query)]
+fn get_credentials(
+    req: GetCredentialsRequest,
+) -> Result<IssuedCredentialsData, IssueCredentialError> {
+    authorize_vc_call("get_credentials")?;
+    check_batch_size(req.credentials.len())?;
+    authorize_vc_request(&req.signed_id_alias, &caller(), time().into())?;
+    let results = req
+        .credentials
+        .into_iter()
+        .map(|credential| get_signed_credential(credential.prepared_context))
+        .collect();
+    Ok(IssuedCredentialsData { results })
+}
+
+fn get_signed_credential(
+    prepared_context: Option<ByteBuf>,
+) -> Result<IssuedCredentialData, IssueCredentialError> {
+    let prepared_context = match prepared_context {
         Some(context) => context,
         None => {
             return Result::<IssuedCredentialData, IssueCredentialError>::Err(internal_error(
@@ -720,16 +2857,16 @@ fn get_credential(req: GetCredentialRequest) -> Result<IssuedCredentialData, Iss
             ))
         }
     };
-    let credential_jwt = match String::from_utf8(prepared_context.into_vec()) {
-        Ok(s) => s,
-        Err(_) => {
-            return Result::<IssuedCredentialData, IssueCredentialError>::Err(internal_error(
-                "invalid prepared_context",
-            ))
+    let prepared_credential = match PreparedCredential::from_bytes(&prepared_context) {
+        Ok(prepared_credential) => prepared_credential,
+        Err(e) => {
+            return Result::<IssuedCredentialData, IssueCredentialError>::Err(internal_error(&e))
         }
     };
-    let signing_input =
-        vc_signing_input(&credential_jwt, &CANISTER_SIG_PK).expect("failed getting signing_input");
+    let encoder = prepared_credential.format.encoder();
+    let signing_input = encoder
+        .signing_input(&prepared_credential, &CANISTER_SIG_PK)
+        .expect("failed getting signing_input");
     let sig_result = SIGNATURES.with(|sigs| {
         let sig_map = sigs.borrow();
         let certified_assets_root_hash = ASSETS.with_borrow(|assets| assets.root_hash());
@@ -751,8 +2888,9 @@ fn get_credential(req: GetCredentialRequest) -> Result<IssuedCredentialData, Iss
             );
         }
     };
-    let vc_jws =
-        vc_jwt_to_jws(&credential_jwt, &CANISTER_SIG_PK, &sig).expect("failed constructing JWS");
+    let vc_jws = encoder
+        .encode(&prepared_credential, &CANISTER_SIG_PK, &sig)
+        .expect("failed constructing credential");
     Result::<IssuedCredentialData, IssueCredentialError>::Ok(IssuedCredentialData { vc_jws })
 }
 
@@ -761,6 +2899,11 @@ fn get_credential(req: GetCredentialRequest) -> Result<IssuedCredentialData, Iss
 async fn vc_consent_message(
     req: Icrc21VcConsentMessageRequest,
 ) -> Result<Icrc21ConsentInfo, Icrc21Error> {
+    check_method_policy("vc_consent_message").map_err(|violation| {
+        Icrc21Error::UnsupportedCanisterCall(Icrc21ErrorInfo {
+            description: violation.to_string(),
+        })
+    })?;
     get_vc_consent_message_en(&req.credential_spec)
 }
 
@@ -769,6 +2912,8 @@ async fn vc_consent_message(
 async fn derivation_origin(
     req: DerivationOriginRequest,
 ) -> Result<DerivationOriginData, DerivationOriginError> {
+    check_method_policy("derivation_origin")
+        .map_err(|violation| DerivationOriginError::Internal(violation.to_string()))?;
     get_derivation_origin(&req.frontend_hostname)
 }
 
@@ -911,10 +3056,52 @@ fn verify_vc_spec(spec: &CredentialSpec) -> Result<(), String> {
     Ok(())
 }
 
+// Removes the optional credential format argument from the spec.
+fn get_format_from_spec(
+    spec: &CredentialSpec,
+) -> Result<(CredentialSpec, CredentialFormat), String> {
+    let mut plain_spec = spec.to_owned();
+    let Some(format) = plain_spec
+        .arguments
+        .as_mut()
+        .and_then(|arguments| arguments.remove(CREDENTIAL_FORMAT_ARG))
+    else {
+        return Ok((plain_spec, CredentialFormat::default()));
+    };
+    let ArgumentValue::String(format) = format else {
+        return Err(format!(
+            "Credential spec has an unexpected value for {}-argument",
+            CREDENTIAL_FORMAT_ARG
+        ));
+    };
+    Ok((plain_spec, format.parse()?))
+}
+
+// Removes the optional nonce argument of the relying party from the spec.
+fn get_nonce_from_spec(spec: &CredentialSpec) -> Result<(CredentialSpec, Option<String>), String> {
+    let mut plain_spec = spec.to_owned();
+    let Some(nonce) = plain_spec
+        .arguments
+        .as_mut()
+        .and_then(|arguments| arguments.remove(CREDENTIAL_NONCE_ARG))
+    else {
+        return Ok((plain_spec, None));
+    };
+    let ArgumentValue::String(nonce) = nonce else {
+        return Err(format!(
+            "Credential spec has an unexpected value for {}-argument",
+            CREDENTIAL_NONCE_ARG
+        ));
+    };
+    Ok((plain_spec, Some(nonce)))
+}
+
 fn verify_spec_and_get_group_owner(
     spec: &CredentialSpec,
 ) -> Result<(CredentialSpec, Principal), String> {
-    let (plain_spec, owner) = get_owner_from_spec(spec)?;
+    let (spec, _format) = get_format_from_spec(spec)?;
+    let (spec, _nonce) = get_nonce_from_spec(&spec)?;
+    let (plain_spec, owner) = get_owner_from_spec(&spec)?;
     verify_vc_spec(&plain_spec)?;
     Ok((plain_spec, owner))
 }
@@ -922,9 +3109,15 @@ fn verify_spec_and_get_group_owner(
 #[query]
 #[candid_method(query)]
 pub fn http_request(req: HttpRequest) -> HttpResponse {
-    // TODO: add `/metrics`-endpoint
+    authorize_or_trap("http_request");
     let parts: Vec<&str> = req.url.split('?').collect();
     let path = parts[0];
+    if path == "/metrics" {
+        return metrics_response();
+    }
+    if let Some(hash) = parse_logo_path(path) {
+        return logo_response(hash);
+    }
     let sigs_root_hash =
         SIGNATURES.with_borrow(|sigs| pruned(labeled_hash(LABEL_SIG, &sigs.root_hash())));
     let maybe_asset = ASSETS.with_borrow(|assets| {
@@ -949,6 +3142,114 @@ pub fn http_request(req: HttpRequest) -> HttpResponse {
     }
 }
 
+// Logos are not certified, but they are addressed by the hash of their content, so clients
+// can verify them against the `logo_url` of the group.
+fn logo_response(hash: LogoHash) -> HttpResponse {
+    let mut headers = static_headers();
+    match LOGOS.with_borrow(|logos| logos.get(&hash)) {
+        Some(logo) => {
+            headers.extend([
+                ("Content-Type".to_string(), logo.content_type),
+                (
+                    "Cache-Control".to_string(),
+                    "public, max-age=31536000, immutable".to_string(),
+                ),
+                ("X-Content-Type-Options".to_string(), "nosniff".to_string()),
+            ]);
+            HttpResponse {
+                status_code: 200,
+                headers,
+                body: logo.content,
+            }
+        }
+        None => HttpResponse {
+            status_code: 404,
+            headers,
+            body: ByteBuf::from(format!("Logo {} not found.", hash)),
+        },
+    }
+}
+
+// Metrics are not certified, as they are meant to be scraped via the raw domain.
+fn metrics_response() -> HttpResponse {
+    let mut writer = MetricsEncoder::new(vec![], (time() / 1_000_000) as i64);
+    let mut headers = static_headers();
+    match encode_metrics(&mut writer) {
+        Ok(()) => {
+            headers.push((
+                "Content-Type".to_string(),
+                "text/plain; version=0.0.4".to_string(),
+            ));
+            HttpResponse {
+                status_code: 200,
+                headers,
+                body: ByteBuf::from(writer.into_inner()),
+            }
+        }
+        Err(err) => HttpResponse {
+            status_code: 500,
+            headers,
+            body: ByteBuf::from(format!("Failed to encode metrics: {}", err)),
+        },
+    }
+}
+
+fn encode_metrics(w: &mut MetricsEncoder<Vec<u8>>) -> std::io::Result<()> {
+    GROUP_STATS.with_borrow(|stats| {
+        w.encode_gauge(
+            "meta_issuer_groups",
+            stats.len() as f64,
+            "Number of groups, as of the last compaction of the group stats.",
+        )?;
+        let mut totals = MembershipCounts::default();
+        for counts in stats.values() {
+            totals.add_counts(counts);
+        }
+        let mut memberships = w.gauge_vec(
+            "meta_issuer_memberships",
+            "Number of memberships by status, as of the last compaction of the group stats.",
+        )?;
+        for (status, count) in totals.by_status() {
+            memberships = memberships.value(&[("status", status)], count as f64)?;
+        }
+        Ok::<(), std::io::Error>(())
+    })?;
+    HOUSEKEEPING_RUNS.with_borrow(|runs| {
+        let mut last_run = w.gauge_vec(
+            "meta_issuer_housekeeping_last_run_timestamp_seconds",
+            "Timestamp of the last run of each housekeeping job.",
+        )?;
+        for (job, run) in runs {
+            last_run = last_run.value(
+                &[("job", job.name())],
+                (run.timestamp_ns / 1_000_000_000) as f64,
+            )?;
+        }
+        let mut processed = w.gauge_vec(
+            "meta_issuer_housekeeping_last_run_processed",
+            "Number of items processed by the last run of each housekeeping job.",
+        )?;
+        for (job, run) in runs {
+            processed = processed.value(&[("job", job.name())], run.processed as f64)?;
+        }
+        Ok::<(), std::io::Error>(())
+    })?;
+    MIGRATION_STATE.with_borrow(|state| {
+        w.encode_gauge(
+            "meta_issuer_completed_migrations",
+            state.get().completed as f64,
+            "Number of completed migrations of the stable data.",
+        )?;
+        w.encode_gauge(
+            "meta_issuer_pending_migrations",
+            MIGRATIONS
+                .len()
+                .saturating_sub(state.get().completed as usize) as f64,
+            "Number of migrations of the stable data that are not complete yet.",
+        )
+    })
+}
+
 fn static_headers() -> Vec<(String, String)> {
     vec![("Access-Control-Allow-Origin".to_string(), "*".to_string())]
 }
@@ -982,16 +3283,36 @@ fn credential_id_for_principal(subject_principal: Principal) -> String {
     )
 }
 
+/// Returns the JWT of the credential, and the key of the group whose membership it attests.
 fn prepare_credential_jwt(
     credential_spec: &CredentialSpec,
     alias_tuple: &AliasTuple,
-) -> Result<String, IssueCredentialError> {
+) -> Result<(String, GroupKey), IssueCredentialError> {
     let (plain_spec, owner) = verify_spec_and_get_group_owner(credential_spec)
         .map_err(IssueCredentialError::UnsupportedCredentialSpec)?;
+    let (_, nonce) = get_nonce_from_spec(credential_spec)
+        .map_err(IssueCredentialError::UnsupportedCredentialSpec)?;
     GROUPS.with_borrow(|groups| {
         verify_principal_owns_credential(alias_tuple.id_dapp, &plain_spec, owner, groups)
     })?;
-    Ok(verifiable_credential(alias_tuple.id_alias, &plain_spec))
+    let status_list_index =
+        member_status_list_index(&plain_spec.credential_type, owner, alias_tuple.id_dapp)?;
+    let credential_jwt = verifiable_credential(alias_tuple.id_alias, &plain_spec);
+    let credential_jwt = add_credential_status(
+        &credential_jwt,
+        credential_status(
+            &status_list_url(&plain_spec.credential_type),
+            status_list_index,
+        ),
+    )
+    .and_then(|credential_jwt| add_issuer_principal(&credential_jwt, owner))
+    .and_then(|credential_jwt| match &nonce {
+        Some(nonce) => add_credential_nonce(&credential_jwt, nonce),
+        None => Ok(credential_jwt),
+    })
+    .map_err(|e| internal_error(&e))?;
+    let group_key = (group_name(&plain_spec.credential_type)?, owner).into();
+    Ok((credential_jwt, group_key))
 }
 
 fn group_name(credential_type: &str) -> Result<String, IssueCredentialError> {
@@ -1017,8 +3338,9 @@ fn verify_principal_owns_credential(
     groups: &GroupsMap,
 ) -> Result<(), IssueCredentialError> {
     let group_name = group_name(&credential_spec.credential_type)?;
-    if let Some(group_record) = groups.get(&(group_name.clone(), owner).into()) {
-        if let Some(member_record) = group_record.members.get(&user) {
+    let key: GroupKey = (group_name.clone(), owner).into();
+    if let Some(group_record) = groups.get(&key) {
+        if let Some(member_record) = find_member(&key, &group_record, user) {
             let stored_spec: CredentialSpec = OrdCredentialSpec {
                 credential_type: credential_spec.credential_type.clone(),
                 arguments: member_record.vc_arguments.clone(),
@@ -1045,6 +3367,13 @@ fn verify_principal_owns_credential(
                 }
             }
             if member_record.membership_status == MembershipStatus::Accepted {
+                // The membership may have expired since the last sweep.
+                if member_record.is_expired(group_record.membership_validity.as_ref(), time()) {
+                    return Err(IssueCredentialError::UnauthorizedSubject(format!(
+                        "membership of user {} in group {} has expired",
+                        user, group_name
+                    )));
+                }
                 return Ok(());
             }
         }
@@ -1055,6 +3384,12 @@ fn verify_principal_owns_credential(
     )))
 }
 
+/// Checks the current call of a VC-spec endpoint against its policy in `METHOD_POLICIES`.
+fn authorize_vc_call(method: &str) -> Result<(), IssueCredentialError> {
+    check_method_policy(method)
+        .map_err(|violation| IssueCredentialError::UnauthorizedSubject(violation.to_string()))
+}
+
 fn internal_error(msg: &str) -> IssueCredentialError {
     IssueCredentialError::Internal(String::from(msg))
 }
@@ -1072,10 +3407,9 @@ candid::export_service!();
 static ASSET_DIR: Dir<'_> = include_dir!("$CARGO_MANIFEST_DIR/frontend/dist");
 pub fn init_assets() {
     ASSETS.with_borrow_mut(|assets| {
-        *assets = CertifiedAssets::certify_assets(
-            collect_assets(&ASSET_DIR, Some(fixup_html)),
-            &static_headers(),
-        );
+        let mut all_assets = collect_assets(&ASSET_DIR, Some(fixup_html));
+        all_assets.extend(status_list_assets());
+        *assets = CertifiedAssets::certify_assets(all_assets, &static_headers());
     });
 
     update_root_hash()
@@ -1110,8 +3444,9 @@ fn fixup_html(html: &str) -> String {
 
 #[cfg(test)]
 mod test {
-    use crate::__export_service;
+    use crate::{__export_service, METHOD_POLICIES};
     use candid_parser::utils::{service_equal, CandidSource};
+    use std::collections::BTreeSet;
     use std::path::Path;
 
     /// Checks candid interface type equality by making sure that the service in the did file is
@@ -1130,4 +3465,25 @@ mod test {
             )
         });
     }
+
+    /// Checks that `METHOD_POLICIES` has exactly one policy for every method of the interface,
+    /// as `inspect_message` rejects the methods without a policy.
+    #[test]
+    fn should_have_a_policy_for_every_method() {
+        let canister_interface = __export_service();
+        let (env, actor) = CandidSource::Text(&canister_interface)
+            .load()
+            .expect("failed to load the canister interface");
+        let actor = actor.expect("missing service");
+        let methods: BTreeSet<&str> = env
+            .as_service(&actor)
+            .expect("invalid service")
+            .iter()
+            .map(|(method, _)| method.as_str())
+            .collect();
+        let policy_methods: BTreeSet<&str> =
+            METHOD_POLICIES.iter().map(|policy| policy.method).collect();
+        assert_eq!(policy_methods.len(), METHOD_POLICIES.len());
+        assert_eq!(policy_methods, methods);
+    }
 }
@@ -0,0 +1,223 @@
+//! Conversion of group members from/to CSV and JSON, for export and import by group owners.
+//!
+//! Both formats have the columns (resp. fields) `principal`, `nickname`, `status`,
+//! `vc_arguments` and `joined_timestamp_ns`, where `vc_arguments` is a JSON-object
+//! (in CSV: a JSON-encoded string, empty if there are no arguments).
+//! On import, only `principal` and `status` are required, and `nickname` is ignored,
+//! as nicknames are set by the users themselves.
+use candid::Principal;
+use meta_issuer::groups_api::{
+    ArgumentValue, MemberData, MembersFormat, MembershipStatus, VcArguments,
+};
+use serde_json::{json, Map, Value};
+
+const PRINCIPAL: &str = "principal";
+const NICKNAME: &str = "nickname";
+const STATUS: &str = "status";
+const VC_ARGUMENTS: &str = "vc_arguments";
+const JOINED_TIMESTAMP_NS: &str = "joined_timestamp_ns";
+
+/// A member parsed from an import.
+pub struct ImportedMember {
+    pub member: Principal,
+    pub membership_status: MembershipStatus,
+    pub vc_arguments: Option<VcArguments>,
+    pub joined_timestamp_ns: Option<u64>,
+}
+
+/// Result of parsing a single row, with its (1-based) row number.
+pub type ParsedRow = (u32, Result<ImportedMember, String>);
+
+pub fn export_members(members: &[MemberData], format: &MembersFormat) -> Result<String, String> {
+    match format {
+        MembersFormat::Csv => export_csv(members),
+        MembersFormat::Json => Ok(Value::Array(
+            members
+                .iter()
+                .map(|member| {
+                    json!({
+                        PRINCIPAL: member.member.to_text(),
+                        NICKNAME: member.nickname,
+                        STATUS: status_to_str(&member.membership_status),
+                        VC_ARGUMENTS: member.vc_arguments.as_ref().map(args_to_json),
+                        JOINED_TIMESTAMP_NS: member.joined_timestamp_ns,
+                    })
+                })
+                .collect(),
+        )
+        .to_string()),
+    }
+}
+
+/// Parses the given members, returning an error only if the content as a whole is malformed.
+pub fn parse_members(content: &str, format: &MembersFormat) -> Result<Vec<ParsedRow>, String> {
+    match format {
+        MembersFormat::Csv => parse_csv(content),
+        MembersFormat::Json => {
+            let rows: Vec<Map<String, Value>> = serde_json::from_str(content)
+                .map_err(|e| format!("malformed JSON member list: {}", e))?;
+            Ok(rows
+                .iter()
+                .enumerate()
+                .map(|(i, row)| (i as u32 + 1, parse_json_row(row)))
+                .collect())
+        }
+    }
+}
+
+fn export_csv(members: &[MemberData]) -> Result<String, String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer
+        .write_record([
+            PRINCIPAL,
+            NICKNAME,
+            STATUS,
+            VC_ARGUMENTS,
+            JOINED_TIMESTAMP_NS,
+        ])
+        .map_err(|e| e.to_string())?;
+    for member in members {
+        writer
+            .write_record([
+                member.member.to_text(),
+                member.nickname.clone(),
+                status_to_str(&member.membership_status).to_string(),
+                member
+                    .vc_arguments
+                    .as_ref()
+                    .map_or(String::new(), |args| args_to_json(args).to_string()),
+                member.joined_timestamp_ns.to_string(),
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+fn parse_csv(content: &str) -> Result<Vec<ParsedRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("malformed CSV header: {}", e))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|header| header == name);
+    let principal_column = column(PRINCIPAL).ok_or("missing principal column")?;
+    let status_column = column(STATUS).ok_or("missing status column")?;
+    let vc_arguments_column = column(VC_ARGUMENTS);
+    let joined_column = column(JOINED_TIMESTAMP_NS);
+
+    Ok(reader
+        .records()
+        .enumerate()
+        .map(|(i, record)| {
+            let parsed = record
+                .map_err(|e| format!("malformed CSV row: {}", e))
+                .and_then(|record| {
+                    let field = |column: Option<usize>| {
+                        column
+                            .and_then(|column| record.get(column))
+                            .filter(|value| !value.is_empty())
+                    };
+                    Ok(ImportedMember {
+                        member: parse_principal(field(Some(principal_column)))?,
+                        membership_status: parse_status(field(Some(status_column)))?,
+                        vc_arguments: field(vc_arguments_column)
+                            .map(|args| {
+                                serde_json::from_str(args)
+                                    .map_err(|e| format!("malformed vc_arguments: {}", e))
+                                    .and_then(|args| args_from_json(&args))
+                            })
+                            .transpose()?,
+                        joined_timestamp_ns: field(joined_column)
+                            .map(|ts| {
+                                ts.parse()
+                                    .map_err(|e| format!("malformed joined_timestamp_ns: {}", e))
+                            })
+                            .transpose()?,
+                    })
+                });
+            (i as u32 + 1, parsed)
+        })
+        .collect())
+}
+
+fn parse_json_row(row: &Map<String, Value>) -> Result<ImportedMember, String> {
+    Ok(ImportedMember {
+        member: parse_principal(row.get(PRINCIPAL).and_then(Value::as_str))?,
+        membership_status: parse_status(row.get(STATUS).and_then(Value::as_str))?,
+        vc_arguments: row
+            .get(VC_ARGUMENTS)
+            .filter(|args| !args.is_null())
+            .map(args_from_json)
+            .transpose()?,
+        joined_timestamp_ns: row
+            .get(JOINED_TIMESTAMP_NS)
+            .filter(|ts| !ts.is_null())
+            .map(|ts| ts.as_u64().ok_or("malformed joined_timestamp_ns"))
+            .transpose()?,
+    })
+}
+
+fn parse_principal(value: Option<&str>) -> Result<Principal, String> {
+    let value = value.ok_or("missing principal")?;
+    let principal =
+        Principal::from_text(value).map_err(|e| format!("bad principal {}: {}", value, e))?;
+    if principal == Principal::anonymous() {
+        return Err("anonymous principal cannot be a member".to_string());
+    }
+    Ok(principal)
+}
+
+fn parse_status(value: Option<&str>) -> Result<MembershipStatus, String> {
+    match value.ok_or("missing status")? {
+        "PendingReview" => Ok(MembershipStatus::PendingReview),
+        "Accepted" => Ok(MembershipStatus::Accepted),
+        "Rejected" => Ok(MembershipStatus::Rejected),
+        "Expired" => Ok(MembershipStatus::Expired),
+        other => Err(format!("unknown status: {}", other)),
+    }
+}
+
+pub fn status_to_str(status: &MembershipStatus) -> &'static str {
+    match status {
+        MembershipStatus::PendingReview => "PendingReview",
+        MembershipStatus::Accepted => "Accepted",
+        MembershipStatus::Rejected => "Rejected",
+        MembershipStatus::Expired => "Expired",
+    }
+}
+
+fn args_to_json(args: &VcArguments) -> Value {
+    Value::Object(
+        args.iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    ArgumentValue::String(s) => json!(s),
+                    ArgumentValue::Int(i) => json!(i),
+                };
+                (name.clone(), value)
+            })
+            .collect(),
+    )
+}
+
+fn args_from_json(args: &Value) -> Result<VcArguments, String> {
+    let args = args.as_object().ok_or("vc_arguments must be an object")?;
+    args.iter()
+        .map(|(name, value)| {
+            let value = match value {
+                Value::String(s) => ArgumentValue::String(s.clone()),
+                Value::Number(n) => ArgumentValue::Int(
+                    n.as_i64()
+                        .and_then(|i| i32::try_from(i).ok())
+                        .ok_or(format!("unsupported value for argument {}", name))?,
+                ),
+                _ => return Err(format!("unsupported value for argument {}", name)),
+            };
+            Ok((name.clone(), value))
+        })
+        .collect()
+}
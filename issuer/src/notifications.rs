@@ -0,0 +1,282 @@
+//! Notifications of owners about membership requests and of members about updates of their
+//! memberships, cf. `get_notifications`.
+//!
+//! Every principal has an outbox in stable memory, which keeps its latest notifications until
+//! they are acknowledged via `ack_notifications`. Principals can also set a webhook, to which
+//! every new notification is posted as JSON via an HTTPS outcall. The outcall is made by all
+//! replicas of the subnet, so the receiver gets duplicates, which share the notification id.
+//! Failed deliveries are not retried, the outbox remains the reference.
+use crate::members_io::status_to_str;
+use candid::{CandidType, Deserialize, Nat, Principal};
+use ic_cdk::api::management_canister::http_request::{
+    http_request, CanisterHttpRequestArgument, HttpHeader, HttpMethod, TransformContext,
+};
+use ic_stable_structures::storable::{Bound, Storable};
+use ic_stable_structures::{Memory, StableBTreeMap};
+use meta_issuer::groups_api::{MembershipStatus, NotificationData, NotificationKind};
+use serde_bytes::ByteBuf;
+use serde_json::json;
+use stable_schema::VersionedRecord;
+use std::borrow::Cow;
+
+/// Max. number of notifications in an outbox, beyond which the oldest ones are dropped.
+pub const MAX_NOTIFICATIONS_PER_RECIPIENT: usize = 100;
+pub const MAX_WEBHOOK_URL_LENGTH: usize = 512;
+/// Name of the query which strips the responses of webhooks to their status,
+/// so that the replicas agree on them.
+pub const WEBHOOK_TRANSFORM_METHOD: &str = "transform_webhook_response";
+const WEBHOOK_MAX_RESPONSE_BYTES: u64 = 1_024;
+/// Cycles attached to a webhook outcall, unused cycles are refunded.
+const WEBHOOK_CYCLES: u128 = 500_000_000;
+
+/// Key of a notification. The notifications of a recipient form a contiguous range.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct NotificationKey {
+    pub recipient: Principal,
+    pub id: u64,
+}
+
+impl Storable for NotificationKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode NotificationKey"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode NotificationKey")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct NotificationRecord {
+    pub kind: NotificationKind,
+    pub timestamp_ns: u64,
+    pub group_name: String,
+    pub owner: Principal,
+    pub member: Principal,
+    pub membership_status: MembershipStatus,
+}
+
+impl NotificationRecord {
+    pub fn to_data(&self, id: u64) -> NotificationData {
+        NotificationData {
+            id,
+            kind: self.kind.clone(),
+            timestamp_ns: self.timestamp_ns,
+            group_name: self.group_name.clone(),
+            owner: self.owner,
+            member: self.member,
+            membership_status: self.membership_status.clone(),
+        }
+    }
+}
+
+impl VersionedRecord for NotificationRecord {
+    const NAME: &'static str = "NotificationRecord";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for NotificationRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(stable_schema::encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        stable_schema::decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct WebhookRecord {
+    pub url: String,
+}
+
+impl VersionedRecord for WebhookRecord {
+    const NAME: &'static str = "WebhookRecord";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for WebhookRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(stable_schema::encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        stable_schema::decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Canister-wide state of the notifications: the next notification id.
+/// Ids are never reused, so that clients can poll for the notifications after the last one seen.
+#[derive(CandidType, Clone, Default, Deserialize)]
+pub struct NotificationState {
+    pub next_notification_id: u64,
+}
+
+impl Storable for NotificationState {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode NotificationState"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode NotificationState")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Adds a notification to the outbox of its recipient, and drops the oldest notifications
+/// of the recipient beyond `MAX_NOTIFICATIONS_PER_RECIPIENT`.
+pub fn push_notification<M: Memory>(
+    outbox: &mut StableBTreeMap<NotificationKey, NotificationRecord, M>,
+    key: NotificationKey,
+    record: NotificationRecord,
+) {
+    let recipient = key.recipient;
+    outbox.insert(key, record);
+    let keys: Vec<NotificationKey> = recipient_notifications(outbox, recipient, None)
+        .map(|(key, _)| key)
+        .collect();
+    let excess = keys.len().saturating_sub(MAX_NOTIFICATIONS_PER_RECIPIENT);
+    for key in &keys[..excess] {
+        outbox.remove(key);
+    }
+}
+
+/// Returns the notifications of the recipient, oldest first, after the given id if set.
+pub fn recipient_notifications<M: Memory>(
+    outbox: &StableBTreeMap<NotificationKey, NotificationRecord, M>,
+    recipient: Principal,
+    since: Option<u64>,
+) -> impl Iterator<Item = (NotificationKey, NotificationRecord)> + '_ {
+    let first_id = since.map_or(0, |id| id.saturating_add(1));
+    outbox
+        .range(
+            NotificationKey {
+                recipient,
+                id: first_id,
+            }..,
+        )
+        .take_while(move |(key, _)| key.recipient == recipient)
+}
+
+/// Removes the notifications of the recipient up to and including the given id.
+pub fn ack_notifications<M: Memory>(
+    outbox: &mut StableBTreeMap<NotificationKey, NotificationRecord, M>,
+    recipient: Principal,
+    up_to: u64,
+) {
+    let acked: Vec<NotificationKey> = recipient_notifications(outbox, recipient, None)
+        .map(|(key, _)| key)
+        .take_while(|key| key.id <= up_to)
+        .collect();
+    for key in acked {
+        outbox.remove(&key);
+    }
+}
+
+pub fn validate_webhook_url(url: &str) -> Result<(), String> {
+    if url.len() > MAX_WEBHOOK_URL_LENGTH {
+        return Err(format!(
+            "webhook URL too long: {} bytes, max. {}",
+            url.len(),
+            MAX_WEBHOOK_URL_LENGTH
+        ));
+    }
+    if !url.starts_with("https://") {
+        return Err(format!("webhook URL must start with https://, got {}", url));
+    }
+    Ok(())
+}
+
+/// The JSON document posted to webhooks.
+pub fn webhook_body(notification: &NotificationData) -> Vec<u8> {
+    let kind = match notification.kind {
+        NotificationKind::MembershipRequested => "MembershipRequested",
+        NotificationKind::MembershipUpdated => "MembershipUpdated",
+    };
+    json!({
+        "id": notification.id,
+        "kind": kind,
+        "timestamp_ns": notification.timestamp_ns,
+        "group_name": notification.group_name,
+        "owner": notification.owner.to_text(),
+        "member": notification.member.to_text(),
+        "membership_status": status_to_str(&notification.membership_status),
+    })
+    .to_string()
+    .into_bytes()
+}
+
+/// Posts the notification to the webhook of its recipient, if one is set.
+pub fn post_to_webhook(
+    webhook: Option<&WebhookRecord>,
+    notification: &NotificationData,
+    sender: &impl WebhookSender,
+) {
+    if let Some(webhook) = webhook {
+        sender.send(&webhook.url, webhook_body(notification));
+    }
+}
+
+/// Delivers webhook requests. The canister uses `HttpsOutcallSender`, tests replace it with
+/// a local stand-in, as HTTPS outcalls are not available to them.
+pub trait WebhookSender {
+    /// Starts posting the body to the URL, without waiting for the response.
+    fn send(&self, url: &str, body: Vec<u8>);
+}
+
+pub struct HttpsOutcallSender;
+
+impl WebhookSender for HttpsOutcallSender {
+    fn send(&self, url: &str, body: Vec<u8>) {
+        let request = CanisterHttpRequestArgument {
+            url: url.to_string(),
+            max_response_bytes: Some(WEBHOOK_MAX_RESPONSE_BYTES),
+            method: HttpMethod::POST,
+            headers: vec![HttpHeader {
+                name: "Content-Type".to_string(),
+                value: "application/json".to_string(),
+            }],
+            body: Some(body),
+            transform: Some(TransformContext::from_name(
+                WEBHOOK_TRANSFORM_METHOD.to_string(),
+                vec![],
+            )),
+        };
+        let url = url.to_string();
+        ic_cdk::spawn(async move {
+            match http_request(request, WEBHOOK_CYCLES).await {
+                Ok((response,)) => {
+                    let status = response.status;
+                    if status < Nat::from(200u32) || status >= Nat::from(300u32) {
+                        ic_cdk::println!("webhook {} responded with {}", url, status);
+                    }
+                }
+                Err((code, msg)) => {
+                    ic_cdk::println!("failed to call webhook {}: {:?} {}", url, code, msg);
+                }
+            }
+        });
+    }
+}
+
+/// Response of a webhook, cf. `transform_webhook_response`.
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct WebhookResponse {
+    pub status: Nat,
+    pub headers: Vec<WebhookHeader>,
+    pub body: ByteBuf,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct WebhookHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct WebhookTransformArgs {
+    pub response: WebhookResponse,
+    pub context: ByteBuf,
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,124 @@
+//! Unit tests of the notification outbox and of the webhook posts.
+use crate::notifications::{
+    ack_notifications, post_to_webhook, push_notification, recipient_notifications,
+    NotificationKey, NotificationRecord, WebhookRecord, WebhookSender,
+    MAX_NOTIFICATIONS_PER_RECIPIENT,
+};
+use candid::Principal;
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap};
+use meta_issuer::groups_api::{MembershipStatus, NotificationKind};
+use serde_json::Value;
+use std::cell::RefCell;
+
+type Outbox = StableBTreeMap<NotificationKey, NotificationRecord, DefaultMemoryImpl>;
+
+fn principal(i: u8) -> Principal {
+    Principal::from_slice(&[i])
+}
+
+fn outbox() -> Outbox {
+    StableBTreeMap::init(DefaultMemoryImpl::default())
+}
+
+fn record(member: Principal) -> NotificationRecord {
+    NotificationRecord {
+        kind: NotificationKind::MembershipRequested,
+        timestamp_ns: 42,
+        group_name: "Verified Humanity".to_string(),
+        owner: principal(1),
+        member,
+        membership_status: MembershipStatus::PendingReview,
+    }
+}
+
+fn push(outbox: &mut Outbox, recipient: Principal, id: u64) {
+    push_notification(
+        outbox,
+        NotificationKey { recipient, id },
+        record(principal(9)),
+    );
+}
+
+fn ids(outbox: &Outbox, recipient: Principal, since: Option<u64>) -> Vec<u64> {
+    recipient_notifications(outbox, recipient, since)
+        .map(|(key, _)| key.id)
+        .collect()
+}
+
+/// Stand-in for the HTTPS outcalls, which records the posted requests.
+#[derive(Default)]
+struct RecordingSender {
+    sent: RefCell<Vec<(String, Vec<u8>)>>,
+}
+
+impl WebhookSender for RecordingSender {
+    fn send(&self, url: &str, body: Vec<u8>) {
+        self.sent.borrow_mut().push((url.to_string(), body));
+    }
+}
+
+#[test]
+fn should_return_notifications_of_recipient_since_id() {
+    let mut outbox = outbox();
+    for id in 0..3 {
+        push(&mut outbox, principal(1), 2 * id);
+        push(&mut outbox, principal(2), 2 * id + 1);
+    }
+    assert_eq!(ids(&outbox, principal(1), None), vec![0, 2, 4]);
+    assert_eq!(ids(&outbox, principal(1), Some(2)), vec![4]);
+    assert_eq!(ids(&outbox, principal(2), Some(0)), vec![1, 3, 5]);
+    assert_eq!(ids(&outbox, principal(3), None), Vec::<u64>::new());
+}
+
+#[test]
+fn should_keep_latest_notifications_of_recipient() {
+    let mut outbox = outbox();
+    let count = MAX_NOTIFICATIONS_PER_RECIPIENT as u64 + 5;
+    for id in 0..count {
+        push(&mut outbox, principal(1), id);
+    }
+    push(&mut outbox, principal(2), count);
+    let kept = ids(&outbox, principal(1), None);
+    assert_eq!(kept.len(), MAX_NOTIFICATIONS_PER_RECIPIENT);
+    assert_eq!(kept.first(), Some(&5));
+    assert_eq!(kept.last(), Some(&(count - 1)));
+    assert_eq!(ids(&outbox, principal(2), None), vec![count]);
+}
+
+#[test]
+fn should_ack_notifications_up_to_id() {
+    let mut outbox = outbox();
+    for id in 0..4 {
+        push(&mut outbox, principal(1), id);
+    }
+    push(&mut outbox, principal(2), 4);
+    ack_notifications(&mut outbox, principal(1), 1);
+    assert_eq!(ids(&outbox, principal(1), None), vec![2, 3]);
+    // Acknowledging does not affect other recipients.
+    ack_notifications(&mut outbox, principal(1), 10);
+    assert_eq!(ids(&outbox, principal(1), None), Vec::<u64>::new());
+    assert_eq!(ids(&outbox, principal(2), None), vec![4]);
+}
+
+#[test]
+fn should_post_notifications_to_webhook() {
+    let sender = RecordingSender::default();
+    let notification = record(principal(2)).to_data(7);
+    post_to_webhook(None, &notification, &sender);
+    assert!(sender.sent.borrow().is_empty());
+
+    let webhook = WebhookRecord {
+        url: "https://example.com/hook".to_string(),
+    };
+    post_to_webhook(Some(&webhook), &notification, &sender);
+    let sent = sender.sent.borrow();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].0, "https://example.com/hook");
+    let body: Value = serde_json::from_slice(&sent[0].1).expect("malformed body");
+    assert_eq!(body["id"], 7);
+    assert_eq!(body["kind"], "MembershipRequested");
+    assert_eq!(body["group_name"], "Verified Humanity");
+    assert_eq!(body["owner"], principal(1).to_text());
+    assert_eq!(body["member"], principal(2).to_text());
+    assert_eq!(body["membership_status"], "PendingReview");
+}
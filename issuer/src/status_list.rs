@@ -0,0 +1,136 @@
+//! Credential status lists following the W3C StatusList2021 specification,
+//! cf. https://www.w3.org/TR/2023/WD-vc-status-list-20230427/
+//!
+//! The meta-issuer maintains a revocation list per group type (i.e. per credential type).
+//! Every accepted member of a group gets an index in the list of the group's type, and the bit
+//! at this index is set when the member is not accepted anymore. Indices are never reused,
+//! so a member who is accepted again gets a fresh index.
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use candid::{CandidType, Deserialize};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ic_stable_structures::storable::{Bound, Storable};
+use serde_bytes::ByteBuf;
+use serde_json::{json, Map, Value};
+use std::borrow::Cow;
+use std::io::Write;
+
+/// Number of entries of a status list, which is the minimum length recommended
+/// by the specification to provide herd privacy.
+pub const STATUS_LIST_LENGTH: u32 = 131_072;
+
+const STATUS_LIST_CONTEXT: &str = "https://w3id.org/vc/status-list/2021/v1";
+const STATUS_PURPOSE: &str = "revocation";
+
+/// A revocation bitstring, together with the next index to be assigned.
+#[derive(CandidType, Clone, Deserialize)]
+pub struct StatusList {
+    bits: ByteBuf,
+    next_index: u32,
+}
+
+impl Default for StatusList {
+    fn default() -> Self {
+        Self {
+            bits: ByteBuf::from(vec![0u8; (STATUS_LIST_LENGTH / 8) as usize]),
+            next_index: 0,
+        }
+    }
+}
+
+impl StatusList {
+    /// Assigns a fresh index in the list.
+    pub fn allocate_index(&mut self) -> Result<u32, String> {
+        if self.next_index >= STATUS_LIST_LENGTH {
+            return Err("status list is full".to_string());
+        }
+        let index = self.next_index;
+        self.next_index += 1;
+        Ok(index)
+    }
+
+    /// Returns the number of indices that can still be assigned.
+    pub fn available_indices(&self) -> u32 {
+        STATUS_LIST_LENGTH.saturating_sub(self.next_index)
+    }
+
+    /// Sets the bit at the given index, i.e. marks the corresponding credentials as revoked.
+    pub fn revoke(&mut self, index: u32) {
+        let (byte, mask) = Self::position(index);
+        self.bits[byte] |= mask;
+    }
+
+    pub fn is_revoked(&self, index: u32) -> bool {
+        let (byte, mask) = Self::position(index);
+        self.bits[byte] & mask != 0
+    }
+
+    /// Returns the GZIP-compressed, base64url-encoded bitstring.
+    pub fn encoded_list(&self) -> String {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(&self.bits)
+            .expect("failed to compress status list");
+        URL_SAFE_NO_PAD.encode(encoder.finish().expect("failed to compress status list"))
+    }
+
+    // The first index corresponds to the left-most bit of the first byte.
+    fn position(index: u32) -> (usize, u8) {
+        ((index / 8) as usize, 0x80 >> (index % 8))
+    }
+}
+
+impl Storable for StatusList {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode StatusList"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode StatusList")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Returns the path at which the status list for the given credential type is served.
+pub fn status_list_path(credential_type: &str) -> String {
+    format!("/status-lists/{}", credential_type)
+}
+
+/// Returns the StatusList2021Credential published at `list_url`.
+pub fn status_list_credential(list_url: &str, issuer_url: &str, list: &StatusList) -> Value {
+    json!({
+        "@context": ["https://www.w3.org/2018/credentials/v1", STATUS_LIST_CONTEXT],
+        "id": list_url,
+        "type": ["VerifiableCredential", "StatusList2021Credential"],
+        "issuer": issuer_url,
+        "credentialSubject": {
+            "id": format!("{}#list", list_url),
+            "type": "StatusList2021",
+            "statusPurpose": STATUS_PURPOSE,
+            "encodedList": list.encoded_list(),
+        },
+    })
+}
+
+/// Returns the `credentialStatus`-entry of a credential with the given index in the list.
+pub fn credential_status(list_url: &str, index: u32) -> Value {
+    json!({
+        "id": format!("{}#{}", list_url, index),
+        "type": "StatusList2021Entry",
+        "statusPurpose": STATUS_PURPOSE,
+        "statusListIndex": index.to_string(),
+        "statusListCredential": list_url,
+    })
+}
+
+/// Adds the `credentialStatus`-entry to the `vc`-claim of the given JWT claims.
+pub fn add_credential_status(credential_jwt: &str, status: Value) -> Result<String, String> {
+    let mut claims: Map<String, Value> = serde_json::from_str(credential_jwt)
+        .map_err(|e| format!("malformed credential JWT: {}", e))?;
+    claims
+        .get_mut("vc")
+        .and_then(Value::as_object_mut)
+        .ok_or("missing vc in credential JWT")?
+        .insert("credentialStatus".to_string(), status);
+    serde_json::to_string(&claims).map_err(|e| format!("failed to encode credential JWT: {}", e))
+}
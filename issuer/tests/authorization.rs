@@ -0,0 +1,611 @@
+//! Tests of the caller roles required by the issuer API, cf. `METHOD_POLICIES`.
+//!
+//! Every endpoint is called by each of the roles, and the outcome is compared to the
+//! expected one. Calls which are refused by the inspection of ingress messages are
+//! rejected before they reach the endpoint, which is accepted in place of the
+//! authentication and authorization errors returned by the endpoints themselves.
+use candid::Principal;
+use canister_tests::framework::{env, principal_1, principal_2, test_principal};
+use ic_test_state_machine_client::{CallError, StateMachine};
+use ic_verifiable_credentials::issuer_api::{
+    ArgumentValue, CredentialSpec, DerivationOriginRequest, GetCredentialRequest,
+    Icrc21ConsentPreferences, Icrc21VcConsentMessageRequest, PrepareCredentialRequest,
+};
+use internet_identity_interface::http_gateway::HttpRequest;
+use meta_issuer::groups_api::{
+    AckNotificationsRequest, AddEvidenceRequest, AddGroupRequest, CredentialToGet,
+    ExportMembersRequest, GetCredentialsRequest, GetEvidenceChunkRequest, GetGroupRequest,
+    GetIssuerVerificationRequest, GetNotificationsRequest, GroupVisibility, GroupsError,
+    ImportMembersRequest, JoinGroupRequest, ListGroupsRequest, MembersFormat,
+    PrepareCredentialsRequest, RateLimits, SetIssuerVerificationRequest,
+    SetMembershipValidityRequest, SetNotificationWebhookRequest, SetUserRequest,
+    UpdateGroupRequest, UpdateMembershipRequest, UploadEvidenceChunkRequest, UserData,
+};
+use serde_bytes::ByteBuf;
+use std::collections::HashMap;
+
+#[allow(dead_code)]
+mod util;
+use crate::util::{
+    api, do_add_group, do_join_group, install_canister_with_controller, WebhookResponse,
+    WebhookTransformArgs, DUMMY_ISSUER_INIT, DUMMY_SIGNED_ID_ALIAS, META_ISSUER_WASM,
+};
+
+const GROUP_NAME: &str = "Verified Humanity";
+const EVIDENCE_SIZE: usize = 10;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Outcome {
+    Allowed,
+    NotAuthenticated,
+    NotAuthorized,
+    NotFound,
+    /// The call was rejected, e.g. by the inspection of ingress messages or by a trap.
+    Rejected,
+    /// Any other error returned by the endpoint.
+    Failed,
+}
+use Outcome::*;
+
+impl Outcome {
+    fn matches(&self, expected: &Outcome) -> bool {
+        match (self, expected) {
+            (Rejected, NotAuthenticated | NotAuthorized) => true,
+            (actual, expected) => actual == expected,
+        }
+    }
+}
+
+fn groups_outcome<T>(result: Result<Result<T, GroupsError>, CallError>) -> Outcome {
+    match result {
+        Ok(Ok(_)) => Allowed,
+        Ok(Err(GroupsError::NotAuthenticated(_))) => NotAuthenticated,
+        Ok(Err(GroupsError::NotAuthorized(_))) => NotAuthorized,
+        Ok(Err(GroupsError::NotFound(_))) => NotFound,
+        Ok(Err(_)) => Failed,
+        Err(_) => Rejected,
+    }
+}
+
+fn other_outcome<T, E>(result: Result<Result<T, E>, CallError>) -> Outcome {
+    match result {
+        Ok(Ok(_)) => Allowed,
+        Ok(Err(_)) => Failed,
+        Err(_) => Rejected,
+    }
+}
+
+/// The callers, in the order of the expected outcomes.
+struct Roles {
+    anonymous: Principal,
+    authenticated: Principal,
+    member: Principal,
+    owner: Principal,
+    admin: Principal,
+}
+
+impl Roles {
+    fn new() -> Self {
+        Self {
+            anonymous: Principal::anonymous(),
+            authenticated: test_principal(3),
+            member: principal_2(),
+            owner: principal_1(),
+            admin: test_principal(4),
+        }
+    }
+
+    fn all(&self) -> [Principal; 5] {
+        [
+            self.anonymous,
+            self.authenticated,
+            self.member,
+            self.owner,
+            self.admin,
+        ]
+    }
+}
+
+/// An issuer controlled by the admin, with a group of the owner, which the member has asked
+/// to join and for which the member has uploaded a piece of evidence.
+struct Fixture {
+    env: StateMachine,
+    canister_id: Principal,
+    roles: Roles,
+    evidence_id: u64,
+}
+
+fn fixture() -> Fixture {
+    let env = env();
+    let roles = Roles::new();
+    let canister_id = install_canister_with_controller(
+        &env,
+        META_ISSUER_WASM.clone(),
+        Some(DUMMY_ISSUER_INIT.clone()),
+        Some(roles.admin),
+    );
+    let _ = do_add_group(GROUP_NAME, roles.owner, &env, canister_id);
+    do_join_group(
+        GROUP_NAME,
+        roles.owner,
+        roles.member,
+        None,
+        &env,
+        canister_id,
+    );
+    let evidence = api::add_evidence(
+        &env,
+        canister_id,
+        roles.member,
+        add_evidence_request(roles.owner),
+    )
+    .expect("API call failed")
+    .expect("Failed add_evidence");
+    let _ = api::upload_evidence_chunk(
+        &env,
+        canister_id,
+        roles.member,
+        upload_evidence_chunk_request(evidence.evidence_id),
+    )
+    .expect("API call failed")
+    .expect("Failed upload_evidence_chunk");
+    Fixture {
+        env,
+        canister_id,
+        roles,
+        evidence_id: evidence.evidence_id,
+    }
+}
+
+fn add_evidence_request(owner: Principal) -> AddEvidenceRequest {
+    AddEvidenceRequest {
+        group_name: GROUP_NAME.to_string(),
+        owner,
+        file_name: "badge.pdf".to_string(),
+        content_type: "application/pdf".to_string(),
+        size: EVIDENCE_SIZE as u32,
+    }
+}
+
+fn upload_evidence_chunk_request(evidence_id: u64) -> UploadEvidenceChunkRequest {
+    UploadEvidenceChunkRequest {
+        evidence_id,
+        chunk_index: 0,
+        content: ByteBuf::from(vec![42; EVIDENCE_SIZE]),
+    }
+}
+
+fn humanity_credential_spec(owner: Principal) -> CredentialSpec {
+    CredentialSpec {
+        credential_type: "VerifiedHumanity".to_string(),
+        arguments: Some(HashMap::from([(
+            "owner".to_string(),
+            ArgumentValue::String(owner.to_text()),
+        )])),
+    }
+}
+
+/// Calls `call` as each of the roles and checks the outcomes against `expected`.
+fn assert_outcomes(
+    fixture: &Fixture,
+    method: &str,
+    expected: [Outcome; 5],
+    call: impl Fn(Principal) -> Outcome,
+) {
+    let roles = ["anonymous", "authenticated", "member", "owner", "admin"];
+    for ((caller, role), expected) in fixture.roles.all().into_iter().zip(roles).zip(expected) {
+        let actual = call(caller);
+        assert!(
+            actual.matches(&expected),
+            "{} called by {}: expected {:?}, got {:?}",
+            method,
+            role,
+            expected,
+            actual
+        );
+    }
+}
+
+#[test]
+fn should_allow_public_methods_for_everyone() {
+    let f = fixture();
+    let (env, canister_id) = (&f.env, f.canister_id);
+    assert_outcomes(&f, "group_types", [Allowed; 5], |caller| {
+        groups_outcome(api::group_types(env, canister_id, caller))
+    });
+    assert_outcomes(&f, "list_groups", [Allowed; 5], |caller| {
+        groups_outcome(api::list_groups(
+            env,
+            canister_id,
+            Some(caller),
+            ListGroupsRequest {
+                group_name_substring: None,
+            },
+        ))
+    });
+    assert_outcomes(&f, "get_rate_limits", [Allowed; 5], |caller| {
+        other_outcome(api::get_rate_limits(env, canister_id, caller).map(Result::<_, ()>::Ok))
+    });
+    assert_outcomes(&f, "get_issuer_verification", [Allowed; 5], |caller| {
+        other_outcome(
+            api::get_issuer_verification(
+                env,
+                canister_id,
+                caller,
+                GetIssuerVerificationRequest {
+                    issuer: f.roles.owner,
+                    credential_type: "VerifiedHumanity".to_string(),
+                },
+            )
+            .map(Result::<_, ()>::Ok),
+        )
+    });
+    assert_outcomes(&f, "vc_consent_message", [Allowed; 5], |caller| {
+        other_outcome(api::vc_consent_message(
+            env,
+            canister_id,
+            caller,
+            &Icrc21VcConsentMessageRequest {
+                credential_spec: humanity_credential_spec(f.roles.owner),
+                preferences: Icrc21ConsentPreferences {
+                    language: "en-US".to_string(),
+                },
+            },
+        ))
+    });
+    assert_outcomes(&f, "derivation_origin", [Allowed; 5], |caller| {
+        other_outcome(api::derivation_origin(
+            env,
+            canister_id,
+            caller,
+            &DerivationOriginRequest {
+                frontend_hostname: "https://example.com".to_string(),
+            },
+        ))
+    });
+    assert_outcomes(&f, "transform_webhook_response", [Allowed; 5], |caller| {
+        other_outcome(
+            api::transform_webhook_response(
+                env,
+                canister_id,
+                caller,
+                &WebhookTransformArgs {
+                    response: WebhookResponse {
+                        status: 200u32.into(),
+                        headers: vec![],
+                        body: ByteBuf::new(),
+                    },
+                    context: ByteBuf::new(),
+                },
+            )
+            .map(Result::<_, ()>::Ok),
+        )
+    });
+    assert_outcomes(&f, "http_request", [Allowed; 5], |caller| {
+        other_outcome(
+            api::http_request(
+                env,
+                canister_id,
+                caller,
+                &HttpRequest {
+                    method: "GET".to_string(),
+                    url: "/metrics".to_string(),
+                    headers: vec![],
+                    body: ByteBuf::new(),
+                    certificate_version: None,
+                },
+            )
+            .map(Result::<_, ()>::Ok),
+        )
+    });
+}
+
+#[test]
+fn should_leave_credential_checks_to_the_id_alias() {
+    // The VC-spec endpoints are open to all callers, the id alias identifies the subject.
+    // The dummy id alias does not belong to any of the roles, so all calls fail
+    // in the endpoints, rather than being rejected.
+    let f = fixture();
+    let (env, canister_id) = (&f.env, f.canister_id);
+    let spec = humanity_credential_spec(f.roles.owner);
+    assert_outcomes(&f, "prepare_credential", [Failed; 5], |caller| {
+        other_outcome(api::prepare_credential(
+            env,
+            canister_id,
+            caller,
+            &PrepareCredentialRequest {
+                credential_spec: spec.clone(),
+                signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+            },
+        ))
+    });
+    assert_outcomes(&f, "get_credential", [Failed; 5], |caller| {
+        other_outcome(api::get_credential(
+            env,
+            canister_id,
+            caller,
+            &GetCredentialRequest {
+                credential_spec: spec.clone(),
+                signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+                prepared_context: None,
+            },
+        ))
+    });
+    assert_outcomes(&f, "prepare_credentials", [Failed; 5], |caller| {
+        other_outcome(api::prepare_credentials(
+            env,
+            canister_id,
+            caller,
+            &PrepareCredentialsRequest {
+                signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+                credential_specs: vec![spec.clone()],
+            },
+        ))
+    });
+    assert_outcomes(&f, "get_credentials", [Failed; 5], |caller| {
+        other_outcome(api::get_credentials(
+            env,
+            canister_id,
+            caller,
+            &GetCredentialsRequest {
+                signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+                credentials: vec![CredentialToGet {
+                    credential_spec: spec.clone(),
+                    prepared_context: None,
+                }],
+            },
+        ))
+    });
+}
+
+#[test]
+fn should_require_authentication_for_user_methods() {
+    let f = fixture();
+    let (env, canister_id) = (&f.env, f.canister_id);
+    let expected = [NotAuthenticated, Allowed, Allowed, Allowed, Allowed];
+    assert_outcomes(&f, "set_user", expected, |caller| {
+        let nickname = caller.to_text();
+        groups_outcome(api::set_user(
+            env,
+            canister_id,
+            caller,
+            SetUserRequest {
+                user_data: UserData {
+                    user_nickname: Some(nickname.clone()),
+                    issuer_nickname: Some(nickname),
+                },
+            },
+        ))
+    });
+    assert_outcomes(&f, "get_user", expected, |caller| {
+        groups_outcome(api::get_user(env, canister_id, caller))
+    });
+    assert_outcomes(
+        &f,
+        "add_group",
+        [NotAuthenticated, Allowed, Allowed, Failed, Allowed],
+        |caller| {
+            groups_outcome(api::add_group(
+                env,
+                canister_id,
+                caller,
+                AddGroupRequest {
+                    group_name: GROUP_NAME.to_string(),
+                },
+            ))
+        },
+    );
+    assert_outcomes(&f, "join_group", expected, |caller| {
+        groups_outcome(api::join_group(
+            env,
+            canister_id,
+            caller,
+            JoinGroupRequest {
+                group_name: GROUP_NAME.to_string(),
+                owner: f.roles.owner,
+                vc_arguments: None,
+                message: None,
+            },
+        ))
+    });
+    assert_outcomes(&f, "list_my_memberships", expected, |caller| {
+        groups_outcome(api::list_my_memberships(env, canister_id, caller))
+    });
+    assert_outcomes(&f, "get_owner_dashboard", expected, |caller| {
+        groups_outcome(api::get_owner_dashboard(env, canister_id, caller))
+    });
+    assert_outcomes(&f, "get_notifications", expected, |caller| {
+        groups_outcome(api::get_notifications(
+            env,
+            canister_id,
+            caller,
+            GetNotificationsRequest { since: None },
+        ))
+    });
+    assert_outcomes(&f, "ack_notifications", expected, |caller| {
+        groups_outcome(api::ack_notifications(
+            env,
+            canister_id,
+            caller,
+            AckNotificationsRequest { up_to: 0 },
+        ))
+    });
+    assert_outcomes(&f, "set_notification_webhook", expected, |caller| {
+        groups_outcome(api::set_notification_webhook(
+            env,
+            canister_id,
+            caller,
+            SetNotificationWebhookRequest { url: None },
+        ))
+    });
+}
+
+#[test]
+fn should_require_group_owner() {
+    let f = fixture();
+    let (env, canister_id) = (&f.env, f.canister_id);
+    let expected = [NotAuthenticated, NotFound, NotFound, Allowed, NotFound];
+    assert_outcomes(&f, "get_group", expected, |caller| {
+        groups_outcome(api::get_group(
+            env,
+            canister_id,
+            caller,
+            GetGroupRequest {
+                group_name: GROUP_NAME.to_string(),
+            },
+        ))
+    });
+    assert_outcomes(&f, "update_membership", expected, |caller| {
+        groups_outcome(api::update_membership(
+            env,
+            canister_id,
+            caller,
+            UpdateMembershipRequest {
+                group_name: GROUP_NAME.to_string(),
+                updates: vec![],
+            },
+        ))
+    });
+    assert_outcomes(&f, "set_membership_validity", expected, |caller| {
+        groups_outcome(api::set_membership_validity(
+            env,
+            canister_id,
+            caller,
+            SetMembershipValidityRequest {
+                group_name: GROUP_NAME.to_string(),
+                validity: None,
+            },
+        ))
+    });
+    assert_outcomes(&f, "update_group", expected, |caller| {
+        groups_outcome(api::update_group(
+            env,
+            canister_id,
+            caller,
+            UpdateGroupRequest {
+                group_name: GROUP_NAME.to_string(),
+                description: None,
+                contact_url: None,
+                tags: vec![],
+                visibility: GroupVisibility::Public,
+                logo: None,
+            },
+        ))
+    });
+    assert_outcomes(&f, "export_members", expected, |caller| {
+        groups_outcome(api::export_members(
+            env,
+            canister_id,
+            caller,
+            ExportMembersRequest {
+                group_name: GROUP_NAME.to_string(),
+                format: MembersFormat::Csv,
+            },
+        ))
+    });
+    assert_outcomes(&f, "import_members", expected, |caller| {
+        groups_outcome(api::import_members(
+            env,
+            canister_id,
+            caller,
+            ImportMembersRequest {
+                group_name: GROUP_NAME.to_string(),
+                format: MembersFormat::Json,
+                content: "[]".to_string(),
+            },
+        ))
+    });
+    assert_outcomes(&f, "get_evidence_chunk", expected, |caller| {
+        groups_outcome(api::get_evidence_chunk(
+            env,
+            canister_id,
+            caller,
+            GetEvidenceChunkRequest {
+                evidence_id: f.evidence_id,
+                chunk_index: 0,
+            },
+        ))
+    });
+}
+
+#[test]
+fn should_require_group_member() {
+    let f = fixture();
+    let (env, canister_id) = (&f.env, f.canister_id);
+    let expected = [NotAuthenticated, NotFound, Allowed, NotFound, NotFound];
+    assert_outcomes(&f, "add_evidence", expected, |caller| {
+        groups_outcome(api::add_evidence(
+            env,
+            canister_id,
+            caller,
+            add_evidence_request(f.roles.owner),
+        ))
+    });
+    assert_outcomes(&f, "upload_evidence_chunk", expected, |caller| {
+        groups_outcome(api::upload_evidence_chunk(
+            env,
+            canister_id,
+            caller,
+            upload_evidence_chunk_request(f.evidence_id),
+        ))
+    });
+}
+
+#[test]
+fn should_require_controller_for_admin_methods() {
+    let f = fixture();
+    let (env, canister_id) = (&f.env, f.canister_id);
+    assert_outcomes(
+        &f,
+        "configure",
+        [Rejected, Rejected, Rejected, Rejected, Allowed],
+        |caller| match api::configure_as(env, canister_id, caller, &DUMMY_ISSUER_INIT.clone()) {
+            Ok(()) => Allowed,
+            Err(_) => Rejected,
+        },
+    );
+    assert_outcomes(
+        &f,
+        "set_rate_limits",
+        [
+            NotAuthorized,
+            NotAuthorized,
+            NotAuthorized,
+            NotAuthorized,
+            Allowed,
+        ],
+        |caller| {
+            groups_outcome(api::set_rate_limits(
+                env,
+                canister_id,
+                caller,
+                &RateLimits::default(),
+            ))
+        },
+    );
+    // Trusted issuers can verify issuers as well, cf. `manage_groups`.
+    assert_outcomes(
+        &f,
+        "set_issuer_verification",
+        [
+            NotAuthenticated,
+            NotAuthorized,
+            NotAuthorized,
+            NotAuthorized,
+            Allowed,
+        ],
+        |caller| {
+            groups_outcome(api::set_issuer_verification(
+                env,
+                canister_id,
+                caller,
+                SetIssuerVerificationRequest {
+                    issuer: f.roles.owner,
+                    credential_type: "VerifiedHumanity".to_string(),
+                    verified: true,
+                    trusted: false,
+                },
+            ))
+        },
+    );
+}
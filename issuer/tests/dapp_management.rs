@@ -1,21 +1,31 @@
 //! Tests related to general dapp management.
 
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use canister_tests::api::http_request;
-use canister_tests::framework::{env, principal_1, time};
+use canister_tests::framework::{env, principal_1, principal_2, test_principal, time};
+use flate2::read::GzDecoder;
 use ic_cdk::api::management_canister::provisional::CanisterId;
 use ic_response_verification::types::VerificationInfo;
 use ic_response_verification::verify_request_response_pair;
 use ic_test_state_machine_client::{CallError, StateMachine};
 use internet_identity_interface::http_gateway::{HttpRequest, HttpResponse};
-
+use meta_issuer::groups_api::{
+    ImportMembersRequest, MembersFormat, MembershipStatus, MembershipUpdate, NotificationKind,
+    UserData,
+};
 use serde_bytes::ByteBuf;
+use serde_json::Value;
+use std::io::Read;
 use std::time::Duration;
 
 #[allow(dead_code)]
 mod util;
 use crate::util::{
-    add_group_with_member, api, do_add_group, do_get_group, install_issuer, DUMMY_ISSUER_INIT,
-    META_ISSUER_WASM,
+    add_group_with_member, api, do_add_group, do_get_group, do_get_notifications,
+    do_get_owner_dashboard, do_get_user, do_join_group, do_list_my_memberships, do_set_user,
+    do_update_membership, install_canister, install_issuer, HousekeepingConfig, IssuerInit,
+    DUMMY_ISSUER_INIT, META_ISSUER_PREVIOUS_WASM, META_ISSUER_WASM,
 };
 
 #[test]
@@ -66,6 +76,7 @@ fn issuer_canister_serves_http_assets() -> Result<(), CallError> {
             "/",
             "/.well-known/ic-domains",
             "/.well-known/ii-alternative-origins",
+            "/status-lists/VerifiedHumanity",
         ] {
             let request = HttpRequest {
                 method: "GET".to_string(),
@@ -94,6 +105,83 @@ fn issuer_canister_serves_http_assets() -> Result<(), CallError> {
     Ok(())
 }
 
+fn get_status_list(env: &StateMachine, canister_id: CanisterId, path: &str) -> Vec<u8> {
+    let request = HttpRequest {
+        method: "GET".to_string(),
+        url: path.to_string(),
+        headers: vec![],
+        body: ByteBuf::new(),
+        certificate_version: Some(2),
+    };
+    let http_response = http_request(env, canister_id, &request).expect("API call failed");
+    assert_eq!(http_response.status_code, 200);
+    let credential: Value =
+        serde_json::from_slice(&http_response.body).expect("malformed status list credential");
+    assert_eq!(credential["type"][1], "StatusList2021Credential");
+    assert_eq!(
+        credential["credentialSubject"]["statusPurpose"],
+        "revocation"
+    );
+    let encoded_list = credential["credentialSubject"]["encodedList"]
+        .as_str()
+        .expect("missing encodedList");
+    let compressed = URL_SAFE_NO_PAD
+        .decode(encoded_list)
+        .expect("malformed encodedList");
+    let mut bits = vec![];
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut bits)
+        .expect("malformed encodedList");
+    bits
+}
+
+#[test]
+fn should_revoke_in_status_list_when_member_is_rejected() {
+    let env = env();
+    let canister_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let path = "/status-lists/VerifiedHumanity";
+    let group_name = "Verified Humanity";
+    let owner = principal_1();
+    let member = principal_2();
+
+    let bits = get_status_list(&env, canister_id, path);
+    assert_eq!(bits.len(), 131_072 / 8);
+    assert!(bits.iter().all(|byte| *byte == 0));
+
+    add_group_with_member(group_name, owner, member, None, &env, canister_id);
+    let bits = get_status_list(&env, canister_id, path);
+    assert_eq!(bits[0] & 0x80, 0);
+
+    do_update_membership(
+        group_name,
+        vec![MembershipUpdate {
+            member,
+            new_status: MembershipStatus::Rejected,
+            note: None,
+        }],
+        owner,
+        &env,
+        canister_id,
+    );
+    let bits = get_status_list(&env, canister_id, path);
+    assert_eq!(bits[0] & 0x80, 0x80);
+
+    // Accepting the member again assigns a fresh index.
+    do_update_membership(
+        group_name,
+        vec![MembershipUpdate {
+            member,
+            new_status: MembershipStatus::Accepted,
+            note: None,
+        }],
+        owner,
+        &env,
+        canister_id,
+    );
+    let bits = get_status_list(&env, canister_id, path);
+    assert_eq!(bits[0], 0x80);
+}
+
 #[test]
 fn should_upgrade_issuer() -> Result<(), CallError> {
     let env = env();
@@ -141,3 +229,279 @@ fn should_retain_groups_upgrade() -> Result<(), CallError> {
     assert_eq!(group_data_before, group_data_after);
     Ok(())
 }
+
+#[test]
+fn should_migrate_data_of_previous_release() -> Result<(), CallError> {
+    let env = env();
+    let issuer_id = install_canister(
+        &env,
+        META_ISSUER_PREVIOUS_WASM.clone(),
+        Some(DUMMY_ISSUER_INIT.clone()),
+    );
+    let owner = principal_1();
+    let member = principal_2();
+    let group_name = "Some group";
+    add_group_with_member(group_name, owner, member, None, &env, issuer_id);
+    let user_data = UserData {
+        user_nickname: Some("Some user".to_string()),
+        issuer_nickname: Some("Some issuer".to_string()),
+    };
+    do_set_user(user_data.clone(), owner, &env, issuer_id);
+
+    // Upgrade to the current release, and let the migrations complete.
+    let arg = candid::encode_one("()").expect("error encoding issuer init arg as candid");
+    env.upgrade_canister(issuer_id, META_ISSUER_WASM.clone(), arg, None)?;
+    env.tick();
+
+    // Check that the data survived the upgrade, and that the canister keeps working.
+    assert_eq!(do_get_user(owner, &env, issuer_id), user_data);
+    let group_data = do_get_group(group_name, owner, &env, issuer_id);
+    assert_eq!(group_data.issuer_nickname, "Some issuer");
+    assert_eq!(group_data.members.len(), 1);
+    assert_eq!(group_data.members[0].member, member);
+    assert_eq!(
+        group_data.members[0].membership_status,
+        MembershipStatus::Accepted
+    );
+    let memberships = do_list_my_memberships(member, &env, issuer_id).memberships;
+    assert_eq!(memberships.len(), 1);
+    assert_eq!(memberships[0].group_name, group_name);
+    let dashboard = do_get_owner_dashboard(owner, &env, issuer_id);
+    assert_eq!(dashboard.groups.len(), 1);
+    assert_eq!(dashboard.groups[0].member_counts.accepted, 1);
+    do_add_group("Another group", owner, &env, issuer_id);
+
+    let metrics = get_metrics(&env, issuer_id);
+    assert_eq!(
+        metric_value(&metrics, "meta_issuer_completed_migrations"),
+        Some(8.0)
+    );
+    assert_eq!(
+        metric_value(&metrics, "meta_issuer_pending_migrations"),
+        Some(0.0)
+    );
+    Ok(())
+}
+
+#[test]
+fn should_not_migrate_data_of_fresh_install() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+
+    let metrics = get_metrics(&env, issuer_id);
+    assert_eq!(
+        metric_value(&metrics, "meta_issuer_completed_migrations"),
+        Some(8.0)
+    );
+    assert_eq!(
+        metric_value(&metrics, "meta_issuer_pending_migrations"),
+        Some(0.0)
+    );
+}
+
+fn get_metrics(env: &StateMachine, canister_id: CanisterId) -> String {
+    let request = HttpRequest {
+        method: "GET".to_string(),
+        url: "/metrics".to_string(),
+        headers: vec![],
+        body: ByteBuf::new(),
+        certificate_version: None,
+    };
+    let http_response = http_request(env, canister_id, &request).expect("API call failed");
+    assert_eq!(http_response.status_code, 200);
+    String::from_utf8(http_response.body.into_vec()).expect("metrics are not UTF-8")
+}
+
+/// Returns the value of the metric with the given name (including labels).
+fn metric_value(metrics: &str, metric: &str) -> Option<f64> {
+    metrics.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        if parts.next() != Some(metric) {
+            return None;
+        }
+        parts
+            .next()
+            .map(|value| value.parse().expect("malformed metric value"))
+    })
+}
+
+#[test]
+fn should_report_housekeeping_in_metrics() {
+    let env = env();
+    let canister_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    add_group_with_member(
+        "Verified Humanity",
+        principal_1(),
+        principal_2(),
+        None,
+        &env,
+        canister_id,
+    );
+
+    let metrics = get_metrics(&env, canister_id);
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"meta_issuer_housekeeping_last_run_timestamp_seconds{job="prune_signatures"}"#
+        ),
+        None
+    );
+
+    // Default interval of the housekeeping.
+    env.advance_time(Duration::from_secs(10 * 60));
+    env.tick();
+
+    let metrics = get_metrics(&env, canister_id);
+    for job in [
+        "prune_signatures",
+        "expire_memberships",
+        "reject_stale_pending",
+        "compact_group_stats",
+    ] {
+        let last_run = metric_value(
+            &metrics,
+            &format!(
+                r#"meta_issuer_housekeeping_last_run_timestamp_seconds{{job="{}"}}"#,
+                job
+            ),
+        )
+        .expect("missing last run of housekeeping job");
+        assert!(last_run > 0.0);
+    }
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"meta_issuer_housekeeping_last_run_processed{job="compact_group_stats"}"#
+        ),
+        Some(1.0)
+    );
+    assert_eq!(metric_value(&metrics, "meta_issuer_groups"), Some(1.0));
+    assert_eq!(
+        metric_value(&metrics, r#"meta_issuer_memberships{status="Accepted"}"#),
+        Some(1.0)
+    );
+}
+
+#[test]
+fn should_reject_stale_pending_requests() {
+    let env = env();
+    let day = Duration::from_secs(24 * 60 * 60);
+    let canister_id = install_issuer(
+        &env,
+        Some(IssuerInit {
+            housekeeping: Some(HousekeepingConfig {
+                interval_s: 60,
+                pending_review_max_age_ns: Some(day.as_nanos() as u64),
+            }),
+            ..DUMMY_ISSUER_INIT.clone()
+        }),
+    );
+    let group_name = "Some group";
+    let owner = principal_1();
+    let stale_member = principal_2();
+    let recent_member = test_principal(3);
+    do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, stale_member, None, &env, canister_id);
+
+    env.advance_time(day);
+    do_join_group(group_name, owner, recent_member, None, &env, canister_id);
+    env.advance_time(Duration::from_secs(60));
+    env.tick();
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    let member_data = |principal| {
+        group_data
+            .members
+            .iter()
+            .find(|member_data| member_data.member == principal)
+            .expect("missing member")
+    };
+    assert_eq!(
+        member_data(stale_member).membership_status,
+        MembershipStatus::Rejected
+    );
+    assert!(member_data(stale_member).reviewer_note.is_some());
+    assert_eq!(
+        member_data(recent_member).membership_status,
+        MembershipStatus::PendingReview
+    );
+
+    // The member is notified as if the owner had rejected the request.
+    let notifications = do_get_notifications(None, stale_member, &env, canister_id).notifications;
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].kind, NotificationKind::MembershipUpdated);
+    assert_eq!(
+        notifications[0].membership_status,
+        MembershipStatus::Rejected
+    );
+    assert!(do_get_notifications(None, recent_member, &env, canister_id)
+        .notifications
+        .is_empty());
+}
+
+#[test]
+fn should_reject_stale_pending_requests_in_batches() {
+    let env = env();
+    let day = Duration::from_secs(24 * 60 * 60);
+    let canister_id = install_issuer(
+        &env,
+        Some(IssuerInit {
+            housekeeping: Some(HousekeepingConfig {
+                interval_s: 60,
+                pending_review_max_age_ns: Some(day.as_nanos() as u64),
+            }),
+            ..DUMMY_ISSUER_INIT.clone()
+        }),
+    );
+    let group_name = "Some group";
+    let owner = principal_1();
+    do_add_group(group_name, owner, &env, canister_id);
+    // More members than a housekeeping job scans per timer call.
+    let member_count = 1_000;
+    let rows: Vec<String> = (0..member_count)
+        .map(|i| {
+            format!(
+                r#"{{"principal":"{}","status":"PendingReview"}}"#,
+                test_principal(10 + i)
+            )
+        })
+        .collect();
+    let result = api::import_members(
+        &env,
+        canister_id,
+        owner,
+        ImportMembersRequest {
+            group_name: group_name.to_string(),
+            format: MembersFormat::Json,
+            content: format!("[{}]", rows.join(",")),
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed import_members");
+    assert_eq!(result.imported_count, member_count as u32);
+
+    // One timer call per batch, plus one for the groups of `compact_group_stats`.
+    env.advance_time(day + Duration::from_secs(60));
+    for _ in 0..5 {
+        env.tick();
+    }
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(group_data.members.len(), member_count as usize);
+    assert!(group_data
+        .members
+        .iter()
+        .all(|member_data| member_data.membership_status == MembershipStatus::Rejected));
+    let metrics = get_metrics(&env, canister_id);
+    assert_eq!(
+        metric_value(
+            &metrics,
+            r#"meta_issuer_housekeeping_last_run_processed{job="reject_stale_pending"}"#
+        ),
+        Some(member_count as f64)
+    );
+    assert_eq!(
+        metric_value(&metrics, r#"meta_issuer_memberships{status="Rejected"}"#),
+        Some(member_count as f64)
+    );
+}
@@ -1,6 +1,8 @@
 //! Tests related to Verifiable Credentials API.
 
 use assert_matches::assert_matches;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use candid::Principal;
 use canister_tests::api::internet_identity::vc_mvp as ii_api;
 use canister_tests::flows;
@@ -22,19 +24,27 @@ use internet_identity_interface::internet_identity::types::vc_mvp::{
     GetIdAliasRequest, PrepareIdAliasRequest,
 };
 use internet_identity_interface::internet_identity::types::FrontendHostname;
+use meta_issuer::groups_api::{
+    CredentialToGet, GetCredentialsRequest, MembershipValidity, PrepareCredentialsRequest,
+    SetIssuerVerificationRequest,
+};
 use relying_party::rp_api;
 use relying_party::rp_api::{
     AddExclusiveContentRequest, ContentData, ContentError, IssuerData, ValidateVpRequest,
+    ValidatedVpData, VpChallenge, VpValidationError,
 };
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::time::UNIX_EPOCH;
 
 #[allow(dead_code)]
 mod util;
 use crate::util::{
-    add_group_with_member, api, install_canister, install_issuer, IssuerInit,
-    DUMMY_ALIAS_ID_DAPP_PRINCIPAL, DUMMY_ISSUER_INIT, DUMMY_SIGNED_ID_ALIAS, II_WASM,
-    RELYING_PARTY_WASM,
+    add_group_with_member, api, do_get_owner_dashboard, do_list_my_memberships,
+    do_set_membership_validity, install_canister, install_canister_with_controller, install_issuer,
+    IssuerInit, DUMMY_ALIAS_ID_DAPP_PRINCIPAL, DUMMY_ISSUER_INIT, DUMMY_SIGNED_ID_ALIAS, II_WASM,
+    META_ISSUER_WASM, RELYING_PARTY_WASM,
 };
 
 #[test]
@@ -179,6 +189,15 @@ fn add_owner(credential_spec: &CredentialSpec, owner: Principal) -> CredentialSp
     spec
 }
 
+fn add_nonce(credential_spec: &CredentialSpec, nonce: &str) -> CredentialSpec {
+    let mut spec = credential_spec.to_owned();
+    spec.arguments.get_or_insert_with(HashMap::new).insert(
+        "nonce".to_string(),
+        ArgumentValue::String(nonce.to_string()),
+    );
+    spec
+}
+
 #[test]
 fn should_fail_prepare_credential_for_unauthorized_principal() {
     let env = env();
@@ -396,6 +415,121 @@ fn should_prepare_credentials_for_authorized_principal() {
     }
 }
 
+#[test]
+fn should_prepare_credential_with_spec_from_my_memberships() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let authorized_principal = Principal::from_text(DUMMY_ALIAS_ID_DAPP_PRINCIPAL).unwrap();
+    let owner = principal_1();
+    let spec = verified_age_credential_spec(18);
+    add_group_with_member(
+        &group_name_for_credential_type(&spec.credential_type),
+        owner,
+        authorized_principal,
+        spec.arguments.clone(),
+        &env,
+        issuer_id,
+    );
+    let memberships = do_list_my_memberships(authorized_principal, &env, issuer_id).memberships;
+    let listed_spec: CredentialSpec = memberships[0]
+        .credential_spec
+        .clone()
+        .expect("missing credential spec")
+        .into();
+    assert_eq!(listed_spec, add_owner(&spec, owner));
+
+    let response = api::prepare_credential(
+        &env,
+        issuer_id,
+        authorized_principal,
+        &PrepareCredentialRequest {
+            credential_spec: listed_spec,
+            signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(response, Ok(_));
+}
+
+#[test]
+fn should_count_issued_credentials_in_owner_dashboard() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let authorized_principal = Principal::from_text(DUMMY_ALIAS_ID_DAPP_PRINCIPAL).unwrap();
+    let owner = principal_1();
+    let spec = verified_humanity_credential_spec();
+    add_group_with_member(
+        &group_name_for_credential_type(&spec.credential_type),
+        owner,
+        authorized_principal,
+        spec.arguments.clone(),
+        &env,
+        issuer_id,
+    );
+    for _ in 0..2 {
+        let response = api::prepare_credential(
+            &env,
+            issuer_id,
+            authorized_principal,
+            &PrepareCredentialRequest {
+                credential_spec: add_owner(&spec, owner),
+                signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+            },
+        )
+        .expect("API call failed");
+        assert_matches!(response, Ok(_));
+    }
+
+    let dashboard = do_get_owner_dashboard(owner, &env, issuer_id);
+    assert_eq!(dashboard.credentials_issued, 2);
+    let group = &dashboard.groups[0];
+    assert_eq!(group.credentials_issued, 2);
+    assert_eq!(group.credentials_issued_by_day.len(), 1);
+    assert_eq!(group.credentials_issued_by_day[0].count, 2);
+}
+
+#[test]
+fn should_fail_prepare_credential_for_expired_membership() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let authorized_principal = Principal::from_text(DUMMY_ALIAS_ID_DAPP_PRINCIPAL).unwrap();
+    let owner = principal_1();
+    let spec = verified_humanity_credential_spec();
+    let group_name = group_name_for_credential_type(&spec.credential_type);
+    add_group_with_member(
+        &group_name,
+        owner,
+        authorized_principal,
+        spec.arguments.clone(),
+        &env,
+        issuer_id,
+    );
+    do_set_membership_validity(
+        &group_name,
+        Some(MembershipValidity {
+            validity_period_ns: 1_000_000_000,
+            reverify_on_expiry: false,
+        }),
+        owner,
+        &env,
+        issuer_id,
+    );
+
+    // The membership expires before the next sweep, and is refused nonetheless.
+    env.advance_time(std::time::Duration::from_secs(2));
+    let response = api::prepare_credential(
+        &env,
+        issuer_id,
+        authorized_principal,
+        &PrepareCredentialRequest {
+            credential_spec: add_owner(&spec, owner),
+            signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(response, Err(IssueCredentialError::UnauthorizedSubject(e)) if e.contains("expired"));
+}
+
 // Test that a VerifiedAge VC is issued for a lower bound implied by the actual age.
 // (specifically: if a user is 21, his/her age is at least 18).
 #[test]
@@ -500,6 +634,258 @@ fn should_fail_prepare_credential_for_differing_claims() {
     }
 }
 
+fn add_format(credential_spec: &CredentialSpec, format: &str) -> CredentialSpec {
+    let mut spec = credential_spec.to_owned();
+    spec.arguments.get_or_insert_with(HashMap::new).insert(
+        "credentialFormat".to_string(),
+        ArgumentValue::String(format.to_string()),
+    );
+    spec
+}
+
+fn issue_credential_in_format(
+    env: &StateMachine,
+    issuer_id: CanisterId,
+    spec: &CredentialSpec,
+    format: &str,
+) -> String {
+    let authorized_principal = Principal::from_text(DUMMY_ALIAS_ID_DAPP_PRINCIPAL).unwrap();
+    let owner = principal_1();
+    add_group_with_member(
+        &group_name_for_credential_type(&spec.credential_type),
+        owner,
+        authorized_principal,
+        spec.arguments.clone(),
+        env,
+        issuer_id,
+    );
+    let credential_spec = add_format(&add_owner(spec, owner), format);
+    let prepared_credential = api::prepare_credential(
+        env,
+        issuer_id,
+        authorized_principal,
+        &PrepareCredentialRequest {
+            credential_spec: credential_spec.clone(),
+            signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+        },
+    )
+    .expect("API call failed")
+    .expect("failed to prepare credential");
+    api::get_credential(
+        env,
+        issuer_id,
+        authorized_principal,
+        &GetCredentialRequest {
+            credential_spec,
+            signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+            prepared_context: prepared_credential.prepared_context,
+        },
+    )
+    .expect("API call failed")
+    .expect("failed to get credential")
+    .vc_jws
+}
+
+#[test]
+fn should_issue_sd_jwt_credential() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let spec = verified_age_credential_spec(18);
+
+    let sd_jwt = issue_credential_in_format(&env, issuer_id, &spec, "sd-jwt");
+
+    let parts: Vec<&str> = sd_jwt.split('~').collect();
+    assert_eq!(
+        parts.len(),
+        3,
+        "expected JWS, one disclosure and empty key binding"
+    );
+    assert_eq!(parts[2], "");
+    let root_pk_raw =
+        extract_raw_root_pk_from_der(&env.root_key()).expect("Failed decoding IC root key.");
+    let claims = verify_credential_jws_with_canister_id(
+        parts[0],
+        &issuer_id,
+        &root_pk_raw,
+        env.time().duration_since(UNIX_EPOCH).unwrap().as_nanos(),
+    )
+    .expect("credential verification failed");
+    let custom_claims = claims
+        .custom()
+        .expect("missing custom claims in JWT claims");
+    assert_eq!(custom_claims["_sd_alg"], "sha-256");
+    let subject = &custom_claims["vc"]["credentialSubject"];
+    assert!(subject.get("VerifiedAge").is_none());
+    let digest = URL_SAFE_NO_PAD.encode(Sha256::digest(parts[1].as_bytes()));
+    assert_eq!(subject["_sd"], serde_json::json!([digest]));
+
+    let disclosure: Value = serde_json::from_slice(
+        &URL_SAFE_NO_PAD
+            .decode(parts[1])
+            .expect("malformed disclosure"),
+    )
+    .expect("malformed disclosure");
+    assert_eq!(disclosure[1], "VerifiedAge");
+    assert_eq!(disclosure[2]["ageAtLeast"], 18);
+}
+
+#[test]
+fn should_issue_json_ld_credential() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let spec = verified_residence_credential_spec("Switzerland");
+
+    let credential = issue_credential_in_format(&env, issuer_id, &spec, "json-ld");
+
+    let document: Value = serde_json::from_str(&credential).expect("malformed JSON-LD VC");
+    assert_eq!(document["type"][1], "VerifiedResidence");
+    assert_eq!(document["issuer"], "https://metaissuer.vc");
+    assert!(document["credentialSubject"]["id"]
+        .as_str()
+        .expect("missing credentialSubject.id")
+        .starts_with("did:icp:"));
+    assert_eq!(
+        document["credentialSubject"]["VerifiedResidence"]["countryName"],
+        "Switzerland"
+    );
+    assert!(document["credentialStatus"].is_object());
+    let proof = &document["proof"];
+    assert_eq!(proof["type"], "DataIntegrityProof");
+    assert_eq!(proof["proofPurpose"], "assertionMethod");
+    assert!(proof["proofValue"]
+        .as_str()
+        .expect("missing proofValue")
+        .starts_with('u'));
+}
+
+#[test]
+fn should_fail_prepare_credential_for_unsupported_format() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let response = api::prepare_credential(
+        &env,
+        issuer_id,
+        Principal::from_text(DUMMY_ALIAS_ID_DAPP_PRINCIPAL).unwrap(),
+        &PrepareCredentialRequest {
+            credential_spec: add_format(
+                &add_owner(&verified_humanity_credential_spec(), principal_1()),
+                "mdoc",
+            ),
+            signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(response,
+        Err(IssueCredentialError::UnsupportedCredentialSpec(e)) if e.contains("mdoc"));
+}
+
+#[test]
+fn should_issue_credentials_in_batch() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let authorized_principal = Principal::from_text(DUMMY_ALIAS_ID_DAPP_PRINCIPAL).unwrap();
+    let owner = principal_1();
+    let member_specs = vec![
+        verified_age_credential_spec(18),
+        verified_humanity_credential_spec(),
+        verified_residence_credential_spec("Switzerland"),
+    ];
+    for spec in &member_specs {
+        add_group_with_member(
+            &group_name_for_credential_type(&spec.credential_type),
+            owner,
+            authorized_principal,
+            spec.arguments.clone(),
+            &env,
+            issuer_id,
+        );
+    }
+    // The last spec is for a group the principal is not a member of.
+    let credential_specs: Vec<CredentialSpec> = member_specs
+        .iter()
+        .chain([verified_employment_credential_spec("DFINITY Foundation")].iter())
+        .map(|spec| add_owner(spec, owner))
+        .collect();
+
+    let prepared = api::prepare_credentials(
+        &env,
+        issuer_id,
+        authorized_principal,
+        &PrepareCredentialsRequest {
+            signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+            credential_specs: credential_specs.clone(),
+        },
+    )
+    .expect("API call failed")
+    .expect("failed to prepare credentials");
+    assert_eq!(prepared.results.len(), 4);
+    assert_matches!(&prepared.results[3], Err(e) if
+        format!("{:?}", e).contains("has no credential"));
+
+    let credentials = credential_specs
+        .into_iter()
+        .zip(prepared.results)
+        .filter_map(|(credential_spec, result)| {
+            result.ok().map(|data| CredentialToGet {
+                credential_spec,
+                prepared_context: data.prepared_context,
+            })
+        })
+        .collect();
+    let issued = api::get_credentials(
+        &env,
+        issuer_id,
+        authorized_principal,
+        &GetCredentialsRequest {
+            signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+            credentials,
+        },
+    )
+    .expect("API call failed")
+    .expect("failed to get credentials");
+    assert_eq!(issued.results.len(), 3);
+    let root_pk_raw =
+        extract_raw_root_pk_from_der(&env.root_key()).expect("Failed decoding IC root key.");
+    for (result, spec) in issued.results.into_iter().zip(member_specs.iter()) {
+        let vc_jws = result.expect("failed to get credential").vc_jws;
+        let claims = verify_credential_jws_with_canister_id(
+            &vc_jws,
+            &issuer_id,
+            &root_pk_raw,
+            env.time().duration_since(UNIX_EPOCH).unwrap().as_nanos(),
+        )
+        .expect("credential verification failed");
+        let vc_claims = claims
+            .custom()
+            .and_then(|custom| custom.get("vc"))
+            .and_then(|vc| vc.as_object())
+            .expect("missing vc claims in JWT custom claims");
+        validate_claims_match_spec(vc_claims, spec).expect("Claims validation failed");
+    }
+}
+
+#[test]
+fn should_fail_batch_prepare_credentials_for_wrong_sender() {
+    let env = env();
+    let issuer_id = install_issuer(&env, Some(DUMMY_ISSUER_INIT.clone()));
+    let response = api::prepare_credentials(
+        &env,
+        issuer_id,
+        principal_1(), // not the same as contained in signed_id_alias
+        &PrepareCredentialsRequest {
+            signed_id_alias: DUMMY_SIGNED_ID_ALIAS.clone(),
+            credential_specs: vec![add_owner(
+                &verified_humanity_credential_spec(),
+                principal_2(),
+            )],
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(response,
+        Err(IssueCredentialError::InvalidIdAlias(e)) if e.contains("id alias could not be verified")
+    );
+}
+
 fn rp_add_exclusive_content(
     env: &StateMachine,
     canister_id: CanisterId,
@@ -509,12 +895,20 @@ fn rp_add_exclusive_content(
     call_candid_as(env, canister_id, sender, "add_exclusive_content", (req,)).map(|(x,)| x)
 }
 
+fn rp_get_vp_challenge(
+    env: &StateMachine,
+    canister_id: CanisterId,
+    sender: Principal,
+) -> Result<Result<VpChallenge, ContentError>, CallError> {
+    call_candid_as(env, canister_id, sender, "get_vp_challenge", ()).map(|(x,)| x)
+}
+
 fn rp_validate_ii_vp(
     env: &StateMachine,
     canister_id: CanisterId,
     sender: Principal,
     req: ValidateVpRequest,
-) -> Result<Result<(), ContentError>, CallError> {
+) -> Result<Result<ValidatedVpData, VpValidationError>, CallError> {
     call_candid_as(env, canister_id, sender, "validate_ii_vp", (req,)).map(|(x,)| x)
 }
 
@@ -528,14 +922,17 @@ fn should_issue_vc_and_validate_e2e() -> Result<(), CallError> {
 
     // Setup canisters
     let ii_id = install_canister::<IssuerInit>(&env, II_WASM.clone(), None);
-    let issuer_id = install_issuer(
+    let issuer_admin = test_principal(9);
+    let issuer_id = install_canister_with_controller(
         &env,
+        META_ISSUER_WASM.clone(),
         Some(IssuerInit {
             ic_root_key_der: env.root_key().to_vec(),
             idp_canister_ids: vec![ii_id],
             derivation_origin: issuer_url.clone(),
             ..DUMMY_ISSUER_INIT.clone()
         }),
+        Some(issuer_admin),
     );
     let rp_id = install_canister(
         &env,
@@ -549,6 +946,10 @@ fn should_issue_vc_and_validate_e2e() -> Result<(), CallError> {
                 canister_id: issuer_id,
             }],
             derivation_origin: rp_url.clone(),
+            issuer_registry: None,
+            rate_limits: None,
+            require_verified_issuers: Some(true),
+            require_nonce: None,
         }),
     );
 
@@ -624,18 +1025,29 @@ fn should_issue_vc_and_validate_e2e() -> Result<(), CallError> {
                 url: content_url.to_string(),
                 credential_issuer: owner,
                 credential_spec: spec.clone(),
+                credential_policy: None,
             },
         )
         .expect("API call failed")
         .expect("Failed add_exclusive_content");
 
+        // Obtain a challenge from the RP before starting the flow.
+        let challenge = rp_get_vp_challenge(
+            &env,
+            rp_id,
+            id_alias_credentials.rp_id_alias_credential.id_dapp,
+        )?
+        .expect("failed to get challenge");
+        // The nonce of the challenge is bound into the requested credential.
+        let requested_spec = add_nonce(&spec_with_owner, &challenge.nonce);
+
         // Request the credential.
         let prepared_credential = api::prepare_credential(
             &env,
             issuer_id,
             alias_tuple.id_dapp,
             &PrepareCredentialRequest {
-                credential_spec: spec_with_owner.clone(),
+                credential_spec: requested_spec.clone(),
                 signed_id_alias: SignedIssuerIdAlias {
                     credential_jws: id_alias_credentials
                         .issuer_id_alias_credential
@@ -651,7 +1063,7 @@ fn should_issue_vc_and_validate_e2e() -> Result<(), CallError> {
             issuer_id,
             alias_tuple.id_dapp,
             &GetCredentialRequest {
-                credential_spec: spec_with_owner.clone(),
+                credential_spec: requested_spec.clone(),
                 signed_id_alias: SignedIssuerIdAlias {
                     credential_jws: id_alias_credentials
                         .issuer_id_alias_credential
@@ -681,6 +1093,16 @@ fn should_issue_vc_and_validate_e2e() -> Result<(), CallError> {
             .as_object()
             .expect("malformed vc claims in JWT custom claims");
         validate_claims_match_spec(vc_claims, &spec).expect("Claims validation failed");
+        let credential_status = vc_claims
+            .get("credentialStatus")
+            .expect("missing credentialStatus in vc claims");
+        assert_eq!(credential_status["type"], "StatusList2021Entry");
+        assert!(credential_status["statusListCredential"]
+            .as_str()
+            .expect("malformed credentialStatus")
+            .ends_with(&format!("/status-lists/{}", spec.credential_type)));
+        assert_eq!(vc_claims["issuerPrincipal"], owner.to_text());
+        assert_eq!(vc_claims["nonce"], challenge.nonce);
         // Request credential validation from RP's backend.
         let vp_jwt = build_ii_verifiable_presentation_jwt(
             id_alias_credentials.rp_id_alias_credential.id_dapp,
@@ -694,12 +1116,64 @@ fn should_issue_vc_and_validate_e2e() -> Result<(), CallError> {
         let validate_vp_request = ValidateVpRequest {
             vp_jwt,
             effective_vc_subject: id_alias_credentials.rp_id_alias_credential.id_dapp,
-            credential_spec: spec,
+            credential_spec: spec.clone(),
             issuer_origin: issuer_url.to_string(),
             issuer_canister_id: Some(issuer_id),
+            challenge: challenge.nonce.clone(),
         };
-        let result = rp_validate_ii_vp(&env, rp_id, principal_1(), validate_vp_request)?;
-        assert!(result.is_ok(), "{:?}", result);
+        // The RP requires the owner to be verified for the credential type.
+        let result = rp_validate_ii_vp(
+            &env,
+            rp_id,
+            id_alias_credentials.rp_id_alias_credential.id_dapp,
+            validate_vp_request.clone(),
+        )?;
+        assert_matches!(result, Err(VpValidationError::UnverifiedIssuer(_)));
+        api::set_issuer_verification(
+            &env,
+            issuer_id,
+            issuer_admin,
+            SetIssuerVerificationRequest {
+                issuer: owner,
+                credential_type: spec.credential_type.clone(),
+                verified: true,
+                trusted: false,
+            },
+        )?
+        .expect("failed to verify issuer");
+        // The credential is not accepted with another challenge of the subject.
+        let other_challenge = rp_get_vp_challenge(
+            &env,
+            rp_id,
+            id_alias_credentials.rp_id_alias_credential.id_dapp,
+        )?
+        .expect("failed to get challenge");
+        let result = rp_validate_ii_vp(
+            &env,
+            rp_id,
+            id_alias_credentials.rp_id_alias_credential.id_dapp,
+            ValidateVpRequest {
+                challenge: other_challenge.nonce,
+                ..validate_vp_request.clone()
+            },
+        )?;
+        assert_matches!(result, Err(VpValidationError::InvalidChallenge(_)));
+        let validated_vp = rp_validate_ii_vp(
+            &env,
+            rp_id,
+            id_alias_credentials.rp_id_alias_credential.id_dapp,
+            validate_vp_request,
+        )?
+        .expect("VP validation failed");
+        assert_eq!(
+            validated_vp.subject,
+            id_alias_credentials.rp_id_alias_credential.id_dapp
+        );
+        assert_eq!(validated_vp.credential_issuer, issuer_id);
+        assert_eq!(
+            validated_vp.credential_spec.credential_type,
+            spec.credential_type
+        );
     }
     Ok(())
 }
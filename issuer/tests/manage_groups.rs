@@ -1,19 +1,33 @@
 //! Tests related to group management API.
 use assert_matches::assert_matches;
 use candid::Principal;
-use canister_tests::framework::{env, principal_1, principal_2, test_principal};
+use canister_tests::framework::{env, principal_1, principal_2, test_principal, time};
+use ic_test_state_machine_client::{CallError, StateMachine};
+use ic_verifiable_credentials::issuer_api::ArgumentValue as IssuerArgumentValue;
+use internet_identity_interface::http_gateway::{HttpRequest, HttpResponse};
 use meta_issuer::groups_api::{
-    AddGroupRequest, GetGroupRequest, GroupsError, ListGroupsRequest, MembershipStatus,
-    MembershipUpdate, PublicGroupData, SetUserRequest, UpdateMembershipRequest, UserData,
+    AckNotificationsRequest, AddEvidenceRequest, AddGroupRequest, ArgumentValue,
+    ArgumentValueCount, CredentialSpec, ExportMembersRequest, GetEvidenceChunkRequest,
+    GetGroupRequest, GetIssuerVerificationRequest, GroupLogo, GroupLogoUpdate, GroupMetadata,
+    GroupVisibility, GroupsError, ImportMembersRequest, ImportMembersResult,
+    IssuerVerificationData, JoinGroupRequest, ListGroupsRequest, MembersFormat, MembershipCounts,
+    MembershipStatus, MembershipUpdate, MembershipValidity, NotificationData, NotificationKind,
+    PendingAgeDistribution, PublicGroupData, RateLimits, SetIssuerVerificationRequest,
+    SetMembershipValidityRequest, SetNotificationWebhookRequest, SetUserRequest,
+    UpdateGroupRequest, UpdateMembershipRequest, UploadEvidenceChunkRequest, UserData,
 };
-use std::collections::HashMap;
+use rate_limit::TokenBucketConfig;
+use serde_bytes::ByteBuf;
+use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
 #[allow(dead_code)]
 mod util;
 use crate::util::{
-    api, do_add_group, do_get_group, do_get_user, do_group_types, do_join_group, do_set_user,
-    do_update_membership, install_issuer,
+    api, do_add_group, do_get_group, do_get_notifications, do_get_owner_dashboard, do_get_user,
+    do_group_types, do_join_group, do_list_my_memberships, do_set_membership_validity, do_set_user,
+    do_update_membership, install_canister_with_controller, install_issuer, IssuerInit,
+    META_ISSUER_WASM,
 };
 
 #[test]
@@ -114,6 +128,30 @@ fn should_fail_set_user_if_anonymous() {
     assert_matches!(result, Err(GroupsError::NotAuthenticated(_)));
 }
 
+#[test]
+fn should_reject_set_user_with_oversized_argument() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let user_data = UserData {
+        user_nickname: Some("a".repeat(8 * 1024)),
+        issuer_nickname: None,
+    };
+    let result = api::set_user(
+        &env,
+        canister_id,
+        principal_1(),
+        SetUserRequest { user_data },
+    );
+    // The call is rejected by `inspect_message`, before it reaches the endpoint.
+    assert_matches!(
+        result,
+        Err(CallError::UserError(e)) if e.description.contains("argument too large")
+    );
+    let result = api::get_user(&env, canister_id, principal_1()).expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotFound(_)));
+}
+
 #[test]
 fn should_fail_set_user_if_duplicate_user_nickname() {
     let env = env();
@@ -330,6 +368,7 @@ fn should_list_groups_authenticated() {
         vec![MembershipUpdate {
             member: owner,
             new_status: MembershipStatus::Accepted,
+            note: None,
         }],
         owner,
         &env,
@@ -454,6 +493,7 @@ fn should_join_group_again_when_rejected() {
         vec![MembershipUpdate {
             member: alice_principal,
             new_status: MembershipStatus::Rejected,
+            note: None,
         }],
         owner,
         &env,
@@ -479,6 +519,277 @@ fn should_join_group_again_when_rejected() {
     );
 }
 
+#[test]
+fn should_count_members_of_groups() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let owner = principal_1();
+    let group_name = "Bob's Club";
+    let other_group_name = "Bob's Club 2";
+    do_add_group(group_name, owner, &env, canister_id);
+    do_add_group(other_group_name, owner, &env, canister_id);
+
+    let members: Vec<Principal> = (10..13).map(test_principal).collect();
+    for member in &members {
+        do_join_group(group_name, owner, *member, None, &env, canister_id);
+    }
+    do_join_group(other_group_name, owner, members[0], None, &env, canister_id);
+    // Joining again does not add a member.
+    do_update_membership(
+        group_name,
+        vec![MembershipUpdate {
+            member: members[1],
+            new_status: MembershipStatus::Rejected,
+            note: None,
+        }],
+        owner,
+        &env,
+        canister_id,
+    );
+    do_join_group(group_name, owner, members[1], None, &env, canister_id);
+    do_join_group(group_name, owner, members[2], None, &env, canister_id);
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(group_data.stats.member_count, 3);
+    let mut member_principals: Vec<Principal> = group_data
+        .members
+        .iter()
+        .map(|member_data| member_data.member)
+        .collect();
+    member_principals.sort();
+    let mut expected_principals = members.clone();
+    expected_principals.sort();
+    assert_eq!(member_principals, expected_principals);
+    let other_group_data = do_get_group(other_group_name, owner, &env, canister_id);
+    assert_eq!(other_group_data.stats.member_count, 1);
+    assert_eq!(other_group_data.members.len(), 1);
+
+    let list = api::list_groups(
+        &env,
+        canister_id,
+        None,
+        ListGroupsRequest {
+            group_name_substring: None,
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed to list groups");
+    let member_counts: BTreeMap<String, u32> = list
+        .groups
+        .iter()
+        .map(|group| (group.group_name.clone(), group.stats.member_count))
+        .collect();
+    assert_eq!(
+        member_counts,
+        BTreeMap::from([
+            (group_name.to_string(), 3),
+            (other_group_name.to_string(), 1)
+        ])
+    );
+}
+
+#[test]
+fn should_list_my_memberships() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let owner = principal_1();
+    let member = principal_2();
+    let other_member = test_principal(10);
+    let club_name = "Bob's Club";
+    let vc_group_name = "Verified Humanity";
+    do_set_user(
+        UserData {
+            user_nickname: None,
+            issuer_nickname: Some("Bob".to_string()),
+        },
+        owner,
+        &env,
+        canister_id,
+    );
+    do_add_group(club_name, owner, &env, canister_id);
+    do_add_group(vc_group_name, owner, &env, canister_id);
+    do_add_group("Other group", owner, &env, canister_id);
+
+    do_join_group(club_name, owner, member, None, &env, canister_id);
+    do_join_group(vc_group_name, owner, member, None, &env, canister_id);
+    do_join_group("Other group", owner, other_member, None, &env, canister_id);
+    do_update_membership(
+        vc_group_name,
+        vec![MembershipUpdate {
+            member,
+            new_status: MembershipStatus::Accepted,
+            note: Some("Welcome!".to_string()),
+        }],
+        owner,
+        &env,
+        canister_id,
+    );
+
+    let memberships = do_list_my_memberships(member, &env, canister_id).memberships;
+    assert_eq!(memberships.len(), 2);
+    let club = &memberships[0];
+    assert_eq!(club.group_name, club_name);
+    assert_eq!(club.owner, owner);
+    assert_eq!(club.issuer_nickname, "Bob");
+    assert_eq!(club.membership_status, MembershipStatus::PendingReview);
+    assert_eq!(club.credential_spec, None);
+    let vc_group = &memberships[1];
+    assert_eq!(vc_group.group_name, vc_group_name);
+    assert_eq!(vc_group.membership_status, MembershipStatus::Accepted);
+    assert_eq!(vc_group.reviewer_note, Some("Welcome!".to_string()));
+    assert_eq!(vc_group.expires_timestamp_ns, None);
+    let group_data = do_get_group(vc_group_name, owner, &env, canister_id);
+    assert_eq!(
+        vc_group.joined_timestamp_ns,
+        group_data.members[0].joined_timestamp_ns
+    );
+    assert_eq!(
+        vc_group.credential_spec,
+        Some(CredentialSpec {
+            credential_type: "VerifiedHumanity".to_string(),
+            arguments: Some(BTreeMap::from([(
+                "owner".to_string(),
+                ArgumentValue::String(owner.to_text())
+            )])),
+        })
+    );
+
+    let other_memberships = do_list_my_memberships(other_member, &env, canister_id).memberships;
+    assert_eq!(other_memberships.len(), 1);
+    assert_eq!(other_memberships[0].group_name, "Other group");
+    assert!(do_list_my_memberships(owner, &env, canister_id)
+        .memberships
+        .is_empty());
+}
+
+#[test]
+fn should_fail_list_my_memberships_if_anonymous() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let result = api::list_my_memberships(&env, canister_id, Principal::anonymous())
+        .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotAuthenticated(_)));
+}
+
+#[test]
+fn should_get_owner_dashboard() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let owner = principal_1();
+    let vc_group_name = "Verified Residence";
+    let club_name = "Bob's Club";
+    do_add_group(vc_group_name, owner, &env, canister_id);
+    do_add_group(club_name, owner, &env, canister_id);
+    do_add_group("Other group", principal_2(), &env, canister_id);
+
+    let members: Vec<Principal> = (10..14).map(test_principal).collect();
+    for (member, country) in members
+        .iter()
+        .zip(["Switzerland", "Switzerland", "Germany"])
+    {
+        let vc_arguments = HashMap::from([(
+            "countryName".to_string(),
+            IssuerArgumentValue::String(country.to_string()),
+        )]);
+        do_join_group(
+            vc_group_name,
+            owner,
+            *member,
+            Some(vc_arguments),
+            &env,
+            canister_id,
+        );
+    }
+    let status_update = |member: Principal, new_status: MembershipStatus| MembershipUpdate {
+        member,
+        new_status,
+        note: None,
+    };
+    do_update_membership(
+        vc_group_name,
+        vec![
+            status_update(members[0], MembershipStatus::Accepted),
+            status_update(members[2], MembershipStatus::Accepted),
+        ],
+        owner,
+        &env,
+        canister_id,
+    );
+    env.advance_time(Duration::from_secs(8 * 24 * 60 * 60));
+    do_join_group(club_name, owner, members[3], None, &env, canister_id);
+    do_update_membership(
+        vc_group_name,
+        vec![status_update(members[2], MembershipStatus::Rejected)],
+        owner,
+        &env,
+        canister_id,
+    );
+
+    let dashboard = do_get_owner_dashboard(owner, &env, canister_id);
+    assert_eq!(dashboard.groups.len(), 2);
+    assert_eq!(dashboard.credentials_issued, 0);
+    assert_eq!(
+        dashboard.member_counts,
+        MembershipCounts {
+            pending_review: 2,
+            accepted: 1,
+            rejected: 1,
+            expired: 0,
+        }
+    );
+    let club = &dashboard.groups[0];
+    assert_eq!(club.group_name, club_name);
+    assert_eq!(club.member_counts.pending_review, 1);
+    assert_eq!(club.pending_age.under_1_day, 1);
+    assert_eq!(club.argument_values, vec![]);
+    let vc_group = &dashboard.groups[1];
+    assert_eq!(vc_group.group_name, vc_group_name);
+    assert_eq!(
+        vc_group.member_counts,
+        MembershipCounts {
+            pending_review: 1,
+            accepted: 1,
+            rejected: 1,
+            expired: 0,
+        }
+    );
+    assert_eq!(
+        vc_group.pending_age,
+        PendingAgeDistribution {
+            under_1_day: 0,
+            under_7_days: 0,
+            under_30_days: 1,
+            over_30_days: 0,
+        }
+    );
+    assert_eq!(
+        vc_group.argument_values,
+        vec![ArgumentValueCount {
+            argument: "countryName".to_string(),
+            value: "Switzerland".to_string(),
+            count: 1,
+        }]
+    );
+    assert_eq!(vc_group.last_activity_timestamp_ns, Some(time(&env)));
+    assert_eq!(vc_group.credentials_issued_by_day, vec![]);
+
+    let other_dashboard = do_get_owner_dashboard(principal_2(), &env, canister_id);
+    assert_eq!(other_dashboard.groups.len(), 1);
+    assert_eq!(
+        other_dashboard.groups[0].member_counts,
+        MembershipCounts::default()
+    );
+}
+
+#[test]
+fn should_fail_get_owner_dashboard_if_anonymous() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let result = api::get_owner_dashboard(&env, canister_id, Principal::anonymous())
+        .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotAuthenticated(_)));
+}
+
 #[test]
 fn should_not_join_group_again_accepted_or_pending() {
     let env = env();
@@ -541,6 +852,7 @@ fn should_not_join_group_again_accepted_or_pending() {
         vec![MembershipUpdate {
             member: alice_principal,
             new_status: MembershipStatus::Accepted,
+            note: None,
         }],
         owner,
         &env,
@@ -608,6 +920,7 @@ fn should_update_membership_single_member() {
         vec![MembershipUpdate {
             member: alice_principal,
             new_status: MembershipStatus::Accepted,
+            note: None,
         }],
         owner,
         &env,
@@ -704,10 +1017,12 @@ fn should_update_membership_multiple_members() {
             MembershipUpdate {
                 member: alice_principal,
                 new_status: MembershipStatus::Accepted,
+                note: None,
             },
             MembershipUpdate {
                 member: bob_principal,
                 new_status: MembershipStatus::Rejected,
+                note: None,
             },
         ],
         bob_principal,
@@ -794,6 +1109,7 @@ fn should_update_membership_multiple_times() {
         vec![MembershipUpdate {
             member: bob_principal,
             new_status: MembershipStatus::Rejected,
+            note: None,
         }],
         owner,
         &env,
@@ -804,6 +1120,7 @@ fn should_update_membership_multiple_times() {
         vec![MembershipUpdate {
             member: alice_principal,
             new_status: MembershipStatus::Accepted,
+            note: None,
         }],
         owner,
         &env,
@@ -838,6 +1155,7 @@ fn should_update_membership_multiple_times() {
         vec![MembershipUpdate {
             member: bob_principal,
             new_status: MembershipStatus::Accepted,
+            note: None,
         }],
         owner,
         &env,
@@ -848,6 +1166,7 @@ fn should_update_membership_multiple_times() {
         vec![MembershipUpdate {
             member: alice_principal,
             new_status: MembershipStatus::Rejected,
+            note: None,
         }],
         owner,
         &env,
@@ -894,6 +1213,7 @@ fn should_fail_update_membership_if_missing_group() {
             updates: vec![MembershipUpdate {
                 member: principal_2(),
                 new_status: MembershipStatus::Accepted,
+                note: None,
             }],
         },
     )
@@ -920,6 +1240,7 @@ fn should_fail_update_membership_if_missing_member() {
             updates: vec![MembershipUpdate {
                 member: principal_2(),
                 new_status: MembershipStatus::Accepted,
+                note: None,
             }],
         },
     )
@@ -946,6 +1267,7 @@ fn should_fail_update_membership_if_not_owner() {
             updates: vec![MembershipUpdate {
                 member: principal_2(),
                 new_status: MembershipStatus::Accepted,
+                note: None,
             }],
         },
     )
@@ -953,3 +1275,1741 @@ fn should_fail_update_membership_if_not_owner() {
 
     assert_matches!(result, Err(GroupsError::NotFound(e)) if e.contains("group"));
 }
+
+#[test]
+fn should_fail_join_group_if_vc_arguments_do_not_match_spec() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Verified Age";
+    let owner = principal_1();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+
+    let result = api::join_group(
+        &env,
+        canister_id,
+        principal_2(),
+        JoinGroupRequest {
+            group_name: group_name.to_string(),
+            owner,
+            vc_arguments: Some(BTreeMap::from([(
+                "countryName".to_string(),
+                ArgumentValue::String("Switzerland".to_string()),
+            )])),
+            message: None,
+        },
+    )
+    .expect("API call failed");
+
+    assert_matches!(result, Err(GroupsError::Internal(e)) if e.contains("ageAtLeast"));
+}
+
+#[test]
+fn should_export_and_import_members() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Verified Age";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    do_set_user(
+        UserData {
+            user_nickname: Some("Alice".to_string()),
+            issuer_nickname: None,
+        },
+        member,
+        &env,
+        canister_id,
+    );
+    do_join_group(
+        group_name,
+        owner,
+        member,
+        Some(HashMap::from([(
+            "ageAtLeast".to_string(),
+            IssuerArgumentValue::Int(21),
+        )])),
+        &env,
+        canister_id,
+    );
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+
+    for format in [MembersFormat::Csv, MembersFormat::Json] {
+        let content = api::export_members(
+            &env,
+            canister_id,
+            owner,
+            ExportMembersRequest {
+                group_name: group_name.to_string(),
+                format: format.clone(),
+            },
+        )
+        .expect("API call failed")
+        .expect("Failed export_members");
+        assert!(content.contains(&member.to_text()));
+        assert!(content.contains("Alice"));
+        assert!(content.contains("PendingReview"));
+
+        // Import the exported members into another owner's group.
+        let other_owner = test_principal(3);
+        let _ = api::add_group(
+            &env,
+            canister_id,
+            other_owner,
+            AddGroupRequest {
+                group_name: group_name.to_string(),
+            },
+        )
+        .expect("API call failed");
+        let result = api::import_members(
+            &env,
+            canister_id,
+            other_owner,
+            ImportMembersRequest {
+                group_name: group_name.to_string(),
+                format,
+                content,
+            },
+        )
+        .expect("API call failed")
+        .expect("Failed import_members");
+        assert_eq!(
+            result,
+            ImportMembersResult {
+                imported_count: 1,
+                errors: vec![],
+            }
+        );
+        let imported_data = do_get_group(group_name, other_owner, &env, canister_id);
+        assert_eq!(imported_data.members, group_data.members);
+    }
+}
+
+#[test]
+fn should_import_members_and_report_row_errors() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Verified Age";
+    let owner = principal_1();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+
+    let content = format!(
+        "principal,status,vc_arguments\n\
+         {member},Accepted,\"{{\"\"ageAtLeast\"\":18}}\"\n\
+         not-a-principal,Accepted,\n\
+         {other},Approved,\n\
+         {other},Accepted,\"{{\"\"countryName\"\":\"\"Switzerland\"\"}}\"\n\
+         {member},Rejected,\"{{\"\"ageAtLeast\"\":18}}\"\n",
+        member = principal_2(),
+        other = test_principal(3),
+    );
+    let result = api::import_members(
+        &env,
+        canister_id,
+        owner,
+        ImportMembersRequest {
+            group_name: group_name.to_string(),
+            format: MembersFormat::Csv,
+            content,
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed import_members");
+
+    assert_eq!(result.imported_count, 1);
+    let error_rows: Vec<u32> = result.errors.iter().map(|error| error.row).collect();
+    assert_eq!(error_rows, vec![2, 3, 4, 5]);
+    assert!(result.errors[0].error.contains("bad principal"));
+    assert!(result.errors[1].error.contains("unknown status"));
+    assert!(result.errors[2].error.contains("ageAtLeast"));
+    assert!(result.errors[3].error.contains("duplicate member"));
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(group_data.members.len(), 1);
+    assert_eq!(group_data.members[0].member, principal_2());
+    assert_eq!(
+        group_data.members[0].membership_status,
+        MembershipStatus::Accepted
+    );
+}
+
+#[test]
+fn should_fail_import_members_if_not_owner() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let _ = do_add_group(group_name, principal_1(), &env, canister_id);
+
+    let result = api::import_members(
+        &env,
+        canister_id,
+        principal_2(), // not the owner
+        ImportMembersRequest {
+            group_name: group_name.to_string(),
+            format: MembersFormat::Json,
+            content: format!(
+                r#"[{{"principal":"{}","status":"Accepted"}}]"#,
+                principal_2()
+            ),
+        },
+    )
+    .expect("API call failed");
+
+    assert_matches!(result, Err(GroupsError::NotFound(e)) if e.contains("group"));
+}
+
+#[test]
+fn should_fail_import_members_if_malformed() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+
+    let result = api::import_members(
+        &env,
+        canister_id,
+        owner,
+        ImportMembersRequest {
+            group_name: group_name.to_string(),
+            format: MembersFormat::Csv,
+            content: "member,status\n".to_string(),
+        },
+    )
+    .expect("API call failed");
+
+    assert_matches!(result, Err(GroupsError::InvalidArgument(e)) if e.contains("principal column"));
+}
+
+#[test]
+fn should_store_applicant_message_and_reviewer_note() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    let message = "I work in the Zurich office, see badge #123";
+    api::join_group(
+        &env,
+        canister_id,
+        member,
+        JoinGroupRequest {
+            group_name: group_name.to_string(),
+            owner,
+            vc_arguments: None,
+            message: Some(message.to_string()),
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed join_group");
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(
+        group_data.members[0].applicant_message,
+        Some(message.to_string())
+    );
+    assert_eq!(group_data.members[0].reviewer_note, None);
+
+    let note = "Badge #123 belongs to someone else";
+    do_update_membership(
+        group_name,
+        vec![MembershipUpdate {
+            member,
+            new_status: MembershipStatus::Rejected,
+            note: Some(note.to_string()),
+        }],
+        owner,
+        &env,
+        canister_id,
+    );
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(
+        group_data.members[0].applicant_message,
+        Some(message.to_string())
+    );
+    assert_eq!(group_data.members[0].reviewer_note, Some(note.to_string()));
+
+    // The note is visible to the member.
+    let list = api::list_groups(
+        &env,
+        canister_id,
+        Some(member),
+        ListGroupsRequest {
+            group_name_substring: None,
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed to list groups");
+    assert_eq!(list.groups[0].reviewer_note, Some(note.to_string()));
+
+    // ... but not to others.
+    let list = api::list_groups(
+        &env,
+        canister_id,
+        Some(test_principal(3)),
+        ListGroupsRequest {
+            group_name_substring: None,
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed to list groups");
+    assert_eq!(list.groups[0].reviewer_note, None);
+}
+
+#[test]
+fn should_fail_join_group_if_message_too_long() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+
+    let result = api::join_group(
+        &env,
+        canister_id,
+        principal_2(),
+        JoinGroupRequest {
+            group_name: group_name.to_string(),
+            owner,
+            vc_arguments: None,
+            message: Some("x".repeat(501)),
+        },
+    )
+    .expect("API call failed");
+
+    assert_matches!(result, Err(GroupsError::InvalidArgument(e)) if e.contains("message too long"));
+}
+
+#[test]
+fn should_fail_update_membership_if_note_too_long() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+
+    let result = api::update_membership(
+        &env,
+        canister_id,
+        owner,
+        UpdateMembershipRequest {
+            group_name: group_name.to_string(),
+            updates: vec![MembershipUpdate {
+                member,
+                new_status: MembershipStatus::Rejected,
+                note: Some("x".repeat(501)),
+            }],
+        },
+    )
+    .expect("API call failed");
+
+    assert_matches!(result, Err(GroupsError::InvalidArgument(e)) if e.contains("note too long"));
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(
+        group_data.members[0].membership_status,
+        MembershipStatus::PendingReview
+    );
+}
+
+const EVIDENCE_CHUNK_SIZE: usize = 256 * 1024;
+
+fn add_evidence_request(group_name: &str, owner: Principal, size: u32) -> AddEvidenceRequest {
+    AddEvidenceRequest {
+        group_name: group_name.to_string(),
+        owner,
+        file_name: "badge.pdf".to_string(),
+        content_type: "application/pdf".to_string(),
+        size,
+    }
+}
+
+#[test]
+fn should_attach_evidence_to_membership_request() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+
+    let document: Vec<u8> = (0..EVIDENCE_CHUNK_SIZE + 10).map(|i| i as u8).collect();
+    let evidence = api::add_evidence(
+        &env,
+        canister_id,
+        member,
+        add_evidence_request(group_name, owner, document.len() as u32),
+    )
+    .expect("API call failed")
+    .expect("Failed add_evidence");
+    assert!(!evidence.complete);
+
+    for (i, chunk) in document.chunks(EVIDENCE_CHUNK_SIZE).enumerate() {
+        let _ = api::upload_evidence_chunk(
+            &env,
+            canister_id,
+            member,
+            UploadEvidenceChunkRequest {
+                evidence_id: evidence.evidence_id,
+                chunk_index: i as u32,
+                content: ByteBuf::from(chunk.to_vec()),
+            },
+        )
+        .expect("API call failed")
+        .expect("Failed upload_evidence_chunk");
+    }
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    let stored = &group_data.members[0].evidence;
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].evidence_id, evidence.evidence_id);
+    assert_eq!(stored[0].file_name, "badge.pdf");
+    assert_eq!(stored[0].size, document.len() as u32);
+    assert!(stored[0].complete);
+
+    let mut downloaded = vec![];
+    for chunk_index in 0..2 {
+        let chunk = api::get_evidence_chunk(
+            &env,
+            canister_id,
+            owner,
+            GetEvidenceChunkRequest {
+                evidence_id: evidence.evidence_id,
+                chunk_index,
+            },
+        )
+        .expect("API call failed")
+        .expect("Failed get_evidence_chunk");
+        downloaded.extend_from_slice(&chunk);
+    }
+    assert_eq!(downloaded, document);
+
+    // Only the owner can read the evidence.
+    for caller in [member, test_principal(3)] {
+        let result = api::get_evidence_chunk(
+            &env,
+            canister_id,
+            caller,
+            GetEvidenceChunkRequest {
+                evidence_id: evidence.evidence_id,
+                chunk_index: 0,
+            },
+        )
+        .expect("API call failed");
+        assert_matches!(result, Err(GroupsError::NotFound(_)));
+    }
+
+    // Evidence is deleted once the owner decides on the request.
+    do_update_membership(
+        group_name,
+        vec![MembershipUpdate {
+            member,
+            new_status: MembershipStatus::Accepted,
+            note: None,
+        }],
+        owner,
+        &env,
+        canister_id,
+    );
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert!(group_data.members[0].evidence.is_empty());
+    let result = api::get_evidence_chunk(
+        &env,
+        canister_id,
+        owner,
+        GetEvidenceChunkRequest {
+            evidence_id: evidence.evidence_id,
+            chunk_index: 0,
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotFound(_)));
+}
+
+#[test]
+fn should_fail_add_evidence_if_not_pending_member() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+
+    let result = api::add_evidence(
+        &env,
+        canister_id,
+        member,
+        add_evidence_request(group_name, owner, 10),
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotFound(_)));
+
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+    do_update_membership(
+        group_name,
+        vec![MembershipUpdate {
+            member,
+            new_status: MembershipStatus::Accepted,
+            note: None,
+        }],
+        owner,
+        &env,
+        canister_id,
+    );
+    let result = api::add_evidence(
+        &env,
+        canister_id,
+        member,
+        add_evidence_request(group_name, owner, 10),
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotFound(_)));
+}
+
+#[test]
+fn should_fail_add_evidence_with_invalid_size() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+
+    for size in [0, 2 * 1024 * 1024 + 1] {
+        let result = api::add_evidence(
+            &env,
+            canister_id,
+            member,
+            add_evidence_request(group_name, owner, size),
+        )
+        .expect("API call failed");
+        assert_matches!(result, Err(GroupsError::InvalidArgument(_)));
+    }
+}
+
+#[test]
+fn should_fail_upload_evidence_chunk_with_wrong_size() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+
+    let evidence = api::add_evidence(
+        &env,
+        canister_id,
+        member,
+        add_evidence_request(group_name, owner, 100),
+    )
+    .expect("API call failed")
+    .expect("Failed add_evidence");
+
+    let result = api::upload_evidence_chunk(
+        &env,
+        canister_id,
+        member,
+        UploadEvidenceChunkRequest {
+            evidence_id: evidence.evidence_id,
+            chunk_index: 0,
+            content: ByteBuf::from(vec![0; 99]),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::InvalidArgument(_)));
+
+    // Only the member who added the evidence can upload it.
+    let result = api::upload_evidence_chunk(
+        &env,
+        canister_id,
+        owner,
+        UploadEvidenceChunkRequest {
+            evidence_id: evidence.evidence_id,
+            chunk_index: 0,
+            content: ByteBuf::from(vec![0; 100]),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotFound(_)));
+}
+
+const MEMBERSHIP_EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+fn accept_member(
+    group_name: &str,
+    owner: Principal,
+    member: Principal,
+    env: &StateMachine,
+    canister_id: Principal,
+) {
+    do_update_membership(
+        group_name,
+        vec![MembershipUpdate {
+            member,
+            new_status: MembershipStatus::Accepted,
+            note: None,
+        }],
+        owner,
+        env,
+        canister_id,
+    );
+}
+
+#[test]
+fn should_expire_memberships() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+    accept_member(group_name, owner, member, &env, canister_id);
+
+    let validity = MembershipValidity {
+        validity_period_ns: 60 * 1_000_000_000,
+        reverify_on_expiry: false,
+    };
+    do_set_membership_validity(group_name, Some(validity.clone()), owner, &env, canister_id);
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(group_data.membership_validity, Some(validity));
+    assert!(group_data.members[0].expires_timestamp_ns.is_some());
+
+    env.advance_time(MEMBERSHIP_EXPIRY_SWEEP_INTERVAL);
+    env.tick();
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(
+        group_data.members[0].membership_status,
+        MembershipStatus::Expired
+    );
+    assert_eq!(group_data.members[0].expires_timestamp_ns, None);
+
+    // Expired members can apply again.
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    assert_eq!(
+        group_data.members[0].membership_status,
+        MembershipStatus::PendingReview
+    );
+}
+
+#[test]
+fn should_return_expired_members_to_pending_review() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let renewed_member = test_principal(3);
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+    do_join_group(group_name, owner, renewed_member, None, &env, canister_id);
+    accept_member(group_name, owner, member, &env, canister_id);
+    accept_member(group_name, owner, renewed_member, &env, canister_id);
+    do_set_membership_validity(
+        group_name,
+        Some(MembershipValidity {
+            validity_period_ns: 90 * 60 * 1_000_000_000,
+            reverify_on_expiry: true,
+        }),
+        owner,
+        &env,
+        canister_id,
+    );
+
+    env.advance_time(MEMBERSHIP_EXPIRY_SWEEP_INTERVAL);
+    env.tick();
+    // Re-accepting renews the membership.
+    accept_member(group_name, owner, renewed_member, &env, canister_id);
+    env.advance_time(MEMBERSHIP_EXPIRY_SWEEP_INTERVAL);
+    env.tick();
+
+    let group_data = do_get_group(group_name, owner, &env, canister_id);
+    let status = |principal: Principal| {
+        group_data
+            .members
+            .iter()
+            .find(|member_data| member_data.member == principal)
+            .map(|member_data| member_data.membership_status.clone())
+    };
+    assert_eq!(status(member), Some(MembershipStatus::PendingReview));
+    assert_eq!(status(renewed_member), Some(MembershipStatus::Accepted));
+}
+
+#[test]
+fn should_fail_set_membership_validity_if_not_owner_or_invalid() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+
+    let result = api::set_membership_validity(
+        &env,
+        canister_id,
+        principal_2(),
+        SetMembershipValidityRequest {
+            group_name: group_name.to_string(),
+            validity: None,
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotFound(_)));
+
+    let result = api::set_membership_validity(
+        &env,
+        canister_id,
+        owner,
+        SetMembershipValidityRequest {
+            group_name: group_name.to_string(),
+            validity: Some(MembershipValidity {
+                validity_period_ns: 0,
+                reverify_on_expiry: false,
+            }),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::InvalidArgument(_)));
+}
+
+const NO_RATE_LIMITS: RateLimits = RateLimits {
+    per_caller: None,
+    global: None,
+    max_groups_per_owner: None,
+    max_pending_requests_per_group: None,
+};
+
+fn set_rate_limits(limits: RateLimits, env: &StateMachine, canister_id: Principal) {
+    // The canister is installed by the anonymous principal, which is thus its controller.
+    api::set_rate_limits(env, canister_id, Principal::anonymous(), &limits)
+        .expect("API call failed")
+        .expect("Failed set_rate_limits");
+}
+
+fn join_group_request(group_name: &str, owner: Principal) -> JoinGroupRequest {
+    JoinGroupRequest {
+        group_name: group_name.to_string(),
+        owner,
+        vc_arguments: None,
+        message: None,
+    }
+}
+
+#[test]
+fn should_rate_limit_calls_per_caller() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    set_rate_limits(
+        RateLimits {
+            per_caller: Some(TokenBucketConfig {
+                capacity: 3,
+                refill_period_ns: Duration::from_secs(60).as_nanos() as u64,
+            }),
+            ..NO_RATE_LIMITS
+        },
+        &env,
+        canister_id,
+    );
+    let owner = principal_1();
+    let member = principal_2();
+    let group_names = ["Club 1", "Club 2", "Club 3"];
+    for group_name in group_names {
+        do_add_group(group_name, owner, &env, canister_id);
+    }
+    for group_name in &group_names[..2] {
+        do_join_group(group_name, owner, member, None, &env, canister_id);
+    }
+    do_set_user(
+        UserData {
+            user_nickname: Some("member".to_string()),
+            issuer_nickname: None,
+        },
+        member,
+        &env,
+        canister_id,
+    );
+
+    let result = api::join_group(
+        &env,
+        canister_id,
+        member,
+        join_group_request(group_names[2], owner),
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::RateLimited(e)) if e.contains("retry in"));
+    // Other callers are not affected, and the caller may call again later.
+    do_join_group(
+        group_names[2],
+        owner,
+        test_principal(3),
+        None,
+        &env,
+        canister_id,
+    );
+    env.advance_time(Duration::from_secs(60));
+    do_join_group(group_names[2], owner, member, None, &env, canister_id);
+}
+
+#[test]
+fn should_rate_limit_calls_of_all_callers() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    set_rate_limits(
+        RateLimits {
+            global: Some(TokenBucketConfig {
+                capacity: 2,
+                refill_period_ns: Duration::from_secs(1).as_nanos() as u64,
+            }),
+            ..NO_RATE_LIMITS
+        },
+        &env,
+        canister_id,
+    );
+    do_add_group("Club 1", principal_1(), &env, canister_id);
+    do_add_group("Club 2", principal_2(), &env, canister_id);
+
+    let result = api::add_group(
+        &env,
+        canister_id,
+        test_principal(3),
+        AddGroupRequest {
+            group_name: "Club 3".to_string(),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::RateLimited(_)));
+}
+
+#[test]
+fn should_limit_groups_per_owner() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    set_rate_limits(
+        RateLimits {
+            max_groups_per_owner: Some(2),
+            ..NO_RATE_LIMITS
+        },
+        &env,
+        canister_id,
+    );
+    let owner = principal_1();
+    do_add_group("Club 1", owner, &env, canister_id);
+    do_add_group("Club 2", owner, &env, canister_id);
+
+    let result = api::add_group(
+        &env,
+        canister_id,
+        owner,
+        AddGroupRequest {
+            group_name: "Club 3".to_string(),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::RateLimited(e)) if e.contains("too many groups"));
+    do_add_group("Club 3", principal_2(), &env, canister_id);
+}
+
+#[test]
+fn should_limit_pending_requests_per_group() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    set_rate_limits(
+        RateLimits {
+            max_pending_requests_per_group: Some(1),
+            ..NO_RATE_LIMITS
+        },
+        &env,
+        canister_id,
+    );
+    let owner = principal_1();
+    let group_name = "Bob's Club";
+    let first_member = principal_2();
+    let second_member = test_principal(3);
+    do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, first_member, None, &env, canister_id);
+
+    let result = api::join_group(
+        &env,
+        canister_id,
+        second_member,
+        join_group_request(group_name, owner),
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::RateLimited(e)) if e.contains("pending"));
+
+    // Deciding on the pending request makes room for the next one.
+    do_update_membership(
+        group_name,
+        vec![MembershipUpdate {
+            member: first_member,
+            new_status: MembershipStatus::Accepted,
+            note: None,
+        }],
+        owner,
+        &env,
+        canister_id,
+    );
+    do_join_group(group_name, owner, second_member, None, &env, canister_id);
+}
+
+#[test]
+fn should_set_rate_limits_only_as_controller() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    assert_eq!(
+        api::get_rate_limits(&env, canister_id, principal_1()).expect("API call failed"),
+        RateLimits::default()
+    );
+
+    let result = api::set_rate_limits(&env, canister_id, principal_1(), &NO_RATE_LIMITS)
+        .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotAuthorized(_)));
+    set_rate_limits(NO_RATE_LIMITS, &env, canister_id);
+    assert_eq!(
+        api::get_rate_limits(&env, canister_id, principal_1()).expect("API call failed"),
+        NO_RATE_LIMITS
+    );
+}
+
+const PNG_LOGO: &[u8] = b"\x89PNG\r\n\x1a\nnot really a png";
+
+fn update_group_request(group_name: &str) -> UpdateGroupRequest {
+    UpdateGroupRequest {
+        group_name: group_name.to_string(),
+        description: Some("Verified humans of the Bob's Club".to_string()),
+        contact_url: Some("https://example.com/contact".to_string()),
+        tags: vec!["humans".to_string(), "club".to_string()],
+        visibility: GroupVisibility::Public,
+        logo: Some(GroupLogoUpdate::Set(GroupLogo {
+            content_type: "image/png".to_string(),
+            content: ByteBuf::from(PNG_LOGO),
+        })),
+    }
+}
+
+fn do_update_group(
+    req: UpdateGroupRequest,
+    caller: Principal,
+    env: &StateMachine,
+    canister_id: Principal,
+) -> GroupMetadata {
+    api::update_group(env, canister_id, caller, req)
+        .expect("API call failed")
+        .expect("Failed update_group")
+}
+
+fn get_logo(logo_url: &str, env: &StateMachine, canister_id: Principal) -> HttpResponse {
+    api::http_request(
+        env,
+        canister_id,
+        Principal::anonymous(),
+        &HttpRequest {
+            method: "GET".to_string(),
+            url: logo_url.to_string(),
+            headers: vec![],
+            body: ByteBuf::new(),
+            certificate_version: None,
+        },
+    )
+    .expect("API call failed")
+}
+
+#[test]
+fn should_update_group_metadata() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let group_data = do_add_group(group_name, owner, &env, canister_id);
+    assert_eq!(group_data.metadata, GroupMetadata::default());
+
+    let metadata = do_update_group(update_group_request(group_name), owner, &env, canister_id);
+    assert_eq!(
+        metadata.description.as_deref(),
+        Some("Verified humans of the Bob's Club")
+    );
+    assert_eq!(
+        metadata.contact_url.as_deref(),
+        Some("https://example.com/contact")
+    );
+    assert_eq!(metadata.tags, vec!["humans", "club"]);
+    assert_eq!(metadata.visibility, GroupVisibility::Public);
+    let logo_url = metadata.logo_url.clone().expect("missing logo URL");
+    assert!(logo_url.starts_with("/logos/"));
+
+    assert_eq!(
+        do_get_group(group_name, owner, &env, canister_id).metadata,
+        metadata
+    );
+    let list = api::list_groups(
+        &env,
+        canister_id,
+        None,
+        ListGroupsRequest {
+            group_name_substring: None,
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed to list groups");
+    assert_eq!(list.groups[0].metadata, metadata);
+
+    let response = get_logo(&logo_url, &env, canister_id);
+    assert_eq!(response.status_code, 200);
+    assert_eq!(response.body.as_slice(), PNG_LOGO);
+    assert!(response
+        .headers
+        .contains(&("Content-Type".to_string(), "image/png".to_string())));
+
+    // Updates without a logo keep the current logo.
+    let metadata = do_update_group(
+        UpdateGroupRequest {
+            description: None,
+            logo: None,
+            ..update_group_request(group_name)
+        },
+        owner,
+        &env,
+        canister_id,
+    );
+    assert_eq!(metadata.description, None);
+    assert_eq!(metadata.logo_url.as_ref(), Some(&logo_url));
+
+    // Removed logos are no longer served.
+    let metadata = do_update_group(
+        UpdateGroupRequest {
+            logo: Some(GroupLogoUpdate::Remove),
+            ..update_group_request(group_name)
+        },
+        owner,
+        &env,
+        canister_id,
+    );
+    assert_eq!(metadata.logo_url, None);
+    assert_eq!(get_logo(&logo_url, &env, canister_id).status_code, 404);
+}
+
+#[test]
+fn should_keep_logo_shared_by_groups() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let owner = principal_1();
+    for group_name in ["first group", "second group"] {
+        let _ = do_add_group(group_name, owner, &env, canister_id);
+    }
+    let first = do_update_group(
+        update_group_request("first group"),
+        owner,
+        &env,
+        canister_id,
+    );
+    let second = do_update_group(
+        update_group_request("second group"),
+        owner,
+        &env,
+        canister_id,
+    );
+    assert_eq!(first.logo_url, second.logo_url);
+    let logo_url = first.logo_url.expect("missing logo URL");
+
+    let _ = do_update_group(
+        UpdateGroupRequest {
+            logo: Some(GroupLogoUpdate::Remove),
+            ..update_group_request("first group")
+        },
+        owner,
+        &env,
+        canister_id,
+    );
+    assert_eq!(get_logo(&logo_url, &env, canister_id).status_code, 200);
+}
+
+#[test]
+fn should_not_list_unlisted_groups() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let owner = principal_1();
+    let member = principal_2();
+    for group_name in ["listed group", "unlisted group", "invite-only group"] {
+        let _ = do_add_group(group_name, owner, &env, canister_id);
+    }
+    let _ = do_update_group(
+        UpdateGroupRequest {
+            visibility: GroupVisibility::Unlisted,
+            ..update_group_request("unlisted group")
+        },
+        owner,
+        &env,
+        canister_id,
+    );
+    let _ = do_update_group(
+        UpdateGroupRequest {
+            visibility: GroupVisibility::InviteOnly,
+            ..update_group_request("invite-only group")
+        },
+        owner,
+        &env,
+        canister_id,
+    );
+
+    let list = api::list_groups(
+        &env,
+        canister_id,
+        Some(member),
+        ListGroupsRequest {
+            group_name_substring: None,
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed to list groups");
+    let mut listed_names: Vec<&str> = list
+        .groups
+        .iter()
+        .map(|group| group.group_name.as_str())
+        .collect();
+    listed_names.sort();
+    assert_eq!(listed_names, vec!["invite-only group", "listed group"]);
+
+    // Unlisted groups can still be joined by those who know them.
+    do_join_group("unlisted group", owner, member, None, &env, canister_id);
+}
+
+#[test]
+fn should_not_join_invite_only_group() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    let _ = do_update_group(
+        UpdateGroupRequest {
+            visibility: GroupVisibility::InviteOnly,
+            ..update_group_request(group_name)
+        },
+        owner,
+        &env,
+        canister_id,
+    );
+
+    let result = api::join_group(
+        &env,
+        canister_id,
+        member,
+        join_group_request(group_name, owner),
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotAuthorized(e)) if e.contains("invitation only"));
+
+    // The owner adds members directly.
+    let result = api::import_members(
+        &env,
+        canister_id,
+        owner,
+        ImportMembersRequest {
+            group_name: group_name.to_string(),
+            format: MembersFormat::Csv,
+            content: format!("principal,status\n{},Accepted\n", member),
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed import_members");
+    assert_eq!(result.imported_count, 1);
+}
+
+#[test]
+fn should_fail_update_group_with_invalid_metadata() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+
+    let logo = |content_type: &str, size: usize| {
+        Some(GroupLogoUpdate::Set(GroupLogo {
+            content_type: content_type.to_string(),
+            content: ByteBuf::from(vec![0; size]),
+        }))
+    };
+    for (req, expected_error) in [
+        (
+            UpdateGroupRequest {
+                description: Some("x".repeat(2_001)),
+                ..update_group_request(group_name)
+            },
+            "description too long",
+        ),
+        (
+            UpdateGroupRequest {
+                contact_url: Some("javascript:alert(1)".to_string()),
+                ..update_group_request(group_name)
+            },
+            "contact URL must start with",
+        ),
+        (
+            UpdateGroupRequest {
+                tags: (0..11).map(|i| format!("tag {}", i)).collect(),
+                ..update_group_request(group_name)
+            },
+            "too many tags",
+        ),
+        (
+            UpdateGroupRequest {
+                tags: vec!["club".to_string(), "club".to_string()],
+                ..update_group_request(group_name)
+            },
+            "duplicate tag",
+        ),
+        (
+            UpdateGroupRequest {
+                logo: logo("image/svg+xml", 10),
+                ..update_group_request(group_name)
+            },
+            "unsupported logo content type",
+        ),
+        (
+            UpdateGroupRequest {
+                logo: logo("image/png", 64 * 1024 + 1),
+                ..update_group_request(group_name)
+            },
+            "logo too large",
+        ),
+    ] {
+        let result = api::update_group(&env, canister_id, owner, req).expect("API call failed");
+        assert_matches!(result, Err(GroupsError::InvalidArgument(e)) if e.contains(expected_error));
+    }
+    assert_eq!(
+        do_get_group(group_name, owner, &env, canister_id).metadata,
+        GroupMetadata::default()
+    );
+}
+
+#[test]
+fn should_fail_update_group_if_not_owner() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let group_name = "Bob's Club";
+    let _ = do_add_group(group_name, principal_1(), &env, canister_id);
+
+    let result = api::update_group(
+        &env,
+        canister_id,
+        principal_2(),
+        update_group_request(group_name),
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(GroupsError::NotFound(_)));
+}
+
+fn set_issuer_verification(
+    verifier: Principal,
+    issuer: Principal,
+    credential_type: &str,
+    verified: bool,
+    trusted: bool,
+    env: &StateMachine,
+    canister_id: Principal,
+) -> Result<(), GroupsError> {
+    api::set_issuer_verification(
+        env,
+        canister_id,
+        verifier,
+        SetIssuerVerificationRequest {
+            issuer,
+            credential_type: credential_type.to_string(),
+            verified,
+            trusted,
+        },
+    )
+    .expect("API call failed")
+}
+
+fn get_issuer_verification(
+    issuer: Principal,
+    credential_type: &str,
+    env: &StateMachine,
+    canister_id: Principal,
+) -> Option<IssuerVerificationData> {
+    api::get_issuer_verification(
+        env,
+        canister_id,
+        principal_2(),
+        GetIssuerVerificationRequest {
+            issuer,
+            credential_type: credential_type.to_string(),
+        },
+    )
+    .expect("API call failed")
+}
+
+fn install_issuer_with_admin(env: &StateMachine, admin: Principal) -> Principal {
+    install_canister_with_controller(
+        env,
+        META_ISSUER_WASM.clone(),
+        None::<IssuerInit>,
+        Some(admin),
+    )
+}
+
+#[test]
+fn should_verify_issuer_as_admin() {
+    let env = env();
+    let admin = test_principal(4);
+    let canister_id = install_issuer_with_admin(&env, admin);
+    let issuer = principal_1();
+    let group_data = do_add_group("Verified Humanity", issuer, &env, canister_id);
+    assert_eq!(group_data.issuer_verification, None);
+    let _ = do_add_group("Verified Age", issuer, &env, canister_id);
+
+    assert_eq!(
+        set_issuer_verification(
+            admin,
+            issuer,
+            "VerifiedHumanity",
+            true,
+            false,
+            &env,
+            canister_id
+        ),
+        Ok(())
+    );
+    let expected = IssuerVerificationData {
+        issuer,
+        credential_type: "VerifiedHumanity".to_string(),
+        verified_by: admin,
+        verified_timestamp_ns: time(&env),
+        trusted: false,
+    };
+    assert_eq!(
+        get_issuer_verification(issuer, "VerifiedHumanity", &env, canister_id),
+        Some(expected.clone())
+    );
+    assert_eq!(
+        do_get_group("Verified Humanity", issuer, &env, canister_id).issuer_verification,
+        Some(expected.clone())
+    );
+    let list = api::list_groups(
+        &env,
+        canister_id,
+        None,
+        ListGroupsRequest {
+            group_name_substring: None,
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed to list groups");
+    let verifications: Vec<_> = list
+        .groups
+        .iter()
+        .map(|group| (group.group_name.as_str(), group.issuer_verification.clone()))
+        .collect();
+    // The verification applies only to groups of the verified credential type.
+    assert_eq!(
+        verifications,
+        vec![
+            ("Verified Age", None),
+            ("Verified Humanity", Some(expected))
+        ]
+    );
+
+    assert_eq!(
+        set_issuer_verification(
+            admin,
+            issuer,
+            "VerifiedHumanity",
+            false,
+            false,
+            &env,
+            canister_id
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        get_issuer_verification(issuer, "VerifiedHumanity", &env, canister_id),
+        None
+    );
+}
+
+#[test]
+fn should_verify_issuer_as_trusted_issuer() {
+    let env = env();
+    let admin = test_principal(4);
+    let canister_id = install_issuer_with_admin(&env, admin);
+    let trusted_issuer = principal_1();
+    let issuer = principal_2();
+    let credential_type = "VerifiedEmployment";
+    assert_eq!(
+        set_issuer_verification(
+            admin,
+            trusted_issuer,
+            credential_type,
+            true,
+            true,
+            &env,
+            canister_id
+        ),
+        Ok(())
+    );
+
+    assert_eq!(
+        set_issuer_verification(
+            trusted_issuer,
+            issuer,
+            credential_type,
+            true,
+            false,
+            &env,
+            canister_id
+        ),
+        Ok(())
+    );
+    let verification = get_issuer_verification(issuer, credential_type, &env, canister_id)
+        .expect("issuer not verified");
+    assert_eq!(verification.verified_by, trusted_issuer);
+    assert!(!verification.trusted);
+
+    // Verifications lapse once the verifier is no longer trusted.
+    assert_eq!(
+        set_issuer_verification(
+            admin,
+            trusted_issuer,
+            credential_type,
+            true,
+            false,
+            &env,
+            canister_id
+        ),
+        Ok(())
+    );
+    assert_eq!(
+        get_issuer_verification(issuer, credential_type, &env, canister_id),
+        None
+    );
+    assert!(get_issuer_verification(trusted_issuer, credential_type, &env, canister_id).is_some());
+}
+
+#[test]
+fn should_fail_set_issuer_verification_if_not_authorized() {
+    let env = env();
+    let admin = test_principal(4);
+    let canister_id = install_issuer_with_admin(&env, admin);
+    let trusted_issuer = principal_1();
+    let issuer = principal_2();
+    let admin_verified_issuer = test_principal(5);
+    for verified_issuer in [trusted_issuer, admin_verified_issuer] {
+        assert_eq!(
+            set_issuer_verification(
+                admin,
+                verified_issuer,
+                "VerifiedAge",
+                true,
+                verified_issuer == trusted_issuer,
+                &env,
+                canister_id
+            ),
+            Ok(())
+        );
+    }
+
+    for (verifier, credential_type, verified_issuer, trusted, expected_error) in [
+        // Issuers who are not trusted cannot verify.
+        (
+            issuer,
+            "VerifiedAge",
+            test_principal(6),
+            false,
+            "may not verify",
+        ),
+        (
+            admin_verified_issuer,
+            "VerifiedAge",
+            issuer,
+            false,
+            "may not verify",
+        ),
+        // Trusted issuers can verify only for their credential type.
+        (
+            trusted_issuer,
+            "VerifiedHumanity",
+            issuer,
+            false,
+            "may not verify",
+        ),
+        (
+            trusted_issuer,
+            "VerifiedAge",
+            issuer,
+            true,
+            "only admins can designate trusted issuers",
+        ),
+        // Trusted issuers cannot change the verifications made by others.
+        (
+            trusted_issuer,
+            "VerifiedAge",
+            admin_verified_issuer,
+            false,
+            "was made by",
+        ),
+    ] {
+        let result = set_issuer_verification(
+            verifier,
+            verified_issuer,
+            credential_type,
+            true,
+            trusted,
+            &env,
+            canister_id,
+        );
+        assert_matches!(result, Err(GroupsError::NotAuthorized(e)) if e.contains(expected_error));
+    }
+    assert_eq!(
+        get_issuer_verification(issuer, "VerifiedAge", &env, canister_id),
+        None
+    );
+}
+
+#[test]
+fn should_fail_set_issuer_verification_with_invalid_argument() {
+    let env = env();
+    let admin = test_principal(4);
+    let canister_id = install_issuer_with_admin(&env, admin);
+
+    for (issuer, credential_type, expected_error) in [
+        (
+            principal_1(),
+            "VerifiedWizardry",
+            "unsupported credential type",
+        ),
+        (
+            Principal::anonymous(),
+            "VerifiedHumanity",
+            "anonymous principal",
+        ),
+    ] {
+        let result = set_issuer_verification(
+            admin,
+            issuer,
+            credential_type,
+            true,
+            false,
+            &env,
+            canister_id,
+        );
+        assert_matches!(result, Err(GroupsError::InvalidArgument(e)) if e.contains(expected_error));
+    }
+}
+
+#[test]
+fn should_notify_owner_of_membership_requests() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+    // Repeated requests while pending are ignored.
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+
+    let notifications = do_get_notifications(None, owner, &env, canister_id).notifications;
+    assert_eq!(
+        notifications,
+        vec![NotificationData {
+            id: 0,
+            kind: NotificationKind::MembershipRequested,
+            timestamp_ns: time(&env),
+            group_name: group_name.to_string(),
+            owner,
+            member,
+            membership_status: MembershipStatus::PendingReview,
+        }]
+    );
+    assert!(do_get_notifications(None, member, &env, canister_id)
+        .notifications
+        .is_empty());
+}
+
+#[test]
+fn should_notify_members_of_membership_updates() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let member = principal_2();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    do_join_group(group_name, owner, member, None, &env, canister_id);
+
+    for new_status in [MembershipStatus::Accepted, MembershipStatus::Rejected] {
+        do_update_membership(
+            group_name,
+            vec![MembershipUpdate {
+                member,
+                new_status,
+                note: None,
+            }],
+            owner,
+            &env,
+            canister_id,
+        );
+    }
+
+    let notifications = do_get_notifications(None, member, &env, canister_id).notifications;
+    let updates: Vec<_> = notifications
+        .iter()
+        .map(|notification| {
+            (
+                notification.kind.clone(),
+                notification.owner,
+                notification.member,
+                notification.membership_status.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        updates,
+        vec![
+            (
+                NotificationKind::MembershipUpdated,
+                owner,
+                member,
+                MembershipStatus::Accepted
+            ),
+            (
+                NotificationKind::MembershipUpdated,
+                owner,
+                member,
+                MembershipStatus::Rejected
+            ),
+        ]
+    );
+    // The owner got notified of the request only.
+    assert_eq!(
+        do_get_notifications(None, owner, &env, canister_id)
+            .notifications
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn should_get_and_ack_notifications_since_id() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    for i in 0..3 {
+        do_join_group(
+            group_name,
+            owner,
+            test_principal(i),
+            None,
+            &env,
+            canister_id,
+        );
+    }
+    let ids = |since: Option<u64>| -> Vec<u64> {
+        do_get_notifications(since, owner, &env, canister_id)
+            .notifications
+            .iter()
+            .map(|notification| notification.id)
+            .collect()
+    };
+    assert_eq!(ids(None), vec![0, 1, 2]);
+    assert_eq!(ids(Some(0)), vec![1, 2]);
+
+    let result = api::ack_notifications(
+        &env,
+        canister_id,
+        owner,
+        AckNotificationsRequest { up_to: 1 },
+    )
+    .expect("API call failed");
+    assert_eq!(result, Ok(()));
+    assert_eq!(ids(None), vec![2]);
+
+    // Ids are not reused after acknowledging.
+    do_join_group(
+        group_name,
+        owner,
+        test_principal(3),
+        None,
+        &env,
+        canister_id,
+    );
+    assert_eq!(ids(Some(2)), vec![3]);
+}
+
+#[test]
+fn should_set_notification_webhook() {
+    let env = env();
+    let canister_id = install_issuer(&env, None);
+    let group_name = "Bob's Club";
+    let owner = principal_1();
+    let _ = do_add_group(group_name, owner, &env, canister_id);
+    let set_webhook = |url: Option<&str>| {
+        api::set_notification_webhook(
+            &env,
+            canister_id,
+            owner,
+            SetNotificationWebhookRequest {
+                url: url.map(str::to_string),
+            },
+        )
+        .expect("API call failed")
+    };
+
+    assert_eq!(set_webhook(Some("https://example.com/hook")), Ok(()));
+    assert_eq!(
+        do_get_notifications(None, owner, &env, canister_id)
+            .webhook_url
+            .as_deref(),
+        Some("https://example.com/hook")
+    );
+    // Notifications are kept in the outbox, regardless of the delivery to the webhook.
+    do_join_group(group_name, owner, principal_2(), None, &env, canister_id);
+    assert_eq!(
+        do_get_notifications(None, owner, &env, canister_id)
+            .notifications
+            .len(),
+        1
+    );
+
+    let long_url = format!("https://example.com/{}", "a".repeat(512));
+    for (url, expected_error) in [
+        ("http://example.com/hook", "must start with https://"),
+        (long_url.as_str(), "webhook URL too long"),
+    ] {
+        assert_matches!(
+            set_webhook(Some(url)),
+            Err(GroupsError::InvalidArgument(e)) if e.contains(expected_error)
+        );
+    }
+
+    assert_eq!(set_webhook(None), Ok(()));
+    assert_eq!(
+        do_get_notifications(None, owner, &env, canister_id).webhook_url,
+        None
+    );
+}
@@ -6,16 +6,26 @@ use ic_test_state_machine_client::{
     call_candid, call_candid_as, query_candid_as, CallError, StateMachine,
 };
 use ic_verifiable_credentials::issuer_api::{
-    ArgumentValue, GetCredentialRequest, Icrc21ConsentInfo, Icrc21Error,
-    Icrc21VcConsentMessageRequest, IssueCredentialError, IssuedCredentialData,
-    PrepareCredentialRequest, PreparedCredentialData, SignedIdAlias as SignedIssuerIdAlias,
+    ArgumentValue, DerivationOriginData, DerivationOriginError, DerivationOriginRequest,
+    GetCredentialRequest, Icrc21ConsentInfo, Icrc21Error, Icrc21VcConsentMessageRequest,
+    IssueCredentialError, IssuedCredentialData, PrepareCredentialRequest, PreparedCredentialData,
+    SignedIdAlias as SignedIssuerIdAlias,
 };
+use internet_identity_interface::http_gateway::{HttpRequest, HttpResponse};
 use lazy_static::lazy_static;
 use meta_issuer::groups_api::{
-    AddGroupRequest, FullGroupData, GetGroupRequest, GroupTypes, GroupsError, JoinGroupRequest,
-    ListGroupsRequest, MembershipStatus, MembershipUpdate, PublicGroupsData, SetUserRequest,
-    UpdateMembershipRequest, UserData,
+    AckNotificationsRequest, AddEvidenceRequest, AddGroupRequest, EvidenceData,
+    ExportMembersRequest, FullGroupData, GetCredentialsRequest, GetEvidenceChunkRequest,
+    GetGroupRequest, GetIssuerVerificationRequest, GetNotificationsRequest, GroupMetadata,
+    GroupTypes, GroupsError, ImportMembersRequest, ImportMembersResult, IssuedCredentialsData,
+    IssuerVerificationData, JoinGroupRequest, ListGroupsRequest, MembershipStatus,
+    MembershipUpdate, MembershipValidity, MembershipsData, NotificationsData, OwnerDashboardData,
+    PrepareCredentialsRequest, PreparedCredentialsData, PublicGroupsData, RateLimits,
+    SetIssuerVerificationRequest, SetMembershipValidityRequest, SetNotificationWebhookRequest,
+    SetUserRequest, UpdateGroupRequest, UpdateMembershipRequest, UploadEvidenceChunkRequest,
+    UserData,
 };
+use serde_bytes::ByteBuf;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
@@ -62,6 +72,19 @@ lazy_static! {
 
     };
 
+    /// Gzipped Wasm module of the previous Meta-Issuer release, for testing upgrades of its data.
+    pub static ref META_ISSUER_PREVIOUS_WASM: Vec<u8> = {
+        let def_path = PathBuf::from("./../").join("meta_issuer_previous.wasm.gz");
+        let err = format!("
+        Could not find Meta Issuer Wasm module of the previous release.
+        I will look for it at {:?}, and you can specify another path with the environment variable META_ISSUER_PREVIOUS_WASM (note that I run from {:?}).
+        You can build it by running ./scripts/build-previous-wasms.sh in <project-home>
+        ", &def_path,
+            &std::env::current_dir().map(|x| x.display().to_string()).unwrap_or_else(|_|
+                "an unknown directory".to_string()));
+                get_wasm_path("META_ISSUER_PREVIOUS_WASM".to_string(), &def_path).expect(&err)
+    };
+
     pub static ref II_WASM: Vec<u8> = {
         let def_path = PathBuf::from("./").join("internet_identity.wasm.gz");
         let err = format!("
@@ -80,6 +103,8 @@ lazy_static! {
         idp_canister_ids: vec![Principal::from_text(DUMMY_II_CANISTER_ID).unwrap()],
         derivation_origin: DUMMY_ISSUER_DERIVATION_ORIGIN.to_string(),
         frontend_hostname: DUMMY_ISSUER_FRONTEND_HOSTNAME.to_string(),
+        housekeeping: None,
+        rate_limits: None,
     };
 
     pub static ref DUMMY_SIGNED_ID_ALIAS: SignedIssuerIdAlias = SignedIssuerIdAlias {
@@ -98,6 +123,35 @@ pub struct IssuerInit {
     pub derivation_origin: String,
     /// Frontend hostname to be used by the issuer.
     pub frontend_hostname: String,
+    /// Configuration of the periodic housekeeping.
+    pub housekeeping: Option<HousekeepingConfig>,
+    /// Limits of the update calls open to anyone.
+    pub rate_limits: Option<RateLimits>,
+}
+
+#[derive(CandidType, Clone, Deserialize)]
+pub struct HousekeepingConfig {
+    pub interval_s: u64,
+    pub pending_review_max_age_ns: Option<u64>,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct WebhookResponse {
+    pub status: candid::Nat,
+    pub headers: Vec<WebhookHeader>,
+    pub body: ByteBuf,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct WebhookHeader {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct WebhookTransformArgs {
+    pub response: WebhookResponse,
+    pub context: ByteBuf,
 }
 
 pub fn install_canister<Init: CandidType>(
@@ -105,12 +159,22 @@ pub fn install_canister<Init: CandidType>(
     wasm: Vec<u8>,
     maybe_init: Option<Init>,
 ) -> CanisterId {
-    let canister_id = env.create_canister(None);
+    install_canister_with_controller(env, wasm, maybe_init, None)
+}
+
+/// Installs a canister controlled by `controller`, or by the anonymous principal if not set.
+pub fn install_canister_with_controller<Init: CandidType>(
+    env: &StateMachine,
+    wasm: Vec<u8>,
+    maybe_init: Option<Init>,
+    controller: Option<Principal>,
+) -> CanisterId {
+    let canister_id = env.create_canister(controller);
     let arg = match maybe_init {
         Some(init) => candid::encode_one(Some(init)).expect("error encoding init arg as candid"),
         None => candid::encode_one("()").expect("error encoding empty arg as candid"),
     };
-    env.install_canister(canister_id, wasm, arg, None);
+    env.install_canister(canister_id, wasm, arg, controller);
     canister_id
 }
 
@@ -156,6 +220,7 @@ pub fn add_group_with_member(
         vec![MembershipUpdate {
             member,
             new_status: MembershipStatus::Accepted,
+            note: None,
         }],
         owner,
         env,
@@ -220,12 +285,44 @@ pub fn do_join_group(
                     .map(|(k, v)| (k, meta_issuer::groups_api::ArgumentValue::from(v)))
                     .collect()
             }),
+            message: None,
         },
     )
     .expect("API call failed")
     .expect("Failed join_group");
 }
 
+pub fn do_get_owner_dashboard(
+    caller: Principal,
+    env: &StateMachine,
+    canister_id: Principal,
+) -> OwnerDashboardData {
+    api::get_owner_dashboard(env, canister_id, caller)
+        .expect("API call failed")
+        .expect("Failed get_owner_dashboard")
+}
+
+pub fn do_get_notifications(
+    since: Option<u64>,
+    caller: Principal,
+    env: &StateMachine,
+    canister_id: Principal,
+) -> NotificationsData {
+    api::get_notifications(env, canister_id, caller, GetNotificationsRequest { since })
+        .expect("API call failed")
+        .expect("Failed get_notifications")
+}
+
+pub fn do_list_my_memberships(
+    caller: Principal,
+    env: &StateMachine,
+    canister_id: Principal,
+) -> MembershipsData {
+    api::list_my_memberships(env, canister_id, caller)
+        .expect("API call failed")
+        .expect("Failed list_my_memberships")
+}
+
 pub fn do_update_membership(
     group_name: &str,
     updates: Vec<MembershipUpdate>,
@@ -246,6 +343,26 @@ pub fn do_update_membership(
     .expect("Failed update_membership");
 }
 
+pub fn do_set_membership_validity(
+    group_name: &str,
+    validity: Option<MembershipValidity>,
+    caller: Principal,
+    env: &StateMachine,
+    canister_id: Principal,
+) {
+    api::set_membership_validity(
+        env,
+        canister_id,
+        caller,
+        SetMembershipValidityRequest {
+            group_name: group_name.to_string(),
+            validity,
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed set_membership_validity");
+}
+
 /// Issuer API.
 pub mod api {
     use super::*;
@@ -259,6 +376,33 @@ pub mod api {
         call_candid(env, canister_id, "configure", (config,))
     }
 
+    pub fn configure_as(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        config: &IssuerInit,
+    ) -> Result<(), CallError> {
+        call_candid_as(env, canister_id, sender, "configure", (config,))
+    }
+
+    pub fn derivation_origin(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: &DerivationOriginRequest,
+    ) -> Result<Result<DerivationOriginData, DerivationOriginError>, CallError> {
+        call_candid_as(env, canister_id, sender, "derivation_origin", (req,)).map(|(x,)| x)
+    }
+
+    pub fn http_request(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: &HttpRequest,
+    ) -> Result<HttpResponse, CallError> {
+        query_candid_as(env, canister_id, sender, "http_request", (req,)).map(|(x,)| x)
+    }
+
     pub fn vc_consent_message(
         env: &StateMachine,
         canister_id: CanisterId,
@@ -307,6 +451,38 @@ pub mod api {
         .map(|(x,)| x)
     }
 
+    pub fn prepare_credentials(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        prepare_credentials_request: &PrepareCredentialsRequest,
+    ) -> Result<Result<PreparedCredentialsData, IssueCredentialError>, CallError> {
+        call_candid_as(
+            env,
+            canister_id,
+            sender,
+            "prepare_credentials",
+            (prepare_credentials_request,),
+        )
+        .map(|(x,)| x)
+    }
+
+    pub fn get_credentials(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        get_credentials_request: &GetCredentialsRequest,
+    ) -> Result<Result<IssuedCredentialsData, IssueCredentialError>, CallError> {
+        query_candid_as(
+            env,
+            canister_id,
+            sender,
+            "get_credentials",
+            (get_credentials_request,),
+        )
+        .map(|(x,)| x)
+    }
+
     pub fn group_types(
         env: &StateMachine,
         canister_id: CanisterId,
@@ -373,6 +549,109 @@ pub mod api {
         call_candid_as(env, canister_id, sender, "join_group", (req,)).map(|(x,)| x)
     }
 
+    pub fn update_group(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: UpdateGroupRequest,
+    ) -> Result<Result<GroupMetadata, GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "update_group", (req,)).map(|(x,)| x)
+    }
+
+    pub fn get_owner_dashboard(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+    ) -> Result<Result<OwnerDashboardData, GroupsError>, CallError> {
+        query_candid_as(env, canister_id, sender, "get_owner_dashboard", ()).map(|(x,)| x)
+    }
+
+    pub fn set_rate_limits(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        limits: &RateLimits,
+    ) -> Result<Result<(), GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "set_rate_limits", (limits,)).map(|(x,)| x)
+    }
+
+    pub fn get_rate_limits(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+    ) -> Result<RateLimits, CallError> {
+        query_candid_as(env, canister_id, sender, "get_rate_limits", ()).map(|(x,)| x)
+    }
+
+    pub fn get_notifications(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: GetNotificationsRequest,
+    ) -> Result<Result<NotificationsData, GroupsError>, CallError> {
+        query_candid_as(env, canister_id, sender, "get_notifications", (req,)).map(|(x,)| x)
+    }
+
+    pub fn ack_notifications(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: AckNotificationsRequest,
+    ) -> Result<Result<(), GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "ack_notifications", (req,)).map(|(x,)| x)
+    }
+
+    pub fn set_notification_webhook(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: SetNotificationWebhookRequest,
+    ) -> Result<Result<(), GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "set_notification_webhook", (req,)).map(|(x,)| x)
+    }
+
+    pub fn transform_webhook_response(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        args: &WebhookTransformArgs,
+    ) -> Result<WebhookResponse, CallError> {
+        query_candid_as(
+            env,
+            canister_id,
+            sender,
+            "transform_webhook_response",
+            (args,),
+        )
+        .map(|(x,)| x)
+    }
+
+    pub fn set_issuer_verification(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: SetIssuerVerificationRequest,
+    ) -> Result<Result<(), GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "set_issuer_verification", (req,)).map(|(x,)| x)
+    }
+
+    pub fn get_issuer_verification(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: GetIssuerVerificationRequest,
+    ) -> Result<Option<IssuerVerificationData>, CallError> {
+        query_candid_as(env, canister_id, sender, "get_issuer_verification", (req,)).map(|(x,)| x)
+    }
+
+    pub fn list_my_memberships(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+    ) -> Result<Result<MembershipsData, GroupsError>, CallError> {
+        query_candid_as(env, canister_id, sender, "list_my_memberships", ()).map(|(x,)| x)
+    }
+
     pub fn update_membership(
         env: &StateMachine,
         canister_id: CanisterId,
@@ -381,4 +660,58 @@ pub mod api {
     ) -> Result<Result<(), GroupsError>, CallError> {
         call_candid_as(env, canister_id, sender, "update_membership", (req,)).map(|(x,)| x)
     }
+
+    pub fn set_membership_validity(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: SetMembershipValidityRequest,
+    ) -> Result<Result<(), GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "set_membership_validity", (req,)).map(|(x,)| x)
+    }
+
+    pub fn export_members(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: ExportMembersRequest,
+    ) -> Result<Result<String, GroupsError>, CallError> {
+        query_candid_as(env, canister_id, sender, "export_members", (req,)).map(|(x,)| x)
+    }
+
+    pub fn import_members(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: ImportMembersRequest,
+    ) -> Result<Result<ImportMembersResult, GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "import_members", (req,)).map(|(x,)| x)
+    }
+
+    pub fn add_evidence(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: AddEvidenceRequest,
+    ) -> Result<Result<EvidenceData, GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "add_evidence", (req,)).map(|(x,)| x)
+    }
+
+    pub fn upload_evidence_chunk(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: UploadEvidenceChunkRequest,
+    ) -> Result<Result<EvidenceData, GroupsError>, CallError> {
+        call_candid_as(env, canister_id, sender, "upload_evidence_chunk", (req,)).map(|(x,)| x)
+    }
+
+    pub fn get_evidence_chunk(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: GetEvidenceChunkRequest,
+    ) -> Result<Result<ByteBuf, GroupsError>, CallError> {
+        query_candid_as(env, canister_id, sender, "get_evidence_chunk", (req,)).map(|(x,)| x)
+    }
 }
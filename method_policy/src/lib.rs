@@ -0,0 +1,171 @@
+//! Policies of the methods of a canister: who may call a method, and how large its argument
+//! may be.
+//!
+//! A canister lists the policies of all its methods in a single table, which is checked both
+//! by its `canister_inspect_message` hook, so that violating ingress messages are rejected
+//! before they are executed (and charged to the canister), and by the endpoints themselves,
+//! which also covers the calls that skip the inspection (queries and inter-canister calls).
+//!
+//! Access to a resource addressed by a call, e.g. a group, depends on the role of the caller
+//! in the resource. Only the endpoints can determine this role, so the inspection requires
+//! just an authenticated caller for such methods, and the endpoints additionally check the
+//! role via `check_resource_roles`.
+use candid::Principal;
+use std::fmt;
+
+/// Max. size of the argument of a method, unless its policy sets another one.
+pub const DEFAULT_MAX_ARG_BYTES: usize = 4 * 1024;
+
+/// Callers which are permitted to call a method.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Access {
+    /// Anyone, including the anonymous principal.
+    Public,
+    /// Any caller except the anonymous principal.
+    Authenticated,
+    /// The owner of the resource addressed by the call.
+    Owner,
+    /// A member of the resource addressed by the call.
+    Member,
+    /// Only the controllers of the canister, which act as its admins.
+    Controller,
+}
+
+/// Roles of the caller in the resource addressed by a call, as determined by the endpoint.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ResourceRoles {
+    pub is_owner: bool,
+    pub is_member: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MethodPolicy {
+    pub method: &'static str,
+    pub access: Access,
+    pub max_arg_bytes: usize,
+}
+
+impl MethodPolicy {
+    pub const fn public(method: &'static str) -> Self {
+        Self::new(method, Access::Public)
+    }
+
+    pub const fn authenticated(method: &'static str) -> Self {
+        Self::new(method, Access::Authenticated)
+    }
+
+    pub const fn owner(method: &'static str) -> Self {
+        Self::new(method, Access::Owner)
+    }
+
+    pub const fn member(method: &'static str) -> Self {
+        Self::new(method, Access::Member)
+    }
+
+    pub const fn controller(method: &'static str) -> Self {
+        Self::new(method, Access::Controller)
+    }
+
+    pub const fn with_max_arg_bytes(self, max_arg_bytes: usize) -> Self {
+        Self {
+            max_arg_bytes,
+            ..self
+        }
+    }
+
+    const fn new(method: &'static str, access: Access) -> Self {
+        Self {
+            method,
+            access,
+            max_arg_bytes: DEFAULT_MAX_ARG_BYTES,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PolicyViolation {
+    UnknownMethod(String),
+    AnonymousCaller,
+    NotController(Principal),
+    NotOwner(Principal),
+    NotMember(Principal),
+    ArgumentTooLarge { size: usize, max_size: usize },
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyViolation::UnknownMethod(method) => write!(f, "unknown method {}", method),
+            PolicyViolation::AnonymousCaller => write!(f, "anonymous caller not permitted"),
+            PolicyViolation::NotController(caller) => write!(f, "{} is not a controller", caller),
+            PolicyViolation::NotOwner(caller) => write!(f, "{} is not the owner", caller),
+            PolicyViolation::NotMember(caller) => write!(f, "{} is not a member", caller),
+            PolicyViolation::ArgumentTooLarge { size, max_size } => write!(
+                f,
+                "argument too large: {} bytes, max. {} bytes",
+                size, max_size
+            ),
+        }
+    }
+}
+
+/// Checks a call of `method` by `caller` with an argument of `arg_bytes` bytes against
+/// the policy of the method in `policies`. Methods without a policy are rejected.
+/// The roles of the caller in the addressed resource are not checked, cf. `check_resource_roles`.
+pub fn check_call(
+    policies: &[MethodPolicy],
+    method: &str,
+    caller: Principal,
+    is_controller: bool,
+    arg_bytes: usize,
+) -> Result<(), PolicyViolation> {
+    let policy = find_policy(policies, method)?;
+    match policy.access {
+        Access::Public => {}
+        Access::Authenticated | Access::Owner | Access::Member => {
+            if caller == Principal::anonymous() {
+                return Err(PolicyViolation::AnonymousCaller);
+            }
+        }
+        Access::Controller => {
+            if !is_controller {
+                return Err(PolicyViolation::NotController(caller));
+            }
+        }
+    }
+    if arg_bytes > policy.max_arg_bytes {
+        return Err(PolicyViolation::ArgumentTooLarge {
+            size: arg_bytes,
+            max_size: policy.max_arg_bytes,
+        });
+    }
+    Ok(())
+}
+
+/// Checks the roles of `caller` in the resource addressed by a call of `method` against
+/// the policy of the method in `policies`, complementing `check_call`.
+pub fn check_resource_roles(
+    policies: &[MethodPolicy],
+    method: &str,
+    caller: Principal,
+    roles: ResourceRoles,
+) -> Result<(), PolicyViolation> {
+    match find_policy(policies, method)?.access {
+        Access::Owner if !roles.is_owner => Err(PolicyViolation::NotOwner(caller)),
+        Access::Member if !roles.is_member => Err(PolicyViolation::NotMember(caller)),
+        _ => Ok(()),
+    }
+}
+
+fn find_policy<'a>(
+    policies: &'a [MethodPolicy],
+    method: &str,
+) -> Result<&'a MethodPolicy, PolicyViolation> {
+    policies
+        .iter()
+        .find(|policy| policy.method == method)
+        .ok_or_else(|| PolicyViolation::UnknownMethod(method.to_string()))
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,117 @@
+//! Unit tests of the method policies.
+use crate::{
+    check_call, check_resource_roles, MethodPolicy, PolicyViolation, ResourceRoles,
+    DEFAULT_MAX_ARG_BYTES,
+};
+use candid::Principal;
+
+const POLICIES: &[MethodPolicy] = &[
+    MethodPolicy::public("list_items"),
+    MethodPolicy::authenticated("add_item").with_max_arg_bytes(100),
+    MethodPolicy::controller("configure"),
+    MethodPolicy::owner("update_item"),
+    MethodPolicy::member("get_item"),
+];
+
+fn user() -> Principal {
+    Principal::from_slice(&[1])
+}
+
+#[test]
+fn should_permit_anyone_to_call_public_methods() {
+    for caller in [Principal::anonymous(), user()] {
+        assert_eq!(check_call(POLICIES, "list_items", caller, false, 0), Ok(()));
+    }
+}
+
+#[test]
+fn should_reject_anonymous_caller_of_authenticated_method() {
+    assert_eq!(
+        check_call(POLICIES, "add_item", Principal::anonymous(), false, 0),
+        Err(PolicyViolation::AnonymousCaller)
+    );
+    assert_eq!(check_call(POLICIES, "add_item", user(), false, 0), Ok(()));
+}
+
+#[test]
+fn should_permit_only_controllers_to_call_controller_methods() {
+    assert_eq!(
+        check_call(POLICIES, "configure", user(), false, 0),
+        Err(PolicyViolation::NotController(user()))
+    );
+    assert_eq!(check_call(POLICIES, "configure", user(), true, 0), Ok(()));
+}
+
+#[test]
+fn should_reject_oversized_arguments() {
+    assert_eq!(check_call(POLICIES, "add_item", user(), false, 100), Ok(()));
+    assert_eq!(
+        check_call(POLICIES, "add_item", user(), false, 101),
+        Err(PolicyViolation::ArgumentTooLarge {
+            size: 101,
+            max_size: 100
+        })
+    );
+    // Methods without an explicit limit use the default one.
+    assert!(check_call(
+        POLICIES,
+        "list_items",
+        user(),
+        false,
+        DEFAULT_MAX_ARG_BYTES + 1
+    )
+    .is_err());
+}
+
+#[test]
+fn should_reject_unknown_methods() {
+    assert_eq!(
+        check_call(POLICIES, "remove_item", user(), true, 0),
+        Err(PolicyViolation::UnknownMethod("remove_item".to_string()))
+    );
+}
+
+#[test]
+fn should_require_authentication_for_resource_methods() {
+    for method in ["update_item", "get_item"] {
+        assert_eq!(
+            check_call(POLICIES, method, Principal::anonymous(), false, 0),
+            Err(PolicyViolation::AnonymousCaller)
+        );
+        // The roles in the resource are checked separately.
+        assert_eq!(check_call(POLICIES, method, user(), false, 0), Ok(()));
+    }
+}
+
+#[test]
+fn should_check_resource_roles() {
+    let owner = ResourceRoles {
+        is_owner: true,
+        is_member: false,
+    };
+    let member = ResourceRoles {
+        is_owner: false,
+        is_member: true,
+    };
+    assert_eq!(
+        check_resource_roles(POLICIES, "update_item", user(), owner),
+        Ok(())
+    );
+    assert_eq!(
+        check_resource_roles(POLICIES, "update_item", user(), member),
+        Err(PolicyViolation::NotOwner(user()))
+    );
+    assert_eq!(
+        check_resource_roles(POLICIES, "get_item", user(), member),
+        Ok(())
+    );
+    assert_eq!(
+        check_resource_roles(POLICIES, "get_item", user(), owner),
+        Err(PolicyViolation::NotMember(user()))
+    );
+    // Methods which do not address a resource do not depend on the roles.
+    assert_eq!(
+        check_resource_roles(POLICIES, "add_item", user(), ResourceRoles::default()),
+        Ok(())
+    );
+}
@@ -0,0 +1,134 @@
+//! Token-bucket rate limiting of update calls, per caller and across all callers.
+//!
+//! A bucket holds up to `capacity` tokens and regains one token per refill period.
+//! Every limited call takes a token from the bucket of its caller and from the global bucket,
+//! and is rejected if either of them is empty. The buckets are kept on the heap, so upgrades
+//! reset them, which is acceptable for protecting the canisters against spam.
+use candid::{CandidType, Deserialize, Principal};
+use std::collections::BTreeMap;
+
+/// Max. number of per-caller buckets, beyond which the full buckets are dropped
+/// (a full bucket is equivalent to a missing one).
+const MAX_TRACKED_CALLERS: usize = 10_000;
+const NS_PER_SECOND: u64 = 1_000_000_000;
+
+#[derive(CandidType, Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct TokenBucketConfig {
+    /// Max. number of calls in a burst.
+    pub capacity: u32,
+    /// Time after which one more call is allowed, up to `capacity` calls.
+    pub refill_period_ns: u64,
+}
+
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    tokens: u32,
+    last_refill_ns: u64,
+}
+
+impl TokenBucket {
+    fn full(config: &TokenBucketConfig, now_ns: u64) -> Self {
+        Self {
+            tokens: config.capacity,
+            last_refill_ns: now_ns,
+        }
+    }
+
+    fn refill(&mut self, config: &TokenBucketConfig, now_ns: u64) {
+        let period_ns = config.refill_period_ns.max(1);
+        let refills = now_ns.saturating_sub(self.last_refill_ns) / period_ns;
+        let tokens = (self.tokens as u64)
+            .saturating_add(refills)
+            .min(config.capacity as u64) as u32;
+        if tokens == config.capacity {
+            self.last_refill_ns = now_ns;
+        } else {
+            self.last_refill_ns += refills * period_ns;
+        }
+        self.tokens = tokens;
+    }
+
+    fn is_full(&self, config: &TokenBucketConfig) -> bool {
+        self.tokens >= config.capacity
+    }
+
+    /// Seconds until the next token is available, rounded up.
+    fn retry_after_s(&self, config: &TokenBucketConfig, now_ns: u64) -> u64 {
+        let next_refill_ns = self.last_refill_ns.saturating_add(config.refill_period_ns);
+        next_refill_ns
+            .saturating_sub(now_ns)
+            .div_ceil(NS_PER_SECOND)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    callers: BTreeMap<Principal, TokenBucket>,
+    global: Option<TokenBucket>,
+}
+
+impl RateLimiter {
+    /// Takes a token for a call by `caller`, or returns an error message if the caller,
+    /// or all callers together, have exhausted their tokens. Limits which are not set
+    /// do not apply. Rejected calls do not take any tokens.
+    pub fn check(
+        &mut self,
+        caller: Principal,
+        per_caller: Option<&TokenBucketConfig>,
+        global: Option<&TokenBucketConfig>,
+        now_ns: u64,
+    ) -> Result<(), String> {
+        let caller_bucket = per_caller.map(|config| {
+            let mut bucket = self
+                .callers
+                .get(&caller)
+                .cloned()
+                .unwrap_or_else(|| TokenBucket::full(config, now_ns));
+            bucket.refill(config, now_ns);
+            (bucket, config)
+        });
+        let global_bucket = global.map(|config| {
+            let mut bucket = self
+                .global
+                .clone()
+                .unwrap_or_else(|| TokenBucket::full(config, now_ns));
+            bucket.refill(config, now_ns);
+            (bucket, config)
+        });
+        if let Some((bucket, config)) = &caller_bucket {
+            if bucket.tokens == 0 {
+                return Err(format!(
+                    "too many calls by {}, retry in {} s",
+                    caller,
+                    bucket.retry_after_s(config, now_ns)
+                ));
+            }
+        }
+        if let Some((bucket, config)) = &global_bucket {
+            if bucket.tokens == 0 {
+                return Err(format!(
+                    "too many calls, retry in {} s",
+                    bucket.retry_after_s(config, now_ns)
+                ));
+            }
+        }
+        if let Some((mut bucket, config)) = caller_bucket {
+            bucket.tokens -= 1;
+            self.callers.insert(caller, bucket);
+            if self.callers.len() > MAX_TRACKED_CALLERS {
+                self.callers.retain(|_, bucket| {
+                    bucket.refill(config, now_ns);
+                    !bucket.is_full(config)
+                });
+            }
+        }
+        if let Some((mut bucket, _)) = global_bucket {
+            bucket.tokens -= 1;
+            self.global = Some(bucket);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
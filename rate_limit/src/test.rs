@@ -0,0 +1,97 @@
+//! Unit tests of the rate limiter.
+use crate::{RateLimiter, TokenBucketConfig};
+use candid::Principal;
+
+const SECOND_NS: u64 = 1_000_000_000;
+
+fn bucket(capacity: u32, refill_period_s: u64) -> TokenBucketConfig {
+    TokenBucketConfig {
+        capacity,
+        refill_period_ns: refill_period_s * SECOND_NS,
+    }
+}
+
+fn principal(i: u8) -> Principal {
+    Principal::from_slice(&[i])
+}
+
+#[test]
+fn should_limit_calls_per_caller() {
+    let mut limiter = RateLimiter::default();
+    let config = bucket(2, 10);
+    for _ in 0..2 {
+        assert!(limiter.check(principal(1), Some(&config), None, 0).is_ok());
+    }
+    let error = limiter
+        .check(principal(1), Some(&config), None, SECOND_NS)
+        .unwrap_err();
+    assert!(error.contains("retry in 9 s"), "{}", error);
+    // Other callers have their own buckets.
+    assert!(limiter.check(principal(2), Some(&config), None, 0).is_ok());
+}
+
+#[test]
+fn should_refill_tokens_over_time() {
+    let mut limiter = RateLimiter::default();
+    let config = bucket(2, 10);
+    for _ in 0..2 {
+        assert!(limiter.check(principal(1), Some(&config), None, 0).is_ok());
+    }
+    assert!(limiter
+        .check(principal(1), Some(&config), None, 10 * SECOND_NS)
+        .is_ok());
+    assert!(limiter
+        .check(principal(1), Some(&config), None, 15 * SECOND_NS)
+        .is_err());
+    // Tokens do not accumulate beyond the capacity.
+    let later = 1_000 * SECOND_NS;
+    for _ in 0..2 {
+        assert!(limiter
+            .check(principal(1), Some(&config), None, later)
+            .is_ok());
+    }
+    assert!(limiter
+        .check(principal(1), Some(&config), None, later)
+        .is_err());
+}
+
+#[test]
+fn should_limit_calls_of_all_callers() {
+    let mut limiter = RateLimiter::default();
+    let per_caller = bucket(5, 1);
+    let global = bucket(3, 1);
+    for i in 0..3 {
+        assert!(limiter
+            .check(principal(i), Some(&per_caller), Some(&global), 0)
+            .is_ok());
+    }
+    let error = limiter
+        .check(principal(4), Some(&per_caller), Some(&global), 0)
+        .unwrap_err();
+    assert!(error.starts_with("too many calls, "), "{}", error);
+}
+
+#[test]
+fn should_not_take_tokens_of_rejected_calls() {
+    let mut limiter = RateLimiter::default();
+    let per_caller = bucket(1, 10);
+    let global = bucket(2, 10);
+    assert!(limiter
+        .check(principal(1), Some(&per_caller), Some(&global), 0)
+        .is_ok());
+    // Rejected by the caller's bucket, so the global bucket keeps its token.
+    assert!(limiter
+        .check(principal(1), Some(&per_caller), Some(&global), 0)
+        .is_err());
+    assert!(limiter
+        .check(principal(2), Some(&per_caller), Some(&global), 0)
+        .is_ok());
+}
+
+#[test]
+fn should_not_limit_without_config() {
+    let mut limiter = RateLimiter::default();
+    for _ in 0..100 {
+        assert!(limiter.check(principal(1), None, None, 0).is_ok());
+    }
+}
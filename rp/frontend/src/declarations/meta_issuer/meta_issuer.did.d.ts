@@ -2,20 +2,55 @@ import type { Principal } from '@dfinity/principal';
 import type { ActorMethod } from '@dfinity/agent';
 import type { IDL } from '@dfinity/candid';
 
+export interface AckNotificationsRequest { 'up_to' : bigint }
+export interface AddEvidenceRequest {
+  'owner' : Principal,
+  'size' : number,
+  'content_type' : string,
+  'file_name' : string,
+  'group_name' : string,
+}
 export interface AddGroupRequest { 'group_name' : string }
 export type ArgumentValue = { 'Int' : number } |
   { 'String' : string };
+export interface ArgumentValueCount {
+  'argument' : string,
+  'value' : string,
+  'count' : number,
+}
 export interface CredentialSpec {
   'arguments' : [] | [Array<[string, ArgumentValue]>],
   'credential_type' : string,
 }
+export interface CredentialToGet {
+  'prepared_context' : [] | [Uint8Array | number[]],
+  'credential_spec' : CredentialSpec,
+}
+export interface DailyCount {
+  'count' : number,
+  'day_start_timestamp_ns' : TimestampNs,
+}
 export interface DerivationOriginData { 'origin' : string }
 export type DerivationOriginError = { 'Internal' : string } |
   { 'UnsupportedOrigin' : string };
 export interface DerivationOriginRequest { 'frontend_hostname' : string }
+export interface EvidenceData {
+  'size' : number,
+  'content_type' : string,
+  'file_name' : string,
+  'complete' : boolean,
+  'evidence_id' : bigint,
+}
+export interface ExportMembersRequest {
+  'group_name' : string,
+  'format' : MembersFormat,
+}
 export interface FullGroupData {
   'members' : Array<MemberData>,
+  'membership_validity' : [] | [MembershipValidity],
   'owner' : Principal,
+  'metadata' : GroupMetadata,
+  'issuer_verification' : [] | [IssuerVerificationData],
   'stats' : GroupStats,
   'issuer_nickname' : string,
   'group_name' : string,
@@ -25,7 +60,42 @@ export interface GetCredentialRequest {
   'prepared_context' : [] | [Uint8Array | number[]],
   'credential_spec' : CredentialSpec,
 }
+export interface GetCredentialsRequest {
+  'signed_id_alias' : SignedIdAlias,
+  'credentials' : Array<CredentialToGet>,
+}
+export interface GetEvidenceChunkRequest {
+  'chunk_index' : number,
+  'evidence_id' : bigint,
+}
 export interface GetGroupRequest { 'group_name' : string }
+export interface GetIssuerVerificationRequest {
+  'issuer' : Principal,
+  'credential_type' : string,
+}
+export interface GetNotificationsRequest { 'since' : [] | [bigint] }
+export interface GroupDashboardData {
+  'member_counts' : MembershipCounts,
+  'credentials_issued' : bigint,
+  'argument_values' : Array<ArgumentValueCount>,
+  'last_activity_timestamp_ns' : [] | [TimestampNs],
+  'credentials_issued_by_day' : Array<DailyCount>,
+  'pending_age' : PendingAgeDistribution,
+  'group_name' : string,
+}
+export interface GroupLogo {
+  'content' : Uint8Array | number[],
+  'content_type' : string,
+}
+export type GroupLogoUpdate = { 'Set' : GroupLogo } |
+  { 'Remove' : null };
+export interface GroupMetadata {
+  'contact_url' : [] | [string],
+  'tags' : Array<string>,
+  'description' : [] | [string],
+  'logo_url' : [] | [string],
+  'visibility' : GroupVisibility,
+}
 export interface GroupStats {
   'created_timestamp_ns' : TimestampNs,
   'member_count' : number,
@@ -35,12 +105,21 @@ export interface GroupType {
   'credential_spec' : CredentialSpec,
 }
 export interface GroupTypes { 'types' : Array<GroupType> }
+export type GroupVisibility = { 'Public' : null } |
+  { 'InviteOnly' : null } |
+  { 'Unlisted' : null };
 export type GroupsError = { 'Internal' : string } |
   { 'NotFound' : string } |
   { 'NotAuthorized' : string } |
   { 'AlreadyExists' : string } |
-  { 'NotAuthenticated' : string };
+  { 'RateLimited' : string } |
+  { 'NotAuthenticated' : string } |
+  { 'InvalidArgument' : string };
 export type HeaderField = [string, string];
+export interface HousekeepingConfig {
+  'interval_s' : bigint,
+  'pending_review_max_age_ns' : [] | [bigint],
+}
 export interface HttpRequest {
   'url' : string,
   'method' : string,
@@ -68,6 +147,16 @@ export interface Icrc21VcConsentMessageRequest {
   'preferences' : Icrc21ConsentPreferences,
   'credential_spec' : CredentialSpec,
 }
+export interface ImportMembersRequest {
+  'content' : string,
+  'group_name' : string,
+  'format' : MembersFormat,
+}
+export interface ImportMembersResult {
+  'errors' : Array<ImportRowError>,
+  'imported_count' : number,
+}
+export interface ImportRowError { 'row' : number, 'error' : string }
 export type IssueCredentialError = { 'Internal' : string } |
   { 'SignatureNotFound' : string } |
   { 'InvalidIdAlias' : string } |
@@ -75,61 +164,198 @@ export type IssueCredentialError = { 'Internal' : string } |
   { 'UnknownSubject' : string } |
   { 'UnsupportedCredentialSpec' : string };
 export interface IssuedCredentialData { 'vc_jws' : string }
+export interface IssuedCredentialsData {
+  'results' : Array<
+    { 'Ok' : IssuedCredentialData } |
+      { 'Err' : IssueCredentialError }
+  >,
+}
 export interface IssuerInit {
+  'housekeeping' : [] | [HousekeepingConfig],
   'derivation_origin' : string,
   'idp_canister_ids' : Array<Principal>,
   'ic_root_key_der' : Uint8Array | number[],
-  'frontend_hostname' : string,
+  'rate_limits' : [] | [RateLimits],
+}
+export interface IssuerVerificationData {
+  'trusted' : boolean,
+  'issuer' : Principal,
+  'verified_by' : Principal,
+  'verified_timestamp_ns' : TimestampNs,
+  'credential_type' : string,
 }
 export interface JoinGroupRequest {
   'owner' : Principal,
   'vc_arguments' : [] | [VcArguments],
+  'message' : [] | [string],
   'group_name' : string,
 }
 export interface ListGroupsRequest { 'group_name_substring' : [] | [string] }
 export interface MemberData {
   'member' : Principal,
+  'reviewer_note' : [] | [string],
   'membership_status' : MembershipStatus,
   'nickname' : string,
   'joined_timestamp_ns' : TimestampNs,
   'vc_arguments' : [] | [VcArguments],
+  'expires_timestamp_ns' : [] | [TimestampNs],
+  'evidence' : Array<EvidenceData>,
+  'applicant_message' : [] | [string],
+}
+export type MembersFormat = { 'Csv' : null } |
+  { 'Json' : null };
+export interface MembershipCounts {
+  'expired' : number,
+  'pending_review' : number,
+  'rejected' : number,
+  'accepted' : number,
+}
+export interface MembershipData {
+  'reviewer_note' : [] | [string],
+  'membership_status' : MembershipStatus,
+  'joined_timestamp_ns' : TimestampNs,
+  'owner' : Principal,
+  'vc_arguments' : [] | [VcArguments],
+  'expires_timestamp_ns' : [] | [TimestampNs],
+  'issuer_nickname' : string,
+  'group_name' : string,
+  'credential_spec' : [] | [CredentialSpec],
 }
 export type MembershipStatus = { 'PendingReview' : null } |
   { 'Rejected' : null } |
-  { 'Accepted' : null };
+  { 'Accepted' : null } |
+  { 'Expired' : null };
 export interface MembershipUpdate {
   'member' : Principal,
+  'note' : [] | [string],
   'new_status' : MembershipStatus,
 }
+export interface MembershipValidity {
+  'reverify_on_expiry' : boolean,
+  'validity_period_ns' : bigint,
+}
+export interface MembershipsData { 'memberships' : Array<MembershipData> }
+export interface NotificationData {
+  'id' : bigint,
+  'member' : Principal,
+  'membership_status' : MembershipStatus,
+  'timestamp_ns' : TimestampNs,
+  'owner' : Principal,
+  'kind' : NotificationKind,
+  'group_name' : string,
+}
+export type NotificationKind = { 'MembershipRequested' : null } |
+  { 'MembershipUpdated' : null };
+export interface NotificationsData {
+  'webhook_url' : [] | [string],
+  'notifications' : Array<NotificationData>,
+}
+export interface OwnerDashboardData {
+  'member_counts' : MembershipCounts,
+  'credentials_issued' : bigint,
+  'groups' : Array<GroupDashboardData>,
+}
+export interface PendingAgeDistribution {
+  'over_30_days' : number,
+  'under_1_day' : number,
+  'under_7_days' : number,
+  'under_30_days' : number,
+}
 export interface PrepareCredentialRequest {
   'signed_id_alias' : SignedIdAlias,
   'credential_spec' : CredentialSpec,
 }
+export interface PrepareCredentialsRequest {
+  'credential_specs' : Array<CredentialSpec>,
+  'signed_id_alias' : SignedIdAlias,
+}
 export interface PreparedCredentialData {
   'prepared_context' : [] | [Uint8Array | number[]],
 }
+export interface PreparedCredentialsData {
+  'results' : Array<
+    { 'Ok' : PreparedCredentialData } |
+      { 'Err' : IssueCredentialError }
+  >,
+}
 export interface PublicGroupData {
+  'reviewer_note' : [] | [string],
   'membership_status' : [] | [MembershipStatus],
   'owner' : Principal,
+  'metadata' : GroupMetadata,
+  'issuer_verification' : [] | [IssuerVerificationData],
   'vc_arguments' : [] | [VcArguments],
   'stats' : GroupStats,
   'issuer_nickname' : string,
   'group_name' : string,
 }
 export interface PublicGroupsData { 'groups' : Array<PublicGroupData> }
+export interface RateLimits {
+  'max_groups_per_owner' : [] | [number],
+  'max_pending_requests_per_group' : [] | [number],
+  'global' : [] | [TokenBucketConfig],
+  'per_caller' : [] | [TokenBucketConfig],
+}
+export interface SetIssuerVerificationRequest {
+  'verified' : boolean,
+  'trusted' : boolean,
+  'issuer' : Principal,
+  'credential_type' : string,
+}
+export interface SetMembershipValidityRequest {
+  'validity' : [] | [MembershipValidity],
+  'group_name' : string,
+}
+export interface SetNotificationWebhookRequest { 'url' : [] | [string] }
 export interface SetUserRequest { 'user_data' : UserData }
 export interface SignedIdAlias { 'credential_jws' : string }
 export type TimestampNs = bigint;
+export interface TokenBucketConfig {
+  'refill_period_ns' : bigint,
+  'capacity' : number,
+}
+export interface UpdateGroupRequest {
+  'contact_url' : [] | [string],
+  'logo' : [] | [GroupLogoUpdate],
+  'tags' : Array<string>,
+  'description' : [] | [string],
+  'visibility' : GroupVisibility,
+  'group_name' : string,
+}
 export interface UpdateMembershipRequest {
   'updates' : Array<MembershipUpdate>,
   'group_name' : string,
 }
+export interface UploadEvidenceChunkRequest {
+  'chunk_index' : number,
+  'content' : Uint8Array | number[],
+  'evidence_id' : bigint,
+}
 export interface UserData {
   'user_nickname' : [] | [string],
   'issuer_nickname' : [] | [string],
 }
 export type VcArguments = Array<[string, ArgumentValue]>;
+export interface WebhookResponse {
+  'status' : bigint,
+  'body' : Uint8Array | number[],
+  'headers' : Array<{ 'value' : string, 'name' : string }>,
+}
+export interface WebhookTransformArgs {
+  'context' : Uint8Array | number[],
+  'response' : WebhookResponse,
+}
 export interface _SERVICE {
+  'ack_notifications' : ActorMethod<
+    [AckNotificationsRequest],
+    { 'Ok' : null } |
+      { 'Err' : GroupsError }
+  >,
+  'add_evidence' : ActorMethod<
+    [AddEvidenceRequest],
+    { 'Ok' : EvidenceData } |
+      { 'Err' : GroupsError }
+  >,
   'add_group' : ActorMethod<
     [AddGroupRequest],
     { 'Ok' : FullGroupData } |
@@ -141,16 +367,46 @@ export interface _SERVICE {
     { 'Ok' : DerivationOriginData } |
       { 'Err' : DerivationOriginError }
   >,
+  'export_members' : ActorMethod<
+    [ExportMembersRequest],
+    { 'Ok' : string } |
+      { 'Err' : GroupsError }
+  >,
   'get_credential' : ActorMethod<
     [GetCredentialRequest],
     { 'Ok' : IssuedCredentialData } |
       { 'Err' : IssueCredentialError }
   >,
+  'get_credentials' : ActorMethod<
+    [GetCredentialsRequest],
+    { 'Ok' : IssuedCredentialsData } |
+      { 'Err' : IssueCredentialError }
+  >,
+  'get_evidence_chunk' : ActorMethod<
+    [GetEvidenceChunkRequest],
+    { 'Ok' : Uint8Array | number[] } |
+      { 'Err' : GroupsError }
+  >,
   'get_group' : ActorMethod<
     [GetGroupRequest],
     { 'Ok' : FullGroupData } |
       { 'Err' : GroupsError }
   >,
+  'get_issuer_verification' : ActorMethod<
+    [GetIssuerVerificationRequest],
+    [] | [IssuerVerificationData]
+  >,
+  'get_notifications' : ActorMethod<
+    [GetNotificationsRequest],
+    { 'Ok' : NotificationsData } |
+      { 'Err' : GroupsError }
+  >,
+  'get_owner_dashboard' : ActorMethod<
+    [],
+    { 'Ok' : OwnerDashboardData } |
+      { 'Err' : GroupsError }
+  >,
+  'get_rate_limits' : ActorMethod<[], RateLimits>,
   'get_user' : ActorMethod<[], { 'Ok' : UserData } | { 'Err' : GroupsError }>,
   'group_types' : ActorMethod<
     [],
@@ -158,6 +414,11 @@ export interface _SERVICE {
       { 'Err' : GroupsError }
   >,
   'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
+  'import_members' : ActorMethod<
+    [ImportMembersRequest],
+    { 'Ok' : ImportMembersResult } |
+      { 'Err' : GroupsError }
+  >,
   'join_group' : ActorMethod<
     [JoinGroupRequest],
     { 'Ok' : null } |
@@ -168,21 +429,65 @@ export interface _SERVICE {
     { 'Ok' : PublicGroupsData } |
       { 'Err' : GroupsError }
   >,
+  'list_my_memberships' : ActorMethod<
+    [],
+    { 'Ok' : MembershipsData } |
+      { 'Err' : GroupsError }
+  >,
   'prepare_credential' : ActorMethod<
     [PrepareCredentialRequest],
     { 'Ok' : PreparedCredentialData } |
       { 'Err' : IssueCredentialError }
   >,
+  'prepare_credentials' : ActorMethod<
+    [PrepareCredentialsRequest],
+    { 'Ok' : PreparedCredentialsData } |
+      { 'Err' : IssueCredentialError }
+  >,
+  'set_issuer_verification' : ActorMethod<
+    [SetIssuerVerificationRequest],
+    { 'Ok' : null } |
+      { 'Err' : GroupsError }
+  >,
+  'set_membership_validity' : ActorMethod<
+    [SetMembershipValidityRequest],
+    { 'Ok' : null } |
+      { 'Err' : GroupsError }
+  >,
+  'set_notification_webhook' : ActorMethod<
+    [SetNotificationWebhookRequest],
+    { 'Ok' : null } |
+      { 'Err' : GroupsError }
+  >,
+  'set_rate_limits' : ActorMethod<
+    [RateLimits],
+    { 'Ok' : null } |
+      { 'Err' : GroupsError }
+  >,
   'set_user' : ActorMethod<
     [SetUserRequest],
     { 'Ok' : null } |
       { 'Err' : GroupsError }
   >,
+  'transform_webhook_response' : ActorMethod<
+    [WebhookTransformArgs],
+    WebhookResponse
+  >,
+  'update_group' : ActorMethod<
+    [UpdateGroupRequest],
+    { 'Ok' : GroupMetadata } |
+      { 'Err' : GroupsError }
+  >,
   'update_membership' : ActorMethod<
     [UpdateMembershipRequest],
     { 'Ok' : null } |
       { 'Err' : GroupsError }
   >,
+  'upload_evidence_chunk' : ActorMethod<
+    [UploadEvidenceChunkRequest],
+    { 'Ok' : EvidenceData } |
+      { 'Err' : GroupsError }
+  >,
   'vc_consent_message' : ActorMethod<
     [Icrc21VcConsentMessageRequest],
     { 'Ok' : Icrc21ConsentInfo } |
@@ -1,25 +1,92 @@
 export const idlFactory = ({ IDL }) => {
+  const HousekeepingConfig = IDL.Record({
+    'interval_s' : IDL.Nat64,
+    'pending_review_max_age_ns' : IDL.Opt(IDL.Nat64),
+  });
+  const TokenBucketConfig = IDL.Record({
+    'refill_period_ns' : IDL.Nat64,
+    'capacity' : IDL.Nat32,
+  });
+  const RateLimits = IDL.Record({
+    'max_groups_per_owner' : IDL.Opt(IDL.Nat32),
+    'max_pending_requests_per_group' : IDL.Opt(IDL.Nat32),
+    'global' : IDL.Opt(TokenBucketConfig),
+    'per_caller' : IDL.Opt(TokenBucketConfig),
+  });
   const IssuerInit = IDL.Record({
+    'housekeeping' : IDL.Opt(HousekeepingConfig),
     'derivation_origin' : IDL.Text,
     'idp_canister_ids' : IDL.Vec(IDL.Principal),
     'ic_root_key_der' : IDL.Vec(IDL.Nat8),
-    'frontend_hostname' : IDL.Text,
+    'rate_limits' : IDL.Opt(RateLimits),
+  });
+  const AckNotificationsRequest = IDL.Record({ 'up_to' : IDL.Nat64 });
+  const GroupsError = IDL.Variant({
+    'Internal' : IDL.Text,
+    'NotFound' : IDL.Text,
+    'NotAuthorized' : IDL.Text,
+    'AlreadyExists' : IDL.Text,
+    'RateLimited' : IDL.Text,
+    'NotAuthenticated' : IDL.Text,
+    'InvalidArgument' : IDL.Text,
+  });
+  const AddEvidenceRequest = IDL.Record({
+    'owner' : IDL.Principal,
+    'size' : IDL.Nat32,
+    'content_type' : IDL.Text,
+    'file_name' : IDL.Text,
+    'group_name' : IDL.Text,
+  });
+  const EvidenceData = IDL.Record({
+    'size' : IDL.Nat32,
+    'content_type' : IDL.Text,
+    'file_name' : IDL.Text,
+    'complete' : IDL.Bool,
+    'evidence_id' : IDL.Nat64,
   });
   const AddGroupRequest = IDL.Record({ 'group_name' : IDL.Text });
   const MembershipStatus = IDL.Variant({
     'PendingReview' : IDL.Null,
     'Rejected' : IDL.Null,
     'Accepted' : IDL.Null,
+    'Expired' : IDL.Null,
   });
   const TimestampNs = IDL.Nat64;
   const ArgumentValue = IDL.Variant({ 'Int' : IDL.Int32, 'String' : IDL.Text });
   const VcArguments = IDL.Vec(IDL.Tuple(IDL.Text, ArgumentValue));
   const MemberData = IDL.Record({
     'member' : IDL.Principal,
+    'reviewer_note' : IDL.Opt(IDL.Text),
     'membership_status' : MembershipStatus,
     'nickname' : IDL.Text,
     'joined_timestamp_ns' : TimestampNs,
     'vc_arguments' : IDL.Opt(VcArguments),
+    'expires_timestamp_ns' : IDL.Opt(TimestampNs),
+    'evidence' : IDL.Vec(EvidenceData),
+    'applicant_message' : IDL.Opt(IDL.Text),
+  });
+  const MembershipValidity = IDL.Record({
+    'reverify_on_expiry' : IDL.Bool,
+    'validity_period_ns' : IDL.Nat64,
+  });
+  const GroupVisibility = IDL.Variant({
+    'Public' : IDL.Null,
+    'InviteOnly' : IDL.Null,
+    'Unlisted' : IDL.Null,
+  });
+  const GroupMetadata = IDL.Record({
+    'contact_url' : IDL.Opt(IDL.Text),
+    'tags' : IDL.Vec(IDL.Text),
+    'description' : IDL.Opt(IDL.Text),
+    'logo_url' : IDL.Opt(IDL.Text),
+    'visibility' : GroupVisibility,
+  });
+  const IssuerVerificationData = IDL.Record({
+    'trusted' : IDL.Bool,
+    'issuer' : IDL.Principal,
+    'verified_by' : IDL.Principal,
+    'verified_timestamp_ns' : TimestampNs,
+    'credential_type' : IDL.Text,
   });
   const GroupStats = IDL.Record({
     'created_timestamp_ns' : TimestampNs,
@@ -27,18 +94,14 @@ export const idlFactory = ({ IDL }) => {
   });
   const FullGroupData = IDL.Record({
     'members' : IDL.Vec(MemberData),
+    'membership_validity' : IDL.Opt(MembershipValidity),
     'owner' : IDL.Principal,
+    'metadata' : GroupMetadata,
+    'issuer_verification' : IDL.Opt(IssuerVerificationData),
     'stats' : GroupStats,
     'issuer_nickname' : IDL.Text,
     'group_name' : IDL.Text,
   });
-  const GroupsError = IDL.Variant({
-    'Internal' : IDL.Text,
-    'NotFound' : IDL.Text,
-    'NotAuthorized' : IDL.Text,
-    'AlreadyExists' : IDL.Text,
-    'NotAuthenticated' : IDL.Text,
-  });
   const DerivationOriginRequest = IDL.Record({
     'frontend_hostname' : IDL.Text,
   });
@@ -47,6 +110,11 @@ export const idlFactory = ({ IDL }) => {
     'Internal' : IDL.Text,
     'UnsupportedOrigin' : IDL.Text,
   });
+  const MembersFormat = IDL.Variant({ 'Csv' : IDL.Null, 'Json' : IDL.Null });
+  const ExportMembersRequest = IDL.Record({
+    'group_name' : IDL.Text,
+    'format' : MembersFormat,
+  });
   const SignedIdAlias = IDL.Record({ 'credential_jws' : IDL.Text });
   const CredentialSpec = IDL.Record({
     'arguments' : IDL.Opt(IDL.Vec(IDL.Tuple(IDL.Text, ArgumentValue))),
@@ -66,7 +134,81 @@ export const idlFactory = ({ IDL }) => {
     'UnknownSubject' : IDL.Text,
     'UnsupportedCredentialSpec' : IDL.Text,
   });
+  const CredentialToGet = IDL.Record({
+    'prepared_context' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    'credential_spec' : CredentialSpec,
+  });
+  const GetCredentialsRequest = IDL.Record({
+    'signed_id_alias' : SignedIdAlias,
+    'credentials' : IDL.Vec(CredentialToGet),
+  });
+  const IssuedCredentialsData = IDL.Record({
+    'results' : IDL.Vec(
+      IDL.Variant({ 'Ok' : IssuedCredentialData, 'Err' : IssueCredentialError })
+    ),
+  });
+  const GetEvidenceChunkRequest = IDL.Record({
+    'chunk_index' : IDL.Nat32,
+    'evidence_id' : IDL.Nat64,
+  });
   const GetGroupRequest = IDL.Record({ 'group_name' : IDL.Text });
+  const GetIssuerVerificationRequest = IDL.Record({
+    'issuer' : IDL.Principal,
+    'credential_type' : IDL.Text,
+  });
+  const GetNotificationsRequest = IDL.Record({ 'since' : IDL.Opt(IDL.Nat64) });
+  const NotificationKind = IDL.Variant({
+    'MembershipRequested' : IDL.Null,
+    'MembershipUpdated' : IDL.Null,
+  });
+  const NotificationData = IDL.Record({
+    'id' : IDL.Nat64,
+    'member' : IDL.Principal,
+    'membership_status' : MembershipStatus,
+    'timestamp_ns' : TimestampNs,
+    'owner' : IDL.Principal,
+    'kind' : NotificationKind,
+    'group_name' : IDL.Text,
+  });
+  const NotificationsData = IDL.Record({
+    'webhook_url' : IDL.Opt(IDL.Text),
+    'notifications' : IDL.Vec(NotificationData),
+  });
+  const MembershipCounts = IDL.Record({
+    'expired' : IDL.Nat32,
+    'pending_review' : IDL.Nat32,
+    'rejected' : IDL.Nat32,
+    'accepted' : IDL.Nat32,
+  });
+  const ArgumentValueCount = IDL.Record({
+    'argument' : IDL.Text,
+    'value' : IDL.Text,
+    'count' : IDL.Nat32,
+  });
+  const DailyCount = IDL.Record({
+    'count' : IDL.Nat32,
+    'day_start_timestamp_ns' : TimestampNs,
+  });
+  const PendingAgeDistribution = IDL.Record({
+    'over_30_days' : IDL.Nat32,
+    'under_1_day' : IDL.Nat32,
+    'under_7_days' : IDL.Nat32,
+    'under_30_days' : IDL.Nat32,
+  });
+  const GroupDashboardData = IDL.Record({
+    'member_counts' : MembershipCounts,
+    'credentials_issued' : IDL.Nat64,
+    'argument_values' : IDL.Vec(ArgumentValueCount),
+    'last_activity_timestamp_ns' : IDL.Opt(TimestampNs),
+    'credentials_issued_by_day' : IDL.Vec(DailyCount),
+    'pending_age' : PendingAgeDistribution,
+    'group_name' : IDL.Text,
+  });
+  const OwnerDashboardData = IDL.Record({
+    'member_counts' : MembershipCounts,
+    'credentials_issued' : IDL.Nat64,
+    'groups' : IDL.Vec(GroupDashboardData),
+  });
   const UserData = IDL.Record({
     'user_nickname' : IDL.Opt(IDL.Text),
     'issuer_nickname' : IDL.Opt(IDL.Text),
@@ -89,23 +231,51 @@ export const idlFactory = ({ IDL }) => {
     'headers' : IDL.Vec(HeaderField),
     'status_code' : IDL.Nat16,
   });
+  const ImportMembersRequest = IDL.Record({
+    'content' : IDL.Text,
+    'group_name' : IDL.Text,
+    'format' : MembersFormat,
+  });
+  const ImportRowError = IDL.Record({ 'row' : IDL.Nat32, 'error' : IDL.Text });
+  const ImportMembersResult = IDL.Record({
+    'errors' : IDL.Vec(ImportRowError),
+    'imported_count' : IDL.Nat32,
+  });
   const JoinGroupRequest = IDL.Record({
     'owner' : IDL.Principal,
     'vc_arguments' : IDL.Opt(VcArguments),
+    'message' : IDL.Opt(IDL.Text),
     'group_name' : IDL.Text,
   });
   const ListGroupsRequest = IDL.Record({
     'group_name_substring' : IDL.Opt(IDL.Text),
   });
   const PublicGroupData = IDL.Record({
+    'reviewer_note' : IDL.Opt(IDL.Text),
     'membership_status' : IDL.Opt(MembershipStatus),
     'owner' : IDL.Principal,
+    'metadata' : GroupMetadata,
+    'issuer_verification' : IDL.Opt(IssuerVerificationData),
     'vc_arguments' : IDL.Opt(VcArguments),
     'stats' : GroupStats,
     'issuer_nickname' : IDL.Text,
     'group_name' : IDL.Text,
   });
   const PublicGroupsData = IDL.Record({ 'groups' : IDL.Vec(PublicGroupData) });
+  const MembershipData = IDL.Record({
+    'reviewer_note' : IDL.Opt(IDL.Text),
+    'membership_status' : MembershipStatus,
+    'joined_timestamp_ns' : TimestampNs,
+    'owner' : IDL.Principal,
+    'vc_arguments' : IDL.Opt(VcArguments),
+    'expires_timestamp_ns' : IDL.Opt(TimestampNs),
+    'issuer_nickname' : IDL.Text,
+    'group_name' : IDL.Text,
+    'credential_spec' : IDL.Opt(CredentialSpec),
+  });
+  const MembershipsData = IDL.Record({
+    'memberships' : IDL.Vec(MembershipData),
+  });
   const PrepareCredentialRequest = IDL.Record({
     'signed_id_alias' : SignedIdAlias,
     'credential_spec' : CredentialSpec,
@@ -113,15 +283,71 @@ export const idlFactory = ({ IDL }) => {
   const PreparedCredentialData = IDL.Record({
     'prepared_context' : IDL.Opt(IDL.Vec(IDL.Nat8)),
   });
+  const PrepareCredentialsRequest = IDL.Record({
+    'credential_specs' : IDL.Vec(CredentialSpec),
+    'signed_id_alias' : SignedIdAlias,
+  });
+  const PreparedCredentialsData = IDL.Record({
+    'results' : IDL.Vec(
+      IDL.Variant({
+        'Ok' : PreparedCredentialData,
+        'Err' : IssueCredentialError,
+      })
+    ),
+  });
+  const SetIssuerVerificationRequest = IDL.Record({
+    'verified' : IDL.Bool,
+    'trusted' : IDL.Bool,
+    'issuer' : IDL.Principal,
+    'credential_type' : IDL.Text,
+  });
+  const SetMembershipValidityRequest = IDL.Record({
+    'validity' : IDL.Opt(MembershipValidity),
+    'group_name' : IDL.Text,
+  });
+  const SetNotificationWebhookRequest = IDL.Record({
+    'url' : IDL.Opt(IDL.Text),
+  });
   const SetUserRequest = IDL.Record({ 'user_data' : UserData });
+  const WebhookResponse = IDL.Record({
+    'status' : IDL.Nat,
+    'body' : IDL.Vec(IDL.Nat8),
+    'headers' : IDL.Vec(IDL.Record({ 'value' : IDL.Text, 'name' : IDL.Text })),
+  });
+  const WebhookTransformArgs = IDL.Record({
+    'context' : IDL.Vec(IDL.Nat8),
+    'response' : WebhookResponse,
+  });
+  const GroupLogo = IDL.Record({
+    'content' : IDL.Vec(IDL.Nat8),
+    'content_type' : IDL.Text,
+  });
+  const GroupLogoUpdate = IDL.Variant({
+    'Set' : GroupLogo,
+    'Remove' : IDL.Null,
+  });
+  const UpdateGroupRequest = IDL.Record({
+    'contact_url' : IDL.Opt(IDL.Text),
+    'logo' : IDL.Opt(GroupLogoUpdate),
+    'tags' : IDL.Vec(IDL.Text),
+    'description' : IDL.Opt(IDL.Text),
+    'visibility' : GroupVisibility,
+    'group_name' : IDL.Text,
+  });
   const MembershipUpdate = IDL.Record({
     'member' : IDL.Principal,
+    'note' : IDL.Opt(IDL.Text),
     'new_status' : MembershipStatus,
   });
   const UpdateMembershipRequest = IDL.Record({
     'updates' : IDL.Vec(MembershipUpdate),
     'group_name' : IDL.Text,
   });
+  const UploadEvidenceChunkRequest = IDL.Record({
+    'chunk_index' : IDL.Nat32,
+    'content' : IDL.Vec(IDL.Nat8),
+    'evidence_id' : IDL.Nat64,
+  });
   const Icrc21ConsentPreferences = IDL.Record({ 'language' : IDL.Text });
   const Icrc21VcConsentMessageRequest = IDL.Record({
     'preferences' : Icrc21ConsentPreferences,
@@ -141,6 +367,16 @@ export const idlFactory = ({ IDL }) => {
     'ConsentMessageUnavailable' : Icrc21ErrorInfo,
   });
   return IDL.Service({
+    'ack_notifications' : IDL.Func(
+        [AckNotificationsRequest],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : GroupsError })],
+        [],
+      ),
+    'add_evidence' : IDL.Func(
+        [AddEvidenceRequest],
+        [IDL.Variant({ 'Ok' : EvidenceData, 'Err' : GroupsError })],
+        [],
+      ),
     'add_group' : IDL.Func(
         [AddGroupRequest],
         [IDL.Variant({ 'Ok' : FullGroupData, 'Err' : GroupsError })],
@@ -157,6 +393,11 @@ export const idlFactory = ({ IDL }) => {
         ],
         [],
       ),
+    'export_members' : IDL.Func(
+        [ExportMembersRequest],
+        [IDL.Variant({ 'Ok' : IDL.Text, 'Err' : GroupsError })],
+        ['query'],
+      ),
     'get_credential' : IDL.Func(
         [GetCredentialRequest],
         [
@@ -167,11 +408,42 @@ export const idlFactory = ({ IDL }) => {
         ],
         ['query'],
       ),
+    'get_credentials' : IDL.Func(
+        [GetCredentialsRequest],
+        [
+          IDL.Variant({
+            'Ok' : IssuedCredentialsData,
+            'Err' : IssueCredentialError,
+          }),
+        ],
+        ['query'],
+      ),
+    'get_evidence_chunk' : IDL.Func(
+        [GetEvidenceChunkRequest],
+        [IDL.Variant({ 'Ok' : IDL.Vec(IDL.Nat8), 'Err' : GroupsError })],
+        ['query'],
+      ),
     'get_group' : IDL.Func(
         [GetGroupRequest],
         [IDL.Variant({ 'Ok' : FullGroupData, 'Err' : GroupsError })],
         ['query'],
       ),
+    'get_issuer_verification' : IDL.Func(
+        [GetIssuerVerificationRequest],
+        [IDL.Opt(IssuerVerificationData)],
+        ['query'],
+      ),
+    'get_notifications' : IDL.Func(
+        [GetNotificationsRequest],
+        [IDL.Variant({ 'Ok' : NotificationsData, 'Err' : GroupsError })],
+        ['query'],
+      ),
+    'get_owner_dashboard' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : OwnerDashboardData, 'Err' : GroupsError })],
+        ['query'],
+      ),
+    'get_rate_limits' : IDL.Func([], [RateLimits], ['query']),
     'get_user' : IDL.Func(
         [],
         [IDL.Variant({ 'Ok' : UserData, 'Err' : GroupsError })],
@@ -183,6 +455,11 @@ export const idlFactory = ({ IDL }) => {
         ['query'],
       ),
     'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
+    'import_members' : IDL.Func(
+        [ImportMembersRequest],
+        [IDL.Variant({ 'Ok' : ImportMembersResult, 'Err' : GroupsError })],
+        [],
+      ),
     'join_group' : IDL.Func(
         [JoinGroupRequest],
         [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : GroupsError })],
@@ -193,6 +470,11 @@ export const idlFactory = ({ IDL }) => {
         [IDL.Variant({ 'Ok' : PublicGroupsData, 'Err' : GroupsError })],
         ['query'],
       ),
+    'list_my_memberships' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : MembershipsData, 'Err' : GroupsError })],
+        ['query'],
+      ),
     'prepare_credential' : IDL.Func(
         [PrepareCredentialRequest],
         [
@@ -203,16 +485,61 @@ export const idlFactory = ({ IDL }) => {
         ],
         [],
       ),
+    'prepare_credentials' : IDL.Func(
+        [PrepareCredentialsRequest],
+        [
+          IDL.Variant({
+            'Ok' : PreparedCredentialsData,
+            'Err' : IssueCredentialError,
+          }),
+        ],
+        [],
+      ),
+    'set_issuer_verification' : IDL.Func(
+        [SetIssuerVerificationRequest],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : GroupsError })],
+        [],
+      ),
+    'set_membership_validity' : IDL.Func(
+        [SetMembershipValidityRequest],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : GroupsError })],
+        [],
+      ),
+    'set_notification_webhook' : IDL.Func(
+        [SetNotificationWebhookRequest],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : GroupsError })],
+        [],
+      ),
+    'set_rate_limits' : IDL.Func(
+        [RateLimits],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : GroupsError })],
+        [],
+      ),
     'set_user' : IDL.Func(
         [SetUserRequest],
         [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : GroupsError })],
         [],
       ),
+    'transform_webhook_response' : IDL.Func(
+        [WebhookTransformArgs],
+        [WebhookResponse],
+        ['query'],
+      ),
+    'update_group' : IDL.Func(
+        [UpdateGroupRequest],
+        [IDL.Variant({ 'Ok' : GroupMetadata, 'Err' : GroupsError })],
+        [],
+      ),
     'update_membership' : IDL.Func(
         [UpdateMembershipRequest],
         [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : GroupsError })],
         [],
       ),
+    'upload_evidence_chunk' : IDL.Func(
+        [UploadEvidenceChunkRequest],
+        [IDL.Variant({ 'Ok' : EvidenceData, 'Err' : GroupsError })],
+        [],
+      ),
     'vc_consent_message' : IDL.Func(
         [Icrc21VcConsentMessageRequest],
         [IDL.Variant({ 'Ok' : Icrc21ConsentInfo, 'Err' : Icrc21Error })],
@@ -221,11 +548,26 @@ export const idlFactory = ({ IDL }) => {
   });
 };
 export const init = ({ IDL }) => {
+  const HousekeepingConfig = IDL.Record({
+    'interval_s' : IDL.Nat64,
+    'pending_review_max_age_ns' : IDL.Opt(IDL.Nat64),
+  });
+  const TokenBucketConfig = IDL.Record({
+    'refill_period_ns' : IDL.Nat64,
+    'capacity' : IDL.Nat32,
+  });
+  const RateLimits = IDL.Record({
+    'max_groups_per_owner' : IDL.Opt(IDL.Nat32),
+    'max_pending_requests_per_group' : IDL.Opt(IDL.Nat32),
+    'global' : IDL.Opt(TokenBucketConfig),
+    'per_caller' : IDL.Opt(TokenBucketConfig),
+  });
   const IssuerInit = IDL.Record({
+    'housekeeping' : IDL.Opt(HousekeepingConfig),
     'derivation_origin' : IDL.Text,
     'idp_canister_ids' : IDL.Vec(IDL.Principal),
     'ic_root_key_der' : IDL.Vec(IDL.Nat8),
-    'frontend_hostname' : IDL.Text,
+    'rate_limits' : IDL.Opt(RateLimits),
   });
   return [IDL.Opt(IssuerInit)];
 };
@@ -2,31 +2,59 @@ import type { Principal } from '@dfinity/principal';
 import type { ActorMethod } from '@dfinity/agent';
 import type { IDL } from '@dfinity/candid';
 
+export interface AccessGrant {
+  'expires_timestamp_ns' : TimestampNs,
+  'grant_id' : string,
+}
 export interface AddExclusiveContentRequest {
   'url' : string,
   'credential_issuer' : Principal,
   'content_name' : string,
+  'credential_policy' : [] | [CredentialPolicy],
   'credential_spec' : CredentialSpec,
 }
 export type ArgumentValue = { 'Int' : number } |
   { 'String' : string };
+export interface CheckAccessRequest {
+  'content_id' : string,
+  'grant_id' : string,
+}
+export interface ContentAccessData {
+  'access_grant' : [] | [AccessGrant],
+  'requirements' : Array<RequirementResult>,
+  'policy_satisfied' : boolean,
+}
 export interface ContentData {
   'url' : string,
   'credential_issuer' : Principal,
   'owner' : Principal,
+  'content_id' : string,
   'content_name' : string,
+  'credential_policy' : [] | [CredentialPolicy],
   'created_timestamp_ns' : TimestampNs,
   'credential_spec' : CredentialSpec,
 }
 export type ContentError = { 'Internal' : string } |
   { 'NotFound' : string } |
   { 'NotAuthorized' : string } |
-  { 'AlreadyExists' : string };
+  { 'AlreadyExists' : string } |
+  { 'RateLimited' : string } |
+  { 'InvalidArgument' : string };
+export type CredentialPolicy = { 'Requirement' : CredentialRequirement } |
+  { 'AllOf' : Array<CredentialPolicy> } |
+  { 'AnyOf' : Array<CredentialPolicy> };
+export interface CredentialRequirement {
+  'credential_issuer' : Principal,
+  'credential_spec' : CredentialSpec,
+}
 export interface CredentialSpec {
   'arguments' : [] | [Array<[string, ArgumentValue]>],
   'credential_type' : string,
 }
-export interface ExclusiveContentList { 'content_items' : Array<ContentData> }
+export interface ExclusiveContentList {
+  'content_items' : Array<ContentData>,
+  'next_cursor' : [] | [string],
+}
 export type HeaderField = [string, string];
 export interface HttpRequest {
   'url' : string,
@@ -43,30 +71,127 @@ export interface HttpResponse {
 export interface ImageData { 'url' : string }
 export interface ImagesList { 'images' : Array<ImageData> }
 export interface IssuerData { 'canister_id' : Principal, 'vc_url' : string }
-export interface ListExclusiveContentRequest { 'owned_by' : [] | [Principal] }
+export interface IssuerRegistry {
+  'canister_id' : Principal,
+  'cache_ttl_ns' : [] | [bigint],
+}
+export interface ListExclusiveContentRequest {
+  'credential_issuer' : [] | [Principal],
+  'cursor' : [] | [string],
+  'limit' : [] | [number],
+  'owned_by' : [] | [Principal],
+  'credential_type' : [] | [string],
+}
 export type ListImagesRequest = {};
+export interface PresentationError {
+  'presentation_index' : number,
+  'error' : VpValidationError,
+}
+export interface PresentedVp {
+  'issuer_origin' : string,
+  'issuer_canister_id' : [] | [Principal],
+  'vp_jwt' : string,
+}
+export interface RateLimits {
+  'global' : [] | [TokenBucketConfig],
+  'per_caller' : [] | [TokenBucketConfig],
+}
+export interface RemoveExclusiveContentRequest { 'content_id' : string }
+export interface RemoveTrustedIssuerRequest { 'vc_url' : string }
+export interface RequirementResult {
+  'met' : boolean,
+  'errors' : Array<PresentationError>,
+  'requirement' : CredentialRequirement,
+}
 export interface RpInit {
+  'derivation_origin' : string,
   'ii_canister_id' : Principal,
+  'require_verified_issuers' : [] | [boolean],
   'ic_root_key_der' : Uint8Array | number[],
+  'issuer_registry' : [] | [IssuerRegistry],
   'issuers' : Array<IssuerData>,
+  'rate_limits' : [] | [RateLimits],
+  'require_nonce' : [] | [boolean],
   'ii_vc_url' : string,
 }
 export type TimestampNs = bigint;
+export interface TokenBucketConfig {
+  'refill_period_ns' : bigint,
+  'capacity' : number,
+}
+export interface TrustedIssuersList {
+  'issuer_registry' : [] | [IssuerRegistry],
+  'issuers' : Array<IssuerData>,
+}
+export interface UpdateExclusiveContentRequest {
+  'url' : string,
+  'credential_issuer' : Principal,
+  'content_id' : string,
+  'content_name' : string,
+  'credential_policy' : [] | [CredentialPolicy],
+  'credential_spec' : CredentialSpec,
+}
 export type UploadImagesRequest = {};
+export interface ValidateContentAccessRequest {
+  'challenge' : string,
+  'content_id' : string,
+  'effective_vc_subject' : Principal,
+  'presentations' : Array<PresentedVp>,
+}
 export interface ValidateVpRequest {
+  'challenge' : string,
   'effective_vc_subject' : Principal,
   'issuer_origin' : string,
   'issuer_canister_id' : [] | [Principal],
   'vp_jwt' : string,
   'credential_spec' : CredentialSpec,
 }
+export interface ValidatedVpData {
+  'subject' : Principal,
+  'credential_issuer' : Principal,
+  'access_grant' : AccessGrant,
+  'issuer_origin' : string,
+  'expires_timestamp_ns' : TimestampNs,
+  'id_alias' : Principal,
+  'credential_spec' : CredentialSpec,
+}
+export interface VpChallenge {
+  'expires_timestamp_ns' : TimestampNs,
+  'nonce' : string,
+}
+export type VpValidationError = { 'UnverifiedIssuer' : string } |
+  { 'SubjectMismatch' : string } |
+  { 'IssuerMismatch' : string } |
+  { 'SpecMismatch' : string } |
+  { 'InvalidSignature' : string } |
+  { 'InvalidChallenge' : string } |
+  { 'InvalidPresentation' : string } |
+  { 'UnsupportedIssuer' : string } |
+  { 'Expired' : string } |
+  { 'AlreadyUsed' : string };
 export interface _SERVICE {
   'add_exclusive_content' : ActorMethod<
     [AddExclusiveContentRequest],
     { 'Ok' : ContentData } |
       { 'Err' : ContentError }
   >,
+  'add_trusted_issuer' : ActorMethod<
+    [IssuerData],
+    { 'Ok' : null } |
+      { 'Err' : ContentError }
+  >,
+  'check_access' : ActorMethod<
+    [CheckAccessRequest],
+    { 'Ok' : ContentData } |
+      { 'Err' : ContentError }
+  >,
   'configure' : ActorMethod<[RpInit], undefined>,
+  'get_rate_limits' : ActorMethod<[], RateLimits>,
+  'get_vp_challenge' : ActorMethod<
+    [],
+    { 'Ok' : VpChallenge } |
+      { 'Err' : ContentError }
+  >,
   'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
   'list_exclusive_content' : ActorMethod<
     [ListExclusiveContentRequest],
@@ -78,15 +203,42 @@ export interface _SERVICE {
     { 'Ok' : ImagesList } |
       { 'Err' : ContentError }
   >,
+  'list_trusted_issuers' : ActorMethod<[], TrustedIssuersList>,
+  'lookup_trusted_issuer' : ActorMethod<[string], [] | [IssuerData]>,
+  'remove_exclusive_content' : ActorMethod<
+    [RemoveExclusiveContentRequest],
+    { 'Ok' : null } |
+      { 'Err' : ContentError }
+  >,
+  'remove_trusted_issuer' : ActorMethod<
+    [RemoveTrustedIssuerRequest],
+    { 'Ok' : null } |
+      { 'Err' : ContentError }
+  >,
+  'set_rate_limits' : ActorMethod<
+    [RateLimits],
+    { 'Ok' : null } |
+      { 'Err' : ContentError }
+  >,
+  'update_exclusive_content' : ActorMethod<
+    [UpdateExclusiveContentRequest],
+    { 'Ok' : ContentData } |
+      { 'Err' : ContentError }
+  >,
   'upload_images' : ActorMethod<
     [UploadImagesRequest],
     { 'Ok' : ImagesList } |
       { 'Err' : ContentError }
   >,
+  'validate_content_access' : ActorMethod<
+    [ValidateContentAccessRequest],
+    { 'Ok' : ContentAccessData } |
+      { 'Err' : ContentError }
+  >,
   'validate_ii_vp' : ActorMethod<
     [ValidateVpRequest],
-    { 'Ok' : null } |
-      { 'Err' : ContentError }
+    { 'Ok' : ValidatedVpData } |
+      { 'Err' : VpValidationError }
   >,
 }
 export declare const idlFactory: IDL.InterfaceFactory;
@@ -1,12 +1,30 @@
 export const idlFactory = ({ IDL }) => {
+  const CredentialPolicy = IDL.Rec();
+  const IssuerRegistry = IDL.Record({
+    'canister_id' : IDL.Principal,
+    'cache_ttl_ns' : IDL.Opt(IDL.Nat64),
+  });
   const IssuerData = IDL.Record({
     'canister_id' : IDL.Principal,
     'vc_url' : IDL.Text,
   });
+  const TokenBucketConfig = IDL.Record({
+    'refill_period_ns' : IDL.Nat64,
+    'capacity' : IDL.Nat32,
+  });
+  const RateLimits = IDL.Record({
+    'global' : IDL.Opt(TokenBucketConfig),
+    'per_caller' : IDL.Opt(TokenBucketConfig),
+  });
   const RpInit = IDL.Record({
+    'derivation_origin' : IDL.Text,
     'ii_canister_id' : IDL.Principal,
+    'require_verified_issuers' : IDL.Opt(IDL.Bool),
     'ic_root_key_der' : IDL.Vec(IDL.Nat8),
+    'issuer_registry' : IDL.Opt(IssuerRegistry),
     'issuers' : IDL.Vec(IssuerData),
+    'rate_limits' : IDL.Opt(RateLimits),
+    'require_nonce' : IDL.Opt(IDL.Bool),
     'ii_vc_url' : IDL.Text,
   });
   const ArgumentValue = IDL.Variant({ 'Int' : IDL.Int32, 'String' : IDL.Text });
@@ -14,10 +32,22 @@ export const idlFactory = ({ IDL }) => {
     'arguments' : IDL.Opt(IDL.Vec(IDL.Tuple(IDL.Text, ArgumentValue))),
     'credential_type' : IDL.Text,
   });
+  const CredentialRequirement = IDL.Record({
+    'credential_issuer' : IDL.Principal,
+    'credential_spec' : CredentialSpec,
+  });
+  CredentialPolicy.fill(
+    IDL.Variant({
+      'Requirement' : CredentialRequirement,
+      'AllOf' : IDL.Vec(CredentialPolicy),
+      'AnyOf' : IDL.Vec(CredentialPolicy),
+    })
+  );
   const AddExclusiveContentRequest = IDL.Record({
     'url' : IDL.Text,
     'credential_issuer' : IDL.Principal,
     'content_name' : IDL.Text,
+    'credential_policy' : IDL.Opt(CredentialPolicy),
     'credential_spec' : CredentialSpec,
   });
   const TimestampNs = IDL.Nat64;
@@ -25,7 +55,9 @@ export const idlFactory = ({ IDL }) => {
     'url' : IDL.Text,
     'credential_issuer' : IDL.Principal,
     'owner' : IDL.Principal,
+    'content_id' : IDL.Text,
     'content_name' : IDL.Text,
+    'credential_policy' : IDL.Opt(CredentialPolicy),
     'created_timestamp_ns' : TimestampNs,
     'credential_spec' : CredentialSpec,
   });
@@ -34,6 +66,16 @@ export const idlFactory = ({ IDL }) => {
     'NotFound' : IDL.Text,
     'NotAuthorized' : IDL.Text,
     'AlreadyExists' : IDL.Text,
+    'RateLimited' : IDL.Text,
+    'InvalidArgument' : IDL.Text,
+  });
+  const CheckAccessRequest = IDL.Record({
+    'content_id' : IDL.Text,
+    'grant_id' : IDL.Text,
+  });
+  const VpChallenge = IDL.Record({
+    'expires_timestamp_ns' : TimestampNs,
+    'nonce' : IDL.Text,
   });
   const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
   const HttpRequest = IDL.Record({
@@ -49,29 +91,115 @@ export const idlFactory = ({ IDL }) => {
     'status_code' : IDL.Nat16,
   });
   const ListExclusiveContentRequest = IDL.Record({
+    'credential_issuer' : IDL.Opt(IDL.Principal),
+    'cursor' : IDL.Opt(IDL.Text),
+    'limit' : IDL.Opt(IDL.Nat32),
     'owned_by' : IDL.Opt(IDL.Principal),
+    'credential_type' : IDL.Opt(IDL.Text),
   });
   const ExclusiveContentList = IDL.Record({
     'content_items' : IDL.Vec(ContentData),
+    'next_cursor' : IDL.Opt(IDL.Text),
   });
   const ListImagesRequest = IDL.Record({});
   const ImageData = IDL.Record({ 'url' : IDL.Text });
   const ImagesList = IDL.Record({ 'images' : IDL.Vec(ImageData) });
+  const TrustedIssuersList = IDL.Record({
+    'issuer_registry' : IDL.Opt(IssuerRegistry),
+    'issuers' : IDL.Vec(IssuerData),
+  });
+  const RemoveExclusiveContentRequest = IDL.Record({ 'content_id' : IDL.Text });
+  const RemoveTrustedIssuerRequest = IDL.Record({ 'vc_url' : IDL.Text });
+  const UpdateExclusiveContentRequest = IDL.Record({
+    'url' : IDL.Text,
+    'credential_issuer' : IDL.Principal,
+    'content_id' : IDL.Text,
+    'content_name' : IDL.Text,
+    'credential_policy' : IDL.Opt(CredentialPolicy),
+    'credential_spec' : CredentialSpec,
+  });
   const UploadImagesRequest = IDL.Record({});
+  const PresentedVp = IDL.Record({
+    'issuer_origin' : IDL.Text,
+    'issuer_canister_id' : IDL.Opt(IDL.Principal),
+    'vp_jwt' : IDL.Text,
+  });
+  const ValidateContentAccessRequest = IDL.Record({
+    'challenge' : IDL.Text,
+    'content_id' : IDL.Text,
+    'effective_vc_subject' : IDL.Principal,
+    'presentations' : IDL.Vec(PresentedVp),
+  });
+  const AccessGrant = IDL.Record({
+    'expires_timestamp_ns' : TimestampNs,
+    'grant_id' : IDL.Text,
+  });
+  const VpValidationError = IDL.Variant({
+    'UnverifiedIssuer' : IDL.Text,
+    'SubjectMismatch' : IDL.Text,
+    'IssuerMismatch' : IDL.Text,
+    'SpecMismatch' : IDL.Text,
+    'InvalidSignature' : IDL.Text,
+    'InvalidChallenge' : IDL.Text,
+    'InvalidPresentation' : IDL.Text,
+    'UnsupportedIssuer' : IDL.Text,
+    'Expired' : IDL.Text,
+    'AlreadyUsed' : IDL.Text,
+  });
+  const PresentationError = IDL.Record({
+    'presentation_index' : IDL.Nat32,
+    'error' : VpValidationError,
+  });
+  const RequirementResult = IDL.Record({
+    'met' : IDL.Bool,
+    'errors' : IDL.Vec(PresentationError),
+    'requirement' : CredentialRequirement,
+  });
+  const ContentAccessData = IDL.Record({
+    'access_grant' : IDL.Opt(AccessGrant),
+    'requirements' : IDL.Vec(RequirementResult),
+    'policy_satisfied' : IDL.Bool,
+  });
   const ValidateVpRequest = IDL.Record({
+    'challenge' : IDL.Text,
     'effective_vc_subject' : IDL.Principal,
     'issuer_origin' : IDL.Text,
     'issuer_canister_id' : IDL.Opt(IDL.Principal),
     'vp_jwt' : IDL.Text,
     'credential_spec' : CredentialSpec,
   });
+  const ValidatedVpData = IDL.Record({
+    'subject' : IDL.Principal,
+    'credential_issuer' : IDL.Principal,
+    'access_grant' : AccessGrant,
+    'issuer_origin' : IDL.Text,
+    'expires_timestamp_ns' : TimestampNs,
+    'id_alias' : IDL.Principal,
+    'credential_spec' : CredentialSpec,
+  });
   return IDL.Service({
     'add_exclusive_content' : IDL.Func(
         [AddExclusiveContentRequest],
         [IDL.Variant({ 'Ok' : ContentData, 'Err' : ContentError })],
         [],
       ),
+    'add_trusted_issuer' : IDL.Func(
+        [IssuerData],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : ContentError })],
+        [],
+      ),
+    'check_access' : IDL.Func(
+        [CheckAccessRequest],
+        [IDL.Variant({ 'Ok' : ContentData, 'Err' : ContentError })],
+        ['query'],
+      ),
     'configure' : IDL.Func([RpInit], [], []),
+    'get_rate_limits' : IDL.Func([], [RateLimits], ['query']),
+    'get_vp_challenge' : IDL.Func(
+        [],
+        [IDL.Variant({ 'Ok' : VpChallenge, 'Err' : ContentError })],
+        [],
+      ),
     'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
     'list_exclusive_content' : IDL.Func(
         [ListExclusiveContentRequest],
@@ -83,27 +211,75 @@ export const idlFactory = ({ IDL }) => {
         [IDL.Variant({ 'Ok' : ImagesList, 'Err' : ContentError })],
         ['query'],
       ),
+    'list_trusted_issuers' : IDL.Func([], [TrustedIssuersList], ['query']),
+    'lookup_trusted_issuer' : IDL.Func(
+        [IDL.Text],
+        [IDL.Opt(IssuerData)],
+        ['query'],
+      ),
+    'remove_exclusive_content' : IDL.Func(
+        [RemoveExclusiveContentRequest],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : ContentError })],
+        [],
+      ),
+    'remove_trusted_issuer' : IDL.Func(
+        [RemoveTrustedIssuerRequest],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : ContentError })],
+        [],
+      ),
+    'set_rate_limits' : IDL.Func(
+        [RateLimits],
+        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : ContentError })],
+        [],
+      ),
+    'update_exclusive_content' : IDL.Func(
+        [UpdateExclusiveContentRequest],
+        [IDL.Variant({ 'Ok' : ContentData, 'Err' : ContentError })],
+        [],
+      ),
     'upload_images' : IDL.Func(
         [UploadImagesRequest],
         [IDL.Variant({ 'Ok' : ImagesList, 'Err' : ContentError })],
         [],
       ),
+    'validate_content_access' : IDL.Func(
+        [ValidateContentAccessRequest],
+        [IDL.Variant({ 'Ok' : ContentAccessData, 'Err' : ContentError })],
+        [],
+      ),
     'validate_ii_vp' : IDL.Func(
         [ValidateVpRequest],
-        [IDL.Variant({ 'Ok' : IDL.Null, 'Err' : ContentError })],
+        [IDL.Variant({ 'Ok' : ValidatedVpData, 'Err' : VpValidationError })],
         [],
       ),
   });
 };
 export const init = ({ IDL }) => {
+  const IssuerRegistry = IDL.Record({
+    'canister_id' : IDL.Principal,
+    'cache_ttl_ns' : IDL.Opt(IDL.Nat64),
+  });
   const IssuerData = IDL.Record({
     'canister_id' : IDL.Principal,
     'vc_url' : IDL.Text,
   });
+  const TokenBucketConfig = IDL.Record({
+    'refill_period_ns' : IDL.Nat64,
+    'capacity' : IDL.Nat32,
+  });
+  const RateLimits = IDL.Record({
+    'global' : IDL.Opt(TokenBucketConfig),
+    'per_caller' : IDL.Opt(TokenBucketConfig),
+  });
   const RpInit = IDL.Record({
+    'derivation_origin' : IDL.Text,
     'ii_canister_id' : IDL.Principal,
+    'require_verified_issuers' : IDL.Opt(IDL.Bool),
     'ic_root_key_der' : IDL.Vec(IDL.Nat8),
+    'issuer_registry' : IDL.Opt(IssuerRegistry),
     'issuers' : IDL.Vec(IssuerData),
+    'rate_limits' : IDL.Opt(RateLimits),
+    'require_nonce' : IDL.Opt(IDL.Bool),
     'ii_vc_url' : IDL.Text,
   });
   return [IDL.Opt(RpInit)];
@@ -22,6 +22,7 @@ export const addExclusiveContent = async ({
     content_name: contentName,
     credential_spec: credentialSpec,
     credential_issuer: owner,
+    credential_policy: [],
   });
   if ('Ok' in response) {
     return response.Ok;
@@ -0,0 +1,16 @@
+import type { Identity } from '@dfinity/agent';
+import type { VpChallenge } from '../../declarations/rp/rp.did';
+import { getRpCanister } from '$lib/utils/actor.utils';
+
+export const getVpChallenge = async ({
+  identity,
+}: {
+  identity: Identity;
+}): Promise<VpChallenge> => {
+  const actor = await getRpCanister(identity);
+  const response = await actor.get_vp_challenge();
+  if ('Ok' in response) {
+    return response.Ok;
+  }
+  throw response.Err;
+};
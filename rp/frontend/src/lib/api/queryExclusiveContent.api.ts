@@ -1,5 +1,5 @@
 import type { Identity } from '@dfinity/agent';
-import type { ExclusiveContentList } from '../../declarations/rp/rp.did';
+import type { ContentData, ExclusiveContentList } from '../../declarations/rp/rp.did';
 import { getRpCanister } from '$lib/utils/actor.utils';
 
 export const queryExclusiveContent = async ({
@@ -8,9 +8,22 @@ export const queryExclusiveContent = async ({
   identity: Identity;
 }): Promise<ExclusiveContentList> => {
   const actor = await getRpCanister(identity);
-  const response = await actor.list_exclusive_content({ owned_by: [] });
-  if ('Ok' in response) {
-    return response.Ok;
-  }
-  throw response.Err;
+  const contentItems: ContentData[] = [];
+  let cursor: [] | [string] = [];
+  // The content is listed in pages, which are requested until there is no next page.
+  do {
+    const response = await actor.list_exclusive_content({
+      owned_by: [],
+      credential_issuer: [],
+      credential_type: [],
+      cursor,
+      limit: [],
+    });
+    if ('Err' in response) {
+      throw response.Err;
+    }
+    contentItems.push(...response.Ok.content_items);
+    cursor = response.Ok.next_cursor;
+  } while (cursor.length > 0);
+  return { content_items: contentItems, next_cursor: [] };
 };
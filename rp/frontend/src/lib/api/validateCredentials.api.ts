@@ -14,6 +14,8 @@ type ValidateCredentialsRequest = {
   vpJwt: string;
   // The same parameters as when the credentials was requested.
   credentialSpec: CredentialSpec;
+  // The nonce of the challenge obtained before requesting the credentials.
+  challenge: string;
 };
 
 export const validateCredentials = async ({
@@ -32,6 +34,7 @@ export const validateCredentials = async ({
       : [],
     vp_jwt: requestParams.vpJwt,
     credential_spec: requestParams.credentialSpec,
+    challenge: requestParams.challenge,
   });
   if ('Err' in response) {
     console.log('Error in response: ', response.Err);
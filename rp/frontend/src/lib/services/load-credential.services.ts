@@ -1,3 +1,4 @@
+import { getVpChallenge } from '$lib/api/getVpChallenge.api';
 import { validateCredentials } from '$lib/api/validateCredentials.api';
 import { credentialsStore } from '$lib/stores/credentials.store';
 import { isNullish } from '$lib/utils/is-nullish.utils';
@@ -8,7 +9,7 @@ import {
   requestVerifiablePresentation,
   type VerifiablePresentationResponse,
 } from '@dfinity/verifiable-credentials/request-verifiable-presentation';
-import type { CredentialSpec } from '../../declarations/rp/rp.did';
+import type { CredentialSpec, VpChallenge } from '../../declarations/rp/rp.did';
 import { credentialArgsToObj } from '$lib/utils/credential-args-to-obj.utils';
 
 const ISSUER_ORIGIN = import.meta.env.VITE_ISSUER_ORIGIN;
@@ -29,6 +30,21 @@ export const loadCredential = async ({
     return null;
   }
   console.info('Loading credential for', credentialSpec.credential_type, owner.toText());
+  // The nonce of the challenge is bound into the requested credential by the issuer,
+  // and checked by the RP when validating the presentation.
+  let challenge: VpChallenge;
+  try {
+    challenge = await getVpChallenge({ identity });
+  } catch (err: unknown) {
+    console.warn('Failed to get a challenge', err);
+    credentialsStore.setCredential({
+      groupName,
+      owner,
+      credentialSpec,
+      hasCredential: false,
+    });
+    return null;
+  }
   return new Promise<null>((resolve) => {
     requestVerifiablePresentation({
       onSuccess: async (verifiablePresentation: VerifiablePresentationResponse) => {
@@ -54,6 +70,7 @@ export const loadCredential = async ({
             issuerCanisterId: Principal.fromText(ISSUER_CANISTER_ID),
             vpJwt: verifiablePresentation.Ok,
             credentialSpec,
+            challenge: challenge.nonce,
           },
         });
         credentialsStore.setCredential({
@@ -84,6 +101,7 @@ export const loadCredential = async ({
             // We need to add the owner so that the issuer can identify which is the issuer
             // from which the credential is being requested.
             owner: owner.toText(),
+            nonce: challenge.nonce,
             ...credentialArgsToObj(credentialSpec),
           },
         },
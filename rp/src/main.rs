@@ -2,23 +2,41 @@
 /// See rp.did for more info about the architecture and conventions.
 use candid::{candid_method, CandidType, Deserialize, Principal};
 use ic_canister_sig_creation::extract_raw_root_pk_from_der;
-use ic_cdk::api::{caller, set_certified_data, time};
-use ic_cdk_macros::{init, query, update};
+use ic_cdk::api::call::{accept_message, arg_data_raw_size, method_name};
+use ic_cdk::api::{caller, is_controller, set_certified_data, time, trap};
+use ic_cdk_macros::{init, inspect_message, query, update};
 use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
 use ic_stable_structures::storable::{Bound, Storable};
 use ic_stable_structures::{DefaultMemoryImpl, RestrictedMemory, StableBTreeMap, StableCell};
 use ic_verifiable_credentials::issuer_api::CredentialSpec;
-use ic_verifiable_credentials::{validate_ii_presentation_and_claims, VcFlowSigners};
 use include_dir::{include_dir, Dir};
+use method_policy::{check_call, MethodPolicy, PolicyViolation};
+use rate_limit::RateLimiter;
 use relying_party::rp_api::{
-    AddExclusiveContentRequest, ContentData, ContentError, ExclusiveContentList, HttpRequest,
-    HttpResponse, ImageData, ImagesList, ListExclusiveContentRequest, ListImagesRequest, RpInit,
-    UploadImagesRequest, ValidateVpRequest,
+    AccessGrant, AddExclusiveContentRequest, CheckAccessRequest, ContentAccessData, ContentData,
+    ContentError, CredentialPolicy, CredentialRequirement, ExclusiveContentList,
+    GetIssuerVerificationRequest, HttpRequest, HttpResponse, ImageData, ImagesList, IssuerData,
+    IssuerRegistry, IssuerVerificationData, ListExclusiveContentRequest, ListImagesRequest,
+    PresentationError, RateLimits, RemoveExclusiveContentRequest, RemoveTrustedIssuerRequest,
+    RequirementResult, RpInit, TrustedIssuersList, UpdateExclusiveContentRequest,
+    UploadImagesRequest, ValidateContentAccessRequest, ValidateVpRequest, ValidatedVpData,
+    VpChallenge, VpValidationError, DEFAULT_CONTENT_PAGE_SIZE, MAX_CONTENT_PAGE_SIZE,
+    MAX_POLICY_REQUIREMENTS,
 };
 use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+use stable_schema::{
+    rewrite_batch, Migration, MigrationBatch, MigrationState, Migrator, VersionedRecord,
+};
 use std::borrow::Cow;
-use std::cell::RefCell;
-use std::collections::BTreeMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound as RangeBound;
+use std::time::Duration;
+use vc_verifier::{
+    claimed_issuer, trusted_issuer_canister_id, ClaimedIssuer, VerifiedCredential, VerifierConfig,
+    VpValidationRequest,
+};
 
 use asset_util::{collect_assets, CertifiedAssets};
 use ic_cdk_macros::post_upgrade;
@@ -28,10 +46,97 @@ use ic_cdk_macros::post_upgrade;
 type Memory = RestrictedMemory<DefaultMemoryImpl>;
 type ConfigCell = StableCell<RpConfig, Memory>;
 type ImagesMap = StableBTreeMap<String, ImageRecord, VirtualMemory<Memory>>;
+type LegacyExclusiveContentMap =
+    StableBTreeMap<String, LegacyExclusiveContentRecord, VirtualMemory<Memory>>;
 type ExclusiveContentMap = StableBTreeMap<String, ExclusiveContentRecord, VirtualMemory<Memory>>;
+type OwnerContentMap = StableBTreeMap<OwnerContentKey, String, VirtualMemory<Memory>>;
+type ContentIndexMap = StableBTreeMap<ContentIndexKey, (), VirtualMemory<Memory>>;
+type ChallengesMap = StableBTreeMap<String, ChallengeRecord, VirtualMemory<Memory>>;
+type ConsumedPresentationsMap = StableBTreeMap<String, u64, VirtualMemory<Memory>>;
+type AccessGrantsMap = StableBTreeMap<String, AccessGrantRecord, VirtualMemory<Memory>>;
+type TrustedIssuersMap = StableBTreeMap<String, Principal, VirtualMemory<Memory>>;
+type MigrationStateCell = StableCell<MigrationState, VirtualMemory<Memory>>;
 
 const IMAGES_MEMORY_ID: MemoryId = MemoryId::new(0u8);
-const EXCLUSIVE_CONTENT_MEMORY_ID: MemoryId = MemoryId::new(1u8);
+const LEGACY_EXCLUSIVE_CONTENT_MEMORY_ID: MemoryId = MemoryId::new(1u8);
+const CHALLENGES_MEMORY_ID: MemoryId = MemoryId::new(2u8);
+const CONSUMED_PRESENTATIONS_MEMORY_ID: MemoryId = MemoryId::new(3u8);
+const ACCESS_GRANTS_MEMORY_ID: MemoryId = MemoryId::new(4u8);
+const TRUSTED_ISSUERS_MEMORY_ID: MemoryId = MemoryId::new(5u8);
+const EXCLUSIVE_CONTENT_MEMORY_ID: MemoryId = MemoryId::new(6u8);
+const OWNER_CONTENT_MEMORY_ID: MemoryId = MemoryId::new(7u8);
+const CONTENT_INDEX_MEMORY_ID: MemoryId = MemoryId::new(8u8);
+const MIGRATION_STATE_MEMORY_ID: MemoryId = MemoryId::new(9u8);
+
+/// Policies of all methods of the RP, checked by `inspect_message` for ingress messages
+/// and by the endpoints via `check_method_policy`.
+const METHOD_POLICIES: &[MethodPolicy] = &[
+    MethodPolicy::public("list_images"),
+    MethodPolicy::public("list_exclusive_content"),
+    MethodPolicy::authenticated("add_exclusive_content"),
+    MethodPolicy::authenticated("update_exclusive_content"),
+    MethodPolicy::authenticated("remove_exclusive_content"),
+    MethodPolicy::authenticated("get_vp_challenge"),
+    MethodPolicy::authenticated("validate_ii_vp").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::authenticated("validate_content_access").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::authenticated("check_access"),
+    MethodPolicy::controller("add_trusted_issuer"),
+    MethodPolicy::controller("remove_trusted_issuer"),
+    MethodPolicy::public("list_trusted_issuers"),
+    MethodPolicy::public("lookup_trusted_issuer"),
+    MethodPolicy::controller("set_rate_limits"),
+    MethodPolicy::public("get_rate_limits"),
+    MethodPolicy::controller("configure").with_max_arg_bytes(64 * 1024),
+    MethodPolicy::public("upload_images").with_max_arg_bytes(2 * 1024 * 1024),
+    MethodPolicy::public("http_request").with_max_arg_bytes(64 * 1024),
+];
+
+// Migrations of the stable data, run after upgrades (cf. `stable_schema`).
+// New migrations must be appended.
+static MIGRATIONS: [Migration; 4] = [
+    Migration {
+        name: "versioned_config",
+        run_batch: migrate_config,
+    },
+    Migration {
+        name: "versioned_exclusive_content",
+        run_batch: migrate_exclusive_content,
+    },
+    Migration {
+        name: "content_ids",
+        run_batch: migrate_legacy_content,
+    },
+    Migration {
+        name: "content_index",
+        run_batch: index_content,
+    },
+];
+static MIGRATOR: Migrator = Migrator {
+    migrations: &MIGRATIONS,
+    load_state: load_migration_state,
+    store_state: store_migration_state,
+    batch_size: 100,
+    batches_per_call: 10,
+};
+
+// The secondary indexes of content items, cf. ContentIndexKey.
+const CONTENT_INDEX_ALL: u8 = 0;
+const CONTENT_INDEX_OWNER: u8 = 1;
+const CONTENT_INDEX_CREDENTIAL_TYPE: u8 = 2;
+const CONTENT_INDEX_CREDENTIAL_ISSUER: u8 = 3;
+/// Max. number of index entries scanned by a call of `list_exclusive_content`.
+const MAX_SCANNED_CONTENT_ITEMS: usize = 1_000;
+
+const SECOND_NS: u64 = 1_000_000_000;
+const MINUTE_NS: u64 = 60 * SECOND_NS;
+const CHALLENGE_VALIDITY_NS: u64 = 5 * MINUTE_NS;
+const ACCESS_GRANT_VALIDITY_NS: u64 = 15 * MINUTE_NS;
+const DEFAULT_REGISTRY_CACHE_TTL_NS: u64 = 60 * MINUTE_NS;
+/// Interval between the passes removing expired challenges, consumed presentations and
+/// access grants, cf. `prune_expired_entries`.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Max. number of entries of a map scanned by a timer call of the pruning.
+const MAX_PRUNED_ENTRIES: usize = 100;
 
 // Internal container of per-image data.
 #[derive(CandidType, Clone, Deserialize)]
@@ -39,13 +144,81 @@ struct ImageRecord {
     pub bytes: Vec<u8>,
 }
 
+// Content item as stored before content ids were introduced, keyed by content name.
+#[derive(CandidType, Clone, Deserialize)]
+struct LegacyExclusiveContentRecord {
+    owner: Principal,
+    created_timestamp_ns: u64,
+    url: String,
+    credential_spec: CredentialSpec,
+    credential_issuer: Principal,
+    credential_policy: Option<CredentialPolicy>,
+}
+
 #[derive(CandidType, Clone, Deserialize)]
 struct ExclusiveContentRecord {
+    content_name: String,
     owner: Principal,
     created_timestamp_ns: u64,
     url: String,
     credential_spec: CredentialSpec,
     credential_issuer: Principal,
+    credential_policy: Option<CredentialPolicy>,
+}
+
+impl ExclusiveContentRecord {
+    /// Returns the policy governing the access to the content.
+    fn credential_policy(&self) -> CredentialPolicy {
+        self.credential_policy
+            .clone()
+            .unwrap_or(CredentialPolicy::Requirement(CredentialRequirement {
+                credential_spec: self.credential_spec.clone(),
+                credential_issuer: self.credential_issuer,
+            }))
+    }
+}
+
+// Key of the index of content items by owner, which also ensures that the names
+// of the content items of an owner are unique.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct OwnerContentKey {
+    owner: Principal,
+    content_name: String,
+}
+
+// Key of the secondary indexes of content items, where `index` identifies the index,
+// and `value` the indexed value (e.g. the owner). Within an indexed value the items
+// are ordered newest first, as the creation timestamp is inverted.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct ContentIndexKey {
+    index: u8,
+    value: Vec<u8>,
+    inverted_timestamp_ns: u64,
+    content_id: String,
+}
+
+// A challenge issued to `caller`, to be used in a single VP validation.
+#[derive(CandidType, Clone, Deserialize)]
+struct ChallengeRecord {
+    caller: Principal,
+    created_timestamp_ns: u64,
+}
+
+// A grant for `grantee` to access either content that can be accessed with `credential_spec`,
+// or the content item `content_id` (whose policy has been checked).
+#[derive(CandidType, Clone, Deserialize)]
+struct AccessGrantRecord {
+    grantee: Principal,
+    credential_spec: Option<CredentialSpec>,
+    content_id: Option<String>,
+    expires_timestamp_ns: u64,
+}
+
+// The result of looking up an issuer origin in the issuer registry.
+#[derive(Clone)]
+struct RegistryCacheEntry {
+    canister_id: Option<Principal>,
+    fetched_timestamp_ns: u64,
 }
 
 impl Storable for ImageRecord {
@@ -58,12 +231,99 @@ impl Storable for ImageRecord {
     const BOUND: Bound = Bound::Unbounded;
 }
 
+impl Storable for LegacyExclusiveContentRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode LegacyExclusiveContentRecord"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode LegacyExclusiveContentRecord")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl VersionedRecord for ExclusiveContentRecord {
+    const NAME: &'static str = "ExclusiveContentRecord";
+    const VERSION: u16 = 1;
+}
+
 impl Storable for ExclusiveContentRecord {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).expect("failed to encode ExclusiveContentRecord"))
+        Cow::Owned(stable_schema::encode(self))
     }
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).expect("failed to decode ExclusiveContentRecord")
+        stable_schema::decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Encoded as the length of the owner principal, followed by the principal and the content name.
+impl Storable for OwnerContentKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let owner = self.owner.as_slice();
+        let mut bytes = Vec::with_capacity(1 + owner.len() + self.content_name.len());
+        bytes.push(owner.len() as u8);
+        bytes.extend_from_slice(owner);
+        bytes.extend_from_slice(self.content_name.as_bytes());
+        Cow::Owned(bytes)
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let owner_len = bytes[0] as usize;
+        Self {
+            owner: Principal::from_slice(&bytes[1..1 + owner_len]),
+            content_name: String::from_utf8(bytes[1 + owner_len..].to_vec())
+                .expect("failed to decode OwnerContentKey"),
+        }
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// Encoded as the index, the length of the value (4 bytes), the value,
+// the inverted timestamp (8 bytes), and the content id.
+impl Storable for ContentIndexKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = Vec::with_capacity(13 + self.value.len() + self.content_id.len());
+        bytes.push(self.index);
+        bytes.extend_from_slice(&(self.value.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.value);
+        bytes.extend_from_slice(&self.inverted_timestamp_ns.to_be_bytes());
+        bytes.extend_from_slice(self.content_id.as_bytes());
+        Cow::Owned(bytes)
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let value_len =
+            u32::from_be_bytes(bytes[1..5].try_into().expect("bad value length")) as usize;
+        let value_end = 5 + value_len;
+        Self {
+            index: bytes[0],
+            value: bytes[5..value_end].to_vec(),
+            inverted_timestamp_ns: u64::from_be_bytes(
+                bytes[value_end..value_end + 8]
+                    .try_into()
+                    .expect("bad timestamp"),
+            ),
+            content_id: String::from_utf8(bytes[value_end + 8..].to_vec())
+                .expect("failed to decode ContentIndexKey"),
+        }
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for ChallengeRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode ChallengeRecord"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode ChallengeRecord")
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+impl Storable for AccessGrantRecord {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("failed to encode AccessGrantRecord"))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(&bytes).expect("failed to decode AccessGrantRecord")
     }
     const BOUND: Bound = Bound::Unbounded;
 }
@@ -80,13 +340,86 @@ thread_local! {
             MEMORY_MANAGER.with(|m| m.borrow().get(IMAGES_MEMORY_ID)),
     ));
 
+    // Content items keyed by name, migrated to EXCLUSIVE_CONTENT in post_upgrade().
+    static LEGACY_EXCLUSIVE_CONTENT : RefCell<LegacyExclusiveContentMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(LEGACY_EXCLUSIVE_CONTENT_MEMORY_ID)),
+    ));
+
+    // Content items, indexed by content id.
     static EXCLUSIVE_CONTENT : RefCell<ExclusiveContentMap> = RefCell::new(
       StableBTreeMap::init(
             MEMORY_MANAGER.with(|m| m.borrow().get(EXCLUSIVE_CONTENT_MEMORY_ID)),
     ));
+
+    // Ids of content items, indexed by owner and content name.
+    static OWNER_CONTENT : RefCell<OwnerContentMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(OWNER_CONTENT_MEMORY_ID)),
+    ));
+
+    // Secondary indexes of content items, for filtering and ordering them.
+    static CONTENT_INDEX : RefCell<ContentIndexMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(CONTENT_INDEX_MEMORY_ID)),
+    ));
+
+    // Challenges which have been issued but not used yet, indexed by nonce.
+    static CHALLENGES : RefCell<ChallengesMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(CHALLENGES_MEMORY_ID)),
+    ));
+
+    // Ids of the credentials in successfully validated VPs, with their expiration.
+    static CONSUMED_PRESENTATIONS : RefCell<ConsumedPresentationsMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(CONSUMED_PRESENTATIONS_MEMORY_ID)),
+    ));
+
+    static ACCESS_GRANTS : RefCell<AccessGrantsMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(ACCESS_GRANTS_MEMORY_ID)),
+    ));
+
+    // Issuers added by the controllers, in addition to the issuers configured in RpInit.
+    static TRUSTED_ISSUERS : RefCell<TrustedIssuersMap> = RefCell::new(
+      StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(TRUSTED_ISSUERS_MEMORY_ID)),
+    ));
+
+    // Progress of the migrations of the stable data.
+    static MIGRATION_STATE : RefCell<MigrationStateCell> = RefCell::new(
+      StableCell::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(MIGRATION_STATE_MEMORY_ID)),
+            MigrationState::default(),
+    ).expect("failed to initialize stable cell"));
     /// Non-stable structures
     // Assets for the management app
     static ASSETS: RefCell<CertifiedAssets> = RefCell::new(CertifiedAssets::default());
+    // Counter to make nonces unique within a round.
+    static NONCE_COUNTER: Cell<u64> = Cell::new(0);
+    // Results of lookups in the issuer registry, indexed by issuer origin.
+    static REGISTRY_CACHE: RefCell<BTreeMap<String, RegistryCacheEntry>> = RefCell::new(BTreeMap::new());
+    // Token buckets of the rate-limited update calls, cf. `check_rate_limit`.
+    static RATE_LIMITER: RefCell<RateLimiter> = RefCell::new(RateLimiter::default());
+    // Cursors of the maps whose pruning pass is still in progress, cf. `prune_expired_entries`.
+    static PRUNE_CURSORS: RefCell<BTreeMap<PrunedMap, String>> = RefCell::new(BTreeMap::new());
+}
+
+// The maps whose expired entries are removed periodically.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum PrunedMap {
+    Challenges,
+    ConsumedPresentations,
+    AccessGrants,
+}
+
+impl PrunedMap {
+    const ALL: [PrunedMap; 3] = [
+        PrunedMap::Challenges,
+        PrunedMap::ConsumedPresentations,
+        PrunedMap::AccessGrants,
+    ];
 }
 
 /// Reserve the first stable memory page for the configuration stable cell.
@@ -102,7 +435,7 @@ fn managed_memory() -> Memory {
     )
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(CandidType, Clone, Deserialize)]
 struct RpConfig {
     /// Root of trust for checking canister signatures.
     ic_root_key_raw: Vec<u8>,
@@ -117,6 +450,18 @@ struct RpConfig {
 
     /// Derivation origin of the user's principal
     derivation_origin: String,
+
+    /// Registry of additionally trusted issuers, if any.
+    issuer_registry: Option<IssuerRegistry>,
+
+    /// Limits of the update calls open to anyone, defaults apply if not set.
+    rate_limits: Option<RateLimits>,
+
+    /// Whether presented credentials must name their issuer principal, which must be verified.
+    require_verified_issuers: Option<bool>,
+
+    /// Whether presented credentials must state the nonce of the challenge, defaults to true.
+    require_nonce: Option<bool>,
 }
 
 impl From<RpInit> for RpConfig {
@@ -132,16 +477,25 @@ impl From<RpInit> for RpConfig {
                 .map(|data| (data.vc_url.to_string(), data.canister_id))
                 .collect(),
             derivation_origin: init.derivation_origin,
+            issuer_registry: init.issuer_registry,
+            rate_limits: init.rate_limits,
+            require_verified_issuers: init.require_verified_issuers,
+            require_nonce: init.require_nonce,
         }
     }
 }
 
+impl VersionedRecord for RpConfig {
+    const NAME: &'static str = "RpConfig";
+    const VERSION: u16 = 1;
+}
+
 impl Storable for RpConfig {
     fn to_bytes(&self) -> Cow<[u8]> {
-        Cow::Owned(candid::encode_one(self).expect("failed to encode RpConfig"))
+        Cow::Owned(stable_schema::encode(self))
     }
     fn from_bytes(bytes: Cow<[u8]>) -> Self {
-        candid::decode_one(&bytes).expect("failed to decode RpConfig")
+        stable_schema::decode(&bytes)
     }
     const BOUND: Bound = Bound::Unbounded;
 }
@@ -154,6 +508,10 @@ impl Default for RpConfig {
             ii_canister_id: Principal::anonymous(),
             issuers: BTreeMap::new(),
             derivation_origin: "".to_string(),
+            issuer_registry: None,
+            rate_limits: None,
+            require_verified_issuers: None,
+            require_nonce: None,
         }
     }
 }
@@ -161,16 +519,117 @@ impl Default for RpConfig {
 #[init]
 #[candid_method(init)]
 fn init(init_arg: Option<RpInit>) {
+    // A fresh install stores all records with their current versions.
+    store_migration_state(MigrationState::completed(&MIGRATIONS));
+    initialize(init_arg);
+}
+
+#[post_upgrade]
+fn post_upgrade(init_arg: Option<RpInit>) {
+    initialize(init_arg);
+    MIGRATOR.run();
+}
+
+fn initialize(init_arg: Option<RpInit>) {
     if let Some(init) = init_arg {
         apply_config(init.into());
     };
     init_assets();
     init_images_map();
+    // Timers do not survive upgrades.
+    ic_cdk_timers::set_timer_interval(PRUNE_INTERVAL, prune_expired_entries);
 }
 
-#[post_upgrade]
-fn post_upgrade(init_arg: Option<RpInit>) {
-    init(init_arg);
+fn load_migration_state() -> MigrationState {
+    MIGRATION_STATE.with_borrow(|state| state.get().clone())
+}
+
+fn store_migration_state(state: MigrationState) {
+    MIGRATION_STATE
+        .with_borrow_mut(|cell| cell.set(state))
+        .expect("failed to store migration state");
+}
+
+fn migrate_config(_cursor: Option<Vec<u8>>, _batch_size: usize) -> MigrationBatch {
+    CONFIG.with_borrow_mut(|config_cell| {
+        let config = config_cell.get().clone();
+        config_cell
+            .set(config)
+            .expect("failed to rewrite RP config");
+    });
+    MigrationBatch {
+        cursor: None,
+        migrated_count: 1,
+    }
+}
+
+fn migrate_exclusive_content(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    EXCLUSIVE_CONTENT.with_borrow_mut(|content| rewrite_batch(content, cursor, batch_size))
+}
+
+/// Indexes a batch of the content items stored before the secondary indexes were introduced.
+/// Content items added since are indexed already, for which this is a no-op.
+fn index_content(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    let start = match cursor {
+        Some(cursor) => RangeBound::Excluded(String::from_bytes(Cow::Owned(cursor))),
+        None => RangeBound::Unbounded,
+    };
+    let items: Vec<(String, ExclusiveContentRecord)> = EXCLUSIVE_CONTENT.with_borrow(|content| {
+        content
+            .range((start, RangeBound::Unbounded))
+            .take(batch_size)
+            .collect()
+    });
+    CONTENT_INDEX.with_borrow_mut(|index| {
+        for (content_id, record) in &items {
+            for key in content_index_keys(content_id, record) {
+                index.insert(key, ());
+            }
+        }
+    });
+    MigrationBatch {
+        cursor: if items.len() < batch_size {
+            None
+        } else {
+            items
+                .last()
+                .map(|(content_id, _)| content_id.to_bytes().into_owned())
+        },
+        migrated_count: items.len() as u64,
+    }
+}
+
+/// Moves a batch of the content items keyed by name to the map keyed by content id.
+/// Moved items are removed from the legacy map, so every batch starts at its beginning.
+fn migrate_legacy_content(_cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    let legacy_items: Vec<(String, LegacyExclusiveContentRecord)> =
+        LEGACY_EXCLUSIVE_CONTENT.with_borrow(|legacy| legacy.iter().take(batch_size).collect());
+    let migrated_count = legacy_items.len() as u64;
+    let mut last_name = None;
+    for (content_name, legacy) in legacy_items {
+        insert_content(
+            new_nonce(b"content-id", content_name.as_bytes()),
+            ExclusiveContentRecord {
+                content_name: content_name.clone(),
+                owner: legacy.owner,
+                created_timestamp_ns: legacy.created_timestamp_ns,
+                url: legacy.url,
+                credential_spec: legacy.credential_spec,
+                credential_issuer: legacy.credential_issuer,
+                credential_policy: legacy.credential_policy,
+            },
+        );
+        LEGACY_EXCLUSIVE_CONTENT.with_borrow_mut(|legacy| legacy.remove(&content_name));
+        last_name = Some(content_name.into_bytes());
+    }
+    MigrationBatch {
+        cursor: if migrated_count < batch_size as u64 {
+            None
+        } else {
+            last_name
+        },
+        migrated_count,
+    }
 }
 
 fn image_name_to_url(image_name: &str) -> String {
@@ -202,6 +661,7 @@ fn init_images_map() {
 #[query]
 #[candid_method(query)]
 fn list_images(_req: ListImagesRequest) -> Result<ImagesList, ContentError> {
+    check_method_policy("list_images")?;
     IMAGES.with_borrow(|images| {
         let mut list = vec![];
         for (image_name, _record) in images.iter() {
@@ -213,107 +673,1111 @@ fn list_images(_req: ListImagesRequest) -> Result<ImagesList, ContentError> {
     })
 }
 
+/// Lists the content items matching the filters of the request, newest first.
+/// The first filter set, in the order owner, credential issuer and credential type,
+/// determines the index used, the other filters are applied to the items in the index.
+/// At most `MAX_SCANNED_CONTENT_ITEMS` items of the index are scanned, so if the other
+/// filters skip many items, a page can have fewer items than requested while there are more.
 #[query]
 #[candid_method(query)]
 fn list_exclusive_content(
-    _req: ListExclusiveContentRequest,
+    req: ListExclusiveContentRequest,
 ) -> Result<ExclusiveContentList, ContentError> {
-    EXCLUSIVE_CONTENT.with_borrow(|content| {
-        let mut list = vec![];
-        for (content_name, record) in content.iter() {
-            list.push(ContentData {
-                content_name,
-                owner: record.owner,
-                url: record.url,
-                created_timestamp_ns: record.created_timestamp_ns,
-                credential_spec: record.credential_spec,
-                credential_issuer: record.credential_issuer,
+    check_method_policy("list_exclusive_content")?;
+    let limit = req
+        .limit
+        .unwrap_or(DEFAULT_CONTENT_PAGE_SIZE)
+        .clamp(1, MAX_CONTENT_PAGE_SIZE) as usize;
+    let (index, value) = if let Some(owner) = req.owned_by {
+        (CONTENT_INDEX_OWNER, owner.as_slice().to_vec())
+    } else if let Some(issuer) = req.credential_issuer {
+        (CONTENT_INDEX_CREDENTIAL_ISSUER, issuer.as_slice().to_vec())
+    } else if let Some(credential_type) = &req.credential_type {
+        (
+            CONTENT_INDEX_CREDENTIAL_TYPE,
+            credential_type.as_bytes().to_vec(),
+        )
+    } else {
+        (CONTENT_INDEX_ALL, vec![])
+    };
+    let start = match &req.cursor {
+        Some(cursor) => {
+            let (created_timestamp_ns, content_id) = parse_content_cursor(cursor)?;
+            RangeBound::Excluded(ContentIndexKey {
+                index,
+                value: value.clone(),
+                inverted_timestamp_ns: u64::MAX - created_timestamp_ns,
+                content_id,
             })
         }
-        Ok(ExclusiveContentList {
-            content_items: list,
+        None => RangeBound::Included(ContentIndexKey {
+            index,
+            value: value.clone(),
+            inverted_timestamp_ns: 0,
+            content_id: String::new(),
+        }),
+    };
+
+    let mut content_items = vec![];
+    let mut next_cursor = None;
+    CONTENT_INDEX.with_borrow(|content_index| {
+        EXCLUSIVE_CONTENT.with_borrow(|content| {
+            // Cursor after the last item returned or skipped.
+            let mut last_cursor = None;
+            let mut scanned = 0;
+            for (key, ()) in content_index.range((start, RangeBound::Unbounded)) {
+                if key.index != index || key.value != value {
+                    break;
+                }
+                if scanned == MAX_SCANNED_CONTENT_ITEMS {
+                    next_cursor = last_cursor;
+                    break;
+                }
+                scanned += 1;
+                if let Some(record) = content
+                    .get(&key.content_id)
+                    .filter(|record| matches_content_filters(&req, record))
+                {
+                    if content_items.len() == limit {
+                        next_cursor = last_cursor;
+                        break;
+                    }
+                    content_items.push(content_data(key.content_id.clone(), record));
+                }
+                last_cursor = Some(content_cursor(&key));
+            }
         })
+    });
+    Ok(ExclusiveContentList {
+        content_items,
+        next_cursor,
     })
 }
 
+fn matches_content_filters(
+    req: &ListExclusiveContentRequest,
+    record: &ExclusiveContentRecord,
+) -> bool {
+    if req.owned_by.is_some_and(|owner| owner != record.owner) {
+        return false;
+    }
+    let policy = record.credential_policy();
+    let requirements = policy.requirements();
+    if let Some(credential_type) = &req.credential_type {
+        if !requirements
+            .iter()
+            .any(|requirement| requirement.credential_spec.credential_type == *credential_type)
+        {
+            return false;
+        }
+    }
+    if let Some(issuer) = req.credential_issuer {
+        if !requirements
+            .iter()
+            .any(|requirement| requirement.credential_issuer == issuer)
+        {
+            return false;
+        }
+    }
+    true
+}
+
+/// Returns the cursor for continuing after the item of the index key.
+fn content_cursor(key: &ContentIndexKey) -> String {
+    format!(
+        "{}-{}",
+        u64::MAX - key.inverted_timestamp_ns,
+        key.content_id
+    )
+}
+
+/// Parses a cursor of the form `<created_timestamp_ns>-<content_id>`.
+fn parse_content_cursor(cursor: &str) -> Result<(u64, String), ContentError> {
+    cursor
+        .split_once('-')
+        .and_then(|(timestamp, content_id)| {
+            Some((timestamp.parse::<u64>().ok()?, content_id.to_string()))
+        })
+        .ok_or(ContentError::InvalidArgument(format!(
+            "invalid cursor: {}",
+            cursor
+        )))
+}
+
+/// Returns the keys of the content item in the secondary indexes.
+fn content_index_keys(content_id: &str, record: &ExclusiveContentRecord) -> Vec<ContentIndexKey> {
+    let policy = record.credential_policy();
+    let requirements = policy.requirements();
+    let credential_types: BTreeSet<&str> = requirements
+        .iter()
+        .map(|requirement| requirement.credential_spec.credential_type.as_str())
+        .collect();
+    let issuers: BTreeSet<Principal> = requirements
+        .iter()
+        .map(|requirement| requirement.credential_issuer)
+        .collect();
+
+    let mut entries = vec![
+        (CONTENT_INDEX_ALL, vec![]),
+        (CONTENT_INDEX_OWNER, record.owner.as_slice().to_vec()),
+    ];
+    entries.extend(credential_types.into_iter().map(|credential_type| {
+        (
+            CONTENT_INDEX_CREDENTIAL_TYPE,
+            credential_type.as_bytes().to_vec(),
+        )
+    }));
+    entries.extend(
+        issuers
+            .into_iter()
+            .map(|issuer| (CONTENT_INDEX_CREDENTIAL_ISSUER, issuer.as_slice().to_vec())),
+    );
+    entries
+        .into_iter()
+        .map(|(index, value)| ContentIndexKey {
+            index,
+            value,
+            inverted_timestamp_ns: u64::MAX - record.created_timestamp_ns,
+            content_id: content_id.to_string(),
+        })
+        .collect()
+}
+
+/// Adds a content item owned by the caller, whose name must be unique among the caller's items.
 #[update]
 #[candid_method]
 fn add_exclusive_content(req: AddExclusiveContentRequest) -> Result<ContentData, ContentError> {
-    EXCLUSIVE_CONTENT.with_borrow_mut(|content| {
-        let data = ContentData {
-            content_name: req.content_name,
-            owner: caller(),
-            url: req.url,
-            created_timestamp_ns: time(),
-            credential_spec: req.credential_spec,
-            credential_issuer: req.credential_issuer,
+    let owner = check_method_policy("add_exclusive_content")?;
+    check_rate_limit(owner)?;
+    validate_content_policy(&req.credential_policy)?;
+    let key = OwnerContentKey {
+        owner,
+        content_name: req.content_name,
+    };
+    if OWNER_CONTENT.with_borrow(|owner_content| owner_content.contains_key(&key)) {
+        return Err(ContentError::AlreadyExists(format!(
+            "content {} already exists",
+            key.content_name
+        )));
+    }
+    let content_id = new_nonce(b"content-id", owner.as_slice());
+    let record = ExclusiveContentRecord {
+        content_name: key.content_name,
+        owner,
+        created_timestamp_ns: time(),
+        url: req.url,
+        credential_spec: req.credential_spec,
+        credential_issuer: req.credential_issuer,
+        credential_policy: req.credential_policy,
+    };
+    insert_content(content_id.clone(), record.clone());
+    Ok(content_data(content_id, record))
+}
+
+/// Replaces the content item, keeping its id and creation time; only the owner can update it.
+#[update]
+#[candid_method]
+fn update_exclusive_content(
+    req: UpdateExclusiveContentRequest,
+) -> Result<ContentData, ContentError> {
+    let owner = check_method_policy("update_exclusive_content")?;
+    check_rate_limit(owner)?;
+    validate_content_policy(&req.credential_policy)?;
+    let existing = owned_content(&req.content_id, owner)?;
+    if existing.content_name != req.content_name {
+        let key = OwnerContentKey {
+            owner,
+            content_name: req.content_name.clone(),
         };
+        if OWNER_CONTENT.with_borrow(|owner_content| owner_content.contains_key(&key)) {
+            return Err(ContentError::AlreadyExists(format!(
+                "content {} already exists",
+                req.content_name
+            )));
+        }
+    }
+    remove_content(&req.content_id, &existing);
+    let record = ExclusiveContentRecord {
+        content_name: req.content_name,
+        owner,
+        created_timestamp_ns: existing.created_timestamp_ns,
+        url: req.url,
+        credential_spec: req.credential_spec,
+        credential_issuer: req.credential_issuer,
+        credential_policy: req.credential_policy,
+    };
+    insert_content(req.content_id.clone(), record.clone());
+    Ok(content_data(req.content_id, record))
+}
 
-        content.insert(
-            data.content_name.clone(),
-            ExclusiveContentRecord {
-                owner: data.owner,
-                created_timestamp_ns: data.created_timestamp_ns,
-                url: data.url.clone(),
-                credential_spec: data.credential_spec.clone(),
-                credential_issuer: data.credential_issuer,
+/// Removes the content item; only the owner can remove it.
+#[update]
+#[candid_method]
+fn remove_exclusive_content(req: RemoveExclusiveContentRequest) -> Result<(), ContentError> {
+    let owner = check_method_policy("remove_exclusive_content")?;
+    let existing = owned_content(&req.content_id, owner)?;
+    remove_content(&req.content_id, &existing);
+    Ok(())
+}
+
+/// Rejects ingress messages which violate `METHOD_POLICIES` before they are executed.
+#[inspect_message]
+fn inspect_message() {
+    let method = method_name();
+    match check_call(
+        METHOD_POLICIES,
+        &method,
+        caller(),
+        is_controller(&caller()),
+        arg_data_raw_size(),
+    ) {
+        Ok(()) => accept_message(),
+        Err(violation) => trap(&format!("{}: {}", method, violation)),
+    }
+}
+
+/// Checks the current call of `method` against its policy in `METHOD_POLICIES`,
+/// and returns the caller.
+fn check_method_policy(method: &str) -> Result<Principal, ContentError> {
+    check_policy(method).map_err(|violation| match violation {
+        PolicyViolation::AnonymousCaller
+        | PolicyViolation::NotController(_)
+        | PolicyViolation::NotOwner(_)
+        | PolicyViolation::NotMember(_) => ContentError::NotAuthorized(violation.to_string()),
+        PolicyViolation::ArgumentTooLarge { .. } => {
+            ContentError::InvalidArgument(violation.to_string())
+        }
+        PolicyViolation::UnknownMethod(_) => ContentError::Internal(violation.to_string()),
+    })
+}
+
+/// Like `check_method_policy`, for methods which cannot return a `ContentError`:
+/// traps if the call violates the policy, as `inspect_message` does for ingress messages.
+fn enforce_method_policy(method: &str) -> Principal {
+    check_policy(method).unwrap_or_else(|violation| trap(&format!("{}: {}", method, violation)))
+}
+
+fn check_policy(method: &str) -> Result<Principal, PolicyViolation> {
+    let caller = caller();
+    check_call(
+        METHOD_POLICIES,
+        method,
+        caller,
+        is_controller(&caller),
+        arg_data_raw_size(),
+    )?;
+    Ok(caller)
+}
+
+/// Takes a token for an update call of the caller, cf. `RateLimits`.
+fn check_rate_limit(caller: Principal) -> Result<(), ContentError> {
+    let limits = rate_limits();
+    RATE_LIMITER
+        .with_borrow_mut(|limiter| {
+            limiter.check(
+                caller,
+                limits.per_caller.as_ref(),
+                limits.global.as_ref(),
+                time(),
+            )
+        })
+        .map_err(ContentError::RateLimited)
+}
+
+fn rate_limits() -> RateLimits {
+    CONFIG.with_borrow(|config| config.get().rate_limits.clone().unwrap_or_default())
+}
+
+fn validate_content_policy(policy: &Option<CredentialPolicy>) -> Result<(), ContentError> {
+    if let Some(policy) = policy {
+        policy.validate().map_err(|err| {
+            ContentError::InvalidArgument(format!("invalid credential policy: {}", err))
+        })?;
+    }
+    Ok(())
+}
+
+/// Returns the content item with the given id, if it is owned by `owner`.
+fn owned_content(
+    content_id: &str,
+    owner: Principal,
+) -> Result<ExclusiveContentRecord, ContentError> {
+    let record = get_content(content_id)?;
+    if record.owner != owner {
+        return Err(ContentError::NotAuthorized(format!(
+            "content {} is owned by another principal",
+            content_id
+        )));
+    }
+    Ok(record)
+}
+
+fn get_content(content_id: &str) -> Result<ExclusiveContentRecord, ContentError> {
+    EXCLUSIVE_CONTENT
+        .with_borrow(|content| content.get(&content_id.to_string()))
+        .ok_or(ContentError::NotFound(format!(
+            "content {} not found",
+            content_id
+        )))
+}
+
+/// Inserts the content item and updates the indexes.
+fn insert_content(content_id: String, record: ExclusiveContentRecord) {
+    CONTENT_INDEX.with_borrow_mut(|index| {
+        for key in content_index_keys(&content_id, &record) {
+            index.insert(key, ());
+        }
+    });
+    OWNER_CONTENT.with_borrow_mut(|owner_content| {
+        owner_content.insert(
+            OwnerContentKey {
+                owner: record.owner,
+                content_name: record.content_name.clone(),
             },
-        );
-        Ok(data)
+            content_id.clone(),
+        )
+    });
+    EXCLUSIVE_CONTENT.with_borrow_mut(|content| content.insert(content_id, record));
+}
+
+/// Removes the content item and its index entries.
+fn remove_content(content_id: &str, record: &ExclusiveContentRecord) {
+    CONTENT_INDEX.with_borrow_mut(|index| {
+        for key in content_index_keys(content_id, record) {
+            index.remove(&key);
+        }
+    });
+    OWNER_CONTENT.with_borrow_mut(|owner_content| {
+        owner_content.remove(&OwnerContentKey {
+            owner: record.owner,
+            content_name: record.content_name.clone(),
+        })
+    });
+    EXCLUSIVE_CONTENT.with_borrow_mut(|content| content.remove(&content_id.to_string()));
+}
+
+fn content_data(content_id: String, record: ExclusiveContentRecord) -> ContentData {
+    ContentData {
+        content_id,
+        content_name: record.content_name,
+        owner: record.owner,
+        url: record.url,
+        created_timestamp_ns: record.created_timestamp_ns,
+        credential_spec: record.credential_spec,
+        credential_issuer: record.credential_issuer,
+        credential_policy: record.credential_policy,
+    }
+}
+
+/// API for validating VPs and accessing exclusive content.
+///
+/// The nonce of the challenge must be requested from the issuer as `nonce`-argument of the
+/// credential spec, so that the credential states it in its `vc.nonce`-claim, and the credential
+/// is accepted only with this challenge (unless disabled via `RpInit.require_nonce`).
+/// Additionally, the credential must have been issued after the challenge was created,
+/// and the ids of the presented credentials are recorded to prevent replays, cf. `mark_presented`.
+#[update]
+#[candid_method]
+fn get_vp_challenge() -> Result<VpChallenge, ContentError> {
+    let caller = check_method_policy("get_vp_challenge")?;
+    let now = time();
+    let nonce = new_nonce(b"vp-challenge", caller.as_slice());
+    CHALLENGES.with_borrow_mut(|challenges| {
+        challenges.insert(
+            nonce.clone(),
+            ChallengeRecord {
+                caller,
+                created_timestamp_ns: now,
+            },
+        )
+    });
+    Ok(VpChallenge {
+        nonce,
+        expires_timestamp_ns: now + CHALLENGE_VALIDITY_NS,
     })
 }
 
+/// Validates the VP, which must be presented by its subject, using a challenge
+/// that was issued to the subject before the requested credential was issued.
+/// Each credential can be presented only once (which is what prevents replays,
+/// cf. `get_vp_challenge`), and on success the caller
+/// gets a short-lived grant for accessing content that requires the credential.
+/// If the RP requires verified issuers, the issuer of the credential must be verified
+/// by the issuer canister, cf. `RpInit.require_verified_issuers`.
 #[update]
 #[candid_method]
-fn validate_ii_vp(req: ValidateVpRequest) -> Result<(), ContentError> {
-    let (ic_root_key_raw, vc_flow_signers, derivation_origin) = CONFIG.with_borrow(|config| {
-        let config = config.get();
-        let Some(issuer_canister_id) = config.issuers.get(&req.issuer_origin) else {
-            return Err(ContentError::NotAuthorized(format!(
-                "issuer not supported: {}",
-                req.issuer_origin,
-            )));
+async fn validate_ii_vp(req: ValidateVpRequest) -> Result<ValidatedVpData, VpValidationError> {
+    let caller = enforce_method_policy("validate_ii_vp");
+    let config = verifier_config(&[&req.issuer_origin]).await;
+    // Checked before the challenge is consumed, as the checks of the presentation
+    // and its recording as presented must not be separated by a call.
+    let issuer = verified_issuer(
+        &config,
+        &req.vp_jwt,
+        &req.issuer_origin,
+        req.issuer_canister_id,
+    )
+    .await?;
+    let now = time();
+    let challenge = consume_challenge(&req.challenge, caller, now)?;
+    check_presenter(caller, req.effective_vc_subject)?;
+
+    let verified = verify_presentation(
+        &config,
+        &VpValidationRequest {
+            vp_jwt: req.vp_jwt,
+            effective_vc_subject: req.effective_vc_subject,
+            credential_spec: req.credential_spec.clone(),
+            issuer_origin: req.issuer_origin,
+            issuer_canister_id: req.issuer_canister_id,
+        },
+        issuer.as_ref(),
+        &req.challenge,
+        &challenge,
+        now,
+    )?;
+    mark_presented(&verified)?;
+
+    let access_grant = new_access_grant(
+        &req.challenge,
+        AccessGrantRecord {
+            grantee: caller,
+            credential_spec: Some(req.credential_spec),
+            content_id: None,
+            expires_timestamp_ns: (now + ACCESS_GRANT_VALIDITY_NS)
+                .min(verified.expiration_timestamp_ns),
+        },
+    );
+    Ok(ValidatedVpData {
+        subject: verified.subject,
+        id_alias: verified.id_alias,
+        issuer_origin: verified.issuer_origin,
+        credential_issuer: verified.issuer_canister_id,
+        credential_spec: CredentialSpec {
+            credential_type: verified.credential_type,
+            arguments: verified.arguments,
+        },
+        expires_timestamp_ns: verified.expiration_timestamp_ns,
+        access_grant,
+    })
+}
+
+/// Returns the issuer principal named by the VP, if the RP requires verified issuers,
+/// after checking with the issuer canister that it is verified for the credential type.
+/// The VP is validated only afterwards, so `verify_presentation` checks that the validated
+/// credential was issued by the returned issuer.
+async fn verified_issuer(
+    config: &VerifierConfig,
+    vp_jwt: &str,
+    issuer_origin: &str,
+    issuer_canister_id: Option<Principal>,
+) -> Result<Option<ClaimedIssuer>, VpValidationError> {
+    let required =
+        CONFIG.with_borrow(|config| config.get().require_verified_issuers.unwrap_or_default());
+    if !required {
+        return Ok(None);
+    }
+    let issuer_canister_id = trusted_issuer_canister_id(config, issuer_origin, issuer_canister_id)?;
+    let Some(issuer) = claimed_issuer(vp_jwt)? else {
+        return Err(VpValidationError::UnverifiedIssuer(
+            "credential does not name its issuer principal".to_string(),
+        ));
+    };
+    check_issuer_verified(
+        issuer_canister_id,
+        issuer.issuer_principal,
+        &issuer.credential_type,
+    )
+    .await?;
+    Ok(Some(issuer))
+}
+
+/// Checks with the issuer canister that the issuer principal is verified for the credential type.
+async fn check_issuer_verified(
+    issuer_canister_id: Principal,
+    credential_issuer: Principal,
+    credential_type: &str,
+) -> Result<(), VpValidationError> {
+    let result: Result<(Option<IssuerVerificationData>,), _> = ic_cdk::call(
+        issuer_canister_id,
+        "get_issuer_verification",
+        (GetIssuerVerificationRequest {
+            issuer: credential_issuer,
+            credential_type: credential_type.to_string(),
+        },),
+    )
+    .await;
+    match result {
+        Ok((Some(_),)) => Ok(()),
+        Ok((None,)) => Err(VpValidationError::UnverifiedIssuer(format!(
+            "issuer {} is not verified for {}",
+            credential_issuer, credential_type
+        ))),
+        Err((code, msg)) => Err(VpValidationError::UnverifiedIssuer(format!(
+            "failed to check the verification of issuer {} with {}: {:?} {}",
+            credential_issuer, issuer_canister_id, code, msg
+        ))),
+    }
+}
+
+/// Validates the presented VPs against the credential policy of the content item,
+/// and reports which requirements of the policy are met.
+/// The challenge and the presentations (including the verification of their issuers, if required)
+/// are handled like in `validate_ii_vp`, but only
+/// if the policy is satisfied: then the challenge is consumed, the credentials used are
+/// recorded as presented, and the caller gets a short-lived grant for accessing the content item.
+/// Otherwise the VPs can be presented again with the same challenge.
+#[update]
+#[candid_method]
+async fn validate_content_access(
+    req: ValidateContentAccessRequest,
+) -> Result<ContentAccessData, ContentError> {
+    let caller = check_method_policy("validate_content_access")?;
+    if req.presentations.len() > MAX_POLICY_REQUIREMENTS {
+        return Err(ContentError::InvalidArgument(format!(
+            "too many presentations: {} (at most {} allowed)",
+            req.presentations.len(),
+            MAX_POLICY_REQUIREMENTS
+        )));
+    }
+    let record = get_content(&req.content_id)?;
+    let issuer_origins: Vec<&str> = req
+        .presentations
+        .iter()
+        .map(|presentation| presentation.issuer_origin.as_str())
+        .collect();
+    let config = verifier_config(&issuer_origins).await;
+    // Checked before the challenge, as the checks of the presentations
+    // and their recording as presented must not be separated by a call.
+    let mut issuers = vec![];
+    for presentation in &req.presentations {
+        issuers.push(
+            verified_issuer(
+                &config,
+                &presentation.vp_jwt,
+                &presentation.issuer_origin,
+                presentation.issuer_canister_id,
+            )
+            .await,
+        );
+    }
+    let now = time();
+    let challenge = check_challenge(&req.challenge, caller, now)
+        .map_err(|err| ContentError::NotAuthorized(format!("{:?}", err)))?;
+    check_presenter(caller, req.effective_vc_subject)
+        .map_err(|err| ContentError::NotAuthorized(format!("{:?}", err)))?;
+
+    let policy = record.credential_policy();
+    let mut results = vec![];
+    let mut used_credentials: Vec<VerifiedCredential> = vec![];
+    for requirement in policy.requirements() {
+        let mut result = RequirementResult {
+            requirement: requirement.clone(),
+            met: false,
+            errors: vec![],
         };
-        if let Some(issuer_canister_id_from_req) = req.issuer_canister_id {
-            if *issuer_canister_id != issuer_canister_id_from_req {
-                return Err(ContentError::NotAuthorized(format!(
-                    "wrong issuer canister id: expected {}, got {}",
-                    issuer_canister_id, issuer_canister_id_from_req
-                )));
+        for (index, presentation) in req.presentations.iter().enumerate() {
+            let verification = match &issuers[index] {
+                Ok(issuer) => verify_presentation(
+                    &config,
+                    &VpValidationRequest {
+                        vp_jwt: presentation.vp_jwt.clone(),
+                        effective_vc_subject: req.effective_vc_subject,
+                        credential_spec: requirement.credential_spec.clone(),
+                        issuer_origin: presentation.issuer_origin.clone(),
+                        issuer_canister_id: presentation.issuer_canister_id,
+                    },
+                    issuer.as_ref(),
+                    &req.challenge,
+                    &challenge,
+                    now,
+                ),
+                Err(error) => Err(error.clone()),
+            };
+            match verification {
+                Ok(verified) => {
+                    result.met = true;
+                    result.errors.clear();
+                    // The same credential may meet several requirements.
+                    if !used_credentials
+                        .iter()
+                        .any(|used| used.credential_id == verified.credential_id)
+                    {
+                        used_credentials.push(verified);
+                    }
+                    break;
+                }
+                Err(error) => result.errors.push(PresentationError {
+                    presentation_index: index as u32,
+                    error,
+                }),
             }
         }
-        Ok((
-            config.ic_root_key_raw.clone(),
-            VcFlowSigners {
+        results.push(result);
+    }
+
+    let policy_satisfied = policy.is_satisfied_by(&|requirement| {
+        results
+            .iter()
+            .any(|result| result.met && result.requirement == *requirement)
+    });
+    let access_grant = if policy_satisfied {
+        let mut expires_timestamp_ns = now + ACCESS_GRANT_VALIDITY_NS;
+        for verified in &used_credentials {
+            mark_presented(verified)
+                .map_err(|err| ContentError::NotAuthorized(format!("{:?}", err)))?;
+            expires_timestamp_ns = expires_timestamp_ns.min(verified.expiration_timestamp_ns);
+        }
+        CHALLENGES.with_borrow_mut(|challenges| challenges.remove(&req.challenge));
+        Some(new_access_grant(
+            &req.challenge,
+            AccessGrantRecord {
+                grantee: caller,
+                credential_spec: None,
+                content_id: Some(req.content_id),
+                expires_timestamp_ns,
+            },
+        ))
+    } else {
+        None
+    };
+    Ok(ContentAccessData {
+        policy_satisfied,
+        requirements: results,
+        access_grant,
+    })
+}
+
+/// Returns the requested content item, if the caller holds a valid grant for the item,
+/// or a grant for a credential which satisfies the item's policy.
+#[query]
+#[candid_method(query)]
+fn check_access(req: CheckAccessRequest) -> Result<ContentData, ContentError> {
+    let caller = check_method_policy("check_access")?;
+    let Some(grant) = ACCESS_GRANTS.with_borrow(|grants| grants.get(&req.grant_id)) else {
+        return Err(ContentError::NotAuthorized(
+            "unknown access grant".to_string(),
+        ));
+    };
+    if grant.grantee != caller {
+        return Err(ContentError::NotAuthorized(
+            "access grant issued to another principal".to_string(),
+        ));
+    }
+    if grant.expires_timestamp_ns < time() {
+        return Err(ContentError::NotAuthorized(
+            "access grant expired".to_string(),
+        ));
+    }
+    let record = get_content(&req.content_id)?;
+    let covered = match (&grant.content_id, &grant.credential_spec) {
+        (Some(content_id), _) => *content_id == req.content_id,
+        (None, Some(credential_spec)) => record
+            .credential_policy()
+            .is_satisfied_by(&|requirement| requirement.credential_spec == *credential_spec),
+        (None, None) => false,
+    };
+    if !covered {
+        return Err(ContentError::NotAuthorized(format!(
+            "access grant does not cover content {}",
+            req.content_id
+        )));
+    }
+    Ok(content_data(req.content_id, record))
+}
+
+/// Returns the configuration for validating VPs, which trusts the issuers configured
+/// in RpInit, the issuers added via `add_trusted_issuer`, and those of the given origins
+/// that are listed in the issuer registry (if any).
+async fn verifier_config(issuer_origins: &[&str]) -> VerifierConfig {
+    let (mut config, issuer_registry) = CONFIG.with_borrow(|config| {
+        let config = config.get();
+        (
+            VerifierConfig {
+                ic_root_key_raw: config.ic_root_key_raw.clone(),
                 ii_origin: config.ii_origin.clone(),
                 ii_canister_id: config.ii_canister_id,
-                issuer_origin: req.issuer_origin,
-                issuer_canister_id: *issuer_canister_id,
+                issuers: config.issuers.clone(),
+                derivation_origin: config.derivation_origin.clone(),
             },
-            config.derivation_origin.clone(),
-        ))
-    })?;
-    match validate_ii_presentation_and_claims(
-        &req.vp_jwt,
-        req.effective_vc_subject,
-        derivation_origin,
-        &vc_flow_signers,
-        &req.credential_spec,
-        &ic_root_key_raw,
-        time() as u128,
-    ) {
-        Ok(()) => Ok(()),
-        Err(err) => Err(ContentError::NotAuthorized(format!(
-            "VP validation error: {:?}",
-            err
+            config.issuer_registry.clone(),
+        )
+    });
+    TRUSTED_ISSUERS.with_borrow(|trusted| config.issuers.extend(trusted.iter()));
+    if let Some(registry) = issuer_registry {
+        for origin in issuer_origins {
+            if config.issuers.contains_key(*origin) {
+                continue;
+            }
+            if let Some(canister_id) = lookup_registry_issuer(&registry, origin).await {
+                config.issuers.insert(origin.to_string(), canister_id);
+            }
+        }
+    }
+    config
+}
+
+/// Looks up the issuer with the given origin in the registry, using cached results if fresh.
+/// Failed lookups are not cached, and the issuer is considered untrusted.
+async fn lookup_registry_issuer(
+    registry: &IssuerRegistry,
+    issuer_origin: &str,
+) -> Option<Principal> {
+    let cache_ttl_ns = registry
+        .cache_ttl_ns
+        .unwrap_or(DEFAULT_REGISTRY_CACHE_TTL_NS);
+    let cached = REGISTRY_CACHE.with_borrow(|cache| cache.get(issuer_origin).cloned());
+    if let Some(entry) = cached {
+        if entry.fetched_timestamp_ns + cache_ttl_ns >= time() {
+            return entry.canister_id;
+        }
+    }
+    let result: Result<(Option<IssuerData>,), _> = ic_cdk::call(
+        registry.canister_id,
+        "lookup_trusted_issuer",
+        (issuer_origin.to_string(),),
+    )
+    .await;
+    let canister_id = match result {
+        Ok((maybe_issuer,)) => maybe_issuer
+            .filter(|issuer| issuer.vc_url == issuer_origin)
+            .map(|issuer| issuer.canister_id),
+        Err((code, msg)) => {
+            ic_cdk::println!(
+                "failed to look up issuer {} in registry {}: {:?} {}",
+                issuer_origin,
+                registry.canister_id,
+                code,
+                msg
+            );
+            return None;
+        }
+    };
+    REGISTRY_CACHE.with_borrow_mut(|cache| {
+        cache.insert(
+            issuer_origin.to_string(),
+            RegistryCacheEntry {
+                canister_id,
+                fetched_timestamp_ns: time(),
+            },
+        )
+    });
+    canister_id
+}
+
+fn check_presenter(caller: Principal, subject: Principal) -> Result<(), VpValidationError> {
+    if caller != subject {
+        return Err(VpValidationError::SubjectMismatch(format!(
+            "VP for {} presented by {}",
+            subject, caller
+        )));
+    }
+    Ok(())
+}
+
+/// Validates the VP, and checks that the requested credential has been issued by the
+/// verified issuer (if any, cf. `verified_issuer`) for the challenge with the given nonce
+/// (if required, cf. `get_vp_challenge`) after the challenge was created,
+/// and that it has not been presented before.
+fn verify_presentation(
+    config: &VerifierConfig,
+    req: &VpValidationRequest,
+    issuer: Option<&ClaimedIssuer>,
+    nonce: &str,
+    challenge: &ChallengeRecord,
+    now: u64,
+) -> Result<VerifiedCredential, VpValidationError> {
+    let verified =
+        vc_verifier::validate_ii_vp(config, req, now as u128).map_err(VpValidationError::from)?;
+    if let Some(issuer) = issuer {
+        if !issuer.issued(&verified) {
+            return Err(VpValidationError::UnverifiedIssuer(format!(
+                "credential was not issued by the verified issuer {}",
+                issuer.issuer_principal
+            )));
+        }
+    }
+    let nonce_required = CONFIG.with_borrow(|config| config.get().require_nonce.unwrap_or(true));
+    if nonce_required && verified.nonce.as_deref() != Some(nonce) {
+        return Err(VpValidationError::InvalidChallenge(match verified.nonce {
+            Some(_) => "credential was issued for another challenge".to_string(),
+            None => "credential does not state the nonce of the challenge".to_string(),
+        }));
+    }
+    // `nbf` has a granularity of seconds.
+    let Some(issuance_timestamp_ns) = verified.issuance_timestamp_ns else {
+        return Err(VpValidationError::InvalidPresentation(
+            "missing nbf in requested credential".to_string(),
+        ));
+    };
+    if issuance_timestamp_ns / SECOND_NS < challenge.created_timestamp_ns / SECOND_NS {
+        return Err(VpValidationError::InvalidChallenge(
+            "credential was issued before the challenge".to_string(),
+        ));
+    }
+    let Some(credential_id) = &verified.credential_id else {
+        return Err(VpValidationError::InvalidPresentation(
+            "missing jti in requested credential".to_string(),
+        ));
+    };
+    if CONSUMED_PRESENTATIONS.with_borrow(|consumed| consumed.contains_key(credential_id)) {
+        return Err(VpValidationError::AlreadyUsed(format!(
+            "credential {} has already been presented",
+            credential_id
+        )));
+    }
+    Ok(verified)
+}
+
+/// Records the credential as presented, so that it cannot be presented again.
+fn mark_presented(verified: &VerifiedCredential) -> Result<(), VpValidationError> {
+    let Some(credential_id) = &verified.credential_id else {
+        return Err(VpValidationError::InvalidPresentation(
+            "missing jti in requested credential".to_string(),
+        ));
+    };
+    CONSUMED_PRESENTATIONS.with_borrow_mut(|consumed| {
+        if consumed
+            .insert(credential_id.clone(), verified.expiration_timestamp_ns)
+            .is_some()
+        {
+            return Err(VpValidationError::AlreadyUsed(format!(
+                "credential {} has already been presented",
+                credential_id
+            )));
+        }
+        Ok(())
+    })
+}
+
+fn new_access_grant(challenge: &str, record: AccessGrantRecord) -> AccessGrant {
+    let access_grant = AccessGrant {
+        grant_id: new_nonce(b"access-grant", challenge.as_bytes()),
+        expires_timestamp_ns: record.expires_timestamp_ns,
+    };
+    ACCESS_GRANTS.with_borrow_mut(|grants| grants.insert(access_grant.grant_id.clone(), record));
+    access_grant
+}
+
+/// Removes the challenge with the given nonce, and returns it if it is valid for the caller.
+fn consume_challenge(
+    nonce: &str,
+    caller: Principal,
+    now: u64,
+) -> Result<ChallengeRecord, VpValidationError> {
+    let challenge = check_challenge(nonce, caller, now)?;
+    CHALLENGES.with_borrow_mut(|challenges| challenges.remove(&nonce.to_string()));
+    Ok(challenge)
+}
+
+/// Returns the challenge with the given nonce, if it is valid for the caller.
+/// Expired challenges are left to `prune_expired_entries`.
+fn check_challenge(
+    nonce: &str,
+    caller: Principal,
+    now: u64,
+) -> Result<ChallengeRecord, VpValidationError> {
+    let Some(challenge) = CHALLENGES.with_borrow(|challenges| challenges.get(&nonce.to_string()))
+    else {
+        return Err(VpValidationError::InvalidChallenge(
+            "unknown challenge".to_string(),
+        ));
+    };
+    if challenge.caller != caller {
+        return Err(VpValidationError::InvalidChallenge(
+            "challenge issued to another principal".to_string(),
+        ));
+    }
+    if challenge.created_timestamp_ns + CHALLENGE_VALIDITY_NS < now {
+        return Err(VpValidationError::InvalidChallenge(
+            "challenge expired".to_string(),
+        ));
+    }
+    Ok(challenge)
+}
+
+fn new_nonce(domain: &[u8], seed: &[u8]) -> String {
+    let counter = NONCE_COUNTER.with(|counter| {
+        counter.set(counter.get() + 1);
+        counter.get()
+    });
+    let mut hasher = Sha256::new();
+    hasher.update(domain);
+    hasher.update(seed);
+    hasher.update(time().to_be_bytes());
+    hasher.update(counter.to_be_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Starts a pass removing the expired challenges, consumed presentations and access grants,
+/// except for the maps whose previous pass is still in progress. Each map is scanned in
+/// batches of `MAX_PRUNED_ENTRIES` entries per timer call, to bound the instructions used
+/// by a call (like the migrations, cf. `stable_schema::Migrator`).
+fn prune_expired_entries() {
+    let maps: Vec<PrunedMap> = PRUNE_CURSORS.with_borrow(|cursors| {
+        PrunedMap::ALL
+            .into_iter()
+            .filter(|map| !cursors.contains_key(map))
+            .collect()
+    });
+    prune_batches(maps);
+}
+
+// Prunes the next batch of the given maps, and schedules a timer for the maps which have not
+// completed their pass.
+fn prune_batches(maps: Vec<PrunedMap>) {
+    let now = time();
+    let mut pending_maps = vec![];
+    for map in maps {
+        let cursor = PRUNE_CURSORS.with_borrow_mut(|cursors| cursors.remove(&map));
+        let next_cursor = match map {
+            PrunedMap::Challenges => CHALLENGES.with_borrow_mut(|challenges| {
+                prune_batch(challenges, cursor, |challenge| {
+                    challenge.created_timestamp_ns + CHALLENGE_VALIDITY_NS < now
+                })
+            }),
+            // A credential can be removed once it has expired, as it cannot be presented anymore.
+            PrunedMap::ConsumedPresentations => {
+                CONSUMED_PRESENTATIONS.with_borrow_mut(|consumed| {
+                    prune_batch(consumed, cursor, |expires_timestamp_ns| {
+                        *expires_timestamp_ns < now
+                    })
+                })
+            }
+            PrunedMap::AccessGrants => ACCESS_GRANTS.with_borrow_mut(|grants| {
+                prune_batch(grants, cursor, |grant| grant.expires_timestamp_ns < now)
+            }),
+        };
+        if let Some(next_cursor) = next_cursor {
+            PRUNE_CURSORS.with_borrow_mut(|cursors| cursors.insert(map, next_cursor));
+            pending_maps.push(map);
+        }
+    }
+    if !pending_maps.is_empty() {
+        ic_cdk_timers::set_timer(Duration::ZERO, move || prune_batches(pending_maps));
+    }
+}
+
+// Removes the expired entries among the batch of entries after the cursor, and returns
+// the cursor of the next batch, if the batch is full.
+fn prune_batch<V: Storable>(
+    map: &mut StableBTreeMap<String, V, VirtualMemory<Memory>>,
+    cursor: Option<String>,
+    is_expired: impl Fn(&V) -> bool,
+) -> Option<String> {
+    let start = match cursor {
+        Some(cursor) => RangeBound::Excluded(cursor),
+        None => RangeBound::Unbounded,
+    };
+    let batch: Vec<(String, V)> = map
+        .range((start, RangeBound::Unbounded))
+        .take(MAX_PRUNED_ENTRIES)
+        .collect();
+    for (key, value) in &batch {
+        if is_expired(value) {
+            map.remove(key);
+        }
+    }
+    if batch.len() < MAX_PRUNED_ENTRIES {
+        None
+    } else {
+        batch.last().map(|(key, _)| key.clone())
+    }
+}
+
+/// API for managing the issuers trusted by the RP.
+#[update]
+#[candid_method]
+fn add_trusted_issuer(issuer: IssuerData) -> Result<(), ContentError> {
+    check_method_policy("add_trusted_issuer")?;
+    if issuer.vc_url.is_empty() {
+        return Err(ContentError::InvalidArgument(
+            "issuer vc_url must not be empty".to_string(),
+        ));
+    }
+    TRUSTED_ISSUERS.with_borrow_mut(|trusted| trusted.insert(issuer.vc_url, issuer.canister_id));
+    Ok(())
+}
+
+#[update]
+#[candid_method]
+fn remove_trusted_issuer(req: RemoveTrustedIssuerRequest) -> Result<(), ContentError> {
+    check_method_policy("remove_trusted_issuer")?;
+    match TRUSTED_ISSUERS.with_borrow_mut(|trusted| trusted.remove(&req.vc_url)) {
+        Some(_) => Ok(()),
+        None => Err(ContentError::NotFound(format!(
+            "issuer {} not found in the trusted issuers",
+            req.vc_url
         ))),
     }
 }
 
-// TODO: restrict or remove `configure()`.
+/// Lists the issuers configured in RpInit and those added via `add_trusted_issuer`,
+/// but not the issuers listed only in the issuer registry.
+#[query]
+#[candid_method(query)]
+fn list_trusted_issuers() -> TrustedIssuersList {
+    enforce_method_policy("list_trusted_issuers");
+    let (mut issuers, issuer_registry) = CONFIG.with_borrow(|config| {
+        let config = config.get();
+        (config.issuers.clone(), config.issuer_registry.clone())
+    });
+    TRUSTED_ISSUERS.with_borrow(|trusted| issuers.extend(trusted.iter()));
+    TrustedIssuersList {
+        issuers: issuers
+            .into_iter()
+            .map(|(vc_url, canister_id)| IssuerData {
+                vc_url,
+                canister_id,
+            })
+            .collect(),
+        issuer_registry,
+    }
+}
+
+/// Registry interface: returns the issuer with the given origin, if it is trusted by this RP.
+/// Lookups are answered from the local trust list only, so that an RP can serve as
+/// the issuer registry of other RPs without recursive lookups.
+#[query]
+#[candid_method(query)]
+fn lookup_trusted_issuer(vc_url: String) -> Option<IssuerData> {
+    enforce_method_policy("lookup_trusted_issuer");
+    let canister_id = CONFIG
+        .with_borrow(|config| config.get().issuers.get(&vc_url).cloned())
+        .or_else(|| TRUSTED_ISSUERS.with_borrow(|trusted| trusted.get(&vc_url)))?;
+    Some(IssuerData {
+        vc_url,
+        canister_id,
+    })
+}
+
+/// Sets the limits of the update calls open to anyone, only for controllers.
+#[update]
+#[candid_method]
+fn set_rate_limits(limits: RateLimits) -> Result<(), ContentError> {
+    check_method_policy("set_rate_limits")?;
+    CONFIG
+        .with_borrow_mut(|config_cell| {
+            let mut config = config_cell.get().clone();
+            config.rate_limits = Some(limits);
+            config_cell.set(config)
+        })
+        .expect("failed to set rate limits");
+    Ok(())
+}
+
+#[query]
+#[candid_method(query)]
+fn get_rate_limits() -> RateLimits {
+    enforce_method_policy("get_rate_limits");
+    rate_limits()
+}
+
+/// Replaces the configuration of the RP, only for controllers.
 #[update]
 #[candid_method]
 fn configure(init: RpInit) {
+    enforce_method_policy("configure");
     apply_config(init.into());
 }
 
@@ -321,11 +1785,14 @@ fn apply_config(config: RpConfig) {
     CONFIG
         .with_borrow_mut(|config_cell| config_cell.set(config))
         .expect("failed to apply RP config");
+    // The registry may have changed.
+    REGISTRY_CACHE.with_borrow_mut(|cache| cache.clear());
 }
 
 #[update]
 #[candid_method]
 async fn upload_images(_req: UploadImagesRequest) -> Result<ImagesList, ContentError> {
+    check_method_policy("upload_images")?;
     panic!("Not implemented");
 }
 
@@ -338,6 +1805,7 @@ fn update_root_hash() {
 #[query]
 #[candid_method(query)]
 pub fn http_request(req: HttpRequest) -> HttpResponse {
+    enforce_method_policy("http_request");
     let parts: Vec<&str> = req.url.split('?').collect();
     let path = parts[0];
     let maybe_asset = ASSETS
@@ -394,8 +1862,9 @@ fn fixup_html(html: &str) -> String {
 
 #[cfg(test)]
 mod test {
-    use crate::__export_service;
+    use crate::{__export_service, METHOD_POLICIES};
     use candid_parser::utils::{service_equal, CandidSource};
+    use std::collections::BTreeSet;
     use std::path::Path;
 
     /// Checks candid interface type equality by making sure that the service in the did file is
@@ -414,4 +1883,25 @@ mod test {
             )
         });
     }
+
+    /// Checks that `METHOD_POLICIES` has exactly one policy for every method of the interface,
+    /// as `inspect_message` rejects the methods without a policy.
+    #[test]
+    fn should_have_a_policy_for_every_method() {
+        let canister_interface = __export_service();
+        let (env, actor) = CandidSource::Text(&canister_interface)
+            .load()
+            .expect("failed to load the canister interface");
+        let actor = actor.expect("missing service");
+        let methods: BTreeSet<&str> = env
+            .as_service(&actor)
+            .expect("invalid service")
+            .iter()
+            .map(|(method, _)| method.as_str())
+            .collect();
+        let policy_methods: BTreeSet<&str> =
+            METHOD_POLICIES.iter().map(|policy| policy.method).collect();
+        assert_eq!(policy_methods.len(), METHOD_POLICIES.len());
+        assert_eq!(policy_methods, methods);
+    }
 }
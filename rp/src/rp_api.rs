@@ -1,5 +1,6 @@
 use candid::{CandidType, Deserialize, Principal};
 use ic_verifiable_credentials::issuer_api::CredentialSpec;
+use rate_limit::TokenBucketConfig;
 use serde_bytes::ByteBuf;
 
 /// Types for requesting a list of available images.
@@ -23,21 +24,40 @@ pub struct ImagesList {
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
 pub struct ListExclusiveContentRequest {
     pub owned_by: Option<Principal>,
+    /// Only content items that have a requirement with this credential type.
+    pub credential_type: Option<String>,
+    /// Only content items that have a requirement with this credential issuer.
+    pub credential_issuer: Option<Principal>,
+    /// The `next_cursor` of the previous page, if any.
+    pub cursor: Option<String>,
+    /// Maximal number of items to return (default: `DEFAULT_CONTENT_PAGE_SIZE`).
+    pub limit: Option<u32>,
 }
 
+pub const DEFAULT_CONTENT_PAGE_SIZE: u32 = 50;
+pub const MAX_CONTENT_PAGE_SIZE: u32 = 100;
+
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
 pub struct ContentData {
+    /// Identifier of the content item, generated by the RP.
+    pub content_id: String,
     pub owner: Principal,
     pub content_name: String,
     pub created_timestamp_ns: u64,
     pub url: String,
     pub credential_spec: CredentialSpec,
     pub credential_issuer: Principal,
+    pub credential_policy: Option<CredentialPolicy>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
 pub struct ExclusiveContentList {
+    /// The matching content items, newest first.
     pub content_items: Vec<ContentData>,
+    /// Cursor for requesting the next page, if there are more items.
+    /// Pages can have fewer items than requested (even none) if many items are filtered out,
+    /// so the end of the list is reached only if there is no cursor.
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
@@ -46,6 +66,94 @@ pub struct AddExclusiveContentRequest {
     pub url: String,
     pub credential_spec: CredentialSpec,
     pub credential_issuer: Principal,
+    /// If present, the access to the content is governed by this policy
+    /// instead of the single requirement given by `credential_spec` and `credential_issuer`.
+    pub credential_policy: Option<CredentialPolicy>,
+}
+
+/// Replaces the content item with the given id; only the owner can update a content item.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct UpdateExclusiveContentRequest {
+    pub content_id: String,
+    pub content_name: String,
+    pub url: String,
+    pub credential_spec: CredentialSpec,
+    pub credential_issuer: Principal,
+    pub credential_policy: Option<CredentialPolicy>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct RemoveExclusiveContentRequest {
+    pub content_id: String,
+}
+
+/// Types for specifying combinations of credentials required for accessing content.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct CredentialRequirement {
+    pub credential_spec: CredentialSpec,
+    pub credential_issuer: Principal,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub enum CredentialPolicy {
+    Requirement(CredentialRequirement),
+    AllOf(Vec<CredentialPolicy>),
+    AnyOf(Vec<CredentialPolicy>),
+}
+
+/// Upper bound on the number of requirements in a policy.
+pub const MAX_POLICY_REQUIREMENTS: usize = 16;
+
+impl CredentialPolicy {
+    /// Returns the requirements of the policy, in order of their appearance.
+    pub fn requirements(&self) -> Vec<&CredentialRequirement> {
+        match self {
+            CredentialPolicy::Requirement(requirement) => vec![requirement],
+            CredentialPolicy::AllOf(policies) | CredentialPolicy::AnyOf(policies) => policies
+                .iter()
+                .flat_map(|policy| policy.requirements())
+                .collect(),
+        }
+    }
+
+    /// Returns true if the policy is satisfied, given a predicate that tells which
+    /// requirements are met.
+    pub fn is_satisfied_by(&self, is_met: &impl Fn(&CredentialRequirement) -> bool) -> bool {
+        match self {
+            CredentialPolicy::Requirement(requirement) => is_met(requirement),
+            CredentialPolicy::AllOf(policies) => {
+                policies.iter().all(|policy| policy.is_satisfied_by(is_met))
+            }
+            CredentialPolicy::AnyOf(policies) => {
+                policies.iter().any(|policy| policy.is_satisfied_by(is_met))
+            }
+        }
+    }
+
+    /// Checks that the policy is well-formed, i.e. it has no empty combinations,
+    /// and it contains at most `MAX_POLICY_REQUIREMENTS` requirements.
+    pub fn validate(&self) -> Result<(), String> {
+        fn check_non_empty(policy: &CredentialPolicy) -> Result<(), String> {
+            match policy {
+                CredentialPolicy::Requirement(_) => Ok(()),
+                CredentialPolicy::AllOf(policies) | CredentialPolicy::AnyOf(policies) => {
+                    if policies.is_empty() {
+                        return Err("empty combination of requirements".to_string());
+                    }
+                    policies.iter().try_for_each(check_non_empty)
+                }
+            }
+        }
+        check_non_empty(self)?;
+        let count = self.requirements().len();
+        if count > MAX_POLICY_REQUIREMENTS {
+            return Err(format!(
+                "too many requirements: {} (at most {} allowed)",
+                count, MAX_POLICY_REQUIREMENTS
+            ));
+        }
+        Ok(())
+    }
 }
 
 // Types related to HTTP-endpoint.
@@ -72,7 +180,69 @@ pub enum ContentError {
     NotAuthorized(String),
     AlreadyExists(String),
     NotFound(String),
+    InvalidArgument(String),
     Internal(String),
+    RateLimited(String),
+}
+
+/// Types for protecting VP validation against replays, and for granting access to content.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct VpChallenge {
+    pub nonce: String,
+    pub expires_timestamp_ns: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct AccessGrant {
+    pub grant_id: String,
+    pub expires_timestamp_ns: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct CheckAccessRequest {
+    pub grant_id: String,
+    pub content_id: String,
+}
+
+/// Types for validating a set of VPs against the policy of a content item.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct PresentedVp {
+    pub vp_jwt: String,
+    pub issuer_origin: String,
+    pub issuer_canister_id: Option<Principal>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ValidateContentAccessRequest {
+    pub content_id: String,
+    pub effective_vc_subject: Principal,
+    pub presentations: Vec<PresentedVp>,
+    /// Nonce of a challenge obtained via `get_vp_challenge` before starting the VC flows.
+    pub challenge: String,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct RequirementResult {
+    pub requirement: CredentialRequirement,
+    pub met: bool,
+    /// Why the requirement is not met by the presentations, if it is not met.
+    pub errors: Vec<PresentationError>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct PresentationError {
+    /// Position of the presentation in `ValidateContentAccessRequest.presentations`.
+    pub presentation_index: u32,
+    pub error: VpValidationError,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ContentAccessData {
+    pub policy_satisfied: bool,
+    /// Results for the requirements of the policy, in order of their appearance.
+    pub requirements: Vec<RequirementResult>,
+    /// Grant for accessing the content item, if the policy is satisfied.
+    pub access_grant: Option<AccessGrant>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
@@ -82,15 +252,108 @@ pub struct ValidateVpRequest {
     pub credential_spec: CredentialSpec,
     pub issuer_origin: String,
     pub issuer_canister_id: Option<Principal>,
+    /// Nonce of a challenge obtained via `get_vp_challenge` before starting the VC flow.
+    pub challenge: String,
+}
+
+/// Claims of a successfully validated VP.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ValidatedVpData {
+    /// The principal for which the VP was presented.
+    pub subject: Principal,
+    /// The id alias which links the subject to the requested credential.
+    pub id_alias: Principal,
+    pub issuer_origin: String,
+    pub credential_issuer: Principal,
+    /// The type and the arguments of the requested credential.
+    pub credential_spec: CredentialSpec,
+    pub expires_timestamp_ns: u64,
+    /// Grant for accessing content that requires the validated credential.
+    pub access_grant: AccessGrant,
 }
 
-#[derive(CandidType, Deserialize)]
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub enum VpValidationError {
+    UnsupportedIssuer(String),
+    IssuerMismatch(String),
+    InvalidPresentation(String),
+    InvalidSignature(String),
+    Expired(String),
+    SubjectMismatch(String),
+    SpecMismatch(String),
+    InvalidChallenge(String),
+    AlreadyUsed(String),
+    UnverifiedIssuer(String),
+}
+
+impl From<vc_verifier::VpValidationError> for VpValidationError {
+    fn from(err: vc_verifier::VpValidationError) -> Self {
+        match err {
+            vc_verifier::VpValidationError::UnsupportedIssuer(e) => {
+                VpValidationError::UnsupportedIssuer(e)
+            }
+            vc_verifier::VpValidationError::IssuerMismatch(e) => {
+                VpValidationError::IssuerMismatch(e)
+            }
+            vc_verifier::VpValidationError::InvalidPresentation(e) => {
+                VpValidationError::InvalidPresentation(e)
+            }
+            vc_verifier::VpValidationError::InvalidSignature(e) => {
+                VpValidationError::InvalidSignature(e)
+            }
+            vc_verifier::VpValidationError::Expired(e) => VpValidationError::Expired(e),
+            vc_verifier::VpValidationError::SubjectMismatch(e) => {
+                VpValidationError::SubjectMismatch(e)
+            }
+            vc_verifier::VpValidationError::SpecMismatch(e) => VpValidationError::SpecMismatch(e),
+        }
+    }
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
 pub struct IssuerData {
     pub vc_url: String,
     pub canister_id: Principal,
 }
 
-#[derive(CandidType, Deserialize)]
+/// Arguments of `get_issuer_verification` of the issuer canister.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct GetIssuerVerificationRequest {
+    pub issuer: Principal,
+    pub credential_type: String,
+}
+
+/// Verification of an issuer principal for a credential type, as attested by the issuer canister.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct IssuerVerificationData {
+    pub issuer: Principal,
+    pub credential_type: String,
+    pub verified_by: Principal,
+    pub verified_timestamp_ns: u64,
+    pub trusted: bool,
+}
+
+/// Types for managing the issuers trusted by the RP.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct RemoveTrustedIssuerRequest {
+    pub vc_url: String,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct TrustedIssuersList {
+    pub issuers: Vec<IssuerData>,
+    pub issuer_registry: Option<IssuerRegistry>,
+}
+
+/// A registry canister which lists trusted issuers, cf. `lookup_trusted_issuer` in rp.did.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct IssuerRegistry {
+    pub canister_id: Principal,
+    /// How long the results of lookups in the registry are cached (default: 1 hour).
+    pub cache_ttl_ns: Option<u64>,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
 pub struct RpInit {
     /// Root of trust for checking canister signatures.
     pub ic_root_key_der: Vec<u8>,
@@ -104,4 +367,45 @@ pub struct RpInit {
 
     /// Derivation origin used to log in Internet Identity
     pub derivation_origin: String,
+
+    /// Registry of additionally trusted issuers, if any.
+    pub issuer_registry: Option<IssuerRegistry>,
+
+    /// Limits of the update calls open to anyone, defaults apply if not set.
+    pub rate_limits: Option<RateLimits>,
+
+    /// If true, presented credentials must name their issuer principal (e.g. the group owner
+    /// at the meta-issuer), which must be verified for the credential type by the issuer
+    /// canister, cf. `IssuerVerificationData`. Not required if not set.
+    pub require_verified_issuers: Option<bool>,
+
+    /// If true (the default), the requested credential must state the nonce of the challenge
+    /// used to present it in the `vc.nonce`-claim, cf. `get_vp_challenge`. Can be disabled
+    /// for issuers which cannot bind a nonce into their credentials.
+    pub require_nonce: Option<bool>,
+}
+
+/// Limits of the update calls open to anyone, which can be tuned by the controllers
+/// via `set_rate_limits`. Limits which are not set do not apply.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct RateLimits {
+    /// Calls of `add_exclusive_content` and `update_exclusive_content` per caller.
+    pub per_caller: Option<TokenBucketConfig>,
+    /// Calls of `add_exclusive_content` and `update_exclusive_content` by all callers together.
+    pub global: Option<TokenBucketConfig>,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        Self {
+            per_caller: Some(TokenBucketConfig {
+                capacity: 10,
+                refill_period_ns: 6_000_000_000,
+            }),
+            global: Some(TokenBucketConfig {
+                capacity: 1_000,
+                refill_period_ns: 10_000_000,
+            }),
+        }
+    }
 }
@@ -1,23 +1,24 @@
 //! Tests related to general dapp management.
 
+use candid::Reserved;
 use canister_tests::api::http_request;
 use canister_tests::framework::{env, principal_1, principal_2, time};
 use ic_cdk::api::management_canister::provisional::CanisterId;
 use ic_response_verification::types::VerificationInfo;
 use ic_response_verification::verify_request_response_pair;
-use ic_test_state_machine_client::{CallError, StateMachine};
+use ic_test_state_machine_client::{call_candid_as, CallError, StateMachine};
 use ic_verifiable_credentials::issuer_api::CredentialSpec;
 use internet_identity_interface::http_gateway::{HttpRequest, HttpResponse};
 
-use relying_party::rp_api::ContentData;
+use relying_party::rp_api::{AddExclusiveContentRequest, ContentData};
 use serde_bytes::ByteBuf;
 use std::time::Duration;
 
 #[allow(dead_code)]
 mod util;
 use crate::util::{
-    do_add_exclusive_content, do_list_exclusive_content, do_list_images, install_rp,
-    RELYING_PARTY_WASM,
+    do_add_exclusive_content, do_list_exclusive_content, do_list_images, install_canister,
+    install_rp, RELYING_PARTY_PREVIOUS_WASM, RELYING_PARTY_WASM,
 };
 
 /// Verifies that the expected assets is delivered and certified.
@@ -154,12 +155,14 @@ fn should_retain_data_after_upgrade() -> Result<(), CallError> {
         canister_id,
     );
     let expected_content_data = ContentData {
+        content_id: content_data.content_id.clone(),
         owner: caller,
         content_name: content_name.to_string(),
         created_timestamp_ns: content_data.created_timestamp_ns,
         url: url.to_string(),
         credential_spec,
         credential_issuer,
+        credential_policy: None,
     };
     let content_list = do_list_exclusive_content(&env, None, canister_id);
     assert_eq!(content_list.content_items.len(), 1);
@@ -178,3 +181,64 @@ fn should_retain_data_after_upgrade() -> Result<(), CallError> {
     assert_eq!(images_list.images.len(), 11);
     Ok(())
 }
+
+#[test]
+fn should_migrate_data_of_previous_release() -> Result<(), CallError> {
+    let env = env();
+    let canister_id = install_canister(&env, RELYING_PARTY_PREVIOUS_WASM.clone());
+    let caller = principal_1();
+    let credential_issuer = principal_2();
+    let credential_spec = CredentialSpec {
+        credential_type: "VerifiedData".to_string(),
+        arguments: None,
+    };
+    // The response type of the previous release may differ, so it is not decoded.
+    for content_name in ["First content", "Second content"] {
+        let _: (Reserved,) = call_candid_as(
+            &env,
+            canister_id,
+            caller,
+            "add_exclusive_content",
+            (AddExclusiveContentRequest {
+                content_name: content_name.to_string(),
+                url: "http://example.com".to_string(),
+                credential_spec: credential_spec.clone(),
+                credential_issuer,
+                credential_policy: None,
+            },),
+        )?;
+    }
+
+    // Upgrade to the current release, and let the migrations complete.
+    let arg = candid::encode_one("()").expect("error encoding issuer init arg as candid");
+    env.upgrade_canister(canister_id, RELYING_PARTY_WASM.clone(), arg, None)?;
+    env.tick();
+
+    // Check that the data survived the upgrade, and that the canister keeps working.
+    let content_list = do_list_exclusive_content(&env, Some(caller), canister_id);
+    let mut content_names: Vec<String> = content_list
+        .content_items
+        .iter()
+        .map(|item| item.content_name.clone())
+        .collect();
+    content_names.sort();
+    assert_eq!(content_names, vec!["First content", "Second content"]);
+    for item in &content_list.content_items {
+        assert_eq!(item.owner, caller);
+        assert_eq!(item.credential_spec, credential_spec);
+        assert_eq!(item.credential_issuer, credential_issuer);
+    }
+    do_add_exclusive_content(
+        "Third content",
+        "http://example.com",
+        &credential_spec,
+        credential_issuer,
+        caller,
+        &env,
+        canister_id,
+    );
+    let content_list = do_list_exclusive_content(&env, Some(caller), canister_id);
+    assert_eq!(content_list.content_items.len(), 3);
+    assert_eq!(do_list_images(&env, canister_id).images.len(), 11);
+    Ok(())
+}
@@ -3,15 +3,25 @@ use assert_matches::assert_matches;
 use candid::Principal;
 use canister_tests::framework::{env, principal_1, principal_2, test_principal};
 use ic_canister_sig_creation::IC_ROOT_PK_DER;
+use ic_test_state_machine_client::{CallError, StateMachine};
 use ic_verifiable_credentials::issuer_api::{ArgumentValue, CredentialSpec};
 use ic_verifiable_credentials::II_ISSUER_URL;
-use relying_party::rp_api::{ContentData, ContentError, IssuerData, RpInit, ValidateVpRequest};
+use rate_limit::TokenBucketConfig;
+use relying_party::rp_api::{
+    AddExclusiveContentRequest, CheckAccessRequest, ContentData, ContentError, CredentialPolicy,
+    CredentialRequirement, IssuerData, IssuerRegistry, ListExclusiveContentRequest,
+    PresentationError, PresentedVp, RateLimits, RemoveExclusiveContentRequest,
+    RemoveTrustedIssuerRequest, RpInit, UpdateExclusiveContentRequest,
+    ValidateContentAccessRequest, ValidateVpRequest, ValidatedVpData, VpValidationError,
+};
 use std::collections::{HashMap, HashSet};
+use std::time::{Duration, UNIX_EPOCH};
 
 #[allow(dead_code)]
 mod util;
 use crate::util::{
-    api, do_add_exclusive_content, do_list_exclusive_content, do_list_images, install_rp,
+    api, do_add_exclusive_content, do_get_vp_challenge, do_list_exclusive_content, do_list_images,
+    install_rp,
 };
 
 #[test]
@@ -67,12 +77,14 @@ fn should_add_exclusive_content() {
         canister_id,
     );
     let expected_content_data = ContentData {
+        content_id: content_data.content_id.clone(),
         owner: caller,
         content_name: content_name.to_string(),
         created_timestamp_ns: content_data.created_timestamp_ns,
         url: url.to_string(),
         credential_spec,
         credential_issuer,
+        credential_policy: None,
     };
     assert_eq!(content_data, expected_content_data);
 }
@@ -101,12 +113,14 @@ fn should_list_exclusive_content() {
     );
     let content_list = do_list_exclusive_content(&env, None, canister_id);
     let expected_content_data = ContentData {
+        content_id: content_data.content_id.clone(),
         owner: caller,
         content_name: content_name.to_string(),
         created_timestamp_ns: content_data.created_timestamp_ns,
         url: url.to_string(),
         credential_spec,
         credential_issuer,
+        credential_policy: None,
     };
     assert_eq!(content_list.content_items.len(), 1);
     assert_eq!(content_list.content_items[0], expected_content_data);
@@ -152,12 +166,14 @@ fn should_list_exclusive_content_multiple_items() {
             canister_id,
         );
         let expected_content_data = ContentData {
+            content_id: content_data.content_id.clone(),
             owner: caller[i],
             content_name: content_name[i].to_string(),
             created_timestamp_ns: content_data.created_timestamp_ns,
             url: url[i].to_string(),
             credential_spec: credential_spec[i].clone(),
             credential_issuer,
+            credential_policy: None,
         };
         expected_list.insert(content_name[i].to_string(), expected_content_data);
     }
@@ -172,6 +188,365 @@ fn should_list_exclusive_content_multiple_items() {
     }
 }
 
+#[test]
+fn should_list_exclusive_content_with_filters() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+    let verified_data = CredentialSpec {
+        credential_type: "VerifiedData".to_string(),
+        arguments: None,
+    };
+    let other_data = CredentialSpec {
+        credential_type: "OtherData".to_string(),
+        arguments: None,
+    };
+    let item_1 = do_add_exclusive_content(
+        "Item 1",
+        "http://example.com",
+        &verified_data,
+        principal_2(),
+        principal_1(),
+        &env,
+        canister_id,
+    );
+    env.advance_time(Duration::from_secs(1));
+    let item_2 = do_add_exclusive_content(
+        "Item 2",
+        "http://example.com",
+        &other_data,
+        principal_2(),
+        principal_2(),
+        &env,
+        canister_id,
+    );
+    env.advance_time(Duration::from_secs(1));
+    let item_3 = do_add_exclusive_content(
+        "Item 3",
+        "http://example.com",
+        &other_data,
+        test_principal(42),
+        principal_1(),
+        &env,
+        canister_id,
+    );
+    let list = |req: ListExclusiveContentRequest| {
+        api::list_exclusive_content(&env, canister_id, &req)
+            .expect("API call failed")
+            .expect("Failed list_exclusive_content")
+            .content_items
+    };
+    let all_items = ListExclusiveContentRequest {
+        owned_by: None,
+        credential_type: None,
+        credential_issuer: None,
+        cursor: None,
+        limit: None,
+    };
+
+    assert_eq!(
+        list(ListExclusiveContentRequest {
+            owned_by: Some(principal_1()),
+            ..all_items.clone()
+        }),
+        vec![item_3.clone(), item_1.clone()]
+    );
+    assert_eq!(
+        list(ListExclusiveContentRequest {
+            credential_type: Some("OtherData".to_string()),
+            ..all_items.clone()
+        }),
+        vec![item_3.clone(), item_2.clone()]
+    );
+    assert_eq!(
+        list(ListExclusiveContentRequest {
+            credential_issuer: Some(principal_2()),
+            ..all_items.clone()
+        }),
+        vec![item_2, item_1]
+    );
+    assert_eq!(
+        list(ListExclusiveContentRequest {
+            owned_by: Some(principal_1()),
+            credential_type: Some("OtherData".to_string()),
+            ..all_items.clone()
+        }),
+        vec![item_3]
+    );
+    assert_eq!(
+        list(ListExclusiveContentRequest {
+            owned_by: Some(test_principal(42)),
+            ..all_items
+        }),
+        vec![]
+    );
+}
+
+#[test]
+fn should_list_exclusive_content_newest_first_in_pages() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+    let mut items = vec![];
+    for i in 0..5 {
+        items.push(
+            api::add_exclusive_content(
+                &env,
+                canister_id,
+                principal_1(),
+                add_exclusive_content_request(&format!("Item {}", i)),
+            )
+            .expect("API call failed")
+            .expect("Failed add_exclusive_content"),
+        );
+        env.advance_time(Duration::from_secs(1));
+    }
+    items.reverse();
+
+    let mut listed = vec![];
+    let mut cursor = None;
+    loop {
+        let page = api::list_exclusive_content(
+            &env,
+            canister_id,
+            &ListExclusiveContentRequest {
+                owned_by: Some(principal_1()),
+                credential_type: None,
+                credential_issuer: None,
+                cursor,
+                limit: Some(2),
+            },
+        )
+        .expect("API call failed")
+        .expect("Failed list_exclusive_content");
+        assert!(page.content_items.len() <= 2);
+        listed.extend(page.content_items);
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    assert_eq!(listed, items);
+
+    let result = api::list_exclusive_content(
+        &env,
+        canister_id,
+        &ListExclusiveContentRequest {
+            owned_by: None,
+            credential_type: None,
+            credential_issuer: None,
+            cursor: Some("not a cursor".to_string()),
+            limit: None,
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(ContentError::InvalidArgument(_)));
+}
+
+fn add_exclusive_content_request(content_name: &str) -> AddExclusiveContentRequest {
+    AddExclusiveContentRequest {
+        content_name: content_name.to_string(),
+        url: "http://example.com".to_string(),
+        credential_spec: CredentialSpec {
+            credential_type: "VerifiedData".to_string(),
+            arguments: None,
+        },
+        credential_issuer: principal_2(),
+        credential_policy: None,
+    }
+}
+
+#[test]
+fn should_not_add_exclusive_content_with_existing_name() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+    let req = add_exclusive_content_request("Some content name");
+
+    let content_data = api::add_exclusive_content(&env, canister_id, principal_1(), req.clone())
+        .expect("API call failed")
+        .expect("Failed add_exclusive_content");
+    let result = api::add_exclusive_content(&env, canister_id, principal_1(), req.clone())
+        .expect("API call failed");
+    assert_matches!(result, Err(ContentError::AlreadyExists(_)));
+
+    // Names are unique per owner, so another owner can use the same name.
+    let other_content_data = api::add_exclusive_content(&env, canister_id, principal_2(), req)
+        .expect("API call failed")
+        .expect("Failed add_exclusive_content");
+    assert_ne!(other_content_data.content_id, content_data.content_id);
+    assert_eq!(other_content_data.owner, principal_2());
+
+    let content_list = do_list_exclusive_content(&env, None, canister_id);
+    assert_eq!(content_list.content_items.len(), 2);
+    assert!(content_list.content_items.contains(&content_data));
+}
+
+#[test]
+fn should_not_add_exclusive_content_as_anonymous() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+
+    let result = api::add_exclusive_content(
+        &env,
+        canister_id,
+        Principal::anonymous(),
+        add_exclusive_content_request("Some content name"),
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(_)));
+    let content_list = do_list_exclusive_content(&env, None, canister_id);
+    assert!(content_list.content_items.is_empty());
+}
+
+#[test]
+fn should_reject_add_exclusive_content_with_oversized_argument() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+
+    let mut req = add_exclusive_content_request("Some content name");
+    req.url = format!("http://example.com/{}", "a".repeat(8 * 1024));
+    let result = api::add_exclusive_content(&env, canister_id, principal_1(), req);
+    // The call is rejected by `inspect_message`, before it reaches the endpoint.
+    assert_matches!(
+        result,
+        Err(CallError::UserError(e)) if e.description.contains("argument too large")
+    );
+    let content_list = do_list_exclusive_content(&env, None, canister_id);
+    assert!(content_list.content_items.is_empty());
+}
+
+#[test]
+fn should_update_exclusive_content() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+    let owner = principal_1();
+    let content_data = api::add_exclusive_content(
+        &env,
+        canister_id,
+        owner,
+        add_exclusive_content_request("Some content name"),
+    )
+    .expect("API call failed")
+    .expect("Failed add_exclusive_content");
+    api::add_exclusive_content(
+        &env,
+        canister_id,
+        owner,
+        add_exclusive_content_request("Other content name"),
+    )
+    .expect("API call failed")
+    .expect("Failed add_exclusive_content");
+
+    let mut update_req = UpdateExclusiveContentRequest {
+        content_id: content_data.content_id.clone(),
+        content_name: "Renamed content".to_string(),
+        url: "http://example.org".to_string(),
+        credential_spec: content_data.credential_spec.clone(),
+        credential_issuer: content_data.credential_issuer,
+        credential_policy: None,
+    };
+    let updated = api::update_exclusive_content(&env, canister_id, owner, &update_req)
+        .expect("API call failed")
+        .expect("Failed update_exclusive_content");
+    assert_eq!(
+        updated,
+        ContentData {
+            content_name: "Renamed content".to_string(),
+            url: "http://example.org".to_string(),
+            ..content_data.clone()
+        }
+    );
+    let content_list = do_list_exclusive_content(&env, None, canister_id);
+    assert!(content_list.content_items.contains(&updated));
+
+    // The new name must not be used by another item of the owner.
+    update_req.content_name = "Other content name".to_string();
+    let result = api::update_exclusive_content(&env, canister_id, owner, &update_req)
+        .expect("API call failed");
+    assert_matches!(result, Err(ContentError::AlreadyExists(_)));
+}
+
+#[test]
+fn should_remove_exclusive_content() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+    let owner = principal_1();
+    let content_data = api::add_exclusive_content(
+        &env,
+        canister_id,
+        owner,
+        add_exclusive_content_request("Some content name"),
+    )
+    .expect("API call failed")
+    .expect("Failed add_exclusive_content");
+
+    let remove_req = RemoveExclusiveContentRequest {
+        content_id: content_data.content_id,
+    };
+    api::remove_exclusive_content(&env, canister_id, owner, &remove_req)
+        .expect("API call failed")
+        .expect("Failed remove_exclusive_content");
+    let content_list = do_list_exclusive_content(&env, None, canister_id);
+    assert!(content_list.content_items.is_empty());
+
+    let result = api::remove_exclusive_content(&env, canister_id, owner, &remove_req)
+        .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotFound(_)));
+
+    // The name can be used again.
+    api::add_exclusive_content(
+        &env,
+        canister_id,
+        owner,
+        add_exclusive_content_request("Some content name"),
+    )
+    .expect("API call failed")
+    .expect("Failed add_exclusive_content");
+}
+
+#[test]
+fn should_not_update_or_remove_content_of_other_owner() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+    let content_data = api::add_exclusive_content(
+        &env,
+        canister_id,
+        principal_1(),
+        add_exclusive_content_request("Some content name"),
+    )
+    .expect("API call failed")
+    .expect("Failed add_exclusive_content");
+
+    for caller in [principal_2(), Principal::anonymous()] {
+        let result = api::update_exclusive_content(
+            &env,
+            canister_id,
+            caller,
+            &UpdateExclusiveContentRequest {
+                content_id: content_data.content_id.clone(),
+                content_name: content_data.content_name.clone(),
+                url: "http://example.org".to_string(),
+                credential_spec: content_data.credential_spec.clone(),
+                credential_issuer: content_data.credential_issuer,
+                credential_policy: None,
+            },
+        )
+        .expect("API call failed");
+        assert_matches!(result, Err(ContentError::NotAuthorized(_)));
+        let result = api::remove_exclusive_content(
+            &env,
+            canister_id,
+            caller,
+            &RemoveExclusiveContentRequest {
+                content_id: content_data.content_id.clone(),
+            },
+        )
+        .expect("API call failed");
+        assert_matches!(result, Err(ContentError::NotAuthorized(_)));
+    }
+
+    let content_list = do_list_exclusive_content(&env, None, canister_id);
+    assert_eq!(content_list.content_items, vec![content_data]);
+}
+
 fn get_validate_vp_request_and_rp_init() -> (ValidateVpRequest, RpInit) {
     let issuer_canister_id =
         Principal::from_text("qdiif-2iaaa-aaaap-ahjaq-cai").expect("wrong principal");
@@ -188,6 +563,7 @@ fn get_validate_vp_request_and_rp_init() -> (ValidateVpRequest, RpInit) {
         credential_spec: CredentialSpec { credential_type: "VerifiedAge".to_string(), arguments: Some(args) },
         issuer_origin: issuer_origin.to_string(),
         issuer_canister_id: Some(issuer_canister_id),
+        challenge: "".to_string(),
     };
 
     let rp_init = RpInit {
@@ -199,10 +575,26 @@ fn get_validate_vp_request_and_rp_init() -> (ValidateVpRequest, RpInit) {
             canister_id: issuer_canister_id,
         }],
         derivation_origin: "https://l7rua-raaaa-aaaap-ahh6a-cai.icp0.io".to_string(),
+        issuer_registry: None,
+        rate_limits: None,
+        require_verified_issuers: None,
+        // The credentials of the Dummy issuer do not state the nonce of a challenge.
+        require_nonce: Some(false),
     };
     (req, rp_init)
 }
 
+/// Obtains a challenge for the subject of the request and validates the VP as the subject.
+fn validate_ii_vp_as_subject(
+    env: &StateMachine,
+    canister_id: Principal,
+    mut req: ValidateVpRequest,
+) -> Result<ValidatedVpData, VpValidationError> {
+    let subject = req.effective_vc_subject;
+    req.challenge = do_get_vp_challenge(env, canister_id, subject).nonce;
+    api::validate_ii_vp(env, canister_id, subject, req).expect("API call failed")
+}
+
 #[test]
 fn should_verify_ii_vp() {
     let (req, rp_init) = get_validate_vp_request_and_rp_init();
@@ -210,9 +602,54 @@ fn should_verify_ii_vp() {
     let env = env();
     let canister_id = install_rp(&env, Some(rp_init));
 
-    let result =
-        api::validate_ii_vp(&env, canister_id, principal_1(), req).expect("API call failed");
-    assert!(result.is_ok(), "Validation failed: {:?}", result.err());
+    let validated_vp =
+        validate_ii_vp_as_subject(&env, canister_id, req.clone()).expect("Validation failed");
+    assert_eq!(validated_vp.subject, req.effective_vc_subject);
+    assert_eq!(
+        validated_vp.id_alias,
+        Principal::from_text("vzlav-qihwb-ytph2-okmop-45h5j-i6jbh-jxjil-t54xo-upw3l-5dlxj-zae")
+            .expect("wrong principal")
+    );
+    assert_eq!(validated_vp.issuer_origin, req.issuer_origin);
+    assert_eq!(Some(validated_vp.credential_issuer), req.issuer_canister_id);
+    assert_eq!(validated_vp.credential_spec, req.credential_spec);
+    assert_eq!(validated_vp.expires_timestamp_ns, 1_730_798_514_000_000_000);
+    assert!(validated_vp.access_grant.expires_timestamp_ns <= validated_vp.expires_timestamp_ns);
+}
+
+#[test]
+fn should_fail_verify_ii_vp_without_issuer_principal_if_verified_issuers_required() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(
+        &env,
+        Some(RpInit {
+            require_verified_issuers: Some(true),
+            ..rp_init
+        }),
+    );
+
+    // The credentials of the Dummy issuer do not name their issuer principal.
+    let result = validate_ii_vp_as_subject(&env, canister_id, req);
+    assert_matches!(result,
+        Err(VpValidationError::UnverifiedIssuer(e)) if e.contains("does not name its issuer principal"));
+}
+
+#[test]
+fn should_fail_verify_ii_vp_without_nonce_by_default() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(
+        &env,
+        Some(RpInit {
+            require_nonce: None,
+            ..rp_init
+        }),
+    );
+
+    let result = validate_ii_vp_as_subject(&env, canister_id, req);
+    assert_matches!(result,
+        Err(VpValidationError::InvalidChallenge(e)) if e.contains("does not state the nonce"));
 }
 
 #[test]
@@ -223,10 +660,9 @@ fn should_fail_verify_ii_vp_with_wrong_issuer_origin() {
     let env = env();
     let canister_id = install_rp(&env, Some(rp_init));
 
-    let result =
-        api::validate_ii_vp(&env, canister_id, principal_1(), req).expect("API call failed");
+    let result = validate_ii_vp_as_subject(&env, canister_id, req);
     assert_matches!(result,
-        Err(ContentError::NotAuthorized(e)) if e.contains("issuer not supported"));
+        Err(VpValidationError::UnsupportedIssuer(e)) if e.contains("issuer not supported"));
 }
 
 #[test]
@@ -237,10 +673,9 @@ fn should_fail_verify_ii_vp_with_wrong_issuer_canister_id() {
     let env = env();
     let canister_id = install_rp(&env, Some(rp_init));
 
-    let result =
-        api::validate_ii_vp(&env, canister_id, principal_1(), req).expect("API call failed");
+    let result = validate_ii_vp_as_subject(&env, canister_id, req);
     assert_matches!(result,
-        Err(ContentError::NotAuthorized(e)) if e.contains("wrong issuer canister id"));
+        Err(VpValidationError::IssuerMismatch(e)) if e.contains("wrong issuer canister id"));
 }
 
 #[test]
@@ -251,10 +686,9 @@ fn should_fail_verify_ii_vp_with_wrong_effective_vc_subject() {
     let env = env();
     let canister_id = install_rp(&env, Some(rp_init));
 
-    let result =
-        api::validate_ii_vp(&env, canister_id, principal_1(), req).expect("API call failed");
+    let result = validate_ii_vp_as_subject(&env, canister_id, req);
     assert_matches!(result,
-        Err(ContentError::NotAuthorized(e)) if e.contains("unexpected vc subject"));
+        Err(VpValidationError::SubjectMismatch(e)) if e.contains("unexpected vc subject"));
 }
 
 #[test]
@@ -268,10 +702,9 @@ fn should_fail_verify_ii_vp_with_wrong_credential_spec() {
     let env = env();
     let canister_id = install_rp(&env, Some(rp_init));
 
-    let result =
-        api::validate_ii_vp(&env, canister_id, principal_1(), req).expect("API call failed");
+    let result = validate_ii_vp_as_subject(&env, canister_id, req);
     assert_matches!(result,
-        Err(ContentError::NotAuthorized(e)) if e.contains("credential_type"));
+        Err(VpValidationError::SpecMismatch(e)) if e.contains("credential_type"));
 }
 
 #[test]
@@ -282,8 +715,721 @@ fn should_fail_verify_ii_vp_with_invalid_vp() {
     let env = env();
     let canister_id = install_rp(&env, Some(rp_init));
 
+    let result = validate_ii_vp_as_subject(&env, canister_id, req);
+    assert_matches!(result,
+        Err(VpValidationError::InvalidPresentation(e)) if e.contains("Failed to parse payload JSON"));
+}
+
+#[test]
+fn should_fail_verify_ii_vp_without_challenge() {
+    let (mut req, rp_init) = get_validate_vp_request_and_rp_init();
+    req.challenge = "some unknown nonce".to_string();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    let subject = req.effective_vc_subject;
+    let result = api::validate_ii_vp(&env, canister_id, subject, req).expect("API call failed");
+    assert_matches!(result,
+        Err(VpValidationError::InvalidChallenge(e)) if e.contains("unknown challenge"));
+}
+
+#[test]
+fn should_fail_verify_ii_vp_with_challenge_of_other_principal() {
+    let (mut req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    req.challenge = do_get_vp_challenge(&env, canister_id, principal_2()).nonce;
+    let subject = req.effective_vc_subject;
+    let result = api::validate_ii_vp(&env, canister_id, subject, req).expect("API call failed");
+    assert_matches!(result,
+        Err(VpValidationError::InvalidChallenge(e)) if e.contains("another principal"));
+}
+
+#[test]
+fn should_fail_verify_ii_vp_with_expired_challenge() {
+    let (mut req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    let subject = req.effective_vc_subject;
+    req.challenge = do_get_vp_challenge(&env, canister_id, subject).nonce;
+    env.advance_time(Duration::from_secs(6 * 60));
+    let result = api::validate_ii_vp(&env, canister_id, subject, req).expect("API call failed");
+    assert_matches!(result,
+        Err(VpValidationError::InvalidChallenge(e)) if e.contains("challenge expired"));
+}
+
+#[test]
+fn should_prune_expired_challenges() {
+    let (mut req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    let subject = req.effective_vc_subject;
+    req.challenge = do_get_vp_challenge(&env, canister_id, subject).nonce;
+    // Expired entries are removed periodically by a timer.
+    env.advance_time(Duration::from_secs(31 * 60));
+    env.tick();
+    let result = api::validate_ii_vp(&env, canister_id, subject, req).expect("API call failed");
+    assert_matches!(result,
+        Err(VpValidationError::InvalidChallenge(e)) if e.contains("unknown challenge"));
+}
+
+#[test]
+fn should_fail_verify_ii_vp_with_reused_challenge() {
+    let (mut req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    let subject = req.effective_vc_subject;
+    req.challenge = do_get_vp_challenge(&env, canister_id, subject).nonce;
+    api::validate_ii_vp(&env, canister_id, subject, req.clone())
+        .expect("API call failed")
+        .expect("Validation failed");
+    let result = api::validate_ii_vp(&env, canister_id, subject, req).expect("API call failed");
+    assert_matches!(result,
+        Err(VpValidationError::InvalidChallenge(e)) if e.contains("unknown challenge"));
+}
+
+#[test]
+fn should_fail_verify_ii_vp_issued_before_challenge() {
+    let (mut req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    // The requested credential of the VP is valid from 1730797614 to 1730798514.
+    env.set_time(UNIX_EPOCH + Duration::from_secs(1_730_797_700));
+    let subject = req.effective_vc_subject;
+    req.challenge = do_get_vp_challenge(&env, canister_id, subject).nonce;
+    let result = api::validate_ii_vp(&env, canister_id, subject, req).expect("API call failed");
+    assert_matches!(result,
+        Err(VpValidationError::InvalidChallenge(e)) if e.contains("issued before the challenge"));
+}
+
+#[test]
+fn should_fail_verify_ii_vp_presented_by_other_principal() {
+    let (mut req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    req.challenge = do_get_vp_challenge(&env, canister_id, principal_1()).nonce;
     let result =
         api::validate_ii_vp(&env, canister_id, principal_1(), req).expect("API call failed");
+    assert_matches!(result, Err(VpValidationError::SubjectMismatch(_)));
+}
+
+#[test]
+fn should_fail_verify_replayed_ii_vp() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    validate_ii_vp_as_subject(&env, canister_id, req.clone()).expect("Validation failed");
+    let result = validate_ii_vp_as_subject(&env, canister_id, req);
     assert_matches!(result,
-        Err(ContentError::NotAuthorized(e)) if e.contains("Failed to parse payload JSON"));
+        Err(VpValidationError::AlreadyUsed(e)) if e.contains("already been presented"));
+}
+
+#[test]
+fn should_not_issue_challenge_to_anonymous() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+
+    let result =
+        api::get_vp_challenge(&env, canister_id, Principal::anonymous()).expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(_)));
+}
+
+#[test]
+fn should_not_validate_or_check_access_as_anonymous() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    let result = api::validate_ii_vp(&env, canister_id, Principal::anonymous(), req);
+    assert_matches!(
+        result,
+        Err(CallError::UserError(e)) if e.description.contains("anonymous caller not permitted")
+    );
+    let result = api::check_access(
+        &env,
+        canister_id,
+        Principal::anonymous(),
+        &CheckAccessRequest {
+            grant_id: "some grant".to_string(),
+            content_id: "some content".to_string(),
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(e)) if e.contains("anonymous"));
+}
+
+#[test]
+fn should_access_content_with_grant() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+    let subject = req.effective_vc_subject;
+    let content_name = "Content for adults";
+    let content_data = do_add_exclusive_content(
+        content_name,
+        "http://example.com",
+        &req.credential_spec,
+        principal_2(),
+        principal_2(),
+        &env,
+        canister_id,
+    );
+    let other_content_data = do_add_exclusive_content(
+        "Other content",
+        "http://example.com",
+        &CredentialSpec {
+            credential_type: "VerifiedData".to_string(),
+            arguments: None,
+        },
+        principal_2(),
+        principal_2(),
+        &env,
+        canister_id,
+    );
+
+    let validated_vp =
+        validate_ii_vp_as_subject(&env, canister_id, req.clone()).expect("Validation failed");
+    let check_access_req = CheckAccessRequest {
+        grant_id: validated_vp.access_grant.grant_id.clone(),
+        content_id: content_data.content_id.clone(),
+    };
+    let accessed_content_data = api::check_access(&env, canister_id, subject, &check_access_req)
+        .expect("API call failed")
+        .expect("Failed check_access");
+    assert_eq!(accessed_content_data, content_data);
+
+    // The grant is bound to the principal that presented the VP.
+    let result = api::check_access(&env, canister_id, principal_1(), &check_access_req)
+        .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(e)) if e.contains("another principal"));
+
+    // The grant covers only content that requires the validated credential.
+    let result = api::check_access(
+        &env,
+        canister_id,
+        subject,
+        &CheckAccessRequest {
+            grant_id: validated_vp.access_grant.grant_id.clone(),
+            content_id: other_content_data.content_id,
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(e)) if e.contains("does not cover"));
+
+    // The grant is short-lived.
+    env.advance_time(Duration::from_secs(16 * 60));
+    let result =
+        api::check_access(&env, canister_id, subject, &check_access_req).expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(e)) if e.contains("expired"));
+}
+
+fn requirement(credential_spec: CredentialSpec) -> CredentialPolicy {
+    CredentialPolicy::Requirement(CredentialRequirement {
+        credential_spec,
+        credential_issuer: principal_2(),
+    })
+}
+
+fn verified_residence_spec(country: &str) -> CredentialSpec {
+    let mut args = HashMap::new();
+    args.insert(
+        "countryName".to_string(),
+        ArgumentValue::String(country.to_string()),
+    );
+    CredentialSpec {
+        credential_type: "VerifiedResidence".to_string(),
+        arguments: Some(args),
+    }
+}
+
+fn add_content_with_policy(
+    env: &StateMachine,
+    canister_id: Principal,
+    content_name: &str,
+    credential_spec: &CredentialSpec,
+    policy: CredentialPolicy,
+) -> Result<ContentData, ContentError> {
+    api::add_exclusive_content(
+        env,
+        canister_id,
+        principal_2(),
+        AddExclusiveContentRequest {
+            content_name: content_name.to_string(),
+            url: "http://example.com".to_string(),
+            credential_spec: credential_spec.clone(),
+            credential_issuer: principal_2(),
+            credential_policy: Some(policy),
+        },
+    )
+    .expect("API call failed")
+}
+
+#[test]
+fn should_add_exclusive_content_with_policy() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+    let policy = CredentialPolicy::AllOf(vec![
+        requirement(req.credential_spec.clone()),
+        CredentialPolicy::AnyOf(vec![
+            requirement(verified_residence_spec("Switzerland")),
+            requirement(verified_residence_spec("Liechtenstein")),
+        ]),
+    ]);
+
+    let content_data = add_content_with_policy(
+        &env,
+        canister_id,
+        "Content with policy",
+        &req.credential_spec,
+        policy.clone(),
+    )
+    .expect("Failed add_exclusive_content");
+    assert_eq!(content_data.credential_policy, Some(policy.clone()));
+
+    let content_list = do_list_exclusive_content(&env, None, canister_id);
+    assert_eq!(content_list.content_items, vec![content_data]);
+}
+
+#[test]
+fn should_not_add_exclusive_content_with_invalid_policy() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    for policy in [
+        CredentialPolicy::AnyOf(vec![]),
+        CredentialPolicy::AllOf(vec![
+            requirement(req.credential_spec.clone()),
+            CredentialPolicy::AnyOf(vec![]),
+        ]),
+        CredentialPolicy::AnyOf(vec![requirement(req.credential_spec.clone()); 17]),
+    ] {
+        let result = add_content_with_policy(
+            &env,
+            canister_id,
+            "Content with policy",
+            &req.credential_spec,
+            policy,
+        );
+        assert_matches!(result, Err(ContentError::InvalidArgument(_)));
+    }
+}
+
+#[test]
+fn should_validate_content_access_with_policy() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+    let subject = req.effective_vc_subject;
+    let age_and_residence = add_content_with_policy(
+        &env,
+        canister_id,
+        "Content for adult residents",
+        &req.credential_spec,
+        CredentialPolicy::AllOf(vec![
+            requirement(req.credential_spec.clone()),
+            requirement(verified_residence_spec("Switzerland")),
+        ]),
+    )
+    .expect("Failed add_exclusive_content");
+    let age_or_residence = add_content_with_policy(
+        &env,
+        canister_id,
+        "Content for adults or residents",
+        &req.credential_spec,
+        CredentialPolicy::AnyOf(vec![
+            requirement(verified_residence_spec("Switzerland")),
+            requirement(req.credential_spec.clone()),
+        ]),
+    )
+    .expect("Failed add_exclusive_content");
+    let presentations = vec![PresentedVp {
+        vp_jwt: req.vp_jwt.clone(),
+        issuer_origin: req.issuer_origin.clone(),
+        issuer_canister_id: req.issuer_canister_id,
+    }];
+
+    let challenge = do_get_vp_challenge(&env, canister_id, subject).nonce;
+
+    // The VP meets only one of the two requirements.
+    let access_data = api::validate_content_access(
+        &env,
+        canister_id,
+        subject,
+        &ValidateContentAccessRequest {
+            content_id: age_and_residence.content_id.clone(),
+            effective_vc_subject: subject,
+            presentations: presentations.clone(),
+            challenge: challenge.clone(),
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed validate_content_access");
+    assert!(!access_data.policy_satisfied);
+    assert_eq!(access_data.access_grant, None);
+    assert_eq!(access_data.requirements.len(), 2);
+    assert!(access_data.requirements[0].met);
+    assert_eq!(access_data.requirements[0].errors, vec![]);
+    assert!(!access_data.requirements[1].met);
+    assert_matches!(
+        access_data.requirements[1].errors.as_slice(),
+        [PresentationError {
+            presentation_index: 0,
+            error: VpValidationError::SpecMismatch(_)
+        }]
+    );
+
+    // One of the requirements is sufficient. As the policy was not satisfied before,
+    // neither the challenge nor the VP have been used up.
+    let request = ValidateContentAccessRequest {
+        content_id: age_or_residence.content_id.clone(),
+        effective_vc_subject: subject,
+        presentations,
+        challenge,
+    };
+    let access_data = api::validate_content_access(&env, canister_id, subject, &request)
+        .expect("API call failed")
+        .expect("Failed validate_content_access");
+    assert!(access_data.policy_satisfied);
+    assert!(!access_data.requirements[0].met);
+    assert!(access_data.requirements[1].met);
+    let access_grant = access_data.access_grant.expect("missing access grant");
+
+    // Now the challenge is consumed, and the VP can no longer meet any requirement.
+    let result = api::validate_content_access(&env, canister_id, subject, &request)
+        .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(e)) if e.contains("unknown challenge"));
+    let access_data = api::validate_content_access(
+        &env,
+        canister_id,
+        subject,
+        &ValidateContentAccessRequest {
+            challenge: do_get_vp_challenge(&env, canister_id, subject).nonce,
+            ..request
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed validate_content_access");
+    assert!(!access_data.policy_satisfied);
+    assert_matches!(
+        access_data.requirements[1].errors.as_slice(),
+        [PresentationError {
+            error: VpValidationError::AlreadyUsed(_),
+            ..
+        }]
+    );
+
+    // The grant covers only the validated content item.
+    let content_data = api::check_access(
+        &env,
+        canister_id,
+        subject,
+        &CheckAccessRequest {
+            grant_id: access_grant.grant_id.clone(),
+            content_id: age_or_residence.content_id.clone(),
+        },
+    )
+    .expect("API call failed")
+    .expect("Failed check_access");
+    assert_eq!(content_data, age_or_residence);
+    let result = api::check_access(
+        &env,
+        canister_id,
+        subject,
+        &CheckAccessRequest {
+            grant_id: access_grant.grant_id,
+            content_id: age_and_residence.content_id,
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(e)) if e.contains("does not cover"));
+}
+
+#[test]
+fn should_fail_validate_content_access_for_missing_content() {
+    let (req, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+    let subject = req.effective_vc_subject;
+
+    let result = api::validate_content_access(
+        &env,
+        canister_id,
+        subject,
+        &ValidateContentAccessRequest {
+            content_id: "missing-content-id".to_string(),
+            effective_vc_subject: subject,
+            presentations: vec![],
+            challenge: do_get_vp_challenge(&env, canister_id, subject).nonce,
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotFound(_)));
+}
+
+/// Returns the request and an RP configuration which does not trust the issuer of the VP,
+/// together with the issuer of the VP.
+fn get_validate_vp_request_and_rp_init_without_issuer() -> (ValidateVpRequest, RpInit, IssuerData) {
+    let (req, mut rp_init) = get_validate_vp_request_and_rp_init();
+    let issuer = rp_init.issuers.pop().expect("missing issuer");
+    (req, rp_init, issuer)
+}
+
+#[test]
+fn should_add_and_remove_trusted_issuer() {
+    let (_, rp_init, issuer) = get_validate_vp_request_and_rp_init_without_issuer();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+    // The canister is installed by the anonymous principal, which is thus its controller.
+    let controller = Principal::anonymous();
+
+    api::add_trusted_issuer(&env, canister_id, controller, &issuer)
+        .expect("API call failed")
+        .expect("Failed add_trusted_issuer");
+    let list = api::list_trusted_issuers(&env, canister_id).expect("API call failed");
+    assert_eq!(list.issuers, vec![issuer.clone()]);
+    assert_eq!(list.issuer_registry, None);
+    assert_eq!(
+        api::lookup_trusted_issuer(&env, canister_id, &issuer.vc_url).expect("API call failed"),
+        Some(issuer.clone())
+    );
+
+    let remove_req = RemoveTrustedIssuerRequest {
+        vc_url: issuer.vc_url.clone(),
+    };
+    api::remove_trusted_issuer(&env, canister_id, controller, &remove_req)
+        .expect("API call failed")
+        .expect("Failed remove_trusted_issuer");
+    let list = api::list_trusted_issuers(&env, canister_id).expect("API call failed");
+    assert!(list.issuers.is_empty());
+    assert_eq!(
+        api::lookup_trusted_issuer(&env, canister_id, &issuer.vc_url).expect("API call failed"),
+        None
+    );
+    let result = api::remove_trusted_issuer(&env, canister_id, controller, &remove_req)
+        .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotFound(_)));
+}
+
+#[test]
+fn should_not_manage_trusted_issuers_as_non_controller() {
+    let (_, rp_init, issuer) = get_validate_vp_request_and_rp_init_without_issuer();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    let result = api::add_trusted_issuer(&env, canister_id, principal_1(), &issuer)
+        .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(_)));
+    let result = api::remove_trusted_issuer(
+        &env,
+        canister_id,
+        principal_1(),
+        &RemoveTrustedIssuerRequest {
+            vc_url: issuer.vc_url,
+        },
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(_)));
+}
+
+#[test]
+fn should_configure_only_as_controller() {
+    let (_, rp_init) = get_validate_vp_request_and_rp_init();
+    let env = env();
+    let canister_id = install_rp(&env, None);
+
+    let result = api::configure_as(&env, canister_id, principal_1(), &rp_init);
+    assert_matches!(
+        result,
+        Err(CallError::UserError(e)) if e.description.contains("not a controller")
+    );
+    assert!(api::list_trusted_issuers(&env, canister_id)
+        .expect("API call failed")
+        .issuers
+        .is_empty());
+
+    // The canister is installed by the anonymous principal, which is thus its controller.
+    api::configure(&env, canister_id, &rp_init).expect("API call failed");
+    assert_eq!(
+        api::list_trusted_issuers(&env, canister_id)
+            .expect("API call failed")
+            .issuers,
+        rp_init.issuers
+    );
+}
+
+#[test]
+fn should_verify_ii_vp_with_added_trusted_issuer() {
+    let (req, rp_init, issuer) = get_validate_vp_request_and_rp_init_without_issuer();
+    let env = env();
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    let result = validate_ii_vp_as_subject(&env, canister_id, req.clone());
+    assert_matches!(result, Err(VpValidationError::UnsupportedIssuer(_)));
+
+    api::add_trusted_issuer(&env, canister_id, Principal::anonymous(), &issuer)
+        .expect("API call failed")
+        .expect("Failed add_trusted_issuer");
+    let validated_vp =
+        validate_ii_vp_as_subject(&env, canister_id, req).expect("Validation failed");
+    assert_eq!(validated_vp.credential_issuer, issuer.canister_id);
+}
+
+#[test]
+fn should_verify_ii_vp_with_issuer_from_registry() {
+    let (req, mut rp_init, issuer) = get_validate_vp_request_and_rp_init_without_issuer();
+    let env = env();
+    // Another RP serves as the registry.
+    let registry_id = install_rp(&env, Some(rp_init.clone()));
+    rp_init.issuer_registry = Some(IssuerRegistry {
+        canister_id: registry_id,
+        cache_ttl_ns: Some(Duration::from_secs(60).as_nanos() as u64),
+    });
+    let canister_id = install_rp(&env, Some(rp_init));
+
+    let result = validate_ii_vp_as_subject(&env, canister_id, req.clone());
+    assert_matches!(result, Err(VpValidationError::UnsupportedIssuer(_)));
+
+    api::add_trusted_issuer(&env, registry_id, Principal::anonymous(), &issuer)
+        .expect("API call failed")
+        .expect("Failed add_trusted_issuer");
+    // The negative result of the previous lookup is still cached.
+    let result = validate_ii_vp_as_subject(&env, canister_id, req.clone());
+    assert_matches!(result, Err(VpValidationError::UnsupportedIssuer(_)));
+
+    env.advance_time(Duration::from_secs(61));
+    let validated_vp =
+        validate_ii_vp_as_subject(&env, canister_id, req).expect("Validation failed");
+    assert_eq!(validated_vp.credential_issuer, issuer.canister_id);
+    // Issuers from the registry are not listed as trusted issuers of the RP.
+    let list = api::list_trusted_issuers(&env, canister_id).expect("API call failed");
+    assert!(list.issuers.is_empty());
+}
+
+#[test]
+fn should_rate_limit_adding_exclusive_content() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+    let limits = RateLimits {
+        per_caller: Some(TokenBucketConfig {
+            capacity: 2,
+            refill_period_ns: Duration::from_secs(10).as_nanos() as u64,
+        }),
+        global: None,
+    };
+    api::set_rate_limits(&env, canister_id, Principal::anonymous(), &limits)
+        .expect("API call failed")
+        .expect("Failed set_rate_limits");
+    assert_eq!(
+        api::get_rate_limits(&env, canister_id).expect("API call failed"),
+        limits
+    );
+
+    for i in 0..2 {
+        api::add_exclusive_content(
+            &env,
+            canister_id,
+            principal_1(),
+            add_exclusive_content_request(&format!("Item {}", i)),
+        )
+        .expect("API call failed")
+        .expect("Failed add_exclusive_content");
+    }
+    let result = api::add_exclusive_content(
+        &env,
+        canister_id,
+        principal_1(),
+        add_exclusive_content_request("Item 2"),
+    )
+    .expect("API call failed");
+    assert_matches!(result, Err(ContentError::RateLimited(_)));
+    // Other callers are not affected.
+    api::add_exclusive_content(
+        &env,
+        canister_id,
+        principal_2(),
+        add_exclusive_content_request("Item 2"),
+    )
+    .expect("API call failed")
+    .expect("Failed add_exclusive_content");
+
+    env.advance_time(Duration::from_secs(10));
+    api::add_exclusive_content(
+        &env,
+        canister_id,
+        principal_1(),
+        add_exclusive_content_request("Item 2"),
+    )
+    .expect("API call failed")
+    .expect("Failed add_exclusive_content");
+}
+
+#[test]
+fn should_rate_limit_updating_exclusive_content() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+    let limits = RateLimits {
+        per_caller: Some(TokenBucketConfig {
+            capacity: 2,
+            refill_period_ns: Duration::from_secs(10).as_nanos() as u64,
+        }),
+        global: None,
+    };
+    api::set_rate_limits(&env, canister_id, Principal::anonymous(), &limits)
+        .expect("API call failed")
+        .expect("Failed set_rate_limits");
+    let content_data = api::add_exclusive_content(
+        &env,
+        canister_id,
+        principal_1(),
+        add_exclusive_content_request("Item"),
+    )
+    .expect("API call failed")
+    .expect("Failed add_exclusive_content");
+    let update_req = UpdateExclusiveContentRequest {
+        content_id: content_data.content_id.clone(),
+        content_name: content_data.content_name.clone(),
+        url: "http://example.org".to_string(),
+        credential_spec: content_data.credential_spec.clone(),
+        credential_issuer: content_data.credential_issuer,
+        credential_policy: None,
+    };
+
+    api::update_exclusive_content(&env, canister_id, principal_1(), &update_req)
+        .expect("API call failed")
+        .expect("Failed update_exclusive_content");
+    let result = api::update_exclusive_content(&env, canister_id, principal_1(), &update_req)
+        .expect("API call failed");
+    assert_matches!(result, Err(ContentError::RateLimited(_)));
+
+    env.advance_time(Duration::from_secs(10));
+    api::update_exclusive_content(&env, canister_id, principal_1(), &update_req)
+        .expect("API call failed")
+        .expect("Failed update_exclusive_content");
+}
+
+#[test]
+fn should_not_set_rate_limits_as_non_controller() {
+    let env = env();
+    let canister_id = install_rp(&env, None);
+
+    let limits = RateLimits {
+        per_caller: None,
+        global: None,
+    };
+    let result =
+        api::set_rate_limits(&env, canister_id, principal_1(), &limits).expect("API call failed");
+    assert_matches!(result, Err(ContentError::NotAuthorized(_)));
+    assert_eq!(
+        api::get_rate_limits(&env, canister_id).expect("API call failed"),
+        RateLimits::default()
+    );
 }
@@ -6,7 +6,7 @@ use ic_verifiable_credentials::issuer_api::CredentialSpec;
 use lazy_static::lazy_static;
 use relying_party::rp_api::{
     AddExclusiveContentRequest, ContentData, ContentError, ExclusiveContentList, ImagesList,
-    ListExclusiveContentRequest, ListImagesRequest, RpInit,
+    ListExclusiveContentRequest, ListImagesRequest, RpInit, VpChallenge,
 };
 use std::path::PathBuf;
 
@@ -25,6 +25,19 @@ lazy_static! {
 
     };
 
+    /// Gzipped Wasm module of the previous RP release, for testing upgrades of its data.
+    pub static ref RELYING_PARTY_PREVIOUS_WASM: Vec<u8> = {
+        let def_path = PathBuf::from("./../").join("relying_party_previous.wasm.gz");
+        let err = format!("
+        Could not find Relying Party Wasm module of the previous release.
+        I will look for it at {:?}, and you can specify another path with the environment variable RELYING_PARTY_PREVIOUS_WASM (note that I run from {:?}).
+        You can build it by running ./scripts/build-previous-wasms.sh in <project-home>
+        ", &def_path,
+            &std::env::current_dir().map(|x| x.display().to_string()).unwrap_or_else(|_|
+                "an unknown directory".to_string()));
+                get_wasm_path("RELYING_PARTY_PREVIOUS_WASM".to_string(), &def_path).expect(&err)
+    };
+
 }
 
 pub fn install_canister(env: &StateMachine, wasm: Vec<u8>) -> CanisterId {
@@ -65,6 +78,7 @@ pub fn do_add_exclusive_content(
             url: url.to_string(),
             credential_spec: credential_spec.clone(),
             credential_issuer,
+            credential_policy: None,
         },
     )
     .expect("API call failed")
@@ -87,17 +101,36 @@ pub fn do_list_exclusive_content(
         canister_id,
         &ListExclusiveContentRequest {
             owned_by: maybe_owner,
+            credential_type: None,
+            credential_issuer: None,
+            cursor: None,
+            limit: None,
         },
     )
     .expect("API call failed")
     .expect("Failed list_exclusive_content")
 }
 
+pub fn do_get_vp_challenge(
+    env: &StateMachine,
+    canister_id: Principal,
+    caller: Principal,
+) -> VpChallenge {
+    api::get_vp_challenge(env, canister_id, caller)
+        .expect("API call failed")
+        .expect("Failed get_vp_challenge")
+}
+
 /// Relying party API.
 pub mod api {
     use super::*;
-    use ic_test_state_machine_client::query_candid;
-    use relying_party::rp_api::{AddExclusiveContentRequest, ContentData, ValidateVpRequest};
+    use ic_test_state_machine_client::{query_candid, query_candid_as};
+    use relying_party::rp_api::{
+        AddExclusiveContentRequest, CheckAccessRequest, ContentAccessData, ContentData, IssuerData,
+        RateLimits, RemoveExclusiveContentRequest, RemoveTrustedIssuerRequest, TrustedIssuersList,
+        UpdateExclusiveContentRequest, ValidateContentAccessRequest, ValidateVpRequest,
+        ValidatedVpData, VpValidationError,
+    };
 
     pub fn configure(
         env: &StateMachine,
@@ -107,6 +140,15 @@ pub mod api {
         call_candid(env, canister_id, "configure", (config,))
     }
 
+    pub fn configure_as(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        config: &RpInit,
+    ) -> Result<(), CallError> {
+        call_candid_as(env, canister_id, sender, "configure", (config,))
+    }
+
     pub fn list_images(
         env: &StateMachine,
         canister_id: CanisterId,
@@ -132,12 +174,105 @@ pub mod api {
         call_candid_as(env, canister_id, sender, "add_exclusive_content", (req,)).map(|(x,)| x)
     }
 
+    pub fn update_exclusive_content(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: &UpdateExclusiveContentRequest,
+    ) -> Result<Result<ContentData, ContentError>, CallError> {
+        call_candid_as(env, canister_id, sender, "update_exclusive_content", (req,)).map(|(x,)| x)
+    }
+
+    pub fn remove_exclusive_content(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: &RemoveExclusiveContentRequest,
+    ) -> Result<Result<(), ContentError>, CallError> {
+        call_candid_as(env, canister_id, sender, "remove_exclusive_content", (req,)).map(|(x,)| x)
+    }
+
+    pub fn get_vp_challenge(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+    ) -> Result<Result<VpChallenge, ContentError>, CallError> {
+        call_candid_as(env, canister_id, sender, "get_vp_challenge", ()).map(|(x,)| x)
+    }
+
+    pub fn check_access(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: &CheckAccessRequest,
+    ) -> Result<Result<ContentData, ContentError>, CallError> {
+        query_candid_as(env, canister_id, sender, "check_access", (req,)).map(|(x,)| x)
+    }
+
     pub fn validate_ii_vp(
         env: &StateMachine,
         canister_id: CanisterId,
         sender: Principal,
         req: ValidateVpRequest,
-    ) -> Result<Result<(), ContentError>, CallError> {
+    ) -> Result<Result<ValidatedVpData, VpValidationError>, CallError> {
         call_candid_as(env, canister_id, sender, "validate_ii_vp", (req,)).map(|(x,)| x)
     }
+
+    pub fn add_trusted_issuer(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        issuer: &IssuerData,
+    ) -> Result<Result<(), ContentError>, CallError> {
+        call_candid_as(env, canister_id, sender, "add_trusted_issuer", (issuer,)).map(|(x,)| x)
+    }
+
+    pub fn remove_trusted_issuer(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: &RemoveTrustedIssuerRequest,
+    ) -> Result<Result<(), ContentError>, CallError> {
+        call_candid_as(env, canister_id, sender, "remove_trusted_issuer", (req,)).map(|(x,)| x)
+    }
+
+    pub fn list_trusted_issuers(
+        env: &StateMachine,
+        canister_id: CanisterId,
+    ) -> Result<TrustedIssuersList, CallError> {
+        query_candid(env, canister_id, "list_trusted_issuers", ()).map(|(x,)| x)
+    }
+
+    pub fn lookup_trusted_issuer(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        vc_url: &str,
+    ) -> Result<Option<IssuerData>, CallError> {
+        query_candid(env, canister_id, "lookup_trusted_issuer", (vc_url,)).map(|(x,)| x)
+    }
+
+    pub fn validate_content_access(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        req: &ValidateContentAccessRequest,
+    ) -> Result<Result<ContentAccessData, ContentError>, CallError> {
+        call_candid_as(env, canister_id, sender, "validate_content_access", (req,)).map(|(x,)| x)
+    }
+
+    pub fn set_rate_limits(
+        env: &StateMachine,
+        canister_id: CanisterId,
+        sender: Principal,
+        limits: &RateLimits,
+    ) -> Result<Result<(), ContentError>, CallError> {
+        call_candid_as(env, canister_id, sender, "set_rate_limits", (limits,)).map(|(x,)| x)
+    }
+
+    pub fn get_rate_limits(
+        env: &StateMachine,
+        canister_id: CanisterId,
+    ) -> Result<RateLimits, CallError> {
+        query_candid(env, canister_id, "get_rate_limits", ()).map(|(x,)| x)
+    }
 }
@@ -0,0 +1,62 @@
+#!/usr/bin/env bash
+
+set -euo pipefail
+
+#########
+# USAGE #
+#########
+
+function usage() {
+    cat >&2 << EOF
+
+Usage:
+  $0 [--ref GIT_REF]
+
+Builds the Wasm modules of the issuer and of the relying party at the given git ref
+(defaults to "origin/main"), and stores them as meta_issuer_previous.wasm.gz and
+relying_party_previous.wasm.gz in the project root. They are used by the upgrade tests.
+
+As the builds include the frontends, the canisters must have been created on the dfx network
+given by DFX_NETWORK (defaults to "local"), cf. scripts/create-env-vars.sh.
+
+EOF
+}
+
+GIT_REF="origin/main"
+
+while [[ $# -gt 0  ]]
+do
+    case "$1" in
+        -h|--help)
+            usage
+            exit 0
+            ;;
+        --ref)
+            GIT_REF="${2:?missing value for '--ref'}"
+            shift; shift;
+            ;;
+        *)
+            echo "ERROR: unknown argument $1"
+            usage
+            echo
+            echo "Use '$0 --help' for more information"
+            exit 1
+            ;;
+    esac
+done
+
+PROJECT_DIR="$( cd "$( dirname "${BASH_SOURCE[0]}" )/.." && pwd )"
+WORKTREE_DIR="$(mktemp -d)"
+trap 'git -C "$PROJECT_DIR" worktree remove --force "$WORKTREE_DIR"' EXIT
+
+export DFX_NETWORK="${DFX_NETWORK:-local}"
+
+git -C "$PROJECT_DIR" worktree add --detach "$WORKTREE_DIR" "$GIT_REF"
+# The frontends' environment is read from the canister ids of the project.
+if [ -d "$PROJECT_DIR/.dfx" ]; then
+    ln -s "$PROJECT_DIR/.dfx" "$WORKTREE_DIR/.dfx"
+fi
+"$WORKTREE_DIR/issuer/build.sh"
+"$WORKTREE_DIR/rp/build.sh"
+mv "$WORKTREE_DIR/meta_issuer.wasm.gz" "$PROJECT_DIR/meta_issuer_previous.wasm.gz"
+mv "$WORKTREE_DIR/relying_party.wasm.gz" "$PROJECT_DIR/relying_party_previous.wasm.gz"
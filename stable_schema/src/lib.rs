@@ -0,0 +1,237 @@
+//! Versioned encoding of the records kept in stable memory, and migrations of stable data
+//! that run in batches across upgrades.
+//!
+//! Records are encoded as the magic bytes `VREC`, followed by the schema version of the record
+//! (2 bytes, big-endian) and the candid encoding of the record. Records stored before versioning
+//! are plain candid (starting with `DIDL`), and are decoded as version 0.
+//!
+//! Decoding older versions is handled by the record type itself (cf. `VersionedRecord`),
+//! so canisters keep working while their data is migrated. Migrations rewrite the stored data
+//! (e.g. to the current record versions), and run in batches: the first batches run
+//! in `post_upgrade`, and the remaining ones in subsequent timer calls.
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::storable::{Bound, Storable};
+use ic_stable_structures::{Memory, StableBTreeMap};
+use serde::de::DeserializeOwned;
+use serde_bytes::ByteBuf;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ops::Bound as RangeBound;
+use std::time::Duration;
+
+const MAGIC: &[u8; 4] = b"VREC";
+const HEADER_LENGTH: usize = MAGIC.len() + 2;
+
+/// Version of the records stored before versioning.
+pub const UNVERSIONED: u16 = 0;
+
+/// A record stored in stable memory, encoded with its schema version.
+pub trait VersionedRecord: CandidType + DeserializeOwned {
+    /// Name of the record type, used in error messages.
+    const NAME: &'static str;
+
+    /// Current schema version of the record. It must be incremented whenever the record changes
+    /// such that encodings of the previous version do not decode as the current type, e.g. when
+    /// a non-optional field is added.
+    const VERSION: u16;
+
+    /// Decodes the candid payload of a record with an older version.
+    /// The default decodes the payload as the current type, which suffices as long as only
+    /// optional fields have been added to the record.
+    fn decode_older(version: u16, payload: &[u8]) -> Result<Self, String> {
+        candid::decode_one(payload).map_err(|e| format!("version {}: {}", version, e))
+    }
+}
+
+/// Encodes the record with its current version.
+pub fn encode<T: VersionedRecord>(record: &T) -> Vec<u8> {
+    let payload = candid::encode_one(record)
+        .unwrap_or_else(|e| panic!("failed to encode {}: {}", T::NAME, e));
+    let mut bytes = Vec::with_capacity(HEADER_LENGTH + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&T::VERSION.to_be_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Decodes a record of the current or an older version, and traps on failure.
+pub fn decode<T: VersionedRecord>(bytes: &[u8]) -> T {
+    try_decode(bytes).unwrap_or_else(|e| panic!("failed to decode {}: {}", T::NAME, e))
+}
+
+/// Decodes a record of the current or an older version.
+pub fn try_decode<T: VersionedRecord>(bytes: &[u8]) -> Result<T, String> {
+    let (version, payload) = split_header(bytes);
+    match version.cmp(&T::VERSION) {
+        Ordering::Equal => candid::decode_one(payload).map_err(|e| e.to_string()),
+        Ordering::Less => T::decode_older(version, payload),
+        Ordering::Greater => Err(format!(
+            "version {} is newer than the supported version {}",
+            version,
+            T::VERSION
+        )),
+    }
+}
+
+/// Returns the version of an encoded record.
+pub fn version(bytes: &[u8]) -> u16 {
+    split_header(bytes).0
+}
+
+fn split_header(bytes: &[u8]) -> (u16, &[u8]) {
+    if bytes.len() >= HEADER_LENGTH && bytes.starts_with(MAGIC) {
+        let version = u16::from_be_bytes([bytes[MAGIC.len()], bytes[MAGIC.len() + 1]]);
+        (version, &bytes[HEADER_LENGTH..])
+    } else {
+        (UNVERSIONED, bytes)
+    }
+}
+
+/// Progress of the migrations of a canister, to be kept in stable memory.
+#[derive(CandidType, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct MigrationState {
+    /// Number of completed migrations, i.e. the index of the next migration to run.
+    pub completed: u32,
+    /// Position within the next migration, if it has been started.
+    pub cursor: Option<ByteBuf>,
+    /// Number of items migrated so far by the next migration.
+    pub migrated_count: u64,
+}
+
+impl MigrationState {
+    /// State of a freshly installed canister, whose data needs no migrations.
+    pub fn completed(migrations: &[Migration]) -> Self {
+        Self {
+            completed: migrations.len() as u32,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_complete(&self, migrations: &[Migration]) -> bool {
+        self.completed as usize >= migrations.len()
+    }
+}
+
+impl VersionedRecord for MigrationState {
+    const NAME: &'static str = "MigrationState";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for MigrationState {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Result of a batch of a migration.
+pub struct MigrationBatch {
+    /// Position after the last migrated item, or `None` if the migration is complete.
+    pub cursor: Option<Vec<u8>>,
+    /// Number of items migrated in the batch.
+    pub migrated_count: u64,
+}
+
+/// A migration of stable data. Migrations are identified by their position in the list
+/// of migrations of a canister, so new migrations must be appended to the list.
+pub struct Migration {
+    pub name: &'static str,
+    /// Migrates up to `batch_size` items after the given cursor (from the start, if `None`).
+    pub run_batch: fn(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch,
+}
+
+/// Runs at most `max_batches` batches of the pending migrations, in order, and updates `state`.
+/// Returns whether all migrations are complete.
+pub fn run_migrations(
+    migrations: &[Migration],
+    state: &mut MigrationState,
+    batch_size: usize,
+    max_batches: usize,
+) -> bool {
+    for _ in 0..max_batches {
+        let Some(migration) = migrations.get(state.completed as usize) else {
+            break;
+        };
+        let cursor = state.cursor.take().map(ByteBuf::into_vec);
+        let batch = (migration.run_batch)(cursor, batch_size);
+        state.migrated_count += batch.migrated_count;
+        match batch.cursor {
+            Some(cursor) => state.cursor = Some(ByteBuf::from(cursor)),
+            None => {
+                state.completed += 1;
+                state.migrated_count = 0;
+            }
+        }
+    }
+    state.is_complete(migrations)
+}
+
+/// Rewrites up to `batch_size` entries of the map after the given cursor, which re-encodes
+/// their values with the current versions.
+pub fn rewrite_batch<K, V, M>(
+    map: &mut StableBTreeMap<K, V, M>,
+    cursor: Option<Vec<u8>>,
+    batch_size: usize,
+) -> MigrationBatch
+where
+    K: Storable + Ord + Clone,
+    V: Storable,
+    M: Memory,
+{
+    let start = match cursor {
+        Some(cursor) => RangeBound::Excluded(K::from_bytes(Cow::Owned(cursor))),
+        None => RangeBound::Unbounded,
+    };
+    let entries: Vec<(K, V)> = map
+        .range((start, RangeBound::Unbounded))
+        .take(batch_size)
+        .collect();
+    let migrated_count = entries.len() as u64;
+    let mut last_key = None;
+    for (key, value) in entries {
+        last_key = Some(key.to_bytes().into_owned());
+        map.insert(key, value);
+    }
+    MigrationBatch {
+        cursor: if migrated_count < batch_size as u64 {
+            None
+        } else {
+            last_key
+        },
+        migrated_count,
+    }
+}
+
+/// Runs the migrations of a canister, batched across timer calls.
+pub struct Migrator {
+    pub migrations: &'static [Migration],
+    pub load_state: fn() -> MigrationState,
+    pub store_state: fn(MigrationState),
+    pub batch_size: usize,
+    /// Max. number of batches per call, to stay within the instruction limit.
+    pub batches_per_call: usize,
+}
+
+impl Migrator {
+    /// Runs the next batches of the pending migrations, and schedules a timer for the remaining
+    /// ones. To be called in `post_upgrade`.
+    pub fn run(&'static self) {
+        let mut state = (self.load_state)();
+        let complete = run_migrations(
+            self.migrations,
+            &mut state,
+            self.batch_size,
+            self.batches_per_call,
+        );
+        (self.store_state)(state);
+        if !complete {
+            ic_cdk_timers::set_timer(Duration::ZERO, || self.run());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,216 @@
+//! Unit tests of versioned records and migrations.
+use crate::{
+    decode, encode, rewrite_batch, run_migrations, try_decode, version, Migration, MigrationBatch,
+    MigrationState, VersionedRecord, UNVERSIONED,
+};
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::storable::{Bound, Storable};
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap};
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+#[derive(CandidType, Clone, Debug, Deserialize, Eq, PartialEq)]
+struct RecordV0 {
+    name: String,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize, Eq, PartialEq)]
+struct Record {
+    name: String,
+    note: Option<String>,
+}
+
+impl VersionedRecord for Record {
+    const NAME: &'static str = "Record";
+    const VERSION: u16 = 1;
+}
+
+impl Storable for Record {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(encode(self))
+    }
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        decode(&bytes)
+    }
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+// A record which got a non-optional field in version 2.
+#[derive(CandidType, Clone, Debug, Deserialize, Eq, PartialEq)]
+struct RecordV2 {
+    name: String,
+    count: u64,
+}
+
+impl VersionedRecord for RecordV2 {
+    const NAME: &'static str = "RecordV2";
+    const VERSION: u16 = 2;
+
+    fn decode_older(version: u16, payload: &[u8]) -> Result<Self, String> {
+        let old: Record =
+            candid::decode_one(payload).map_err(|e| format!("version {}: {}", version, e))?;
+        Ok(RecordV2 {
+            name: old.name,
+            count: 0,
+        })
+    }
+}
+
+fn record(name: &str) -> Record {
+    Record {
+        name: name.to_string(),
+        note: None,
+    }
+}
+
+#[test]
+fn should_encode_and_decode_current_version() {
+    let record = Record {
+        name: "some name".to_string(),
+        note: Some("some note".to_string()),
+    };
+    let bytes = encode(&record);
+    assert_eq!(version(&bytes), 1);
+    assert_eq!(decode::<Record>(&bytes), record);
+}
+
+#[test]
+fn should_decode_unversioned_record() {
+    let bytes = candid::encode_one(RecordV0 {
+        name: "legacy".to_string(),
+    })
+    .unwrap();
+    assert_eq!(version(&bytes), UNVERSIONED);
+    assert_eq!(decode::<Record>(&bytes), record("legacy"));
+}
+
+#[test]
+fn should_decode_older_version() {
+    let bytes = encode(&record("old"));
+    assert_eq!(
+        decode::<RecordV2>(&bytes),
+        RecordV2 {
+            name: "old".to_string(),
+            count: 0
+        }
+    );
+}
+
+#[test]
+fn should_fail_decoding_newer_version() {
+    let bytes = encode(&RecordV2 {
+        name: "new".to_string(),
+        count: 1,
+    });
+    let result = try_decode::<Record>(&bytes);
+    assert!(result.unwrap_err().contains("newer"));
+}
+
+thread_local! {
+    static ITEMS: RefCell<Vec<u32>> = RefCell::new(vec![]);
+}
+
+// Doubles the items, starting at the index in the cursor.
+fn double_items(cursor: Option<Vec<u8>>, batch_size: usize) -> MigrationBatch {
+    let start = cursor.map_or(0, |cursor| cursor[0] as usize);
+    ITEMS.with_borrow_mut(|items| {
+        let end = items.len().min(start + batch_size);
+        for item in &mut items[start..end] {
+            *item *= 2;
+        }
+        MigrationBatch {
+            cursor: (end < items.len()).then(|| vec![end as u8]),
+            migrated_count: (end - start) as u64,
+        }
+    })
+}
+
+fn increment_items(_cursor: Option<Vec<u8>>, _batch_size: usize) -> MigrationBatch {
+    ITEMS.with_borrow_mut(|items| items.iter_mut().for_each(|item| *item += 1));
+    MigrationBatch {
+        cursor: None,
+        migrated_count: 1,
+    }
+}
+
+const MIGRATIONS: [Migration; 2] = [
+    Migration {
+        name: "double_items",
+        run_batch: double_items,
+    },
+    Migration {
+        name: "increment_items",
+        run_batch: increment_items,
+    },
+];
+
+#[test]
+fn should_run_migrations_in_batches() {
+    ITEMS.set((1..=5).collect());
+    let mut state = MigrationState::default();
+
+    assert!(!run_migrations(&MIGRATIONS, &mut state, 2, 1));
+    assert_eq!(
+        ITEMS.with_borrow(|items| items.clone()),
+        vec![2, 4, 3, 4, 5]
+    );
+    assert_eq!(state.completed, 0);
+    assert_eq!(state.migrated_count, 2);
+
+    assert!(!run_migrations(&MIGRATIONS, &mut state, 2, 2));
+    assert_eq!(
+        ITEMS.with_borrow(|items| items.clone()),
+        vec![2, 4, 6, 8, 10]
+    );
+    assert_eq!(state.completed, 1);
+    assert_eq!(state.cursor, None);
+
+    assert!(run_migrations(&MIGRATIONS, &mut state, 2, 10));
+    assert_eq!(
+        ITEMS.with_borrow(|items| items.clone()),
+        vec![3, 5, 7, 9, 11]
+    );
+    assert!(state.is_complete(&MIGRATIONS));
+
+    // Completed migrations are not run again.
+    assert!(run_migrations(&MIGRATIONS, &mut state, 2, 10));
+    assert_eq!(
+        ITEMS.with_borrow(|items| items.clone()),
+        vec![3, 5, 7, 9, 11]
+    );
+}
+
+#[test]
+fn should_not_run_migrations_of_fresh_install() {
+    ITEMS.set(vec![1]);
+    let mut state = MigrationState::completed(&MIGRATIONS);
+    assert!(run_migrations(&MIGRATIONS, &mut state, 2, 10));
+    assert_eq!(ITEMS.with_borrow(|items| items.clone()), vec![1]);
+}
+
+#[test]
+fn should_rewrite_map_in_batches() {
+    let memory = DefaultMemoryImpl::default();
+    // Store unversioned records, as if written by an older canister version.
+    let mut legacy: StableBTreeMap<u32, Vec<u8>, _> = StableBTreeMap::init(memory.clone());
+    for i in 0..5 {
+        legacy.insert(i, candid::encode_one(record(&i.to_string())).unwrap());
+    }
+
+    let mut map: StableBTreeMap<u32, Record, _> = StableBTreeMap::init(memory.clone());
+    let batch = rewrite_batch(&mut map, None, 2);
+    assert_eq!(batch.migrated_count, 2);
+    let batch = rewrite_batch(&mut map, batch.cursor, 2);
+    assert_eq!(batch.migrated_count, 2);
+    let batch = rewrite_batch(&mut map, batch.cursor, 2);
+    assert_eq!(batch.migrated_count, 1);
+    assert_eq!(batch.cursor, None);
+
+    for (i, record) in map.iter() {
+        assert_eq!(record.name, i.to_string());
+    }
+    let raw: StableBTreeMap<u32, Vec<u8>, _> = StableBTreeMap::init(memory);
+    assert!(raw
+        .iter()
+        .all(|(_, bytes)| version(&bytes) == Record::VERSION));
+}
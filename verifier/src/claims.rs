@@ -0,0 +1,195 @@
+//! Decoding of the claims contained in an II verifiable presentation.
+//!
+//! The functions in this module do NOT check any signatures, so the returned claims
+//! must be used only after the presentation has been verified, or for diagnostics.
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use candid::Principal;
+use ic_verifiable_credentials::issuer_api::ArgumentValue;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+const DID_ICP_PREFIX: &str = "did:icp:";
+const VERIFIABLE_CREDENTIAL_TYPE: &str = "VerifiableCredential";
+const ID_ALIAS_CREDENTIAL_TYPE: &str = "InternetIdentityIdAlias";
+
+/// The two credentials contained in a VP created by the II VC flow.
+#[derive(Clone, Debug)]
+pub(crate) struct DecodedPresentation {
+    pub id_alias: DecodedCredential,
+    pub requested: DecodedCredential,
+}
+
+/// The registered and the `vc`-claims of a single credential.
+#[derive(Clone, Debug)]
+pub(crate) struct DecodedCredential {
+    pub issuer: String,
+    pub subject: String,
+    pub credential_id: Option<String>,
+    pub not_before_s: Option<u64>,
+    pub expiration_s: Option<u64>,
+    pub vc: Map<String, Value>,
+}
+
+impl DecodedCredential {
+    /// Returns true if the credential is expired at the given time.
+    pub fn is_expired_at(&self, time_s: u64) -> bool {
+        self.expiration_s.map_or(false, |exp| time_s > exp)
+    }
+
+    /// Returns the principal identified by the `sub`-claim.
+    pub fn subject_principal(&self) -> Result<Principal, String> {
+        principal_from_did(&self.subject)
+    }
+
+    /// Returns the principal of the id alias contained in an id alias credential.
+    pub fn id_alias_principal(&self) -> Result<Principal, String> {
+        let id_alias = self
+            .credential_subject(ID_ALIAS_CREDENTIAL_TYPE)?
+            .get("hasIdAlias")
+            .and_then(Value::as_str)
+            .ok_or("missing hasIdAlias in id alias credential")?;
+        Principal::from_text(id_alias).map_err(|e| format!("bad id alias {}: {}", id_alias, e))
+    }
+
+    /// Returns the credential type, i.e. the entry in `vc.type` other than "VerifiableCredential".
+    pub fn credential_type(&self) -> Result<String, String> {
+        self.vc
+            .get("type")
+            .and_then(Value::as_array)
+            .ok_or("missing type in vc claims")?
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|t| *t != VERIFIABLE_CREDENTIAL_TYPE)
+            .map(str::to_string)
+            .ok_or("missing credential type in vc claims".to_string())
+    }
+
+    /// Returns the issuer principal named in `vc.issuerPrincipal`, if present.
+    pub fn issuer_principal(&self) -> Result<Option<Principal>, String> {
+        let Some(issuer) = self.vc.get("issuerPrincipal") else {
+            return Ok(None);
+        };
+        let issuer = issuer
+            .as_str()
+            .ok_or("malformed issuerPrincipal in vc claims")?;
+        Principal::from_text(issuer)
+            .map(Some)
+            .map_err(|e| format!("bad issuer principal {}: {}", issuer, e))
+    }
+
+    /// Returns the nonce of the relying party stated in `vc.nonce`, if present.
+    pub fn nonce(&self) -> Result<Option<String>, String> {
+        let Some(nonce) = self.vc.get("nonce") else {
+            return Ok(None);
+        };
+        nonce
+            .as_str()
+            .map(|nonce| Some(nonce.to_string()))
+            .ok_or("malformed nonce in vc claims".to_string())
+    }
+
+    /// Returns the arguments of the credential, as stated in `vc.credentialSubject`.
+    pub fn arguments(&self) -> Result<Option<HashMap<String, ArgumentValue>>, String> {
+        let credential_type = self.credential_type()?;
+        let mut arguments = HashMap::new();
+        for (name, value) in self.credential_subject(&credential_type)? {
+            let argument = match value {
+                Value::String(s) => ArgumentValue::String(s.clone()),
+                Value::Number(n) => n
+                    .as_i64()
+                    .and_then(|i| i32::try_from(i).ok())
+                    .map(ArgumentValue::Int)
+                    .ok_or(format!("unsupported value of argument {}: {}", name, n))?,
+                _ => return Err(format!("unsupported value of argument {}: {}", name, value)),
+            };
+            arguments.insert(name.clone(), argument);
+        }
+        Ok(if arguments.is_empty() {
+            None
+        } else {
+            Some(arguments)
+        })
+    }
+
+    fn credential_subject(&self, credential_type: &str) -> Result<&Map<String, Value>, String> {
+        self.vc
+            .get("credentialSubject")
+            .and_then(|subject| subject.get(credential_type))
+            .and_then(Value::as_object)
+            .ok_or(format!(
+                "missing credentialSubject for {} in vc claims",
+                credential_type
+            ))
+    }
+}
+
+/// Decodes an (unsigned) VP JWT which contains an id alias credential followed by the requested credential.
+pub(crate) fn decode_presentation(vp_jwt: &str) -> Result<DecodedPresentation, String> {
+    let claims = decode_jwt_payload(vp_jwt)?;
+    let credentials = claims
+        .get("vp")
+        .and_then(|vp| vp.get("verifiableCredential"))
+        .and_then(Value::as_array)
+        .ok_or("missing verifiableCredential in VP")?;
+    let [id_alias_jws, requested_jws] = credentials.as_slice() else {
+        return Err(format!(
+            "expected 2 credentials in VP, got {}",
+            credentials.len()
+        ));
+    };
+    Ok(DecodedPresentation {
+        id_alias: decode_credential(id_alias_jws.as_str().ok_or("malformed id alias JWS")?)?,
+        requested: decode_credential(
+            requested_jws
+                .as_str()
+                .ok_or("malformed requested credential JWS")?,
+        )?,
+    })
+}
+
+/// Decodes the claims of a credential JWS.
+pub(crate) fn decode_credential(credential_jws: &str) -> Result<DecodedCredential, String> {
+    let mut claims = decode_jwt_payload(credential_jws)?;
+    let string_claim = |claims: &Map<String, Value>, name: &str| {
+        claims
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or(format!("missing {} in credential claims", name))
+    };
+    let issuer = string_claim(&claims, "iss")?;
+    let subject = string_claim(&claims, "sub")?;
+    let Some(Value::Object(vc)) = claims.remove("vc") else {
+        return Err("missing vc in credential claims".to_string());
+    };
+    Ok(DecodedCredential {
+        issuer,
+        subject,
+        credential_id: claims
+            .get("jti")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        not_before_s: claims.get("nbf").and_then(Value::as_u64),
+        expiration_s: claims.get("exp").and_then(Value::as_u64),
+        vc,
+    })
+}
+
+pub(crate) fn principal_from_did(did: &str) -> Result<Principal, String> {
+    let text = did
+        .strip_prefix(DID_ICP_PREFIX)
+        .ok_or(format!("not a {} DID: {}", DID_ICP_PREFIX, did))?;
+    Principal::from_text(text).map_err(|e| format!("bad principal in DID {}: {}", did, e))
+}
+
+fn decode_jwt_payload(jwt: &str) -> Result<Map<String, Value>, String> {
+    let payload = jwt
+        .split('.')
+        .nth(1)
+        .ok_or("malformed JWT: missing payload")?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|e| format!("malformed JWT payload: {}", e))?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("malformed JWT payload JSON: {}", e))
+}
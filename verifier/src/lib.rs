@@ -0,0 +1,261 @@
+//! Validation of verifiable presentations (VPs) obtained via the II VC flow.
+//!
+//! This is the logic used by the relying party canister to check VPs, without any dependency
+//! on canister state, so that it can be used both in canisters and in native code.
+//! All the context needed for the validation is passed explicitly, in particular the current time.
+use candid::Principal;
+use ic_verifiable_credentials::issuer_api::{ArgumentValue, CredentialSpec};
+use ic_verifiable_credentials::{
+    validate_claims_match_spec, validate_ii_presentation_and_claims, VcFlowSigners,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+mod claims;
+
+/// Parameters of a relying party which determine the VPs it accepts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifierConfig {
+    /// Root of trust for checking canister signatures.
+    pub ic_root_key_raw: Vec<u8>,
+
+    /// II instance that is allowed to provide id alias credentials.
+    pub ii_origin: String,
+    pub ii_canister_id: Principal,
+
+    /// Issuers that are trusted by the relying party.
+    /// (a map from the origin to canister id)
+    pub issuers: BTreeMap<String, Principal>,
+
+    /// Derivation origin of the user's principal
+    pub derivation_origin: String,
+}
+
+/// A VP to be validated, together with the expectations about its content.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VpValidationRequest {
+    pub vp_jwt: String,
+    pub effective_vc_subject: Principal,
+    pub credential_spec: CredentialSpec,
+    pub issuer_origin: String,
+    pub issuer_canister_id: Option<Principal>,
+}
+
+/// The claims of a successfully validated VP.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifiedCredential {
+    /// The principal for which the VP was presented, i.e. `effective_vc_subject`.
+    pub subject: Principal,
+    /// The id alias which links the subject to the requested credential.
+    pub id_alias: Principal,
+    pub issuer_origin: String,
+    pub issuer_canister_id: Principal,
+    pub credential_type: String,
+    pub arguments: Option<HashMap<String, ArgumentValue>>,
+    /// The issuer principal named by the requested credential (`vc.issuerPrincipal`-claim),
+    /// if present, e.g. the group owner at the meta-issuer.
+    pub issuer_principal: Option<Principal>,
+    /// The nonce of the relying party bound into the requested credential (`vc.nonce`-claim),
+    /// if present.
+    pub nonce: Option<String>,
+    /// The id of the requested credential (`jti`-claim), if present.
+    pub credential_id: Option<String>,
+    /// Start of the validity of the requested credential (`nbf`-claim), if present.
+    pub issuance_timestamp_ns: Option<u64>,
+    /// Expiration of the requested credential.
+    pub expiration_timestamp_ns: u64,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VpValidationError {
+    /// The issuer origin is not among the trusted issuers.
+    UnsupportedIssuer(String),
+    /// The issuer canister or the issuer of the credential does not match the trusted issuer.
+    IssuerMismatch(String),
+    /// The VP or the credentials contained in it are malformed.
+    InvalidPresentation(String),
+    /// The signature of a credential contained in the VP is not valid.
+    InvalidSignature(String),
+    /// A credential contained in the VP is expired.
+    Expired(String),
+    /// The VP was not presented for the expected subject.
+    SubjectMismatch(String),
+    /// The requested credential does not match the credential spec.
+    SpecMismatch(String),
+}
+
+impl fmt::Display for VpValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VpValidationError::UnsupportedIssuer(msg)
+            | VpValidationError::IssuerMismatch(msg)
+            | VpValidationError::InvalidPresentation(msg)
+            | VpValidationError::InvalidSignature(msg)
+            | VpValidationError::Expired(msg)
+            | VpValidationError::SubjectMismatch(msg)
+            | VpValidationError::SpecMismatch(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// Validates the given VP wrt. the configuration of a relying party, and returns
+/// the claims of the requested credential contained in the VP.
+pub fn validate_ii_vp(
+    config: &VerifierConfig,
+    req: &VpValidationRequest,
+    current_time_ns: u128,
+) -> Result<VerifiedCredential, VpValidationError> {
+    let issuer_canister_id =
+        trusted_issuer_canister_id(config, &req.issuer_origin, req.issuer_canister_id)?;
+    let vc_flow_signers = VcFlowSigners {
+        ii_origin: config.ii_origin.clone(),
+        ii_canister_id: config.ii_canister_id,
+        issuer_origin: req.issuer_origin.clone(),
+        issuer_canister_id,
+    };
+    if let Err(err) = validate_ii_presentation_and_claims(
+        &req.vp_jwt,
+        req.effective_vc_subject,
+        config.derivation_origin.clone(),
+        &vc_flow_signers,
+        &req.credential_spec,
+        &config.ic_root_key_raw,
+        current_time_ns,
+    ) {
+        return Err(failure_reason(req, current_time_ns, format!("{:?}", err)));
+    }
+
+    // The VP has been verified, so its claims can be trusted.
+    let presentation =
+        claims::decode_presentation(&req.vp_jwt).map_err(VpValidationError::InvalidPresentation)?;
+    let requested = presentation.requested;
+    if !same_origin(&requested.issuer, &req.issuer_origin) {
+        return Err(VpValidationError::IssuerMismatch(format!(
+            "credential issued by {}, expected {}",
+            requested.issuer, req.issuer_origin
+        )));
+    }
+    let expiration_s = requested
+        .expiration_s
+        .ok_or(VpValidationError::InvalidPresentation(
+            "missing exp in requested credential".to_string(),
+        ))?;
+    Ok(VerifiedCredential {
+        subject: req.effective_vc_subject,
+        id_alias: requested
+            .subject_principal()
+            .map_err(VpValidationError::InvalidPresentation)?,
+        issuer_origin: req.issuer_origin.clone(),
+        issuer_canister_id,
+        credential_type: requested
+            .credential_type()
+            .map_err(VpValidationError::InvalidPresentation)?,
+        arguments: requested
+            .arguments()
+            .map_err(VpValidationError::InvalidPresentation)?,
+        issuer_principal: requested
+            .issuer_principal()
+            .map_err(VpValidationError::InvalidPresentation)?,
+        nonce: requested
+            .nonce()
+            .map_err(VpValidationError::InvalidPresentation)?,
+        credential_id: requested.credential_id,
+        issuance_timestamp_ns: requested.not_before_s.map(|nbf| nbf * 1_000_000_000),
+        expiration_timestamp_ns: expiration_s * 1_000_000_000,
+    })
+}
+
+/// The issuer principal and the type of the requested credential of a VP, as claimed by the VP.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClaimedIssuer {
+    pub issuer_principal: Principal,
+    pub credential_type: String,
+}
+
+impl ClaimedIssuer {
+    /// Returns true if the validated credential was issued by the claimed issuer.
+    pub fn issued(&self, verified: &VerifiedCredential) -> bool {
+        verified.issuer_principal == Some(self.issuer_principal)
+            && verified.credential_type == self.credential_type
+    }
+}
+
+/// Returns the issuer principal named by the requested credential of the VP, if any.
+/// The VP is NOT validated, so the result may be used only for lookups which precede
+/// the validation, and must match the `VerifiedCredential` before it is relied upon.
+pub fn claimed_issuer(vp_jwt: &str) -> Result<Option<ClaimedIssuer>, VpValidationError> {
+    let requested = claims::decode_presentation(vp_jwt)
+        .map_err(VpValidationError::InvalidPresentation)?
+        .requested;
+    let Some(issuer_principal) = requested
+        .issuer_principal()
+        .map_err(VpValidationError::InvalidPresentation)?
+    else {
+        return Ok(None);
+    };
+    Ok(Some(ClaimedIssuer {
+        issuer_principal,
+        credential_type: requested
+            .credential_type()
+            .map_err(VpValidationError::InvalidPresentation)?,
+    }))
+}
+
+/// Returns the canister id of the issuer with the given origin, if the issuer is trusted.
+pub fn trusted_issuer_canister_id(
+    config: &VerifierConfig,
+    issuer_origin: &str,
+    expected_canister_id: Option<Principal>,
+) -> Result<Principal, VpValidationError> {
+    let Some(issuer_canister_id) = config.issuers.get(issuer_origin) else {
+        return Err(VpValidationError::UnsupportedIssuer(format!(
+            "issuer not supported: {}",
+            issuer_origin,
+        )));
+    };
+    if let Some(expected_canister_id) = expected_canister_id {
+        if *issuer_canister_id != expected_canister_id {
+            return Err(VpValidationError::IssuerMismatch(format!(
+                "wrong issuer canister id: expected {}, got {}",
+                issuer_canister_id, expected_canister_id
+            )));
+        }
+    }
+    Ok(*issuer_canister_id)
+}
+
+/// Determines why a VP failed the validation, based on its (unverified) claims.
+/// The checks that can be performed on the claims alone take precedence,
+/// remaining failures are attributed to the signatures.
+fn failure_reason(
+    req: &VpValidationRequest,
+    current_time_ns: u128,
+    details: String,
+) -> VpValidationError {
+    let Ok(presentation) = claims::decode_presentation(&req.vp_jwt) else {
+        return VpValidationError::InvalidPresentation(details);
+    };
+    let current_time_s = (current_time_ns / 1_000_000_000) as u64;
+    if presentation.id_alias.is_expired_at(current_time_s)
+        || presentation.requested.is_expired_at(current_time_s)
+    {
+        return VpValidationError::Expired(details);
+    }
+    if presentation.id_alias.subject_principal() != Ok(req.effective_vc_subject) {
+        return VpValidationError::SubjectMismatch(details);
+    }
+    if !same_origin(&presentation.requested.issuer, &req.issuer_origin) {
+        return VpValidationError::IssuerMismatch(details);
+    }
+    if validate_claims_match_spec(&presentation.requested.vc, &req.credential_spec).is_err() {
+        return VpValidationError::SpecMismatch(details);
+    }
+    VpValidationError::InvalidSignature(details)
+}
+
+fn same_origin(origin_1: &str, origin_2: &str) -> bool {
+    origin_1.trim_end_matches('/') == origin_2.trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,281 @@
+//! Unit tests with fixtures created with the Dummy Relying Party and the Dummy Issuer.
+use crate::claims::{decode_credential, decode_presentation};
+use crate::{
+    claimed_issuer, validate_ii_vp, VerifierConfig, VpValidationError, VpValidationRequest,
+};
+use assert_matches::assert_matches;
+use candid::Principal;
+use ic_canister_sig_creation::{extract_raw_root_pk_from_der, IC_ROOT_PK_DER};
+use ic_verifiable_credentials::issuer_api::{ArgumentValue, CredentialSpec};
+use ic_verifiable_credentials::{build_ii_verifiable_presentation_jwt, II_ISSUER_URL};
+use std::collections::{BTreeMap, HashMap};
+
+/// VP for a `VerifiedAge`-credential with `ageAtLeast: 18`, created with the Dummy issuer:
+/// `https://l7rua-raaaa-aaaap-ahh6a-cai.icp0.io/`
+/// (same as in rp/tests/manage_content.rs).  Uses mainnet root key.
+const DUMMY_VP_JWT: &str = "eyJ0eXAiOiJKV1QiLCJhbGciOiJub25lIn0.eyJpc3MiOiJkaWQ6aWNwOnFkaWlmLTJpYWFhLWFhYWFwLWFoamFxLWNhaSIsInZwIjp7IkBjb250ZXh0IjoiaHR0cHM6Ly93d3cudzMub3JnLzIwMTgvY3JlZGVudGlhbHMvdjEiLCJ0eXBlIjoiVmVyaWZpYWJsZVByZXNlbnRhdGlvbiIsInZlcmlmaWFibGVDcmVkZW50aWFsIjpbImV5SnFkMnNpT25zaWEzUjVJam9pYjJOMElpd2lZV3huSWpvaVNXTkRjeUlzSW1zaU9pSk5SSGQzUkVGWlMwdDNXVUpDUVVkRWRVVk5Ra0ZuVFhOQlFXOUJRVUZCUVVGSFFVRktkMFZDWHpKUmNXbGlUMnROYVhKblEwZ3dkMlpZYWtGZmRFbzBiVVF3Y1V0aGVHZDZNM0YyVG5Sc1JtSXlheUo5TENKcmFXUWlPaUprYVdRNmFXTndPbVpuZEdVMUxXTnBZV0ZoTFdGaFlXRmtMV0ZoWVhSeExXTmhhU0lzSW1Gc1p5STZJa2xqUTNNaWZRLmV5SmxlSEFpT2pFM016QTNPVGcxTVRBc0ltbHpjeUk2SW1oMGRIQnpPaTh2YVdSbGJuUnBkSGt1YVdNd0xtRndjQzhpTENKdVltWWlPakUzTXpBM09UYzJNVEFzSW1wMGFTSTZJbVJoZEdFNmRHVjRkQzl3YkdGcGJqdGphR0Z5YzJWMFBWVlVSaTA0TEhScGJXVnpkR0Z0Y0Y5dWN6b3hOek13TnprM05qRXdOVEU0TURnek9EQTJMR0ZzYVdGelgyaGhjMmc2TURGbFpqRmtaRFJrTTJFeE56WTFOV05qWmpKbU5qWmlNamRoTTJVd01UZzNOVEJrT0RGaE5ETm1ZV05rTW1Vek56Qm1NV1UxTW1JellUTXdOekkyTnlJc0luTjFZaUk2SW1ScFpEcHBZM0E2TjJWaWIya3RkSGwxZVhNdFlYRnROR010ZHpKc04ya3RkbWQxWTIwdGVIWmhkM2d0YkdWdGVuZ3RObXR4TW1jdFpqVXpkVGN0ZVhabWFESXRibUZsSWl3aWRtTWlPbnNpUUdOdmJuUmxlSFFpT2lKb2RIUndjem92TDNkM2R5NTNNeTV2Y21jdk1qQXhPQzlqY21Wa1pXNTBhV0ZzY3k5Mk1TSXNJblI1Y0dVaU9sc2lWbVZ5YVdacFlXSnNaVU55WldSbGJuUnBZV3dpTENKSmJuUmxjbTVsZEVsa1pXNTBhWFI1U1dSQmJHbGhjeUpkTENKamNtVmtaVzUwYVdGc1UzVmlhbVZqZENJNmV5SkpiblJsY201bGRFbGtaVzUwYVhSNVNXUkJiR2xoY3lJNmV5SmtaWEpwZG1GMGFXOXVUM0pwWjJsdUlqb2lhSFIwY0hNNkx5OXNOM0oxWVMxeVlXRmhZUzFoWVdGaGNDMWhhR2cyWVMxallXa3VhV013TG1Gd2NDSXNJbWhoYzBsa1FXeHBZWE1pT2lKMmVteGhkaTF4YVdoM1lpMTVkSEJvTWkxdmEyMXZjQzAwTldnMWFpMXBObXBpYUMxcWVHcHBiQzEwTlRSNGJ5MTFjSGN6YkMwMVpHeDRhaTE2WVdVaWZYMTlmUS4yZG4zb210alpYSjBhV1pwWTJGMFpWa0ZiZG5aOTZOa2RISmxaWU1CZ3dHREFZSUVXQ0NtV3ZvNDRpUWlpR09vbnJ0enotVmMtY0MxNXNqNGRXdzVpTTE0T1haZWk0TUNTR05oYm1semRHVnlnd0dEQVlNQmd3R0RBWU1CZ2dSWUlEWWpfU3A3VFNlRGtMU3c0Q1pFbXl3ZEdZM1dGc20tejZ2cGo1a2hKdHlZZ3dHQ0JGZ2dRRUw3S0VUYklHNFhKS0pJN3ZPVDljc2RJaWdQS1kyVWo4R09Da0NGTTBPREFZSUVXQ0NOUGJ4YkdzZ0g2MDh4TzVGeExibFAzMHBRQm9JSGNaOGN1amQzR3lySTc0TUNTZ0FBQUFBQVlBQW5BUUdEQVlNQmd3Sk9ZMlZ5ZEdsbWFXVmtYMlJoZEdHQ0ExZ2cxZGFobzVZVzA0NEx6OTF3S2tQaWdzQWNBbnlCTTVJcW1iRzZ2ajVIa0ppQ0JGZ2diTTFyc3hwVWRoMUtWdW5QMk11amhOVzQtMGNZVG95aFBMY09CUElnbXM2Q0JGZ2dGRm5LMkpEdnF0dS16eFhjVG05ZFMzS21yUlRhMGI4cmwyNG9CcThFMzRhQ0JGZ2dQZWVCM2dnUjlhaEdrV2JGbFBsRFBaWnZhRzlQUUdXdGs1WGpDX3JCVS1LQ0JGZ2d5eXFVQlhBRXJqTnZ0U3Vqa1JmUGtLcXQ3LUF0My1rZ1c4d1R5UFlWQ2dLQ0JGZ2d2Qi1iVEZUMmJyajhKVGdla0dRYTVaNzRmRmtCaGpWUllxVXN0SWRTUXN1Q0JGZ2dETzJzMGF2VnVwMWNaa3hZUTZxVktyTHFYeHI4WFB1ZHB2REc5aUExR1V1Q0JGZ2dkSGxaMDE2Y2VCRHByNWZzSjVDMC1lNElKOUhlSjJCWHJMby1EZC1ZMUw2Q0JGZ2dTS0RIcmMzWTVsMEpDR0R3Zm5TYUFnV0RCWHJDdHY4a0F2aUdlOHUtS1RPREFZSUVXQ0RzVlFoMEZ6MXhHaGZ3MGZzTXBqSmdGRDJQTDhsV2R0UEh1Z1ExSXlISUZJTUNSSFJwYldXQ0EwbktfSU8tbk9qQmdoaHBjMmxuYm1GMGRYSmxXREM1dzM2Vy04RGJVcnBETm14NFBpM3E2UW0wcEdjVkxNTEZiQy1EMlY1R09RR0lNR3NzNlhOWDN3TGpMUV9UbTVacVpHVnNaV2RoZEdsdmJxSnBjM1ZpYm1WMFgybGtXQjBzVmJOSDdQSm9iSU40SFd4WjBiUS1lMHk2amV0c0d6ZGhCX0xOQW10alpYSjBhV1pwWTJGMFpWa0NsTm5aOTZKa2RISmxaWU1CZ2dSWUlDQV9NWmEyWjhmWURoU3dwUENmY19QMFVhMEJ0VWxTZGctamhPLWM5eGZJZ3dHREFZSUVXQ0NkUG5OVjU4dTZpcWxJSWNYZV9rbUQ2bHREY0JId0RlVC1vMDd5cVRacjVJTUNSbk4xWW01bGRJTUJnd0dEQVlNQmdnUllJSWM1LTc3ZFBlMnFqLTlCaHdObndKQmIzamRyWTkwMzRyRjItd2kxZ2dVdmd3R0NCRmdnZ19JMFpfVmVPeWljbnNiS09mNmNBdTlsdlI1dTFjRVlac0txdm5hLXFuYURBWU1DV0Iwc1ZiTkg3UEpvYklONEhXeFowYlEtZTB5NmpldHNHemRoQl9MTkFvTUJnd0pQWTJGdWFYTjBaWEpmY21GdVoyVnpnZ05ZTXRuWjk0S0NTZ0FBQUFBQVlBQUFBUUZLQUFBQUFBQmdBSzRCQVlKS0FBQUFBQUJnQUxBQkFVb0FBQUFBQUdfX193RUJnd0pLY0hWaWJHbGpYMnRsZVlJRFdJVXdnWUl3SFFZTkt3WUJCQUdDM0h3RkF3RUNBUVlNS3dZQkJBR0MzSHdGQXdJQkEyRUFrQWRSSUhlT3NocFRDZ0s4eDJQbjlLR1NrelVHbG1yM3RVd1FwTkt5VGVhb2F5QU9ORUM2NWlaNzlNU0kyYUVkQkhMRGpCdGlJUm1QbU9UbWlDdWppbHBPT3FXdnpvbWJmNEplMlZyZm9TWXBhSUJ6Vlc4blIxSnlFLWpYUGtET2dnUllJRGJ6elNWOWtQczQ1Q1dYOFpPbDRESGIxWVcyS1NlVHV3VGJSNVNBUE9CdWdnUllJSWotb050cDg0LWM4X3VvajRvRUR6eXR5YTUzY3ZvYVFHcHVwR1Q2aFl1ZWdnUllJR2xoN3hOOEt1NExCR2NJTHZiVHdTd0Q2VEFUdGdLa3kySVVKdzVJU0dQeGdnUllJQmNna0pBREU3QTFTWjdLMGQyUU9Vekl6S3VjcnVPcGpBdUZOZnJxdVJwemd3SkVkR2x0WllJRFNmYmtfZXFVdGJTQ0dHbHphV2R1WVhSMWNtVllNSmNRRzRMSUlLRzl5M1BzTkl4cThvalBiTm5fNWNFTkZjWnRNaDdIWHVYNVhwejZZX1JxQWI0Z2R3N3JBYjVvUG1SMGNtVmxnd0dDQkZnZ3dqa1N1bWRHNEE2V0lJenVWdDdhODJIdGxxdlpSNHNRb2VZLTJHN1Y4X21EQWtOemFXZURBbGdnT0psa1V2OWhHVW1jVXVpdlRPczNyRFJKMk1ycFdvR1VUdE1OVVFxYlRnaURBWUlFV0NBbmljaVdSQ1hSNmNJNDluMW1Gbk9aM2k2Y2VTTVZ0czJwZGhLN19PaVpaSU1DV0NDOTdpSDktdTlBT0pCYzhrbUJDaV9vUDROZXJ1c3BzdWVpS1htdUYwNThSNElEUUEiLCJleUpxZDJzaU9uc2lhM1I1SWpvaWIyTjBJaXdpWVd4bklqb2lTV05EY3lJc0ltc2lPaUpOUkhkM1JFRlpTMHQzV1VKQ1FVZEVkVVZOUWtGblRYTkJRVzlCUVVGQlFVRmxRVFpSVVVWQ2JtOXZWVEo1TW1nNU9HUnRMWEZCY25Kc1gxQlZVWFJFUldKcE1VUkNPVlJOVUhOc2NHbGZaMlZzWnlKOUxDSnJhV1FpT2lKa2FXUTZhV053T25Ga2FXbG1MVEpwWVdGaExXRmhZV0Z3TFdGb2FtRnhMV05oYVNJc0ltRnNaeUk2SWtsalEzTWlmUS5leUpsZUhBaU9qRTNNekEzT1RnMU1UUXNJbWx6Y3lJNkltaDBkSEJ6T2k4dlpIVnRiWGt0YVhOemRXVnlMblpqTHlJc0ltNWlaaUk2TVRjek1EYzVOell4TkN3aWFuUnBJam9pWkdGMFlUcDBaWGgwTDNCc1lXbHVPMk5vWVhKelpYUTlWVlJHTFRnc2FYTnpkV1Z5T21oMGRIQnpPaTh2WkhWdGJYa3RhWE56ZFdWeUxuWmpMSFJwYldWemRHRnRjRjl1Y3pveE56TXdOemszTmpFME5qZzJOREF5T0RZNUxITjFZbXBsWTNRNmRucHNZWFl0Y1dsb2QySXRlWFJ3YURJdGIydHRiM0F0TkRWb05Xb3RhVFpxWW1ndGFuaHFhV3d0ZERVMGVHOHRkWEIzTTJ3dE5XUnNlR290ZW1GbElpd2ljM1ZpSWpvaVpHbGtPbWxqY0RwMmVteGhkaTF4YVdoM1lpMTVkSEJvTWkxdmEyMXZjQzAwTldnMWFpMXBObXBpYUMxcWVHcHBiQzEwTlRSNGJ5MTFjSGN6YkMwMVpHeDRhaTE2WVdVaUxDSjJZeUk2ZXlKQVkyOXVkR1Y0ZENJNkltaDBkSEJ6T2k4dmQzZDNMbmN6TG05eVp5OHlNREU0TDJOeVpXUmxiblJwWVd4ekwzWXhJaXdpZEhsd1pTSTZXeUpXWlhKcFptbGhZbXhsUTNKbFpHVnVkR2xoYkNJc0lsWmxjbWxtYVdWa1FXZGxJbDBzSW1OeVpXUmxiblJwWVd4VGRXSnFaV04wSWpwN0lsWmxjbWxtYVdWa1FXZGxJanA3SW1GblpVRjBUR1ZoYzNRaU9qRTRmWDE5ZlEuMmRuM29tdGpaWEowYVdacFkyRjBaVmtHWU5uWjk2TmtkSEpsWllNQmd3R0RBWUlFV0NDbVd2bzQ0aVFpaUdPb25ydHp6LVZjLWNDMTVzajRkV3c1aU0xNE9YWmVpNE1DU0dOaGJtbHpkR1Z5Z3dHREFZSUVXQ0N5WUpjNmV1dVZDS2VnX2hIWU8zY2t6R3poMDhySXg3eXRNemZHelpZQVdvTUJnZ1JZSUJHWTgzUVZYSnNzOGU2bEJxRUlSMVlPRWF6NldQXzZSQUhTb1JyVkVrU1Bnd0dDQkZnZ3lUa21yb2JpbFpWYWh2WERCRWZwUllFS1FsUWdjLUJFdE1LaEFtSlduakNEQVlNQmd3R0NCRmdnU21mcWgzOFJ2ejI0ei0zVTd3TUhiaDU5NmYydFlqQUgwM0pKR3BKSlFzMkRBWUlFV0NDbFpuUXNPNnBzZWRnMjJDYVQxT21KUGtUN1JVOHowUGVvaTRzOFJFWnBSSU1CZ3dHREFZSUVXQ0RKd1BhdlBVWk41UDNaalR2dUI0ck93d2Q1VzhtajloREVFcnJTVWt3RWxZTUJnd0dDQkZnZ05qWVFKdmNfNUdEUGhKd3RpVEFwS0xZUUNaX09TZXpCbHdodWxwOUZua0NEQVlJRVdDRHE4MHZZb0FmOUxhT1ZZMTVFOHJfMndybzVpZERvLUc0WEsxanQyVGJRaklNQmdnUllJSGo2RFdlVFg3N1A3MG5JaWl6LVlsUXoxTHZBRE5WeVBBdHZYT1hFU2RpVGd3SktBQUFBQUFIZ09rRUJBWU1CZ3dHREFrNWpaWEowYVdacFpXUmZaR0YwWVlJRFdDQ2JIUV9aejV5bGJZSEpoN3R2Sl9HaFZubm9DMzdYNHVISHJkOFZtQ2hfOVlJRVdDQ3VTemxvaFVqYTBkci1PNW10M1RWMVRROUZXWExsV0lVWnFzUUVPZ0ZmRVlJRVdDQUpicVVvaUROUzBtbnpMZzY4OGlTejgzelJZTzRCYnFzLTgxbHhPRVhkXzRJRVdDQ3p5U1IwYXdRYkw2OUJQSERvbjRQLW94OXlDckdUZG92N19nR1FTaERCQ0lJRVdDRFgzS0p3YmdYOXNuRDNZT1hHZ0plVERUSFRBakJIMEM4MzByelZ4LUQydG9JRVdDQUtkUnk5RzQ1aGxMaUpKcnFmVHNzejJvcXFIc24xaFdUVUlpVzBLMnN4NDRJRVdDQUxKZFNVNWZPNVE0dlI5cE5wUDVVSEhfV1N1TlBlVDlhWlpuSUV0X2tKR0lJRVdDQWhWWGJ2QXJkZTBDSzFJbEVrM3F3UmNLQm1wUWJOdUFPMUFnQ1VoVEJWeDRJRVdDQkl6SFlqVl9uV2ZId0lNNWE5WFZ4SFpyc0lZbGVucXBuMWkxQU1IQmNacm9JRVdDRGVNUGNrYzFKRWNnZkVFbExEMEFSb1M3WFktbjRnazI5ZDJjMUo3YkI1Q0lNQmdnUllJTEs3VzgxR1Bub0paNGFEc3NWS0lZNVo2LV9WUUlFYVd4dG5IUzVHLTFUWGd3SkVkR2x0WllJRFNiWHk1OHVxNk1HQ0dHbHphV2R1WVhSMWNtVllNS2hBZ0ZldVgxdVF5QkdQX01XRG1RbDRsZVY2QnMydmxNZlJSTnpkbmdTaHNHMlU4bWRIMW9jemRxTTFUN3lIZG1wa1pXeGxaMkYwYVc5dW9tbHpkV0p1WlhSZmFXUllIWk5scU1ueWYwRXdZSVVJTmRHRm1rRmNaMWRjMHd3RW9ZUDlDV01DYTJObGNuUnBabWxqWVhSbFdRSjkyZG4zb21SMGNtVmxnd0dDQkZnZ3ZRNEhOYnN4bkdYTkwwdVhvSUl4ajdFdWdxV21GUGNOSVc5a3BpNkRsdnlEQVlNQmdnUllJTGxKQTJ5d09RbHFuTGYzMzFqcmdFUlJ6VmJBS1FVVkRaTkpGNjB3ZmRoN2d3SkdjM1ZpYm1WMGd3R0RBWUlFV0NDNFM3ZHdZa3JCZEllbjVpSGRjUmJ3LUF6WXZCTWtDOVk2eVBSbVAxX3lVSU1CZ3dHQ0JGZ2daVThpS2cxM2hjbEFhMHpEQ1hEcTVLeE9tU0dScWlDWEFGb3hXVWlzNmR1REFZSUVXQ0RJQ2FMM0hFZzJlZW9JUDhvOXBZTjk0cEJ0NEhNVm5IWnJRNVktbnU3cWFJTUJnZ1JZSUpibzIzeWFLREp1NndlMkJmdTRZSVJZdGc2bUJXcU9GLU1YeGdYMGdPd3Bnd0pZSFpObHFNbnlmMEV3WUlVSU5kR0Zta0ZjWjFkYzB3d0VvWVA5Q1dNQ2d3R0RBazlqWVc1cGMzUmxjbDl5WVc1blpYT0NBMWdiMmRuM2dZSktBQUFBQUFIZ0FBQUJBVW9BQUFBQUFlX19fd0VCZ3dKS2NIVmliR2xqWDJ0bGVZSURXSVV3Z1lJd0hRWU5Ld1lCQkFHQzNId0ZBd0VDQVFZTUt3WUJCQUdDM0h3RkF3SUJBMkVBaXBHeU93bUs2MzNWNVNWeGZ3d3AwNVo4WDA1aDBMWEh4cGNYTG05V2dxcGVRajFDQjNhVnhSZXJwLU8yTnRxVUNUalgxY3J1NG13OGNjeG9pUFZDeFpadVlPcE5rZDI1V3RDWlFvTVhFSmZqSlFKcjd3QXhLTFJHUVFWc2h0QktnZ1JZSU5scEt6TzVOaUhmcGJaQlN1Y1NyUWlDb1BZcGRzTjhVT3JjcFY3MlpvbVFnZ1JZSVBOZURBbnZvcFZsZlpzekFSVF81UlYxdFN0RnNhRFJ1T3c1UmMwTlFnRTFnd0pFZEdsdFpZSURTWkRodnEzbGtPT0JHR2x6YVdkdVlYUjFjbVZZTUpqdHMtMkVKaE5NMTBfc2ZhU3hDQjg0Vm1UWWM1X0FGeWZaYmNwNUVvOUVCOHdHbmlrNW1wVXh2U0NQdnpieXoyUjBjbVZsZ3dKRGMybG5nd0pZSUd1YVFhaWIxMmFnNUxvNWdCOEZLTFlBdGhFNTY3alNWN1B6V0xFdklBc05nd0pZSUQ4WF9xUEtyaUowZ2pXdXFXZUFoZmF6aWhWWFUzcXZVVWhDZE9tNkpZTW9nZ05BIl19fQ.";
+
+/// Dummy alias JWS (same as in issuer/tests/util/mod.rs).
+/// id dapp: 7eboi-tyuys-aqm4c-w2l7i-vgucm-xvawx-lemzx-6kq2g-f53u7-yvfh2-nae
+/// id alias: evacf-r7slg-yca2r-i353q-x2lyx-jvb77-hh6ci-fwvuv-ckqis-plqzg-zqe
+const DUMMY_ALIAS_JWS: &str = "eyJqd2siOnsia3R5Ijoib2N0IiwiYWxnIjoiSWNDcyIsImsiOiJNRHd3REFZS0t3WUJCQUdEdUVNQkFnTXNBQW9BQUFBQUFHQUFKd0VCTTc1TzhhaEdaUm5pQ3lPWHZBcnlKaWNXaWxEbmVmdy1ZaW9mU1FScDF4USJ9LCJraWQiOiJkaWQ6aWNwOmZndGU1LWNpYWFhLWFhYWFkLWFhYXRxLWNhaSIsImFsZyI6IkljQ3MifQ.eyJleHAiOjE3MzA3OTcyMzMsImlzcyI6Imh0dHBzOi8vaWRlbnRpdHkuaWMwLmFwcC8iLCJuYmYiOjE3MzA3OTYzMzMsImp0aSI6ImRhdGE6dGV4dC9wbGFpbjtjaGFyc2V0PVVURi04LHRpbWVzdGFtcF9uczoxNzMwNzk2MzMzNjcyMjQ1NDc0LGFsaWFzX2hhc2g6Yjc3OTVmZDJiNDY3MTA2NGE2YjZmNmFlMDIwZjk3ZWJlYmE2NWI2ZmUxNTA0ZTIxZDcyOGJhZTAxMzQwMjIwYyIsInN1YiI6ImRpZDppY3A6N2Vib2ktdHl1eXMtYXFtNGMtdzJsN2ktdmd1Y20teHZhd3gtbGVtengtNmtxMmctZjUzdTcteXZmaDItbmFlIiwidmMiOnsiQGNvbnRleHQiOiJodHRwczovL3d3dy53My5vcmcvMjAxOC9jcmVkZW50aWFscy92MSIsInR5cGUiOlsiVmVyaWZpYWJsZUNyZWRlbnRpYWwiLCJJbnRlcm5ldElkZW50aXR5SWRBbGlhcyJdLCJjcmVkZW50aWFsU3ViamVjdCI6eyJJbnRlcm5ldElkZW50aXR5SWRBbGlhcyI6eyJkZXJpdmF0aW9uT3JpZ2luIjoiaHR0cHM6Ly9sN3J1YS1yYWFhYS1hYWFhcC1haGg2YS1jYWkuaWMwLmFwcCIsImhhc0lkQWxpYXMiOiJldmFjZi1yN3NsZy15Y2Eyci1pMzUzcS14Mmx5eC1qdmI3Ny1oaDZjaS1md3Z1di1ja3Fpcy1wbHF6Zy16cWUifX19fQ.2dn3omtjZXJ0aWZpY2F0ZVkFbdnZ96NkdHJlZYMBgwGDAYIEWCCmWvo44iQiiGOonrtzz-Vc-cC15sj4dWw5iM14OXZei4MCSGNhbmlzdGVygwGDAYMBgwGDAYMBggRYIDYj_Sp7TSeDkLSw4CZEmywdGY3WFsm-z6vpj5khJtyYgwGCBFggQEL7KETbIG4XJKJI7vOT9csdIigPKY2Uj8GOCkCFM0ODAYIEWCCNPbxbGsgH608xO5FxLblP30pQBoIHcZ8cujd3GyrI74MCSgAAAAAAYAAnAQGDAYMBgwJOY2VydGlmaWVkX2RhdGGCA1gghQUiyD3srYn8hjdcO3nMj7jlr2iCzEEYYSOE5AGaLcaCBFggbM1rsxpUdh1KVunP2MujhNW4-0cYToyhPLcOBPIgms6CBFggFFnK2JDvqtu-zxXcTm9dS3KmrRTa0b8rl24oBq8E34aCBFggPeeB3ggR9ahGkWbFlPlDPZZvaG9PQGWtk5XjC_rBU-KCBFggyyqUBXAErjNvtSujkRfPkKqt7-At3-kgW8wTyPYVCgKCBFggvB-bTFT2brj8JTgekGQa5Z74fFkBhjVRYqUstIdSQsuCBFggDO2s0avVup1cZkxYQ6qVKrLqXxr8XPudpvDG9iA1GUuCBFggdHlZ016ceBDpr5fsJ5C0-e4IJ9HeJ2BXrLo-Dd-Y1L6CBFggTfWPC5xMuHTrfpld1Pu_3QbKRFkwtChcdf9_m0N8M1aDAYIEWCB4M9cRIc5igAWtqNuOK58ubw19qHxKZ4IKmvTgYBgWxoMCRHRpbWWCA0mMx__KicPBghhpc2lnbmF0dXJlWDCXecrOkjZ2CfdPluCeq6y0YySMAVru6vpGX8GYOryRGQeQJn18wtzZ5p7agOfdEc9qZGVsZWdhdGlvbqJpc3VibmV0X2lkWB0sVbNH7PJobIN4HWxZ0bQ-e0y6jetsGzdhB_LNAmtjZXJ0aWZpY2F0ZVkClNnZ96JkdHJlZYMBggRYIG27-yoKqqCgQXw-1FRQ4P6eqesH5QLoYdx25aRYKNRYgwGDAYIEWCCT5wenc4PnWnz1HXMCJVfV3hiQZXTbD5LE9buS_bTEB4MCRnN1Ym5ldIMBgwGDAYMBggRYIIc5-77dPe2qj-9BhwNnwJBb3jdrY9034rF2-wi1ggUvgwGCBFggg_I0Z_VeOyicnsbKOf6cAu9lvR5u1cEYZsKqvna-qnaDAYMCWB0sVbNH7PJobIN4HWxZ0bQ-e0y6jetsGzdhB_LNAoMBgwJPY2FuaXN0ZXJfcmFuZ2VzggNYMtnZ94KCSgAAAAAAYAAAAQFKAAAAAABgAK4BAYJKAAAAAABgALABAUoAAAAAAG___wEBgwJKcHVibGljX2tleYIDWIUwgYIwHQYNKwYBBAGC3HwFAwECAQYMKwYBBAGC3HwFAwIBA2EAkAdRIHeOshpTCgK8x2Pn9KGSkzUGlmr3tUwQpNKyTeaoayAONEC65iZ79MSI2aEdBHLDjBtiIRmPmOTmiCujilpOOqWvzombf4Je2VrfoSYpaIBzVW8nR1JyE-jXPkDOggRYIDbzzSV9kPs45CWX8ZOl4DHb1YW2KSeTuwTbR5SAPOBuggRYIIj-oNtp84-c8_uoj4oEDzytya53cvoaQGpupGT6hYueggRYIGlh7xN8Ku4LBGcILvbTwSwD6TATtgKky2IUJw5ISGPxggRYILmsedpaOOJBqlW6S4s2vPkoERAwZyW5emA-8pAW1P7hgwJEdGltZYIDScHNhcbntLSCGGlzaWduYXR1cmVYMIswpM8elThfCHQknyufP2s1-hgDV1L1LaepAY-bI_m2DjAMYnsakUpuF9aH7158x2R0cmVlgwGCBFggwjkSumdG4A6WIIzuVt7a82HtlqvZR4sQoeY-2G7V8_mDAkNzaWeDAlgggcprggWNF0Gv498eoWFpgAVS_vb_N-s4Fju-4gqFfnGDAYIEWCD-YbyK4DAm8dqHqxfaDyXATsr5w23cQCVc8EOWLFP9U4MCWCDaXyxpJhYi2JuSYjrNGWPbypHJ8_RUoMpPPXz6ursns4IDQA";
+
+const DUMMY_ISSUER_ORIGIN: &str = "https://dummy-issuer.vc/";
+const DUMMY_ISSUER_CANISTER_ID: &str = "qdiif-2iaaa-aaaap-ahjaq-cai";
+const DUMMY_II_CANISTER_ID: &str = "fgte5-ciaaa-aaaad-aaatq-cai";
+const DUMMY_ID_DAPP: &str = "7eboi-tyuys-aqm4c-w2l7i-vgucm-xvawx-lemzx-6kq2g-f53u7-yvfh2-nae";
+const DUMMY_ID_ALIAS: &str = "vzlav-qihwb-ytph2-okmop-45h5j-i6jbh-jxjil-t54xo-upw3l-5dlxj-zae";
+const DUMMY_DERIVATION_ORIGIN: &str = "https://l7rua-raaaa-aaaap-ahh6a-cai.icp0.io";
+
+/// The credentials in DUMMY_VP_JWT are valid from 1730797614 until 1730798510 (seconds).
+const VALID_TIME_NS: u128 = 1_730_798_000 * 1_000_000_000;
+const REQUESTED_VC_EXPIRATION_NS: u64 = 1_730_798_514 * 1_000_000_000;
+
+fn principal(text: &str) -> Principal {
+    Principal::from_text(text).expect("wrong principal")
+}
+
+fn verifier_config() -> VerifierConfig {
+    VerifierConfig {
+        ic_root_key_raw: extract_raw_root_pk_from_der(IC_ROOT_PK_DER)
+            .expect("failed to extract raw root pk from der"),
+        ii_origin: II_ISSUER_URL.to_string(),
+        ii_canister_id: principal(DUMMY_II_CANISTER_ID),
+        issuers: BTreeMap::from([(
+            DUMMY_ISSUER_ORIGIN.to_string(),
+            principal(DUMMY_ISSUER_CANISTER_ID),
+        )]),
+        derivation_origin: DUMMY_DERIVATION_ORIGIN.to_string(),
+    }
+}
+
+fn verified_age_spec(age_at_least: i32) -> CredentialSpec {
+    CredentialSpec {
+        credential_type: "VerifiedAge".to_string(),
+        arguments: Some(HashMap::from([(
+            "ageAtLeast".to_string(),
+            ArgumentValue::Int(age_at_least),
+        )])),
+    }
+}
+
+fn validation_request() -> VpValidationRequest {
+    VpValidationRequest {
+        vp_jwt: DUMMY_VP_JWT.to_string(),
+        effective_vc_subject: principal(DUMMY_ID_DAPP),
+        credential_spec: verified_age_spec(18),
+        issuer_origin: DUMMY_ISSUER_ORIGIN.to_string(),
+        issuer_canister_id: Some(principal(DUMMY_ISSUER_CANISTER_ID)),
+    }
+}
+
+#[test]
+fn should_validate_vp() {
+    let verified = validate_ii_vp(&verifier_config(), &validation_request(), VALID_TIME_NS)
+        .expect("VP validation failed");
+
+    assert_eq!(verified.subject, principal(DUMMY_ID_DAPP));
+    assert_eq!(verified.id_alias, principal(DUMMY_ID_ALIAS));
+    assert_eq!(verified.issuer_origin, DUMMY_ISSUER_ORIGIN);
+    assert_eq!(
+        verified.issuer_canister_id,
+        principal(DUMMY_ISSUER_CANISTER_ID)
+    );
+    assert_eq!(verified.credential_type, "VerifiedAge");
+    assert_eq!(verified.arguments, verified_age_spec(18).arguments);
+    assert_eq!(verified.issuer_principal, None);
+    assert_eq!(verified.nonce, None);
+    assert_eq!(
+        verified.credential_id,
+        Some("data:text/plain;charset=UTF-8,issuer:https://dummy-issuer.vc,timestamp_ns:1730797614686402869,subject:vzlav-qihwb-ytph2-okmop-45h5j-i6jbh-jxjil-t54xo-upw3l-5dlxj-zae".to_string())
+    );
+    assert_eq!(
+        verified.issuance_timestamp_ns,
+        Some(1_730_797_614 * 1_000_000_000)
+    );
+    assert_eq!(verified.expiration_timestamp_ns, REQUESTED_VC_EXPIRATION_NS);
+}
+
+#[test]
+fn should_validate_vp_without_issuer_canister_id() {
+    let req = VpValidationRequest {
+        issuer_canister_id: None,
+        ..validation_request()
+    };
+    let verified =
+        validate_ii_vp(&verifier_config(), &req, VALID_TIME_NS).expect("VP validation failed");
+    assert_eq!(
+        verified.issuer_canister_id,
+        principal(DUMMY_ISSUER_CANISTER_ID)
+    );
+}
+
+#[test]
+fn should_fail_for_unsupported_issuer() {
+    let req = VpValidationRequest {
+        issuer_origin: "https://wrong.origin".to_string(),
+        ..validation_request()
+    };
+    let result = validate_ii_vp(&verifier_config(), &req, VALID_TIME_NS);
+    assert_matches!(result,
+        Err(VpValidationError::UnsupportedIssuer(e)) if e.contains("issuer not supported"));
+}
+
+#[test]
+fn should_fail_for_wrong_issuer_canister_id() {
+    let req = VpValidationRequest {
+        issuer_canister_id: Some(principal(DUMMY_II_CANISTER_ID)),
+        ..validation_request()
+    };
+    let result = validate_ii_vp(&verifier_config(), &req, VALID_TIME_NS);
+    assert_matches!(result,
+        Err(VpValidationError::IssuerMismatch(e)) if e.contains("wrong issuer canister id"));
+}
+
+#[test]
+fn should_fail_for_credential_of_other_issuer() {
+    let other_issuer_origin = "https://other-issuer.vc/";
+    let mut config = verifier_config();
+    config.issuers.insert(
+        other_issuer_origin.to_string(),
+        principal(DUMMY_ISSUER_CANISTER_ID),
+    );
+    let req = VpValidationRequest {
+        issuer_origin: other_issuer_origin.to_string(),
+        ..validation_request()
+    };
+    let result = validate_ii_vp(&config, &req, VALID_TIME_NS);
+    assert_matches!(result, Err(VpValidationError::IssuerMismatch(_)));
+}
+
+#[test]
+fn should_fail_for_wrong_subject() {
+    let req = VpValidationRequest {
+        effective_vc_subject: principal(DUMMY_ID_ALIAS),
+        ..validation_request()
+    };
+    let result = validate_ii_vp(&verifier_config(), &req, VALID_TIME_NS);
+    assert_matches!(result,
+        Err(VpValidationError::SubjectMismatch(e)) if e.contains("unexpected vc subject"));
+}
+
+#[test]
+fn should_fail_for_wrong_credential_spec() {
+    for credential_spec in [
+        verified_age_spec(21),
+        CredentialSpec {
+            credential_type: "VerifiedHumanity".to_string(),
+            arguments: None,
+        },
+    ] {
+        let req = VpValidationRequest {
+            credential_spec,
+            ..validation_request()
+        };
+        let result = validate_ii_vp(&verifier_config(), &req, VALID_TIME_NS);
+        assert_matches!(result, Err(VpValidationError::SpecMismatch(_)));
+    }
+}
+
+#[test]
+fn should_fail_for_expired_vp() {
+    let after_expiration_ns = (REQUESTED_VC_EXPIRATION_NS + 1_000_000_000) as u128;
+    let result = validate_ii_vp(
+        &verifier_config(),
+        &validation_request(),
+        after_expiration_ns,
+    );
+    assert_matches!(result, Err(VpValidationError::Expired(_)));
+}
+
+#[test]
+fn should_fail_for_malformed_vp() {
+    let mut req = validation_request();
+    req.vp_jwt.insert(42, 'a');
+    let result = validate_ii_vp(&verifier_config(), &req, VALID_TIME_NS);
+    assert_matches!(result,
+        Err(VpValidationError::InvalidPresentation(e)) if e.contains("Failed to parse payload JSON"));
+}
+
+#[test]
+fn should_fail_for_vp_with_foreign_id_alias() {
+    // The requested credential was issued for an id alias different from the one in DUMMY_ALIAS_JWS.
+    let requested_vc_jws = requested_vc_jws(DUMMY_VP_JWT);
+    let vp_jwt = build_ii_verifiable_presentation_jwt(
+        principal(DUMMY_ID_DAPP),
+        DUMMY_ALIAS_JWS.to_string(),
+        requested_vc_jws,
+    )
+    .expect("failed building VP");
+    let req = VpValidationRequest {
+        vp_jwt,
+        ..validation_request()
+    };
+    let result = validate_ii_vp(&verifier_config(), &req, VALID_TIME_NS);
+    assert!(result.is_err(), "unexpected success: {:?}", result);
+}
+
+#[test]
+fn should_decode_id_alias_credential() {
+    let credential = decode_credential(DUMMY_ALIAS_JWS).expect("failed decoding credential");
+    assert_eq!(credential.issuer, II_ISSUER_URL);
+    assert_eq!(
+        credential.subject_principal().expect("bad subject"),
+        principal(DUMMY_ID_DAPP)
+    );
+    assert_eq!(
+        credential.id_alias_principal().expect("bad id alias"),
+        principal("evacf-r7slg-yca2r-i353q-x2lyx-jvb77-hh6ci-fwvuv-ckqis-plqzg-zqe")
+    );
+    assert_eq!(
+        credential.credential_type().expect("bad type"),
+        "InternetIdentityIdAlias"
+    );
+    assert_eq!(credential.expiration_s, Some(1_730_797_233));
+}
+
+#[test]
+fn should_decode_presentation() {
+    let presentation = decode_presentation(DUMMY_VP_JWT).expect("failed decoding VP");
+    assert_eq!(
+        presentation
+            .id_alias
+            .id_alias_principal()
+            .expect("bad id alias"),
+        principal(DUMMY_ID_ALIAS)
+    );
+    assert_eq!(
+        presentation
+            .requested
+            .subject_principal()
+            .expect("bad subject"),
+        principal(DUMMY_ID_ALIAS)
+    );
+    assert_eq!(presentation.requested.issuer, DUMMY_ISSUER_ORIGIN);
+    assert_eq!(
+        presentation.requested.arguments().expect("bad arguments"),
+        verified_age_spec(18).arguments
+    );
+}
+
+#[test]
+fn should_not_claim_issuer_of_credential_without_issuer_principal() {
+    assert_eq!(claimed_issuer(DUMMY_VP_JWT), Ok(None));
+    assert_matches!(
+        claimed_issuer("not a VP"),
+        Err(VpValidationError::InvalidPresentation(_))
+    );
+}
+
+fn requested_vc_jws(vp_jwt: &str) -> String {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    let payload = vp_jwt.split('.').nth(1).expect("missing VP payload");
+    let claims: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).expect("bad base64"))
+            .expect("bad JSON");
+    claims["vp"]["verifiableCredential"][1]
+        .as_str()
+        .expect("missing requested credential")
+        .to_string()
+}